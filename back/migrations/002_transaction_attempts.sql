@@ -0,0 +1,19 @@
+-- migrations/002_transaction_attempts.sql
+-- Every send attempt made by the transaction sender
+
+CREATE TABLE IF NOT EXISTS transaction_attempts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    send_id UUID NOT NULL,
+    signature VARCHAR(88) NOT NULL,
+    attempt INTEGER NOT NULL,
+    rebuild INTEGER NOT NULL,
+    blockhash VARCHAR(44) NOT NULL,
+    compute_unit_limit INTEGER,
+    compute_unit_price BIGINT,
+    outcome VARCHAR(20) NOT NULL,
+    error_message TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_attempt_signature ON transaction_attempts(signature);
+CREATE INDEX IF NOT EXISTS idx_attempt_send ON transaction_attempts(send_id);
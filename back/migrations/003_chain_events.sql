@@ -0,0 +1,29 @@
+-- migrations/003_chain_events.sql
+-- Events decoded from program logs by the indexer
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS slot BIGINT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS block_time BIGINT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS log_index INTEGER;
+
+-- One signature can emit several events (and a transfer or settlement touches
+-- two vaults), so rows are unique per event and user rather than per signature.
+ALTER TABLE transactions DROP CONSTRAINT IF EXISTS transactions_signature_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tx_event ON transactions(signature, log_index, user_pubkey);
+CREATE INDEX IF NOT EXISTS idx_tx_signature ON transactions(signature);
+
+CREATE TABLE IF NOT EXISTS chain_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    signature VARCHAR(88) NOT NULL,
+    log_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    block_time BIGINT,
+    event_name VARCHAR(40) NOT NULL,
+    vault_pda VARCHAR(44),
+    sequence BIGINT,
+    data TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (signature, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_chain_events_vault ON chain_events(vault_pda, sequence);
+CREATE INDEX IF NOT EXISTS idx_chain_events_slot ON chain_events(slot);
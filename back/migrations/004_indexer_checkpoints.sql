@@ -0,0 +1,9 @@
+-- migrations/004_indexer_checkpoints.sql
+-- Newest transaction the backfill has processed for each address it walks
+
+CREATE TABLE IF NOT EXISTS indexer_checkpoints (
+    address VARCHAR(44) PRIMARY KEY,
+    signature VARCHAR(88) NOT NULL,
+    slot BIGINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
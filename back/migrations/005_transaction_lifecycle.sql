@@ -0,0 +1,9 @@
+-- migrations/005_transaction_lifecycle.sql
+-- Transactions are written as Pending before they are sent and moved on by the confirmer
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS confirmed_at TIMESTAMP;
+
+-- A vault's Initialize row is written before the vault exists
+ALTER TABLE transactions ALTER COLUMN vault_id DROP NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_tx_status ON transactions(status);
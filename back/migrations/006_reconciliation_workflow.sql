@@ -0,0 +1,15 @@
+-- migrations/006_reconciliation_workflow.sql
+-- Full-program reconciliation compares each vault with several sources and
+-- mismatches are closed through the API
+
+ALTER TABLE reconciliation_logs ADD COLUMN IF NOT EXISTS source VARCHAR(20) NOT NULL DEFAULT 'cache';
+ALTER TABLE reconciliation_logs ADD COLUMN IF NOT EXISTS vault_pda VARCHAR(44);
+ALTER TABLE reconciliation_logs ADD COLUMN IF NOT EXISTS slot BIGINT;
+ALTER TABLE reconciliation_logs ADD COLUMN IF NOT EXISTS resolution_action TEXT;
+ALTER TABLE reconciliation_logs ADD COLUMN IF NOT EXISTS resolved_at TIMESTAMP;
+
+-- A vault missing from vault_accounts is itself a mismatch worth logging
+ALTER TABLE reconciliation_logs ALTER COLUMN vault_id DROP NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_reconcile_status ON reconciliation_logs(status);
+CREATE INDEX IF NOT EXISTS idx_reconcile_checked ON reconciliation_logs(checked_at DESC);
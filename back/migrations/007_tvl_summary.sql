@@ -0,0 +1,13 @@
+-- migrations/007_tvl_summary.sql
+-- Database-side totals, cross-checked against the on-chain program account scan
+
+CREATE OR REPLACE VIEW tvl_summary AS
+SELECT
+    COUNT(*) as total_vaults,
+    SUM(total_balance) as total_value_locked,
+    SUM(locked_balance) as total_locked,
+    SUM(available_balance) as total_available,
+    AVG(total_balance) as avg_balance,
+    NOW() as calculated_at
+FROM vault_accounts
+WHERE status = 'ACTIVE';
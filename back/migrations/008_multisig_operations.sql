@@ -0,0 +1,30 @@
+-- migrations/008_multisig_operations.sql
+-- Vault operations proposed under a multisig config and the co-signer
+-- approvals collected for them before the transaction is submitted
+
+CREATE TABLE IF NOT EXISTS multisig_operations (
+    id UUID PRIMARY KEY,
+    owner_pubkey VARCHAR(44) NOT NULL,
+    vault_pda VARCHAR(44) NOT NULL,
+    action TEXT NOT NULL,
+    transaction TEXT NOT NULL,
+    message TEXT NOT NULL,
+    signers TEXT[] NOT NULL,
+    threshold SMALLINT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    tx_signature VARCHAR(88),
+    error_message TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    executed_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS multisig_approvals (
+    operation_id UUID NOT NULL REFERENCES multisig_operations(id) ON DELETE CASCADE,
+    signer_pubkey VARCHAR(44) NOT NULL,
+    signature VARCHAR(88) NOT NULL,
+    approved_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (operation_id, signer_pubkey)
+);
+
+CREATE INDEX IF NOT EXISTS idx_multisig_status ON multisig_operations(status);
+CREATE INDEX IF NOT EXISTS idx_multisig_owner ON multisig_operations(owner_pubkey);
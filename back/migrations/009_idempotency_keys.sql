@@ -0,0 +1,19 @@
+-- migrations/009_idempotency_keys.sql
+-- Idempotency-Key headers on POST requests, so a retried request replays its
+-- first result instead of sending another transaction. Keys are scoped to the
+-- session wallet that sent them
+
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    wallet VARCHAR(44) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    request_hash VARCHAR(64) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'in_progress',
+    response_status SMALLINT,
+    response_body TEXT,
+    tx_signature VARCHAR(88),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP,
+    PRIMARY KEY (wallet, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_created ON idempotency_keys(created_at);
@@ -0,0 +1,5 @@
+-- migrations/010_transaction_history.sql
+-- Transaction history is paged by (created_at, id) per user
+
+CREATE INDEX IF NOT EXISTS idx_tx_user_created ON transactions(user_pubkey, created_at, id);
+CREATE INDEX IF NOT EXISTS idx_tx_created ON transactions(created_at);
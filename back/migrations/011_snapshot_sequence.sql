@@ -0,0 +1,6 @@
+-- migrations/011_snapshot_sequence.sql
+-- Snapshots record the vault sequence they were read at, so point-in-time
+-- balances can replay indexed events from exactly that point
+
+ALTER TABLE balance_snapshots ADD COLUMN IF NOT EXISTS sequence BIGINT;
+CREATE INDEX IF NOT EXISTS idx_snapshots_user_sequence ON balance_snapshots(user_pubkey, sequence);
@@ -0,0 +1,19 @@
+-- migrations/012_chain_event_vaults.sql
+-- Every vault an indexed event touched, so a vault's history can be read by
+-- sequence or time without decoding every event. A transfer or settlement
+-- has a row per side
+
+CREATE TABLE IF NOT EXISTS chain_event_vaults (
+    signature VARCHAR(88) NOT NULL,
+    log_index INTEGER NOT NULL,
+    vault_pda VARCHAR(44) NOT NULL,
+    sequence BIGINT,
+    slot BIGINT NOT NULL,
+    event_time BIGINT NOT NULL,
+    PRIMARY KEY (signature, log_index, vault_pda),
+    FOREIGN KEY (signature, log_index) REFERENCES chain_events(signature, log_index) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_chain_event_vaults_sequence ON chain_event_vaults(vault_pda, sequence);
+CREATE INDEX IF NOT EXISTS idx_chain_event_vaults_time ON chain_event_vaults(vault_pda, event_time);
+CREATE INDEX IF NOT EXISTS idx_chain_event_vaults_slot ON chain_event_vaults(vault_pda, slot);
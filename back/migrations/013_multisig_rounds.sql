@@ -0,0 +1,23 @@
+-- migrations/013_multisig_rounds.sql
+-- Multisig operations are approved by any threshold of co-signers before a
+-- transaction exists; it is built for those approvers, who sign it in turn
+
+ALTER TABLE multisig_operations ALTER COLUMN transaction DROP NOT NULL;
+ALTER TABLE multisig_operations ADD COLUMN IF NOT EXISTS transaction_message TEXT;
+ALTER TABLE multisig_operations ADD COLUMN IF NOT EXISTS signing TEXT[] NOT NULL DEFAULT '{}';
+
+-- Operations proposed before this asked named approvers to sign the
+-- transaction itself and cannot continue
+UPDATE multisig_operations
+SET status = 'expired', error_message = 'Proposed before approvals were collected separately; propose it again'
+WHERE status = 'pending' AND transaction IS NOT NULL AND transaction_message IS NULL;
+
+CREATE TABLE IF NOT EXISTS multisig_signatures (
+    operation_id UUID NOT NULL REFERENCES multisig_operations(id) ON DELETE CASCADE,
+    signer_pubkey VARCHAR(44) NOT NULL,
+    signature VARCHAR(88) NOT NULL,
+    signed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (operation_id, signer_pubkey)
+);
+
+CREATE INDEX IF NOT EXISTS idx_multisig_vault_status ON multisig_operations(vault_pda, status);
@@ -0,0 +1,18 @@
+-- migrations/014_utc_timestamps.sql
+-- Timestamp columns hold UTC wall-clock time; defaults must not follow the
+-- session time zone
+
+ALTER TABLE vault_accounts ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE vault_accounts ALTER COLUMN updated_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE transactions ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE balance_snapshots ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE audit_logs ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE reconciliation_logs ALTER COLUMN checked_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE alerts ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE transaction_attempts ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE chain_events ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE indexer_checkpoints ALTER COLUMN updated_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE multisig_operations ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE multisig_approvals ALTER COLUMN approved_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE idempotency_keys ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
+ALTER TABLE multisig_signatures ALTER COLUMN signed_at SET DEFAULT (NOW() AT TIME ZONE 'UTC');
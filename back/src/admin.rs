@@ -0,0 +1,155 @@
+//! Operator API under `/admin`, separate from wallet sessions. Callers present
+//! an API key from `[admin]` in `X-Api-Key` (or `Authorization: ApiKey <key>`)
+//! and every route requires a minimum role:
+//!
+//! - `viewer`: metrics, alerts, audit logs, reconciliation and backfill status,
+//! - `operator`: runs reconciliation and resolves mismatches and alerts,
+//! - `admin`: rescans history and lists the configured keys.
+
+use crate::auth::reject;
+use crate::handlers;
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+    Extension, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Roles in increasing order of privilege; each includes the ones below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// One `[[admin.keys]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    /// Who holds the key; recorded in audit entries.
+    pub name: String,
+    pub key: String,
+    pub role: Role,
+}
+
+/// `[admin]` in the config file. Without keys every `/admin` route rejects.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    pub keys: Vec<ApiKey>,
+}
+
+impl AdminConfig {
+    fn find(&self, key: &str) -> Option<&ApiKey> {
+        self.keys
+            .iter()
+            .find(|k| constant_time_eq(k.key.as_bytes(), key.as_bytes()))
+    }
+}
+
+/// The key holder behind an `/admin` request, available to handlers as an
+/// `Extension`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Operator {
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Clone)]
+struct Gate {
+    config: Arc<AdminConfig>,
+    role: Role,
+}
+
+/// Routes for `/admin`; the handlers' own extensions come from the outer router.
+pub fn router(config: AdminConfig) -> Router {
+    let config = Arc::new(config);
+    let gate = |role| {
+        middleware::from_fn_with_state(
+            Gate {
+                config: config.clone(),
+                role,
+            },
+            require_role,
+        )
+    };
+
+    let viewer = Router::new()
+        .route("/whoami", get(handlers::whoami))
+        .route("/metrics", get(handlers::get_metrics_history))
+        .route("/security-alerts", get(handlers::get_security_alerts))
+        .route("/alerts", get(handlers::get_active_alerts))
+        .route("/audit-logs", get(handlers::get_audit_logs))
+        .route("/backfill", get(handlers::get_backfill_progress))
+        .route("/reconciliation", get(handlers::get_reconciliation_logs))
+        .route_layer(gate(Role::Viewer));
+    let operator = Router::new()
+        .route("/reconciliation", post(handlers::run_reconciliation))
+        .route(
+            "/reconciliation/{id}/resolve",
+            post(handlers::resolve_reconciliation),
+        )
+        .route("/reconcile/{user}", post(handlers::reconcile_vault))
+        .route("/alerts/{id}/resolve", post(handlers::resolve_alert))
+        .route_layer(gate(Role::Operator));
+    let admin = Router::new()
+        .route("/backfill", post(handlers::start_backfill))
+        .route("/keys", get(handlers::list_api_keys))
+        .route_layer(gate(Role::Admin));
+
+    viewer.merge(operator).merge(admin).layer(Extension(config))
+}
+
+async fn require_role(State(gate): State<Gate>, mut req: Request, next: Next) -> Response {
+    let Some(key) = api_key(req.headers()) else {
+        return reject(StatusCode::UNAUTHORIZED, "Missing API key");
+    };
+    let Some(holder) = gate.config.find(key) else {
+        return reject(StatusCode::UNAUTHORIZED, "Unknown API key");
+    };
+    if holder.role < gate.role {
+        return reject(
+            StatusCode::FORBIDDEN,
+            &format!("Requires the {} role", gate.role.as_str()),
+        );
+    }
+
+    req.extensions_mut().insert(Operator {
+        name: holder.name.clone(),
+        role: holder.role,
+    });
+    next.run(req).await
+}
+
+fn api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get("x-api-key") {
+        return key.to_str().ok().map(str::trim);
+    }
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("ApiKey ")
+        .map(str::trim)
+}
+
+/// Compare without leaking how much of a guessed key was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
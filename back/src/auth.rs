@@ -0,0 +1,271 @@
+//! Sign-in with Solana. A client asks for a nonce, signs the returned message
+//! with its wallet and trades the signature for a short-lived session token.
+//!
+//! Vault-mutating handlers take their body as `OwnerJson`, which only lets a
+//! request through when `Authorization: Bearer <token>` belongs to the wallet
+//! that owns the vault the request names.
+
+use crate::handlers::ErrorResponse;
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Result};
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// `[auth]` in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Domain named in the sign-in message, so a signature for another site
+    /// cannot be replayed here.
+    pub domain: String,
+    /// How long a nonce can be signed before it expires.
+    pub nonce_ttl_secs: u64,
+    /// How long a session token stays valid.
+    pub session_ttl_secs: u64,
+    /// Unsigned nonces held at once. Further nonce requests are refused until
+    /// some are spent or expire.
+    pub max_pending_challenges: usize,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            domain: "localhost".to_string(),
+            nonce_ttl_secs: 300,
+            session_ttl_secs: 900,
+            max_pending_challenges: 10_000,
+        }
+    }
+}
+
+/// A message for the wallet to sign.
+#[derive(Debug, Clone, Serialize)]
+pub struct Challenge {
+    pub nonce: String,
+    pub message: String,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub token: String,
+    pub pubkey: String,
+    pub expires_at: i64,
+}
+
+struct PendingChallenge {
+    pubkey: Pubkey,
+    message: String,
+    expires_at: i64,
+}
+
+pub struct AuthService {
+    config: AuthConfig,
+    challenges: RwLock<HashMap<String, PendingChallenge>>,
+    sessions: RwLock<HashMap<String, (Pubkey, i64)>>,
+}
+
+impl AuthService {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config,
+            challenges: RwLock::new(HashMap::new()),
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Drop expired nonces and sessions once per nonce lifetime.
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let every = Duration::from_secs(self.config.nonce_ttl_secs);
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                self.sweep().await;
+            }
+        });
+    }
+
+    /// Drop expired nonces and sessions.
+    pub async fn sweep(&self) {
+        let now = Utc::now().timestamp();
+        self.challenges
+            .write()
+            .await
+            .retain(|_, c| c.expires_at > now);
+        self.sessions
+            .write()
+            .await
+            .retain(|_, (_, expires)| *expires > now);
+    }
+
+    /// Issue a one-time nonce for `pubkey` and the message to sign with it.
+    /// Fails while `max_pending_challenges` unexpired nonces are outstanding.
+    pub async fn challenge(&self, pubkey: Pubkey) -> Result<Challenge> {
+        let now = Utc::now();
+        let expires_at = now.timestamp() + self.config.nonce_ttl_secs as i64;
+        let nonce = Uuid::new_v4().simple().to_string();
+        let message = format!(
+            "{domain} wants you to sign in with your Solana account:\n\
+             {pubkey}\n\n\
+             Sign in to manage your collateral vault.\n\n\
+             Nonce: {nonce}\n\
+             Issued At: {issued}\n\
+             Expiration Time: {expires}",
+            domain = self.config.domain,
+            issued = now.to_rfc3339(),
+            expires = DateTime::from_timestamp(expires_at, 0)
+                .unwrap_or(now)
+                .to_rfc3339(),
+        );
+
+        let mut challenges = self.challenges.write().await;
+        if challenges.len() >= self.config.max_pending_challenges {
+            challenges.retain(|_, c| c.expires_at > now.timestamp());
+            if challenges.len() >= self.config.max_pending_challenges {
+                bail!("Too many pending sign-in requests, try again later");
+            }
+        }
+        challenges.insert(
+            nonce.clone(),
+            PendingChallenge {
+                pubkey,
+                message: message.clone(),
+                expires_at,
+            },
+        );
+        Ok(Challenge {
+            nonce,
+            message,
+            expires_at,
+        })
+    }
+
+    /// Check `signature` over the message issued with `nonce` and open a
+    /// session. The nonce is spent whether or not the signature is valid.
+    pub async fn verify(
+        &self,
+        pubkey: Pubkey,
+        nonce: &str,
+        signature: &Signature,
+    ) -> Result<Session> {
+        let now = Utc::now().timestamp();
+        let Some(challenge) = self.challenges.write().await.remove(nonce) else {
+            bail!("Unknown or already used nonce");
+        };
+        if challenge.expires_at <= now {
+            bail!("Nonce expired");
+        }
+        if challenge.pubkey != pubkey {
+            bail!("Nonce was issued to a different wallet");
+        }
+        if !signature.verify(pubkey.as_ref(), challenge.message.as_bytes()) {
+            bail!("Signature does not match the wallet");
+        }
+
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let expires_at = now + self.config.session_ttl_secs as i64;
+        self.sessions
+            .write()
+            .await
+            .insert(token.clone(), (pubkey, expires_at));
+        Ok(Session {
+            token,
+            pubkey: pubkey.to_string(),
+            expires_at,
+        })
+    }
+
+    /// Wallet of a live session.
+    pub async fn authenticate(&self, token: &str) -> Option<Pubkey> {
+        let sessions = self.sessions.read().await;
+        let (pubkey, expires_at) = sessions.get(token)?;
+        (*expires_at > Utc::now().timestamp()).then_some(*pubkey)
+    }
+}
+
+pub(crate) fn reject(status: StatusCode, error: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// The wallet behind the request's session token.
+pub struct AuthenticatedWallet(pub Pubkey);
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthenticatedWallet {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Response> {
+        let Some(auth) = parts.extensions.get::<Arc<AuthService>>().cloned() else {
+            return Err(reject(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Authentication is not configured",
+            ));
+        };
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| reject(StatusCode::UNAUTHORIZED, "Missing bearer token"))?;
+
+        match auth.authenticate(token.trim()).await {
+            Some(pubkey) => Ok(AuthenticatedWallet(pubkey)),
+            None => Err(reject(
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired session",
+            )),
+        }
+    }
+}
+
+/// A request body naming the owner of the vault it acts on.
+pub trait OwnedRequest {
+    fn owner(&self) -> &str;
+}
+
+/// JSON body of a vault-mutating request, accepted only from a session of
+/// the vault's owner.
+pub struct OwnerJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for OwnerJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + OwnedRequest,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Response> {
+        let (mut parts, body) = req.into_parts();
+        let AuthenticatedWallet(wallet) =
+            AuthenticatedWallet::from_request_parts(&mut parts, state).await?;
+        let Json(value) = Json::<T>::from_request(Request::from_parts(parts, body), state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        if value.owner() != wallet.to_string() {
+            return Err(reject(
+                StatusCode::FORBIDDEN,
+                "Session wallet does not own this vault",
+            ));
+        }
+        Ok(OwnerJson(value))
+    }
+}
@@ -0,0 +1,294 @@
+//! Fills gaps in the event index from `getSignaturesForAddress`, for events
+//! emitted while the log subscription was down or falling behind.
+//!
+//! The program ID and every known vault PDA are walked separately. Each
+//! address has a checkpoint: the newest signature already processed. A run
+//! pages back to the checkpoint (or, when a start slot is given, to that
+//! slot), keeping only the newest page and where each older page starts.
+//! Pages are then fetched again and indexed oldest first, and the checkpoint
+//! moves after each transaction, so an interrupted run resumes where it
+//! stopped. A transaction whose logs the node cannot return stops the run
+//! before it. Indexing is idempotent, so overlapping runs and the live
+//! subscription never duplicate rows.
+
+use crate::chain::{ChainClient, SignatureInfo};
+use crate::db::{Database, IndexerCheckpoint};
+use crate::indexer::EventIndexer;
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration};
+
+/// `getSignaturesForAddress` returns at most this many entries per call.
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackfillState {
+    Idle,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillProgress {
+    pub state: BackfillState,
+    pub from_slot: Option<u64>,
+    pub addresses_total: usize,
+    pub addresses_done: usize,
+    pub current_address: Option<String>,
+    pub signatures_found: usize,
+    pub transactions_processed: usize,
+    pub events_indexed: usize,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+impl Default for BackfillProgress {
+    fn default() -> Self {
+        Self {
+            state: BackfillState::Idle,
+            from_slot: None,
+            addresses_total: 0,
+            addresses_done: 0,
+            current_address: None,
+            signatures_found: 0,
+            transactions_processed: 0,
+            events_indexed: 0,
+            started_at: None,
+            finished_at: None,
+            error: None,
+        }
+    }
+}
+
+pub struct Backfill {
+    chain: Arc<dyn ChainClient>,
+    database: Arc<Database>,
+    indexer: Arc<EventIndexer>,
+    program_id: Pubkey,
+    page_size: usize,
+    progress: RwLock<BackfillProgress>,
+}
+
+impl Backfill {
+    pub fn new(
+        chain: Arc<dyn ChainClient>,
+        database: Arc<Database>,
+        indexer: Arc<EventIndexer>,
+        program_id: Pubkey,
+    ) -> Self {
+        Self {
+            chain,
+            database,
+            indexer,
+            program_id,
+            page_size: PAGE_SIZE,
+            progress: RwLock::new(BackfillProgress::default()),
+        }
+    }
+
+    /// Fetch signatures `page_size` at a time instead of the RPC maximum.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.clamp(1, PAGE_SIZE);
+        self
+    }
+
+    pub async fn progress(&self) -> BackfillProgress {
+        self.progress.read().await.clone()
+    }
+
+    /// Run in the background. Errors if a run is already in progress.
+    pub async fn start(self: &Arc<Self>, from_slot: Option<u64>) -> Result<BackfillProgress> {
+        let progress = self.begin(from_slot).await?;
+        let backfill = self.clone();
+        tokio::spawn(async move { backfill.execute(from_slot).await });
+        Ok(progress)
+    }
+
+    /// Backfill from the checkpoints every time the indexer's subscription
+    /// drops transactions.
+    pub fn follow_indexer(self: &Arc<Self>) {
+        let backfill = self.clone();
+        tokio::spawn(async move {
+            loop {
+                backfill.indexer.lagged().await;
+                // A run already underway may be past the gap; start another
+                // once it finishes.
+                while backfill.start(None).await.is_err() {
+                    sleep(Duration::from_secs(1)).await;
+                }
+            }
+        });
+    }
+
+    /// Run to completion and return the final progress.
+    pub async fn run(&self, from_slot: Option<u64>) -> Result<BackfillProgress> {
+        self.begin(from_slot).await?;
+        let progress = self.execute(from_slot).await;
+        match progress.state {
+            BackfillState::Failed => Err(anyhow!(
+                "Backfill failed: {}",
+                progress.error.unwrap_or_default()
+            )),
+            _ => Ok(progress),
+        }
+    }
+
+    async fn begin(&self, from_slot: Option<u64>) -> Result<BackfillProgress> {
+        let mut progress = self.progress.write().await;
+        if progress.state == BackfillState::Running {
+            return Err(anyhow!("A backfill is already running"));
+        }
+        *progress = BackfillProgress {
+            state: BackfillState::Running,
+            from_slot,
+            started_at: Some(Utc::now().timestamp()),
+            ..BackfillProgress::default()
+        };
+        Ok(progress.clone())
+    }
+
+    async fn execute(&self, from_slot: Option<u64>) -> BackfillProgress {
+        println!("⏪ Backfill started");
+        let result = self.walk_all(from_slot).await;
+
+        let mut progress = self.progress.write().await;
+        progress.current_address = None;
+        progress.finished_at = Some(Utc::now().timestamp());
+        match result {
+            Ok(()) => {
+                progress.state = BackfillState::Completed;
+                println!(
+                    "✅ Backfill complete: {} transactions, {} new events",
+                    progress.transactions_processed, progress.events_indexed
+                );
+            }
+            Err(e) => {
+                progress.state = BackfillState::Failed;
+                progress.error = Some(format!("{:#}", e));
+                eprintln!("❌ Backfill failed: {:#}", e);
+            }
+        }
+        progress.clone()
+    }
+
+    async fn walk_all(&self, from_slot: Option<u64>) -> Result<()> {
+        let mut addresses = vec![self.program_id];
+        for vault in self.database.get_vault_pdas().await? {
+            let vault: Pubkey = vault
+                .parse()
+                .with_context(|| format!("invalid vault PDA {}", vault))?;
+            if !addresses.contains(&vault) {
+                addresses.push(vault);
+            }
+        }
+        self.progress.write().await.addresses_total = addresses.len();
+
+        for address in addresses {
+            self.progress.write().await.current_address = Some(address.to_string());
+            self.walk(&address, from_slot)
+                .await
+                .with_context(|| format!("backfilling {}", address))?;
+            self.progress.write().await.addresses_done += 1;
+        }
+        Ok(())
+    }
+
+    async fn walk(&self, address: &Pubkey, from_slot: Option<u64>) -> Result<()> {
+        let key = address.to_string();
+        let checkpoint = self.database.get_checkpoint(&key).await?;
+        // An explicit start slot rescans from there; otherwise pick up after
+        // the checkpoint.
+        let until = match (&checkpoint, from_slot) {
+            (Some(checkpoint), None) => Some(checkpoint.signature.parse::<Signature>()?),
+            _ => None,
+        };
+
+        let (newest, mut more) = self.page(address, None, until, from_slot).await?;
+        let mut found = newest.len();
+        let mut cursors: Vec<Signature> = Vec::new();
+        let mut oldest = newest.last().map(|info| info.signature.clone());
+        while more {
+            let Some(last) = oldest else { break };
+            let cursor: Signature = last.parse()?;
+            let (page, next) = self.page(address, Some(cursor), until, from_slot).await?;
+            found += page.len();
+            cursors.push(cursor);
+            oldest = page.last().map(|info| info.signature.clone());
+            more = next;
+        }
+        self.progress.write().await.signatures_found += found;
+
+        // Older history cannot change, so a page fetched again from the same
+        // cursor is the one seen above.
+        for cursor in cursors.into_iter().rev() {
+            let (page, _) = self.page(address, Some(cursor), until, from_slot).await?;
+            self.index_page(&key, checkpoint.as_ref(), page).await?;
+        }
+        self.index_page(&key, checkpoint.as_ref(), newest).await
+    }
+
+    /// Index a page oldest first, moving the checkpoint after each
+    /// transaction.
+    async fn index_page(
+        &self,
+        key: &str,
+        checkpoint: Option<&IndexerCheckpoint>,
+        page: Vec<SignatureInfo>,
+    ) -> Result<()> {
+        for info in page.into_iter().rev() {
+            if info.err.is_none() {
+                let signature: Signature = info.signature.parse()?;
+                let Some(logs) = self.chain.get_transaction_logs(&signature).await? else {
+                    return Err(anyhow!(
+                        "logs of {} are not available; stopped before it",
+                        signature
+                    ));
+                };
+                let indexed = self.indexer.index_logs(&logs).await?;
+                self.progress.write().await.events_indexed += indexed;
+            }
+            self.progress.write().await.transactions_processed += 1;
+
+            // A rescan of older history must not move the checkpoint back.
+            if checkpoint.is_none_or(|c| info.slot >= c.slot) {
+                self.database
+                    .set_checkpoint(IndexerCheckpoint {
+                        address: key.to_string(),
+                        signature: info.signature,
+                        slot: info.slot,
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// One page of the address's history, newest first, ending at the
+    /// checkpoint or the start slot. The flag says whether older pages follow.
+    async fn page(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        from_slot: Option<u64>,
+    ) -> Result<(Vec<SignatureInfo>, bool)> {
+        let mut page = self
+            .chain
+            .get_signatures_for_address(address, before, until, self.page_size)
+            .await?;
+        let full = page.len() == self.page_size;
+        if let Some(cut) = from_slot.and_then(|slot| page.iter().position(|info| info.slot < slot))
+        {
+            page.truncate(cut);
+            return Ok((page, false));
+        }
+        Ok((page, full))
+    }
+}
@@ -0,0 +1,240 @@
+//! Point-in-time balances: what a vault's total, locked and available
+//! balances were at a unix time or slot, for disputes and margin audits.
+//!
+//! Every state change bumps the vault's `sequence`, and every event carries
+//! it. The answer starts from the latest `balance_snapshots` row read at or
+//! before the last sequence in range (or from zero at `VaultInitialized`).
+//! Only the vault's indexed events after that snapshot are loaded, and their
+//! balance changes are replayed in sequence order. If any sequence the
+//! answer could depend on was never indexed, or the replay takes a balance
+//! below zero or past `u64::MAX`, no answer is given.
+
+use crate::chain::ChainClient;
+use crate::db::{BalanceSnapshot, Database, EventCutoff, VaultEventRange};
+use crate::indexer::{BalanceChange, IndexedEvent};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use collateral_vault::state::CollateralVault;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+/// `?at=`: a unix timestamp, an RFC 3339 time, or `slot:<n>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointInTime {
+    Timestamp(i64),
+    Slot(u64),
+}
+
+impl FromStr for PointInTime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(slot) = s.strip_prefix("slot:") {
+            return Ok(PointInTime::Slot(slot.parse()?));
+        }
+        if let Ok(timestamp) = s.parse() {
+            return Ok(PointInTime::Timestamp(timestamp));
+        }
+        DateTime::parse_from_rfc3339(s)
+            .map(|t| PointInTime::Timestamp(t.timestamp()))
+            .map_err(|_| anyhow!("expected a unix timestamp, an RFC 3339 time or slot:<n>"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Balances {
+    pub total_balance: u64,
+    pub locked_balance: u64,
+    pub available_balance: u64,
+}
+
+/// One event in the replay, with the balances after it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayedEvent {
+    pub sequence: u64,
+    pub event: &'static str,
+    pub signature: String,
+    pub slot: u64,
+    pub log_index: u32,
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub balances: Balances,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoricalBalance {
+    pub user: String,
+    pub vault: String,
+    pub at: PointInTime,
+    /// The vault's sequence as of `at`.
+    pub sequence: u64,
+    #[serde(flatten)]
+    pub balances: Balances,
+    /// Where the replay started; `None` when it started from the vault's
+    /// creation.
+    pub snapshot: Option<BalanceSnapshot>,
+    pub events: Vec<ReplayedEvent>,
+}
+
+/// Sequences the answer depends on that the indexer has not recorded.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexGap {
+    pub first_missing_sequence: u64,
+    pub missing_sequences: u64,
+}
+
+/// A replayed change the running balance cannot absorb: the snapshot or the
+/// indexed events disagree with what the vault recorded.
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceInconsistency {
+    pub sequence: u64,
+    /// `total_balance`, `locked_balance` or `available_balance`.
+    pub balance: &'static str,
+    pub before: u64,
+    pub change: i128,
+}
+
+pub enum BalanceAt {
+    Balance(Box<HistoricalBalance>),
+    /// The vault had no events yet at that point.
+    NotFound,
+    Gap(IndexGap),
+    Inconsistent(BalanceInconsistency),
+}
+
+pub async fn balance_at(
+    database: &Database,
+    chain: &dyn ChainClient,
+    user: Pubkey,
+    vault: Pubkey,
+    at: PointInTime,
+) -> Result<BalanceAt> {
+    let cutoff = match at {
+        PointInTime::Timestamp(at) => EventCutoff::Time(at),
+        PointInTime::Slot(slot) => EventCutoff::Slot(slot),
+    };
+    let (target, first_after) = database
+        .get_vault_sequence_bounds(&vault.to_string(), cutoff)
+        .await?;
+    // A missing sequence below the first event after `at` could have come
+    // before it. With no indexed event after `at`, everything up to the
+    // vault's current sequence could have.
+    let bound = match first_after {
+        Some(seq) => seq,
+        None => match chain.get_account(&vault).await {
+            Ok(account) => {
+                let mut data: &[u8] = &account.data;
+                CollateralVault::try_deserialize(&mut data)?.sequence + 1
+            }
+            Err(_) if target.is_none() => return Ok(BalanceAt::NotFound),
+            Err(e) => return Err(e),
+        },
+    };
+
+    let snapshot = match target {
+        Some(target) => {
+            database
+                .get_snapshot_at_sequence(&vault.to_string(), target)
+                .await?
+        }
+        None => None,
+    };
+    let start = snapshot
+        .as_ref()
+        .and_then(|s| s.sequence)
+        .map_or(0, |seq| seq + 1);
+    let events: Vec<(u64, IndexedEvent)> = match target {
+        Some(target) => IndexedEvent::load_for_vault(
+            database,
+            &vault,
+            VaultEventRange {
+                after_sequence: start.checked_sub(1),
+                through_sequence: Some(target),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .filter_map(|e| e.event.sequence_for(&vault).map(|seq| (seq, e)))
+        .collect(),
+        None => Vec::new(),
+    };
+    // Sequences between `target` and `bound` are unindexed by construction.
+    let indexed: BTreeSet<u64> = events.iter().map(|(seq, _)| *seq).collect();
+    let mut missing = (start..bound).filter(|seq| !indexed.contains(seq));
+    if let Some(first) = missing.next() {
+        return Ok(BalanceAt::Gap(IndexGap {
+            first_missing_sequence: first,
+            missing_sequences: 1 + missing.count() as u64,
+        }));
+    }
+    let Some(target) = target else {
+        return Ok(BalanceAt::NotFound);
+    };
+
+    let mut balances = snapshot.as_ref().map_or(Balances::default(), |s| Balances {
+        total_balance: s.total_balance,
+        locked_balance: s.locked_balance,
+        available_balance: s.available_balance,
+    });
+    let mut replayed = Vec::new();
+    let mut events = events;
+    events.sort_by_key(|(seq, _)| *seq);
+    for (seq, indexed) in &events {
+        for change in indexed.event.balance_changes() {
+            if change.vault != vault {
+                continue;
+            }
+            if let Err(inconsistency) = apply(*seq, &change, &mut balances) {
+                return Ok(BalanceAt::Inconsistent(inconsistency));
+            }
+        }
+        replayed.push(ReplayedEvent {
+            sequence: *seq,
+            event: indexed.event.name(),
+            signature: indexed.signature.clone(),
+            slot: indexed.slot,
+            log_index: indexed.log_index,
+            timestamp: indexed.timestamp,
+            balances,
+        });
+    }
+
+    Ok(BalanceAt::Balance(Box::new(HistoricalBalance {
+        user: user.to_string(),
+        vault: vault.to_string(),
+        at,
+        sequence: target,
+        balances,
+        snapshot,
+        events: replayed,
+    })))
+}
+
+/// Apply the balance change at `sequence` to the running balances, leaving
+/// them untouched if any would leave the range of a `u64`.
+fn apply(
+    sequence: u64,
+    change: &BalanceChange,
+    b: &mut Balances,
+) -> Result<(), BalanceInconsistency> {
+    let shift = |balance: &'static str, before: u64, change: i128| {
+        u64::try_from(before as i128 + change).map_err(|_| BalanceInconsistency {
+            sequence,
+            balance,
+            before,
+            change,
+        })
+    };
+    *b = Balances {
+        total_balance: shift("total_balance", b.total_balance, change.total)?,
+        locked_balance: shift("locked_balance", b.locked_balance, change.locked)?,
+        available_balance: shift("available_balance", b.available_balance, change.available)?,
+    };
+    Ok(())
+}
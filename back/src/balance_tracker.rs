@@ -1,12 +1,12 @@
 use anchor_client::solana_sdk::pubkey::Pubkey;
-use anchor_lang::AccountDeserialize;
+use anchor_lang::{AccountDeserialize, Discriminator};
 use anyhow::Result;
 use collateral_vault::state::CollateralVault;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::db::{BalanceSnapshot, Database};
+use crate::db::{BalanceSnapshot, Database, ReconciliationLog, ReconciliationSource, TvlSummary};
 use crate::vault_manager::VaultManager;
 use chrono::Utc;
 use uuid::Uuid;
@@ -30,6 +30,25 @@ pub struct BalanceAlert {
     pub timestamp: i64,
 }
 
+/// When `check_balance_alerts` raises an alert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertThresholds {
+    /// Available balance (base units) below which a vault is flagged.
+    pub low_balance: u64,
+    /// Locked share of the total balance, in percent, above which a vault is flagged.
+    pub high_locked_ratio_percent: f64,
+}
+
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        Self {
+            low_balance: 1_000_000,
+            high_locked_ratio_percent: 80.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AlertType {
     LowBalance,
@@ -38,11 +57,32 @@ pub enum AlertType {
     Discrepancy,
 }
 
+/// Program-wide totals from one scan of every vault account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TvlSnapshot {
+    /// Slot of the scan; every figure below was observed at it.
+    pub slot: u64,
+    pub total_value_locked: u64,
+    pub total_locked: u64,
+    pub total_available: u64,
+    pub total_vaults: usize,
+    /// Vault accounts left out of the totals because they did not decode as
+    /// the current layout, such as vaults not yet migrated.
+    pub unreadable_vaults: usize,
+    pub observed_at: i64,
+    /// The `tvl_summary` view at the time of the scan, with Postgres.
+    pub database: Option<TvlSummary>,
+    /// Whether `database` agrees with the scan; `None` without Postgres.
+    pub matches_database: Option<bool>,
+}
+
 pub struct BalanceTracker {
     vault_manager: Arc<VaultManager>,
     database: Arc<Database>,
     cached_balances: Arc<RwLock<std::collections::HashMap<String, VaultBalance>>>,
     alerts: Arc<RwLock<Vec<BalanceAlert>>>,
+    thresholds: AlertThresholds,
+    tvl: Arc<RwLock<Option<TvlSnapshot>>>,
 }
 
 impl BalanceTracker {
@@ -52,19 +92,23 @@ impl BalanceTracker {
             database,
             cached_balances: Arc::new(RwLock::new(std::collections::HashMap::new())),
             alerts: Arc::new(RwLock::new(Vec::new())),
+            thresholds: AlertThresholds::default(),
+            tvl: Arc::new(RwLock::new(None)),
         }
     }
 
+    pub fn with_alert_thresholds(mut self, thresholds: AlertThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
     /// Fetch vault balance from on-chain
     pub async fn get_vault_balance(&self, user: Pubkey) -> Result<VaultBalance> {
-        // Derive vault PDA
-        let (vault_pda, _) = Pubkey::find_program_address(
-            &[b"vault", user.as_ref()],
-            &self.vault_manager.program.id(),
-        );
+        // Resolve the vault the user owns now
+        let vault_pda = self.vault_manager.vault_address(&user).await?;
 
         // Fetch account data
-        let account = self.vault_manager.program.rpc().get_account(&vault_pda)?;
+        let account = self.vault_manager.chain.get_account(&vault_pda).await?;
 
         // Deserialize vault data
         let mut data: &[u8] = &account.data;
@@ -89,9 +133,11 @@ impl BalanceTracker {
         let snapshot = BalanceSnapshot {
             id: Uuid::new_v4().to_string(),
             user: balance.owner.clone(),
+            vault: vault_pda.to_string(),
             total_balance: balance.total_balance,
             locked_balance: balance.locked_balance,
             available_balance: balance.available_balance,
+            sequence: Some(vault.sequence),
             timestamp: Utc::now().timestamp(),
         };
         self.database.insert_snapshot(snapshot).await?;
@@ -116,18 +162,96 @@ impl BalanceTracker {
         cache.get(user).cloned()
     }
 
-    /// Calculate total value locked across all vaults
-    pub async fn calculate_tvl(&self) -> u64 {
-        let cache = self.cached_balances.read().await;
-        cache.values().map(|v| v.total_balance).sum()
+    /// Latest program-wide totals, scanning the chain if there are none yet.
+    pub async fn tvl(&self) -> Result<TvlSnapshot> {
+        if let Some(snapshot) = self.tvl.read().await.clone() {
+            return Ok(snapshot);
+        }
+        self.refresh_tvl().await
+    }
+
+    /// Recompute the totals from every `CollateralVault` account and
+    /// cross-check them with the `tvl_summary` view.
+    pub async fn refresh_tvl(&self) -> Result<TvlSnapshot> {
+        let (slot, accounts) = self
+            .vault_manager
+            .chain
+            .get_program_accounts(
+                &self.vault_manager.program_id,
+                CollateralVault::DISCRIMINATOR,
+            )
+            .await?;
+
+        let mut snapshot = TvlSnapshot {
+            slot,
+            total_value_locked: 0,
+            total_locked: 0,
+            total_available: 0,
+            total_vaults: 0,
+            unreadable_vaults: 0,
+            observed_at: Utc::now().timestamp(),
+            database: None,
+            matches_database: None,
+        };
+        let overflow = || anyhow::anyhow!("TVL overflows u64 at slot {}", slot);
+        for (address, account) in accounts {
+            let mut data: &[u8] = &account.data;
+            let vault = match CollateralVault::try_deserialize(&mut data) {
+                Ok(vault) => vault,
+                Err(e) => {
+                    eprintln!("⚠️  Skipping unreadable vault account {}: {}", address, e);
+                    snapshot.unreadable_vaults += 1;
+                    continue;
+                }
+            };
+            snapshot.total_value_locked = snapshot
+                .total_value_locked
+                .checked_add(vault.total_balance)
+                .ok_or_else(overflow)?;
+            snapshot.total_locked = snapshot
+                .total_locked
+                .checked_add(vault.locked_balance)
+                .ok_or_else(overflow)?;
+            snapshot.total_available = snapshot
+                .total_available
+                .checked_add(vault.available_balance)
+                .ok_or_else(overflow)?;
+            snapshot.total_vaults += 1;
+        }
+
+        if let Some(summary) = self.database.get_tvl_summary().await? {
+            let matches = summary
+                == TvlSummary {
+                    total_vaults: snapshot.total_vaults as u64,
+                    total_value_locked: snapshot.total_value_locked,
+                    total_locked: snapshot.total_locked,
+                    total_available: snapshot.total_available,
+                };
+            if !matches {
+                eprintln!(
+                    "⚠️  tvl_summary disagrees with chain at slot {}: {} vaults / {} TVL in the database, {} / {} on chain",
+                    slot,
+                    summary.total_vaults,
+                    summary.total_value_locked,
+                    snapshot.total_vaults,
+                    snapshot.total_value_locked
+                );
+            }
+            snapshot.database = Some(summary);
+            snapshot.matches_database = Some(matches);
+        }
+
+        *self.tvl.write().await = Some(snapshot.clone());
+        Ok(snapshot)
     }
 
     /// Monitor and check for balance alerts
     async fn check_balance_alerts(&self, balance: &VaultBalance) {
         let mut alerts = self.alerts.write().await;
 
-        // Alert 1: Low balance (less than 1000 tokens)
-        if balance.available_balance < 1_000_000 && balance.available_balance > 0 {
+        // Alert 1: Low balance
+        if balance.available_balance < self.thresholds.low_balance && balance.available_balance > 0
+        {
             let timestamp = Utc::now().timestamp();
             let alert = BalanceAlert {
                 vault: balance.owner.clone(),
@@ -150,11 +274,11 @@ impl BalanceTracker {
                 .await;
         }
 
-        // Alert 2: High locked ratio (>80% locked)
+        // Alert 2: High locked ratio
         if balance.total_balance > 0 {
             let locked_ratio =
                 (balance.locked_balance as f64 / balance.total_balance as f64) * 100.0;
-            if locked_ratio > 80.0 {
+            if locked_ratio > self.thresholds.high_locked_ratio_percent {
                 let message = format!("High locked ratio: {:.2}%", locked_ratio);
                 let timestamp = Utc::now().timestamp();
                 let alert = BalanceAlert {
@@ -240,40 +364,44 @@ impl BalanceTracker {
                     .await;
                 let _ = self
                     .database
-                    .log_reconciliation(
-                        user_str.as_str(),
+                    .log_reconciliation(ReconciliationLog::compare(
+                        user_str,
+                        None,
+                        ReconciliationSource::Cache,
                         on_chain.total_balance,
                         cached_balance.total_balance,
-                        "MISMATCH",
-                    )
+                        None,
+                    ))
                     .await;
                 return Ok(false);
             }
         }
 
-        let user_str = user.to_string();
         let _ = self
             .database
-            .log_reconciliation(
-                user_str.as_str(),
+            .log_reconciliation(ReconciliationLog::compare(
+                user.to_string(),
+                None,
+                ReconciliationSource::Cache,
                 on_chain.total_balance,
                 on_chain.total_balance,
-                "MATCH",
-            )
+                None,
+            ))
             .await;
 
         Ok(true)
     }
 
     /// Start background monitoring task
-    pub fn start_monitoring(self: Arc<Self>) {
+    pub fn start_monitoring(self: Arc<Self>, every: tokio::time::Duration) {
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+            let mut interval = tokio::time::interval(every);
             loop {
                 interval.tick().await;
                 // Monitor TVL
-                let tvl = self.calculate_tvl().await;
-                println!("Current TVL: {} tokens", tvl);
+                if let Err(e) = self.refresh_tvl().await {
+                    eprintln!("❌ TVL refresh failed: {}", e);
+                }
 
                 // You can add more monitoring logic here
             }
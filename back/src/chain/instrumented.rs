@@ -0,0 +1,145 @@
+use super::{ChainClient, ProgramLogs, SignatureInfo, SignatureStatus, Simulation, TransactionFee};
+use crate::metrics::Metrics;
+use anchor_client::solana_sdk::{
+    account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::broadcast;
+
+/// Wraps another `ChainClient` and records the latency and errors of every
+/// call under the JSON-RPC method it maps to.
+pub struct InstrumentedChain {
+    inner: Arc<dyn ChainClient>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedChain {
+    pub fn new(inner: Arc<dyn ChainClient>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+
+    async fn observe<T>(
+        &self,
+        method: &'static str,
+        call: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let start = Instant::now();
+        let result = call.await;
+        self.metrics
+            .observe_rpc(method, start.elapsed(), result.is_ok());
+        result
+    }
+}
+
+#[async_trait]
+impl ChainClient for InstrumentedChain {
+    async fn get_account(&self, address: &Pubkey) -> Result<Account> {
+        self.observe("getAccountInfo", self.inner.get_account(address))
+            .await
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+        self.observe("getLatestBlockhash", self.inner.get_latest_blockhash())
+            .await
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool> {
+        self.observe("isBlockhashValid", self.inner.is_blockhash_valid(blockhash))
+            .await
+    }
+
+    async fn simulate_transaction(&self, tx: &Transaction) -> Result<Simulation> {
+        self.observe("simulateTransaction", self.inner.simulate_transaction(tx))
+            .await
+    }
+
+    async fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        self.observe("sendTransaction", self.inner.send_transaction(tx))
+            .await
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<std::result::Result<(), String>>> {
+        self.observe(
+            "getSignatureStatuses",
+            self.inner.get_signature_status(signature),
+        )
+        .await
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<SignatureStatus>>> {
+        self.observe(
+            "getSignatureStatuses",
+            self.inner.get_signature_statuses(signatures),
+        )
+        .await
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        discriminator: &[u8],
+    ) -> Result<(u64, Vec<(Pubkey, Account)>)> {
+        self.observe(
+            "getProgramAccounts",
+            self.inner.get_program_accounts(program_id, discriminator),
+        )
+        .await
+    }
+
+    async fn get_block_time(&self, slot: u64) -> Result<Option<i64>> {
+        self.observe("getBlockTime", self.inner.get_block_time(slot))
+            .await
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        self.observe("getSlot", self.inner.get_slot()).await
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        self.observe(
+            "getSignaturesForAddress",
+            self.inner
+                .get_signatures_for_address(address, before, until, limit),
+        )
+        .await
+    }
+
+    async fn get_transaction_logs(&self, signature: &Signature) -> Result<Option<ProgramLogs>> {
+        self.observe("getTransaction", self.inner.get_transaction_logs(signature))
+            .await
+    }
+
+    async fn get_transaction_fee(&self, signature: &Signature) -> Result<Option<TransactionFee>> {
+        self.observe("getTransaction", self.inner.get_transaction_fee(signature))
+            .await
+    }
+
+    async fn get_recent_prioritization_fees(&self, accounts: &[Pubkey]) -> Result<Vec<u64>> {
+        self.observe(
+            "getRecentPrioritizationFees",
+            self.inner.get_recent_prioritization_fees(accounts),
+        )
+        .await
+    }
+
+    async fn subscribe_logs(&self, program_id: Pubkey) -> Result<broadcast::Receiver<ProgramLogs>> {
+        self.observe("logsSubscribe", self.inner.subscribe_logs(program_id))
+            .await
+    }
+}
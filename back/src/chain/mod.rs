@@ -0,0 +1,175 @@
+pub mod instrumented;
+pub mod rpc;
+pub mod simulated;
+
+use anchor_client::solana_sdk::{
+    account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use solana_nonce::{state::State as NonceState, versions::Versions as NonceVersions};
+use solana_system_interface::instruction::SystemInstruction;
+use tokio::sync::broadcast;
+
+pub use instrumented::InstrumentedChain;
+pub use rpc::RpcChainClient;
+pub use simulated::SimulatedChain;
+
+/// Logs of one transaction that mentioned a subscribed program.
+#[derive(Debug, Clone)]
+pub struct ProgramLogs {
+    pub signature: String,
+    pub slot: u64,
+    pub err: Option<String>,
+    pub logs: Vec<String>,
+}
+
+/// One entry of `getSignaturesForAddress`.
+#[derive(Debug, Clone)]
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    pub err: Option<String>,
+    pub block_time: Option<i64>,
+}
+
+/// Who paid for a landed transaction, and how much.
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionFee {
+    pub payer: Pubkey,
+    pub lamports: u64,
+}
+
+/// How deeply a landed transaction is confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+/// Where a landed transaction stands.
+#[derive(Debug, Clone)]
+pub struct SignatureStatus {
+    pub slot: u64,
+    pub commitment: Commitment,
+    pub err: Option<String>,
+}
+
+/// Result of simulating a transaction against the current bank.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub err: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+}
+
+/// Everything the backend needs from a Solana cluster. `RpcChainClient` talks
+/// to a real RPC node; `SimulatedChain` runs the vault program in memory.
+#[async_trait]
+pub trait ChainClient: Send + Sync {
+    /// Fetch an account; errors if it does not exist.
+    async fn get_account(&self, address: &Pubkey) -> Result<Account>;
+
+    /// Latest blockhash and the last block height at which it is valid.
+    async fn get_latest_blockhash(&self) -> Result<(Hash, u64)>;
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool>;
+
+    async fn simulate_transaction(&self, tx: &Transaction) -> Result<Simulation>;
+
+    /// Submit a signed transaction (with preflight) without waiting for confirmation.
+    async fn send_transaction(&self, tx: &Transaction) -> Result<Signature>;
+
+    /// `None` while unknown, otherwise whether the landed transaction succeeded.
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<std::result::Result<(), String>>>;
+
+    /// Statuses of up to 256 signatures, searching the node's full history.
+    /// `None` for signatures the node has not seen.
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<SignatureStatus>>>;
+
+    /// Every account owned by `program_id` whose data starts with
+    /// `discriminator`, and the slot they were read at.
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        discriminator: &[u8],
+    ) -> Result<(u64, Vec<(Pubkey, Account)>)>;
+
+    /// Estimated production time of `slot`, if the node still has it.
+    async fn get_block_time(&self, slot: u64) -> Result<Option<i64>>;
+
+    /// The slot the node has processed up to.
+    async fn get_slot(&self) -> Result<u64>;
+
+    /// Confirmed transactions that touched `address`, newest first. Starts
+    /// below `before` and stops above `until` (both exclusive) when given.
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>>;
+
+    /// Logs of a confirmed transaction, or `None` if the node does not have it.
+    async fn get_transaction_logs(&self, signature: &Signature) -> Result<Option<ProgramLogs>>;
+
+    /// Fee payer and fee of a confirmed transaction, or `None` if the node
+    /// does not have it.
+    async fn get_transaction_fee(&self, signature: &Signature) -> Result<Option<TransactionFee>>;
+
+    /// Priority fees (micro-lamports per CU) recently paid to write-lock `accounts`.
+    async fn get_recent_prioritization_fees(&self, accounts: &[Pubkey]) -> Result<Vec<u64>>;
+
+    /// Stream the logs of every confirmed transaction that mentions `program_id`.
+    async fn subscribe_logs(&self, program_id: Pubkey) -> Result<broadcast::Receiver<ProgramLogs>>;
+
+    /// Whether `tx` can still land: its blockhash is valid or, for a durable
+    /// nonce transaction, the nonce account still holds it.
+    async fn is_transaction_live(&self, tx: &Transaction) -> Result<bool> {
+        let Some(nonce) = durable_nonce(tx) else {
+            return self.is_blockhash_valid(&tx.message.recent_blockhash).await;
+        };
+        Ok(match self.get_account(&nonce).await {
+            Ok(account) => nonce_hash(&account) == Some(tx.message.recent_blockhash),
+            Err(_) => false,
+        })
+    }
+}
+
+/// The nonce account a durable nonce transaction advances, if `tx` is one:
+/// its first instruction is the system program's `AdvanceNonceAccount`.
+pub fn durable_nonce(tx: &Transaction) -> Option<Pubkey> {
+    let message = &tx.message;
+    let first = message.instructions.first()?;
+    if message.account_keys.get(first.program_id_index as usize)
+        != Some(&solana_system_interface::program::ID)
+    {
+        return None;
+    }
+    match bincode::deserialize(&first.data) {
+        Ok(SystemInstruction::AdvanceNonceAccount) => message
+            .account_keys
+            .get(*first.accounts.first()? as usize)
+            .copied(),
+        _ => None,
+    }
+}
+
+/// The value an initialized nonce account currently holds.
+pub fn nonce_hash(account: &Account) -> Option<Hash> {
+    match bincode::deserialize::<NonceVersions>(&account.data).ok()? {
+        NonceVersions::Current(state) => match *state {
+            NonceState::Initialized(data) => Some(data.blockhash()),
+            NonceState::Uninitialized => None,
+        },
+        NonceVersions::Legacy(_) => None,
+    }
+}
@@ -0,0 +1,309 @@
+use super::{
+    ChainClient, Commitment, ProgramLogs, SignatureInfo, SignatureStatus, Simulation,
+    TransactionFee,
+};
+use anchor_client::solana_account_decoder::UiAccountEncoding;
+use anchor_client::solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, hash::Hash, pubkey::Pubkey,
+    signature::Signature, transaction::Transaction,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::json;
+use solana_client::nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig, RpcTransactionLogsConfig,
+    RpcTransactionLogsFilter,
+};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionConfirmationStatus, UiTransactionEncoding,
+};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// `ChainClient` backed by a JSON-RPC node (HTTP for requests, websocket for logs).
+pub struct RpcChainClient {
+    rpc: Arc<RpcClient>,
+    ws_url: String,
+}
+
+impl RpcChainClient {
+    pub fn new(rpc_url: String, commitment: CommitmentConfig) -> Self {
+        let ws_url = websocket_url(&rpc_url);
+        Self {
+            rpc: Arc::new(RpcClient::new_with_commitment(rpc_url, commitment)),
+            ws_url,
+        }
+    }
+
+    pub fn rpc(&self) -> &Arc<RpcClient> {
+        &self.rpc
+    }
+
+    async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<EncodedConfirmedTransactionWithStatusMeta>> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(self.rpc.commitment()),
+            max_supported_transaction_version: Some(0),
+        };
+        // `getTransaction` answers null for transactions the node does not have.
+        Ok(self
+            .rpc
+            .send(
+                RpcRequest::GetTransaction,
+                json!([signature.to_string(), config]),
+            )
+            .await?)
+    }
+}
+
+/// The websocket endpoint lives on the same host as the HTTP one.
+fn websocket_url(rpc_url: &str) -> String {
+    if let Some(rest) = rpc_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        rpc_url.to_string()
+    }
+}
+
+#[async_trait]
+impl ChainClient for RpcChainClient {
+    async fn get_account(&self, address: &Pubkey) -> Result<Account> {
+        Ok(self.rpc.get_account(address).await?)
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+        Ok(self
+            .rpc
+            .get_latest_blockhash_with_commitment(self.rpc.commitment())
+            .await?)
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool> {
+        Ok(self
+            .rpc
+            .is_blockhash_valid(blockhash, self.rpc.commitment())
+            .await?)
+    }
+
+    async fn simulate_transaction(&self, tx: &Transaction) -> Result<Simulation> {
+        let result = self.rpc.simulate_transaction(tx).await?.value;
+        Ok(Simulation {
+            err: result.err.map(|e| e.to_string()),
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed,
+        })
+    }
+
+    async fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        Ok(self.rpc.send_transaction(tx).await?)
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<std::result::Result<(), String>>> {
+        Ok(self
+            .rpc
+            .get_signature_status(signature)
+            .await?
+            .map(|status| status.map_err(|e| e.to_string())))
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<SignatureStatus>>> {
+        Ok(self
+            .rpc
+            .get_signature_statuses_with_history(signatures)
+            .await?
+            .value
+            .into_iter()
+            .map(|status| {
+                status.map(|status| SignatureStatus {
+                    slot: status.slot,
+                    commitment: match status.confirmation_status {
+                        Some(TransactionConfirmationStatus::Processed) => Commitment::Processed,
+                        Some(TransactionConfirmationStatus::Confirmed) => Commitment::Confirmed,
+                        // Older nodes leave it out; no confirmation count means rooted.
+                        Some(TransactionConfirmationStatus::Finalized) => Commitment::Finalized,
+                        None if status.confirmations.is_none() => Commitment::Finalized,
+                        None => Commitment::Confirmed,
+                    },
+                    err: status.err.map(|e| e.to_string()),
+                })
+            })
+            .collect())
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        discriminator: &[u8],
+    ) -> Result<(u64, Vec<(Pubkey, Account)>)> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                0,
+                discriminator,
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.rpc.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            // The context carries the slot the scan was served at.
+            with_context: Some(true),
+            sort_results: None,
+        };
+        let response: Response<Vec<RpcKeyedAccount>> = self
+            .rpc
+            .send(
+                RpcRequest::GetProgramAccounts,
+                json!([program_id.to_string(), config]),
+            )
+            .await?;
+
+        let accounts = response
+            .value
+            .into_iter()
+            .map(|keyed| {
+                let account = keyed
+                    .account
+                    .decode::<Account>()
+                    .ok_or_else(|| anyhow!("undecodable account {}", keyed.pubkey))?;
+                Ok((keyed.pubkey.parse()?, account))
+            })
+            .collect::<Result<_>>()?;
+        Ok((response.context.slot, accounts))
+    }
+
+    async fn get_block_time(&self, slot: u64) -> Result<Option<i64>> {
+        // Recent blocks may not be available yet; callers treat that as unknown.
+        Ok(self.rpc.get_block_time(slot).await.ok())
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        Ok(self.rpc.get_slot().await?)
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before,
+            until,
+            limit: Some(limit),
+            commitment: Some(self.rpc.commitment()),
+        };
+        Ok(self
+            .rpc
+            .get_signatures_for_address_with_config(address, config)
+            .await?
+            .into_iter()
+            .map(|status| SignatureInfo {
+                signature: status.signature,
+                slot: status.slot,
+                err: status.err.map(|e| e.to_string()),
+                block_time: status.block_time,
+            })
+            .collect())
+    }
+
+    async fn get_transaction_logs(&self, signature: &Signature) -> Result<Option<ProgramLogs>> {
+        let Some(tx) = self.get_transaction(signature).await? else {
+            return Ok(None);
+        };
+        let Some(meta) = tx.transaction.meta else {
+            return Ok(None);
+        };
+        Ok(Some(ProgramLogs {
+            signature: signature.to_string(),
+            slot: tx.slot,
+            err: meta.err.map(|e| e.to_string()),
+            logs: Option::from(meta.log_messages).unwrap_or_default(),
+        }))
+    }
+
+    async fn get_transaction_fee(&self, signature: &Signature) -> Result<Option<TransactionFee>> {
+        let Some(tx) = self.get_transaction(signature).await? else {
+            return Ok(None);
+        };
+        let (Some(meta), Some(decoded)) =
+            (tx.transaction.meta, tx.transaction.transaction.decode())
+        else {
+            return Ok(None);
+        };
+        Ok(decoded
+            .message
+            .static_account_keys()
+            .first()
+            .map(|payer| TransactionFee {
+                payer: *payer,
+                lamports: meta.fee,
+            }))
+    }
+
+    async fn get_recent_prioritization_fees(&self, accounts: &[Pubkey]) -> Result<Vec<u64>> {
+        Ok(self
+            .rpc
+            .get_recent_prioritization_fees(accounts)
+            .await?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect())
+    }
+
+    async fn subscribe_logs(&self, program_id: Pubkey) -> Result<broadcast::Receiver<ProgramLogs>> {
+        let client = PubsubClient::new(&self.ws_url).await?;
+        let commitment = self.rpc.commitment();
+        let (sender, receiver) = broadcast::channel(1024);
+
+        tokio::spawn(async move {
+            let subscription = client
+                .logs_subscribe(
+                    RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+                    RpcTransactionLogsConfig {
+                        commitment: Some(commitment),
+                    },
+                )
+                .await;
+            let (mut stream, _unsubscribe) = match subscription {
+                Ok(sub) => sub,
+                Err(e) => {
+                    eprintln!("logsSubscribe failed: {}", e);
+                    return;
+                }
+            };
+
+            while let Some(response) = stream.next().await {
+                let logs = ProgramLogs {
+                    signature: response.value.signature,
+                    slot: response.context.slot,
+                    err: response.value.err.map(|e| e.to_string()),
+                    logs: response.value.logs,
+                };
+                if sender.send(logs).is_err() {
+                    // Every receiver is gone.
+                    break;
+                }
+            }
+        });
+
+        Ok(receiver)
+    }
+}
@@ -0,0 +1,2421 @@
+//! In-memory chain that runs the `collateral_vault` instructions the backend
+//! builds, so handlers and services can be exercised without a validator.
+//!
+//! The model follows the program's account constraints and handler checks in
+//! the same order and returns the same `ErrorCode`s. SPL token balances are
+//! tracked and compute units are charged at a flat rate per instruction;
+//! lamports and rent are not modelled. Of the system program only the
+//! instructions behind durable nonces are run.
+
+use super::{
+    durable_nonce, nonce_hash, ChainClient, Commitment, ProgramLogs, SignatureInfo,
+    SignatureStatus, Simulation, TransactionFee,
+};
+use anchor_client::solana_sdk::{
+    account::Account, hash::Hash, instruction::Instruction, pubkey::Pubkey, rent::Rent,
+    signature::Signature, transaction::Transaction,
+};
+use anchor_lang::error::{Error as AnchorError, ErrorCode as AnchorErrorCode};
+use anchor_lang::{
+    require, AccountDeserialize, AccountSerialize, AnchorDeserialize, Discriminator, Event, Owner,
+};
+use anchor_spl::token::spl_token::{
+    self,
+    solana_program::{program_option::COption, program_pack::Pack},
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use collateral_vault::errors::ErrorCode;
+use collateral_vault::events::*;
+use collateral_vault::instruction as ix;
+use collateral_vault::instructions::forced_unlock::{
+    FORCED_UNLOCK_DELAY_SECONDS, FORCED_UNLOCK_INACTIVITY_SECONDS,
+};
+use collateral_vault::instructions::recovery::{validate_guardians, RECOVERY_DELAY_SECONDS};
+use collateral_vault::instructions::security::WITHDRAWAL_DELAY_SECONDS;
+use collateral_vault::state::{
+    CollateralVault, ForcedUnlockRequest, LegacyCollateralVault, MultisigConfig, RecoveryConfig,
+    RecoveryRequest, SettlementBucket, VaultAuthority, WithdrawalRequest,
+};
+use solana_compute_budget_interface as compute_budget;
+use solana_nonce::{
+    state::{Data as NonceData, DurableNonce, State as NonceState},
+    versions::Versions as NonceVersions,
+};
+use solana_system_interface::instruction::SystemInstruction;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Blockhashes stay valid for this many slots, as on mainnet.
+const BLOCKHASH_VALIDITY: u64 = 150;
+/// A landed transaction is reported finalized once it is this many slots deep.
+const FINALITY_DEPTH: u64 = 32;
+/// Base fee per signature, as on mainnet.
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Compute units charged per vault instruction (the real program's usage is
+/// in this range) and per compute budget instruction.
+const UNITS_PER_INSTRUCTION: u64 = 25_000;
+const COMPUTE_BUDGET_UNITS: u64 = 150;
+const SYSTEM_UNITS: u64 = 150;
+/// Runtime defaults when a transaction sets no compute unit limit.
+const DEFAULT_UNITS_PER_INSTRUCTION: u64 = 200_000;
+const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+
+type ProgramResult<T = ()> = std::result::Result<T, AnchorError>;
+
+struct ChainState {
+    accounts: HashMap<Pubkey, Account>,
+    slot: u64,
+    unix_timestamp: i64,
+    recent_blockhashes: VecDeque<Hash>,
+    /// Landing slot and result of every processed transaction.
+    statuses: HashMap<Signature, (u64, std::result::Result<(), String>)>,
+    prioritization_fees: Vec<u64>,
+    block_times: HashMap<u64, i64>,
+    /// Landed transactions in order, with the accounts each one referenced.
+    history: Vec<(ProgramLogs, Vec<Pubkey>)>,
+    /// What each landed transaction was charged. Fees are recorded, not
+    /// debited.
+    fees: HashMap<Signature, TransactionFee>,
+    /// Status and blockhash lookups still to fail, like an overloaded node.
+    failing_lookups: u32,
+    /// Landed transactions whose details the node no longer serves.
+    pruned: HashSet<Signature>,
+}
+
+impl ChainState {
+    /// The transaction's blockhash is recent, or it is the value its durable
+    /// nonce account holds.
+    fn is_live(&self, tx: &Transaction) -> bool {
+        let blockhash = &tx.message.recent_blockhash;
+        if self.recent_blockhashes.contains(blockhash) {
+            return true;
+        }
+        durable_nonce(tx)
+            .and_then(|nonce| self.accounts.get(&nonce))
+            .and_then(nonce_hash)
+            .is_some_and(|hash| hash == *blockhash)
+    }
+
+    fn lookup(&mut self) -> Result<()> {
+        if self.failing_lookups == 0 {
+            return Ok(());
+        }
+        self.failing_lookups -= 1;
+        Err(anyhow!("Simulated RPC error: request timed out"))
+    }
+}
+
+pub struct SimulatedChain {
+    program_id: Pubkey,
+    state: Mutex<ChainState>,
+    logs: broadcast::Sender<ProgramLogs>,
+}
+
+impl Default for SimulatedChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedChain {
+    pub fn new() -> Self {
+        let (logs, _) = broadcast::channel(1024);
+        let chain = Self {
+            program_id: collateral_vault::ID,
+            state: Mutex::new(ChainState {
+                accounts: HashMap::new(),
+                slot: 1,
+                unix_timestamp: chrono::Utc::now().timestamp(),
+                recent_blockhashes: VecDeque::from([Hash::new_unique()]),
+                statuses: HashMap::new(),
+                prioritization_fees: Vec::new(),
+                block_times: HashMap::new(),
+                history: Vec::new(),
+                fees: HashMap::new(),
+                failing_lookups: 0,
+                pruned: HashSet::new(),
+            }),
+            logs,
+        };
+        chain.add_program(collateral_vault::ID);
+        chain
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    /// Deploy an (empty) executable account, e.g. an authorized position program.
+    pub fn add_program(&self, program_id: Pubkey) {
+        self.set_account(
+            program_id,
+            Account {
+                lamports: 1,
+                data: Vec::new(),
+                owner: anchor_client::solana_sdk::bpf_loader_upgradeable::ID,
+                executable: true,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    pub fn set_account(&self, address: Pubkey, account: Account) {
+        self.state.lock().unwrap().accounts.insert(address, account);
+    }
+
+    pub fn create_mint(&self, mint: Pubkey, decimals: u8) {
+        let mut data = vec![0u8; Mint::LEN];
+        Mint::pack(
+            Mint {
+                mint_authority: COption::None,
+                supply: 0,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            &mut data,
+        )
+        .expect("mint packs");
+        self.set_account(mint, token_program_account(data));
+    }
+
+    /// Credit `amount` to `owner`'s associated token account, creating it if needed.
+    pub fn mint_to(&self, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+        let address = anchor_spl::associated_token::get_associated_token_address(owner, mint);
+        let mut state = self.state.lock().unwrap();
+        let mut token = state
+            .accounts
+            .get(&address)
+            .and_then(|a| TokenAccount::unpack(&a.data).ok())
+            .unwrap_or(TokenAccount {
+                mint: *mint,
+                owner: *owner,
+                state: AccountState::Initialized,
+                ..Default::default()
+            });
+        token.amount += amount;
+
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(token, &mut data).expect("token account packs");
+        state.accounts.insert(address, token_program_account(data));
+        address
+    }
+
+    pub fn token_balance(&self, address: &Pubkey) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        let account = state.accounts.get(address)?;
+        TokenAccount::unpack(&account.data).ok().map(|t| t.amount)
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.state.lock().unwrap().slot
+    }
+
+    pub fn unix_timestamp(&self) -> i64 {
+        self.state.lock().unwrap().unix_timestamp
+    }
+
+    /// Move the clock forward, e.g. past a withdrawal delay.
+    pub fn advance_clock(&self, seconds: i64) {
+        self.state.lock().unwrap().unix_timestamp += seconds;
+    }
+
+    /// Run `tx` against a copy of the accounts. Nothing is committed here.
+    fn execute(&self, state: &ChainState, tx: &Transaction) -> Execution {
+        let message = &tx.message;
+        let mut execution = Execution {
+            accounts: state.accounts.clone(),
+            logs: Vec::new(),
+            units_consumed: 0,
+            fee: 0,
+            err: None,
+        };
+
+        // The compute budget program is applied before anything else runs.
+        let mut unit_limit = None;
+        let mut unit_price = 0u64;
+        let mut vault_instructions = 0;
+        for (index, compiled) in message.instructions.iter().enumerate() {
+            let program_id = message.account_keys[compiled.program_id_index as usize];
+            if program_id != compute_budget::ID {
+                vault_instructions += 1;
+                continue;
+            }
+            match compiled.data.split_first() {
+                Some((2, units)) if units.len() == 4 => {
+                    unit_limit = Some(u32::from_le_bytes(units.try_into().unwrap()) as u64)
+                }
+                Some((3, price)) if price.len() == 8 => {
+                    unit_price = u64::from_le_bytes(price.try_into().unwrap())
+                }
+                _ => {
+                    execution.err = Some(format!(
+                        "Error processing Instruction {}: invalid instruction data",
+                        index
+                    ));
+                    return execution;
+                }
+            }
+        }
+        let unit_limit = unit_limit
+            .unwrap_or(DEFAULT_UNITS_PER_INSTRUCTION * vault_instructions)
+            .min(MAX_COMPUTE_UNITS);
+        // Priority fee is micro-lamports per requested unit, rounded up.
+        let priority = (unit_price as u128 * unit_limit as u128).div_ceil(1_000_000) as u64;
+        execution.fee =
+            LAMPORTS_PER_SIGNATURE * message.header.num_required_signatures as u64 + priority;
+
+        for (index, compiled) in message.instructions.iter().enumerate() {
+            let program_id = message.account_keys[compiled.program_id_index as usize];
+            if program_id == compute_budget::ID {
+                execution.units_consumed += COMPUTE_BUDGET_UNITS;
+                continue;
+            }
+
+            let metas: Vec<(Pubkey, bool)> = compiled
+                .accounts
+                .iter()
+                .map(|&i| {
+                    (
+                        message.account_keys[i as usize],
+                        message.is_signer(i as usize),
+                    )
+                })
+                .collect();
+
+            execution
+                .logs
+                .push(format!("Program {} invoke [1]", program_id));
+            if program_id == solana_system_interface::program::ID {
+                let latest = state
+                    .recent_blockhashes
+                    .back()
+                    .expect("always one blockhash");
+                execution.units_consumed += SYSTEM_UNITS;
+                match run_system_instruction(
+                    &mut execution.accounts,
+                    &metas,
+                    &compiled.data,
+                    latest,
+                ) {
+                    Ok(()) => execution
+                        .logs
+                        .push(format!("Program {} success", program_id)),
+                    Err(error) => {
+                        execution
+                            .logs
+                            .push(format!("Program {} failed: {}", program_id, error));
+                        execution.err =
+                            Some(format!("Error processing Instruction {}: {}", index, error));
+                        return execution;
+                    }
+                }
+                continue;
+            }
+            if program_id != self.program_id {
+                execution.err = Some(format!(
+                    "Error processing Instruction {}: program {} is not loaded in the simulated chain",
+                    index, program_id
+                ));
+                return execution;
+            }
+
+            let remaining = unit_limit.saturating_sub(execution.units_consumed);
+            if remaining < UNITS_PER_INSTRUCTION {
+                execution.units_consumed = unit_limit;
+                execution.logs.push(format!(
+                    "Program {} failed: exceeded CUs meter at BPF instruction",
+                    program_id
+                ));
+                execution.err = Some(format!(
+                    "Error processing Instruction {}: Computational budget exceeded",
+                    index
+                ));
+                return execution;
+            }
+            execution.units_consumed += UNITS_PER_INSTRUCTION;
+
+            let mut ctx = Context {
+                program_id: self.program_id,
+                accounts: &mut execution.accounts,
+                metas: &metas,
+                now: state.unix_timestamp,
+                logs: &mut execution.logs,
+            };
+            let result = ctx.execute(&compiled.data);
+            execution.logs.push(format!(
+                "Program {} consumed {} of {} compute units",
+                program_id, UNITS_PER_INSTRUCTION, remaining
+            ));
+            match result {
+                Ok(()) => execution
+                    .logs
+                    .push(format!("Program {} success", program_id)),
+                Err(e) => {
+                    let error = describe_error(e);
+                    execution
+                        .logs
+                        .push(format!("Program {} failed: {}", program_id, error));
+                    execution.err =
+                        Some(format!("Error processing Instruction {}: {}", index, error));
+                    return execution;
+                }
+            }
+        }
+
+        execution
+    }
+
+    /// Execute and commit `tx` atomically, as `sendTransaction` with preflight
+    /// would: a failing transaction is rejected and never lands.
+    fn process(&self, tx: &Transaction) -> Result<Option<ProgramLogs>> {
+        tx.verify()
+            .map_err(|e| anyhow!("Transaction signature verification failure: {}", e))?;
+        let signature = tx.signatures[0];
+
+        let mut state = self.state.lock().unwrap();
+        if state.statuses.contains_key(&signature) {
+            // A resend of a transaction that already landed is a no-op.
+            return Ok(None);
+        }
+        if !state.is_live(tx) {
+            return Err(anyhow!("Blockhash not found"));
+        }
+
+        let execution = self.execute(&state, tx);
+        if let Some(err) = execution.err {
+            return Err(anyhow!("Transaction simulation failed: {}", err));
+        }
+        Ok(Some(Self::commit(
+            &mut state,
+            signature,
+            execution,
+            tx.message.account_keys.clone(),
+        )))
+    }
+
+    /// Land a successful execution in the next slot.
+    fn commit(
+        state: &mut ChainState,
+        signature: Signature,
+        execution: Execution,
+        account_keys: Vec<Pubkey>,
+    ) -> ProgramLogs {
+        state.accounts = execution.accounts;
+        state.slot += 1;
+        let (slot, now) = (state.slot, state.unix_timestamp);
+        state.statuses.insert(signature, (slot, Ok(())));
+        state.block_times.insert(slot, now);
+        state.fees.insert(
+            signature,
+            TransactionFee {
+                payer: account_keys[0],
+                lamports: execution.fee,
+            },
+        );
+        state.recent_blockhashes.push_back(Hash::new_unique());
+        if state.recent_blockhashes.len() as u64 > BLOCKHASH_VALIDITY {
+            state.recent_blockhashes.pop_front();
+        }
+
+        let logs = ProgramLogs {
+            signature: signature.to_string(),
+            slot: state.slot,
+            err: None,
+            logs: execution.logs,
+        };
+        state.history.push((logs.clone(), account_keys));
+        logs
+    }
+
+    /// Run `instruction` the way an authorized position program would
+    /// through CPI: `program` is the outer program and signs for its
+    /// `VaultAuthority::CALLER_SEED` PDA. No other signatures are available.
+    /// Lands and broadcasts like a transaction.
+    pub fn invoke_from_program(
+        &self,
+        program: Pubkey,
+        instruction: &Instruction,
+    ) -> Result<ProgramLogs> {
+        let caller = Pubkey::find_program_address(&[VaultAuthority::CALLER_SEED], &program).0;
+        let mut state = self.state.lock().unwrap();
+        if !state.accounts.get(&program).is_some_and(|a| a.executable) {
+            return Err(anyhow!(
+                "program {} is not loaded in the simulated chain",
+                program
+            ));
+        }
+        if instruction.program_id != self.program_id {
+            return Err(anyhow!("only the vault program can be invoked"));
+        }
+        let metas: Vec<(Pubkey, bool)> = instruction
+            .accounts
+            .iter()
+            .map(|meta| (meta.pubkey, meta.is_signer && meta.pubkey == caller))
+            .collect();
+
+        let mut execution = Execution {
+            accounts: state.accounts.clone(),
+            logs: vec![
+                format!("Program {} invoke [1]", program),
+                format!("Program {} invoke [2]", self.program_id),
+            ],
+            units_consumed: UNITS_PER_INSTRUCTION,
+            fee: 0,
+            err: None,
+        };
+        let mut ctx = Context {
+            program_id: self.program_id,
+            accounts: &mut execution.accounts,
+            metas: &metas,
+            now: state.unix_timestamp,
+            logs: &mut execution.logs,
+        };
+        if let Err(e) = ctx.execute(&instruction.data) {
+            return Err(anyhow!(
+                "Transaction simulation failed: Error processing Instruction 0: {}",
+                describe_error(e)
+            ));
+        }
+        execution
+            .logs
+            .push(format!("Program {} success", self.program_id));
+        execution.logs.push(format!("Program {} success", program));
+
+        let mut account_keys = vec![program];
+        account_keys.extend(instruction.accounts.iter().map(|meta| meta.pubkey));
+        let logs = Self::commit(&mut state, Signature::new_unique(), execution, account_keys);
+        let _ = self.logs.send(logs.clone());
+        Ok(logs)
+    }
+
+    /// Produce `slots` empty slots, e.g. to let landed transactions finalize.
+    pub fn advance_slots(&self, slots: u64) {
+        self.state.lock().unwrap().slot += slots;
+    }
+
+    /// Expire every outstanding blockhash, as if the network stalled for
+    /// longer than a blockhash lifetime.
+    pub fn expire_blockhashes(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.slot += BLOCKHASH_VALIDITY;
+        state.recent_blockhashes = VecDeque::from([Hash::new_unique()]);
+    }
+
+    /// Fail the next `count` signature status and blockhash validity lookups.
+    pub fn fail_lookups(&self, count: u32) {
+        self.state.lock().unwrap().failing_lookups = count;
+    }
+
+    /// Forget the logs and fees of these landed transactions, as a node with
+    /// pruned history does. They are still listed by address. An empty list
+    /// restores everything.
+    pub fn prune_transactions(&self, signatures: &[Signature]) {
+        self.state.lock().unwrap().pruned = signatures.iter().copied().collect();
+    }
+
+    /// Set the fees `get_recent_prioritization_fees` reports.
+    pub fn set_prioritization_fees(&self, fees: Vec<u64>) {
+        self.state.lock().unwrap().prioritization_fees = fees;
+    }
+}
+
+struct Execution {
+    accounts: HashMap<Pubkey, Account>,
+    logs: Vec<String>,
+    units_consumed: u64,
+    /// Lamports the fee payer is charged.
+    fee: u64,
+    err: Option<String>,
+}
+
+/// The system program instructions that create, advance and close a durable
+/// nonce account. Lamports are not moved.
+fn run_system_instruction(
+    accounts: &mut HashMap<Pubkey, Account>,
+    metas: &[(Pubkey, bool)],
+    data: &[u8],
+    latest_blockhash: &Hash,
+) -> std::result::Result<(), String> {
+    let key = |index: usize| {
+        metas
+            .get(index)
+            .map(|(key, _)| *key)
+            .ok_or_else(|| "insufficient account keys for instruction".to_string())
+    };
+    let signed = |key: &Pubkey| metas.iter().any(|(k, signer)| k == key && *signer);
+    let load_nonce = |accounts: &HashMap<Pubkey, Account>, nonce: &Pubkey| {
+        accounts
+            .get(nonce)
+            .filter(|a| a.owner == solana_system_interface::program::ID)
+            .and_then(|a| bincode::deserialize::<NonceVersions>(&a.data).ok())
+            .ok_or_else(|| "invalid account data for instruction".to_string())
+    };
+    let store_nonce = |accounts: &mut HashMap<Pubkey, Account>, nonce: &Pubkey, data| {
+        let account = accounts.get_mut(nonce).expect("nonce account loaded");
+        let encoded = bincode::serialize(&NonceVersions::new(NonceState::Initialized(data)))
+            .expect("nonce state serializes");
+        account.data[..encoded.len()].copy_from_slice(&encoded);
+    };
+    let next_nonce = DurableNonce::from_blockhash(latest_blockhash);
+
+    match bincode::deserialize(data).map_err(|_| "invalid instruction data".to_string())? {
+        SystemInstruction::CreateAccountWithSeed {
+            base,
+            seed,
+            lamports,
+            space,
+            owner,
+        } => {
+            let address = key(1)?;
+            let expected = Pubkey::create_with_seed(&base, &seed, &owner)
+                .map_err(|e| format!("invalid seed: {}", e))?;
+            if address != expected || !signed(&key(0)?) || !signed(&base) {
+                return Err("missing required signature for instruction".to_string());
+            }
+            if accounts.contains_key(&address) {
+                return Err("account already in use".to_string());
+            }
+            accounts.insert(
+                address,
+                Account {
+                    lamports,
+                    data: vec![0; space as usize],
+                    owner,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            );
+            Ok(())
+        }
+        SystemInstruction::InitializeNonceAccount(authority) => {
+            let nonce = key(0)?;
+            if *load_nonce(accounts, &nonce)?.state() != NonceState::Uninitialized {
+                return Err("account already initialized".to_string());
+            }
+            store_nonce(
+                accounts,
+                &nonce,
+                NonceData::new(authority, next_nonce, 5_000),
+            );
+            Ok(())
+        }
+        SystemInstruction::AdvanceNonceAccount => {
+            let nonce = key(0)?;
+            let NonceState::Initialized(data) = load_nonce(accounts, &nonce)?.state().clone()
+            else {
+                return Err("invalid account data for instruction".to_string());
+            };
+            if !signed(&data.authority) {
+                return Err("missing required signature for instruction".to_string());
+            }
+            if data.durable_nonce == next_nonce {
+                return Err("nonce blockhash not expired".to_string());
+            }
+            store_nonce(
+                accounts,
+                &nonce,
+                NonceData::new(data.authority, next_nonce, 5_000),
+            );
+            Ok(())
+        }
+        SystemInstruction::WithdrawNonceAccount(_) => {
+            let nonce = key(0)?;
+            let authorized = match load_nonce(accounts, &nonce)?.state() {
+                NonceState::Initialized(data) => signed(&data.authority),
+                NonceState::Uninitialized => signed(&nonce),
+            };
+            if !authorized {
+                return Err("missing required signature for instruction".to_string());
+            }
+            accounts.remove(&nonce);
+            Ok(())
+        }
+        _ => Err("system instruction is not supported by the simulated chain".to_string()),
+    }
+}
+
+fn token_program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Render an error the way the RPC reports a failed instruction.
+fn describe_error(error: AnchorError) -> String {
+    match error {
+        AnchorError::AnchorError(e) => format!(
+            "custom program error: {:#x} ({}: {})",
+            e.error_code_number, e.error_name, e.error_msg
+        ),
+        AnchorError::ProgramError(e) => e.program_error.to_string(),
+    }
+}
+
+#[async_trait]
+impl ChainClient for SimulatedChain {
+    async fn get_account(&self, address: &Pubkey) -> Result<Account> {
+        self.state
+            .lock()
+            .unwrap()
+            .accounts
+            .get(address)
+            .cloned()
+            .ok_or_else(|| anyhow!("AccountNotFound: pubkey={}", address))
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+        let state = self.state.lock().unwrap();
+        let blockhash = *state
+            .recent_blockhashes
+            .back()
+            .expect("always one blockhash");
+        Ok((blockhash, state.slot + BLOCKHASH_VALIDITY))
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        state.lookup()?;
+        Ok(state.recent_blockhashes.contains(blockhash))
+    }
+
+    async fn simulate_transaction(&self, tx: &Transaction) -> Result<Simulation> {
+        let state = self.state.lock().unwrap();
+        if !state.is_live(tx) {
+            return Ok(Simulation {
+                err: Some("Blockhash not found".to_string()),
+                logs: Vec::new(),
+                units_consumed: Some(0),
+            });
+        }
+        let execution = self.execute(&state, tx);
+        Ok(Simulation {
+            err: execution.err,
+            logs: execution.logs,
+            units_consumed: Some(execution.units_consumed),
+        })
+    }
+
+    async fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        if let Some(logs) = self.process(tx)? {
+            // Nobody listening is fine.
+            let _ = self.logs.send(logs);
+        }
+        Ok(tx.signatures[0])
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<std::result::Result<(), String>>> {
+        let mut state = self.state.lock().unwrap();
+        state.lookup()?;
+        Ok(state
+            .statuses
+            .get(signature)
+            .map(|(_, result)| result.clone()))
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<SignatureStatus>>> {
+        let mut state = self.state.lock().unwrap();
+        state.lookup()?;
+        Ok(signatures
+            .iter()
+            .map(|signature| {
+                let (slot, result) = state.statuses.get(signature)?;
+                Some(SignatureStatus {
+                    slot: *slot,
+                    commitment: if state.slot >= slot + FINALITY_DEPTH {
+                        Commitment::Finalized
+                    } else {
+                        Commitment::Confirmed
+                    },
+                    err: result.clone().err(),
+                })
+            })
+            .collect())
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        discriminator: &[u8],
+    ) -> Result<(u64, Vec<(Pubkey, Account)>)> {
+        let state = self.state.lock().unwrap();
+        let accounts = state
+            .accounts
+            .iter()
+            .filter(|(_, account)| {
+                account.owner == *program_id && account.data.starts_with(discriminator)
+            })
+            .map(|(address, account)| (*address, account.clone()))
+            .collect();
+        Ok((state.slot, accounts))
+    }
+
+    async fn get_block_time(&self, slot: u64) -> Result<Option<i64>> {
+        Ok(self.state.lock().unwrap().block_times.get(&slot).copied())
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        Ok(self.state.lock().unwrap().slot)
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let state = self.state.lock().unwrap();
+        let mut newest_first = state
+            .history
+            .iter()
+            .rev()
+            .filter(|(_, accounts)| accounts.contains(address))
+            .map(|(logs, _)| logs);
+        if let Some(before) = before.map(|s| s.to_string()) {
+            if !newest_first.any(|logs| logs.signature == before) {
+                return Ok(Vec::new());
+            }
+        }
+        let until = until.map(|s| s.to_string());
+        Ok(newest_first
+            .take_while(|logs| Some(&logs.signature) != until.as_ref())
+            .take(limit)
+            .map(|logs| SignatureInfo {
+                signature: logs.signature.clone(),
+                slot: logs.slot,
+                err: logs.err.clone(),
+                block_time: state.block_times.get(&logs.slot).copied(),
+            })
+            .collect())
+    }
+
+    async fn get_transaction_logs(&self, signature: &Signature) -> Result<Option<ProgramLogs>> {
+        let state = self.state.lock().unwrap();
+        if state.pruned.contains(signature) {
+            return Ok(None);
+        }
+        let signature = signature.to_string();
+        Ok(state
+            .history
+            .iter()
+            .find(|(logs, _)| logs.signature == signature)
+            .map(|(logs, _)| logs.clone()))
+    }
+
+    async fn get_transaction_fee(&self, signature: &Signature) -> Result<Option<TransactionFee>> {
+        let state = self.state.lock().unwrap();
+        if state.pruned.contains(signature) {
+            return Ok(None);
+        }
+        Ok(state.fees.get(signature).copied())
+    }
+
+    async fn get_recent_prioritization_fees(&self, _accounts: &[Pubkey]) -> Result<Vec<u64>> {
+        Ok(self.state.lock().unwrap().prioritization_fees.clone())
+    }
+
+    async fn subscribe_logs(&self, program_id: Pubkey) -> Result<broadcast::Receiver<ProgramLogs>> {
+        if program_id != self.program_id {
+            return Err(anyhow!("program {} is not loaded", program_id));
+        }
+        Ok(self.logs.subscribe())
+    }
+}
+
+/// One instruction executing against the working copy of the accounts.
+struct Context<'a> {
+    program_id: Pubkey,
+    accounts: &'a mut HashMap<Pubkey, Account>,
+    metas: &'a [(Pubkey, bool)],
+    now: i64,
+    logs: &'a mut Vec<String>,
+}
+
+impl Context<'_> {
+    fn execute(&mut self, data: &[u8]) -> ProgramResult {
+        require!(data.len() >= 8, AnchorErrorCode::InstructionMissing);
+        let (discriminator, args) = data.split_at(8);
+
+        if discriminator == ix::InitializeVault::DISCRIMINATOR {
+            let args = decode::<ix::InitializeVault>(args)?;
+            self.log("InitializeVault");
+            self.initialize_vault(args.authorized_programs)
+        } else if discriminator == ix::MigrateVault::DISCRIMINATOR {
+            decode::<ix::MigrateVault>(args)?;
+            self.log("MigrateVault");
+            self.migrate_vault()
+        } else if discriminator == ix::Deposit::DISCRIMINATOR {
+            let args = decode::<ix::Deposit>(args)?;
+            self.log("Deposit");
+            self.deposit(args.amount)
+        } else if discriminator == ix::Withdraw::DISCRIMINATOR {
+            let args = decode::<ix::Withdraw>(args)?;
+            self.log("Withdraw");
+            self.withdraw(args.amount)
+        } else if discriminator == ix::LockCollateral::DISCRIMINATOR {
+            let args = decode::<ix::LockCollateral>(args)?;
+            self.log("LockCollateral");
+            self.lock_collateral(args.amount)
+        } else if discriminator == ix::UnlockCollateral::DISCRIMINATOR {
+            let args = decode::<ix::UnlockCollateral>(args)?;
+            self.log("UnlockCollateral");
+            self.unlock_collateral(args.amount)
+        } else if discriminator == ix::TransferCollateral::DISCRIMINATOR {
+            let args = decode::<ix::TransferCollateral>(args)?;
+            self.log("TransferCollateral");
+            self.transfer_collateral(args.from_vault, args.to_vault, args.amount)
+        } else if discriminator == ix::AddAuthorizedProgram::DISCRIMINATOR {
+            let args = decode::<ix::AddAuthorizedProgram>(args)?;
+            self.log("AddAuthorizedProgram");
+            self.add_authorized_program(args.program)
+        } else if discriminator == ix::RemoveAuthorizedProgram::DISCRIMINATOR {
+            let args = decode::<ix::RemoveAuthorizedProgram>(args)?;
+            self.log("RemoveAuthorizedProgram");
+            self.remove_authorized_program(args.program)
+        } else if discriminator == ix::InitializeMultisig::DISCRIMINATOR {
+            let args = decode::<ix::InitializeMultisig>(args)?;
+            self.log("InitializeMultisig");
+            self.initialize_multisig(args.signers, args.threshold)
+        } else if discriminator == ix::RequestWithdrawal::DISCRIMINATOR {
+            let args = decode::<ix::RequestWithdrawal>(args)?;
+            self.log("RequestWithdrawal");
+            self.request_withdrawal(args.request_id, args.amount)
+        } else if discriminator == ix::ExecuteWithdrawal::DISCRIMINATOR {
+            decode::<ix::ExecuteWithdrawal>(args)?;
+            self.log("ExecuteWithdrawal");
+            self.execute_withdrawal()
+        } else if discriminator == ix::SettlePnl::DISCRIMINATOR {
+            let args = decode::<ix::SettlePnl>(args)?;
+            self.log("SettlePnl");
+            self.settle_pnl(args)
+        } else if discriminator == ix::ConfigureRecovery::DISCRIMINATOR {
+            let args = decode::<ix::ConfigureRecovery>(args)?;
+            self.log("ConfigureRecovery");
+            self.configure_recovery(args.guardians, args.threshold, true)
+        } else if discriminator == ix::UpdateRecovery::DISCRIMINATOR {
+            let args = decode::<ix::UpdateRecovery>(args)?;
+            self.log("UpdateRecovery");
+            self.configure_recovery(args.guardians, args.threshold, false)
+        } else if discriminator == ix::InitiateRecovery::DISCRIMINATOR {
+            let args = decode::<ix::InitiateRecovery>(args)?;
+            self.log("InitiateRecovery");
+            self.initiate_recovery(args.new_owner)
+        } else if discriminator == ix::ApproveRecovery::DISCRIMINATOR {
+            decode::<ix::ApproveRecovery>(args)?;
+            self.log("ApproveRecovery");
+            self.approve_recovery()
+        } else if discriminator == ix::VetoRecovery::DISCRIMINATOR {
+            decode::<ix::VetoRecovery>(args)?;
+            self.log("VetoRecovery");
+            self.veto_recovery()
+        } else if discriminator == ix::ExecuteRecovery::DISCRIMINATOR {
+            decode::<ix::ExecuteRecovery>(args)?;
+            self.log("ExecuteRecovery");
+            self.execute_recovery()
+        } else if discriminator == ix::RequestForcedUnlock::DISCRIMINATOR {
+            decode::<ix::RequestForcedUnlock>(args)?;
+            self.log("RequestForcedUnlock");
+            self.request_forced_unlock()
+        } else if discriminator == ix::ExecuteForcedUnlock::DISCRIMINATOR {
+            decode::<ix::ExecuteForcedUnlock>(args)?;
+            self.log("ExecuteForcedUnlock");
+            self.execute_forced_unlock()
+        } else if discriminator == ix::Heartbeat::DISCRIMINATOR {
+            decode::<ix::Heartbeat>(args)?;
+            self.log("Heartbeat");
+            self.heartbeat()
+        } else {
+            Err(AnchorErrorCode::InstructionFallbackNotFound.into())
+        }
+    }
+
+    fn log(&mut self, instruction: &str) {
+        self.logs
+            .push(format!("Program log: Instruction: {}", instruction));
+    }
+
+    fn emit<E: Event>(&mut self, event: E) {
+        self.logs
+            .push(format!("Program data: {}", BASE64.encode(event.data())));
+    }
+
+    // ---- account helpers ----
+
+    fn key(&self, index: usize) -> ProgramResult<Pubkey> {
+        self.metas
+            .get(index)
+            .map(|(key, _)| *key)
+            .ok_or_else(|| AnchorErrorCode::AccountNotEnoughKeys.into())
+    }
+
+    fn signer(&self, index: usize) -> ProgramResult<Pubkey> {
+        match self.metas.get(index) {
+            Some((key, true)) => Ok(*key),
+            Some(_) => Err(AnchorErrorCode::AccountNotSigner.into()),
+            None => Err(AnchorErrorCode::AccountNotEnoughKeys.into()),
+        }
+    }
+
+    fn executable(&self, index: usize) -> ProgramResult<bool> {
+        let key = self.key(index)?;
+        Ok(self.accounts.get(&key).is_some_and(|a| a.executable))
+    }
+
+    fn pda(&self, seeds: &[&[u8]]) -> (Pubkey, u8) {
+        Pubkey::find_program_address(seeds, &self.program_id)
+    }
+
+    /// `seeds = [...], bump = stored_bump` on an existing account.
+    fn check_seeds(&self, key: &Pubkey, seeds: &[&[u8]], bump: u8) -> ProgramResult {
+        let (expected, expected_bump) = self.pda(seeds);
+        require!(
+            *key == expected && bump == expected_bump,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        Ok(())
+    }
+
+    fn load<T: AccountDeserialize + Owner>(&self, index: usize) -> ProgramResult<(Pubkey, T)> {
+        let key = self.key(index)?;
+        let account = self
+            .accounts
+            .get(&key)
+            .ok_or(AnchorErrorCode::AccountNotInitialized)?;
+        require!(
+            account.owner == T::owner(),
+            AnchorErrorCode::AccountOwnedByWrongProgram
+        );
+        let mut data: &[u8] = &account.data;
+        Ok((key, T::try_deserialize(&mut data)?))
+    }
+
+    fn store<T: AccountSerialize>(&mut self, key: &Pubkey, value: &T) -> ProgramResult {
+        let account = self
+            .accounts
+            .get_mut(key)
+            .ok_or(AnchorErrorCode::AccountNotInitialized)?;
+        let mut data = Vec::with_capacity(account.data.len());
+        value.try_serialize(&mut data)?;
+        require!(
+            data.len() <= account.data.len(),
+            AnchorErrorCode::AccountDidNotSerialize
+        );
+        account.data[..data.len()].copy_from_slice(&data);
+        Ok(())
+    }
+
+    /// `init` with `space`; fails like the system program if the address is taken.
+    fn init<T: AccountSerialize + Owner>(
+        &mut self,
+        key: &Pubkey,
+        space: usize,
+        value: &T,
+    ) -> ProgramResult {
+        if self.accounts.contains_key(key) {
+            return Err(anchor_lang::prelude::ProgramError::AccountAlreadyInitialized.into());
+        }
+        self.accounts.insert(
+            *key,
+            Account {
+                lamports: Rent::default().minimum_balance(space),
+                data: vec![0u8; space],
+                owner: T::owner(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        self.store(key, value)
+    }
+
+    fn load_token(&self, index: usize) -> ProgramResult<(Pubkey, TokenAccount)> {
+        let key = self.key(index)?;
+        let account = self
+            .accounts
+            .get(&key)
+            .ok_or(AnchorErrorCode::AccountNotInitialized)?;
+        require!(
+            account.owner == spl_token::ID,
+            AnchorErrorCode::AccountOwnedByWrongProgram
+        );
+        let token = TokenAccount::unpack(&account.data)
+            .map_err(|_| AnchorErrorCode::AccountDidNotDeserialize)?;
+        Ok((key, token))
+    }
+
+    fn store_token(&mut self, key: &Pubkey, token: TokenAccount) -> ProgramResult {
+        let account = self
+            .accounts
+            .get_mut(key)
+            .ok_or(AnchorErrorCode::AccountNotInitialized)?;
+        TokenAccount::pack(token, &mut account.data)?;
+        Ok(())
+    }
+
+    fn check_token_program(&self, index: usize) -> ProgramResult {
+        require!(
+            self.key(index)? == spl_token::ID,
+            AnchorErrorCode::InvalidProgramId
+        );
+        Ok(())
+    }
+
+    /// SPL `transfer` with `authority` already proven (signer or vault PDA).
+    fn token_transfer(
+        &mut self,
+        from: &Pubkey,
+        to: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+    ) -> ProgramResult {
+        let token_error = |e: spl_token::error::TokenError| -> AnchorError {
+            anchor_lang::prelude::ProgramError::from(e).into()
+        };
+        let unpack = |accounts: &HashMap<Pubkey, Account>, key: &Pubkey| {
+            accounts
+                .get(key)
+                .and_then(|a| TokenAccount::unpack(&a.data).ok())
+                .ok_or(token_error(
+                    spl_token::error::TokenError::UninitializedState,
+                ))
+        };
+
+        let mut source = unpack(self.accounts, from)?;
+        let mut destination = unpack(self.accounts, to)?;
+        if source.mint != destination.mint {
+            return Err(token_error(spl_token::error::TokenError::MintMismatch));
+        }
+        if source.owner != *authority {
+            return Err(token_error(spl_token::error::TokenError::OwnerMismatch));
+        }
+        if source.amount < amount {
+            return Err(token_error(spl_token::error::TokenError::InsufficientFunds));
+        }
+        if from == to {
+            return Ok(());
+        }
+
+        source.amount -= amount;
+        destination.amount = destination
+            .amount
+            .checked_add(amount)
+            .ok_or(token_error(spl_token::error::TokenError::Overflow))?;
+        self.store_token(from, source)?;
+        self.store_token(to, destination)
+    }
+
+    /// `seeds = [b"vault", vault.original_owner]` plus the owner-signer constraint.
+    fn load_owned_vault(
+        &self,
+        index: usize,
+        owner: &Pubkey,
+    ) -> ProgramResult<(Pubkey, CollateralVault)> {
+        let (key, vault) = self.load::<CollateralVault>(index)?;
+        self.check_seeds(&key, &[b"vault", vault.original_owner.as_ref()], vault.bump)?;
+        require!(vault.owner == *owner, ErrorCode::InvalidAuthority);
+        Ok((key, vault))
+    }
+
+    /// Vault authority bound to `vault` through both `has_one` and the vault's pointer.
+    fn load_vault_authority(
+        &self,
+        index: usize,
+        vault_key: &Pubkey,
+        vault: &CollateralVault,
+    ) -> ProgramResult<(Pubkey, VaultAuthority)> {
+        let (key, authority) = self.load::<VaultAuthority>(index)?;
+        self.check_seeds(
+            &key,
+            &[b"vault_authority", vault_key.as_ref()],
+            authority.bump,
+        )?;
+        require!(
+            authority.vault == *vault_key,
+            ErrorCode::InvalidVaultAuthority
+        );
+        require!(
+            vault.vault_authority == key,
+            ErrorCode::InvalidVaultAuthority
+        );
+        Ok((key, authority))
+    }
+
+    /// `seeds = [VaultAuthority::CALLER_SEED], seeds::program = program` on a signer.
+    fn check_caller(&self, index: usize, program: &Pubkey) -> ProgramResult<Pubkey> {
+        let caller = self.signer(index)?;
+        let (expected, _) = Pubkey::find_program_address(&[VaultAuthority::CALLER_SEED], program);
+        require!(caller == expected, AnchorErrorCode::ConstraintSeeds);
+        Ok(caller)
+    }
+
+    /// `multisig_config` at `index`, checked like `require_cosigners`: if the
+    /// vault has a config, `threshold` of its co-signers must sign among the
+    /// accounts after it.
+    fn require_cosigners(&self, index: usize, vault_key: &Pubkey) -> ProgramResult {
+        let key = self.key(index)?;
+        let (expected, _) = self.pda(&[b"multisig", vault_key.as_ref()]);
+        require!(key == expected, AnchorErrorCode::ConstraintSeeds);
+        if self
+            .accounts
+            .get(&key)
+            .is_none_or(|a| a.owner != self.program_id)
+        {
+            return Ok(());
+        }
+        let (_, config) = self.load::<MultisigConfig>(index)?;
+        let signed: HashSet<&Pubkey> = self.metas[index + 1..]
+            .iter()
+            .filter(|(key, is_signer)| *is_signer && config.is_signer(key))
+            .map(|(key, _)| key)
+            .collect();
+        require!(
+            signed.len() >= config.threshold as usize,
+            ErrorCode::MultisigThresholdNotMet
+        );
+        Ok(())
+    }
+
+    /// The vault's recovery config, with `is_guardian(guardian)` enforced.
+    fn load_recovery_config(
+        &self,
+        index: usize,
+        vault_key: &Pubkey,
+        guardian: &Pubkey,
+    ) -> ProgramResult<RecoveryConfig> {
+        let (key, config) = self.load::<RecoveryConfig>(index)?;
+        self.check_seeds(&key, &[b"recovery", vault_key.as_ref()], config.bump)?;
+        require!(config.vault == *vault_key, ErrorCode::InvalidVaultAuthority);
+        require!(config.is_guardian(guardian), ErrorCode::NotGuardian);
+        Ok(config)
+    }
+
+    /// The vault's pending recovery request, with `has_one = vault`.
+    fn load_recovery_request(
+        &self,
+        index: usize,
+        vault_key: &Pubkey,
+    ) -> ProgramResult<(Pubkey, RecoveryRequest)> {
+        let (key, request) = self.load::<RecoveryRequest>(index)?;
+        self.check_seeds(
+            &key,
+            &[b"recovery_request", vault_key.as_ref()],
+            request.bump,
+        )?;
+        require!(
+            request.vault == *vault_key,
+            ErrorCode::InvalidVaultAuthority
+        );
+        Ok((key, request))
+    }
+
+    // ---- instructions ----
+
+    fn initialize_vault(&mut self, authorized_programs: Vec<Pubkey>) -> ProgramResult {
+        let user = self.signer(0)?;
+        let vault_key = self.key(1)?;
+        let (expected_vault, vault_bump) = self.pda(&[b"vault", user.as_ref()]);
+        require!(
+            vault_key == expected_vault,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        let authority_key = self.key(2)?;
+        let (expected_authority, authority_bump) =
+            self.pda(&[b"vault_authority", vault_key.as_ref()]);
+        require!(
+            authority_key == expected_authority,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        let mint_key = self.key(3)?;
+        let mint = self
+            .accounts
+            .get(&mint_key)
+            .filter(|a| a.owner == spl_token::ID)
+            .and_then(|a| Mint::unpack(&a.data).ok())
+            .ok_or(AnchorErrorCode::AccountNotInitialized)?;
+        require!(mint.decimals == 6, ErrorCode::InvalidMint);
+        let token_key = self.key(4)?;
+        let (expected_token, _) = self.pda(&[b"vault_token", user.as_ref()]);
+        require!(
+            token_key == expected_token,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        self.check_token_program(6)?;
+
+        require!(
+            authorized_programs.len() <= VaultAuthority::MAX_AUTHORIZED_PROGRAMS,
+            ErrorCode::AuthorizedProgramsCapacity
+        );
+        let mut deduped = Vec::with_capacity(authorized_programs.len());
+        let mut seen = HashSet::with_capacity(authorized_programs.len());
+        for program in authorized_programs {
+            require!(program != Pubkey::default(), ErrorCode::InvalidAuthority);
+            require!(seen.insert(program), ErrorCode::AuthorizationAlreadyExists);
+            deduped.push(program);
+        }
+
+        let vault = CollateralVault {
+            owner: user,
+            token_account: token_key,
+            vault_authority: authority_key,
+            total_balance: 0,
+            locked_balance: 0,
+            available_balance: 0,
+            total_deposited: 0,
+            total_withdrawn: 0,
+            created_at: self.now,
+            bump: vault_bump,
+            sequence: 0,
+            last_position_activity: self.now,
+            original_owner: user,
+        };
+        let authority = VaultAuthority {
+            vault: vault_key,
+            authorized_programs: deduped,
+            bump: authority_bump,
+        };
+        self.init(
+            &vault_key,
+            8 + std::mem::size_of::<CollateralVault>(),
+            &vault,
+        )?;
+        self.init(&authority_key, 8 + VaultAuthority::MAX_SIZE, &authority)?;
+        if self.accounts.contains_key(&token_key) {
+            return Err(anchor_lang::prelude::ProgramError::AccountAlreadyInitialized.into());
+        }
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint: mint_key,
+                owner: vault_key,
+                state: AccountState::Initialized,
+                ..Default::default()
+            },
+            &mut data,
+        )?;
+        self.accounts.insert(token_key, token_program_account(data));
+
+        self.emit(VaultInitialized {
+            user,
+            vault: vault_key,
+            actor: user,
+            caller_program: None,
+            sequence: 0,
+            amount: 0,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn migrate_vault(&mut self) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let vault_key = self.key(1)?;
+        let account = self
+            .accounts
+            .get(&vault_key)
+            .ok_or(AnchorErrorCode::AccountNotInitialized)?;
+        require!(
+            account.owner == self.program_id,
+            AnchorErrorCode::ConstraintOwner
+        );
+        require!(
+            self.key(2)? == anchor_lang::system_program::ID,
+            AnchorErrorCode::InvalidProgramId
+        );
+
+        let space = 8 + std::mem::size_of::<CollateralVault>();
+        require!(
+            account.data.starts_with(CollateralVault::DISCRIMINATOR),
+            AnchorErrorCode::AccountDiscriminatorMismatch
+        );
+        require!(account.data.len() < space, ErrorCode::AlreadyMigrated);
+        let legacy = LegacyCollateralVault::deserialize(&mut &account.data[8..])
+            .map_err(|_| AnchorErrorCode::AccountDidNotDeserialize)?;
+        require!(legacy.owner == owner, ErrorCode::InvalidAuthority);
+        self.check_seeds(&vault_key, &[b"vault", owner.as_ref()], legacy.bump)?;
+
+        let account = self.accounts.get_mut(&vault_key).expect("checked above");
+        account.data.resize(space, 0);
+        account.lamports = account.lamports.max(Rent::default().minimum_balance(space));
+        let vault = CollateralVault {
+            owner,
+            token_account: legacy.token_account,
+            vault_authority: legacy.vault_authority,
+            total_balance: legacy.total_balance,
+            locked_balance: legacy.locked_balance,
+            available_balance: legacy.available_balance,
+            total_deposited: legacy.total_deposited,
+            total_withdrawn: legacy.total_withdrawn,
+            created_at: legacy.created_at,
+            bump: legacy.bump,
+            sequence: 0,
+            last_position_activity: self.now,
+            original_owner: owner,
+        };
+        self.store(&vault_key, &vault)?;
+
+        self.emit(VaultMigrated {
+            user: owner,
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence: 0,
+            total_balance: vault.total_balance,
+            locked_balance: vault.locked_balance,
+            available_balance: vault.available_balance,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn deposit(&mut self, amount: u64) -> ProgramResult {
+        let user = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &user)?;
+        let (user_token_key, user_token) = self.load_token(2)?;
+        let (vault_token_key, _) = self.load_token(3)?;
+        require!(
+            vault_token_key == vault.token_account,
+            AnchorErrorCode::ConstraintAddress
+        );
+        self.check_token_program(4)?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(user_token.owner == user, ErrorCode::InvalidAuthority);
+        self.token_transfer(&user_token_key, &vault_token_key, &user, amount)?;
+
+        vault.total_balance = vault
+            .total_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        vault.available_balance = vault
+            .available_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        vault.total_deposited = vault
+            .total_deposited
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(DepositEvent {
+            user,
+            vault: vault_key,
+            actor: user,
+            caller_program: None,
+            sequence,
+            amount,
+            new_balance: vault.total_balance,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn withdraw(&mut self, amount: u64) -> ProgramResult {
+        let user = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &user)?;
+        let (vault_token_key, _) = self.load_token(2)?;
+        require!(
+            vault_token_key == vault.token_account,
+            AnchorErrorCode::ConstraintAddress
+        );
+        let (user_token_key, user_token) = self.load_token(3)?;
+        self.check_token_program(4)?;
+        self.require_cosigners(5, &vault_key)?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            vault.available_balance >= amount,
+            ErrorCode::InsufficientFunds
+        );
+        require!(vault.locked_balance == 0, ErrorCode::ActivePosition);
+        require!(user_token.owner == user, ErrorCode::InvalidAuthority);
+        self.token_transfer(&vault_token_key, &user_token_key, &vault_key, amount)?;
+
+        vault.total_balance = vault
+            .total_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+        vault.available_balance = vault
+            .available_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+        vault.total_withdrawn = vault
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(WithdrawEvent {
+            user,
+            vault: vault_key,
+            actor: user,
+            caller_program: None,
+            sequence,
+            amount,
+            new_balance: vault.total_balance,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn lock_collateral(&mut self, amount: u64) -> ProgramResult {
+        let user = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &user)?;
+        let (_, authority) = self.load_vault_authority(2, &vault_key, &vault)?;
+        let authority_program = self.key(3)?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            authority.is_authorized(&authority_program),
+            ErrorCode::Unauthorized
+        );
+        require!(self.executable(3)?, ErrorCode::Unauthorized);
+        require!(
+            vault.available_balance >= amount,
+            ErrorCode::InsufficientFunds
+        );
+
+        vault.available_balance = vault
+            .available_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+        vault.locked_balance = vault
+            .locked_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        vault.last_position_activity = self.now;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(CollateralLocked {
+            user,
+            vault: vault_key,
+            actor: user,
+            caller_program: Some(authority_program),
+            sequence,
+            amount,
+            new_locked_balance: vault.locked_balance,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn unlock_collateral(&mut self, amount: u64) -> ProgramResult {
+        let user = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &user)?;
+        let (_, authority) = self.load_vault_authority(2, &vault_key, &vault)?;
+        let authority_program = self.key(3)?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            authority.is_authorized(&authority_program),
+            ErrorCode::Unauthorized
+        );
+        require!(self.executable(3)?, ErrorCode::Unauthorized);
+        require!(
+            vault.locked_balance >= amount,
+            ErrorCode::InsufficientLockedFunds
+        );
+
+        vault.locked_balance = vault
+            .locked_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+        vault.available_balance = vault
+            .available_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        vault.last_position_activity = self.now;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(CollateralUnlocked {
+            user,
+            vault: vault_key,
+            actor: user,
+            caller_program: Some(authority_program),
+            sequence,
+            amount,
+            new_available_balance: vault.available_balance,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn transfer_collateral(
+        &mut self,
+        from_vault: Pubkey,
+        to_vault: Pubkey,
+        amount: u64,
+    ) -> ProgramResult {
+        let operator = self.signer(0)?;
+        let (from_key, mut from) = self.load::<CollateralVault>(1)?;
+        self.check_seeds(
+            &from_key,
+            &[b"vault", from.original_owner.as_ref()],
+            from.bump,
+        )?;
+        let (from_authority_key, from_authority) = self.load::<VaultAuthority>(2)?;
+        require!(
+            from.vault_authority == from_authority_key,
+            ErrorCode::InvalidVaultAuthority
+        );
+        self.check_seeds(
+            &from_authority_key,
+            &[b"vault_authority", from_key.as_ref()],
+            from_authority.bump,
+        )?;
+        require!(
+            from_authority.vault == from_key,
+            ErrorCode::InvalidVaultAuthority
+        );
+        let (to_key, mut to) = self.load::<CollateralVault>(3)?;
+        self.check_seeds(&to_key, &[b"vault", to.original_owner.as_ref()], to.bump)?;
+        let (to_authority_key, to_authority) = self.load::<VaultAuthority>(4)?;
+        require!(
+            to.vault_authority == to_authority_key,
+            ErrorCode::InvalidVaultAuthority
+        );
+        self.check_seeds(
+            &to_authority_key,
+            &[b"vault_authority", to_key.as_ref()],
+            to_authority.bump,
+        )?;
+        require!(
+            to_authority.vault == to_key,
+            ErrorCode::InvalidVaultAuthority
+        );
+        let authority_program = self.key(5)?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            operator == from.owner || operator == to.owner,
+            ErrorCode::InvalidAuthority
+        );
+        require!(
+            from_key == from_vault && to_key == to_vault,
+            ErrorCode::InvalidAuthority
+        );
+        require!(
+            from_authority.is_authorized(&authority_program),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            to_authority.is_authorized(&authority_program),
+            ErrorCode::Unauthorized
+        );
+        require!(self.executable(5)?, ErrorCode::Unauthorized);
+        require!(
+            from.available_balance >= amount,
+            ErrorCode::InsufficientFunds
+        );
+
+        from.available_balance = from
+            .available_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+        from.total_balance = from
+            .total_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+        to.available_balance = to
+            .available_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        to.total_balance = to
+            .total_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        let from_sequence = from.next_sequence()?;
+        let to_sequence = to.next_sequence()?;
+        // Anchor writes accounts back in declaration order.
+        self.store(&from_key, &from)?;
+        self.store(&to_key, &to)?;
+
+        self.emit(CollateralTransferred {
+            from_vault,
+            to_vault,
+            actor: operator,
+            caller_program: Some(authority_program),
+            from_sequence,
+            to_sequence,
+            amount,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn add_authorized_program(&mut self, program: Pubkey) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &owner)?;
+        let (authority_key, mut authority) = self.load_vault_authority(2, &vault_key, &vault)?;
+        self.require_cosigners(3, &vault_key)?;
+
+        require!(program != Pubkey::default(), ErrorCode::InvalidAuthority);
+        require!(
+            !authority.authorized_programs.contains(&program),
+            ErrorCode::AuthorizationAlreadyExists
+        );
+        require!(
+            authority.authorized_programs.len() < VaultAuthority::MAX_AUTHORIZED_PROGRAMS,
+            ErrorCode::AuthorizedProgramsCapacity
+        );
+        authority.authorized_programs.push(program);
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+        self.store(&authority_key, &authority)?;
+
+        self.emit(AuthorizedProgramAdded {
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence,
+            program,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn remove_authorized_program(&mut self, program: Pubkey) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &owner)?;
+        let (authority_key, mut authority) = self.load_vault_authority(2, &vault_key, &vault)?;
+        self.require_cosigners(3, &vault_key)?;
+
+        let index = authority
+            .authorized_programs
+            .iter()
+            .position(|p| p == &program)
+            .ok_or(ErrorCode::Unauthorized)?;
+        authority.authorized_programs.swap_remove(index);
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+        self.store(&authority_key, &authority)?;
+
+        self.emit(AuthorizedProgramRemoved {
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence,
+            program,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn initialize_multisig(&mut self, signers: Vec<Pubkey>, threshold: u8) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &owner)?;
+        let config_key = self.key(2)?;
+        let (expected_config, config_bump) = self.pda(&[b"multisig", vault_key.as_ref()]);
+        require!(
+            config_key == expected_config,
+            AnchorErrorCode::ConstraintSeeds
+        );
+
+        require!(
+            !signers.is_empty() && signers.len() <= MultisigConfig::MAX_SIGNERS,
+            ErrorCode::InvalidSignerSet
+        );
+        require!(
+            threshold > 0 && threshold as usize <= signers.len(),
+            ErrorCode::InvalidSignerSet
+        );
+        let mut seen = HashSet::with_capacity(signers.len());
+        for signer in &signers {
+            require!(
+                *signer != Pubkey::default() && seen.insert(*signer),
+                ErrorCode::InvalidSignerSet
+            );
+        }
+
+        let signer_count = signers.len() as u8;
+        let config = MultisigConfig {
+            vault: vault_key,
+            signers,
+            threshold,
+            bump: config_bump,
+        };
+        self.init(&config_key, 8 + MultisigConfig::MAX_SIZE, &config)?;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(MultisigInitialized {
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence,
+            threshold,
+            signer_count,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn request_withdrawal(&mut self, request_id: u64, amount: u64) -> ProgramResult {
+        let user = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &user)?;
+        let request_key = self.key(2)?;
+        let (expected_request, request_bump) =
+            self.pda(&[b"withdrawal", vault_key.as_ref(), &request_id.to_le_bytes()]);
+        require!(
+            request_key == expected_request,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        self.require_cosigners(4, &vault_key)?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            vault.available_balance >= amount,
+            ErrorCode::InsufficientFunds
+        );
+        require!(vault.locked_balance == 0, ErrorCode::ActivePosition);
+
+        let request = WithdrawalRequest {
+            vault: vault_key,
+            user,
+            amount,
+            requested_at: self.now,
+            available_at: self.now + WITHDRAWAL_DELAY_SECONDS,
+            request_id,
+            executed: false,
+            bump: request_bump,
+        };
+        self.init(
+            &request_key,
+            8 + std::mem::size_of::<WithdrawalRequest>(),
+            &request,
+        )?;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(WithdrawalRequested {
+            user,
+            vault: vault_key,
+            actor: user,
+            caller_program: None,
+            sequence,
+            amount,
+            available_at: request.available_at,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn execute_withdrawal(&mut self) -> ProgramResult {
+        let user = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &user)?;
+        require!(
+            vault.vault_authority != Pubkey::default(),
+            ErrorCode::InvalidVaultAuthority
+        );
+        let (request_key, request) = self.load::<WithdrawalRequest>(2)?;
+        self.check_seeds(
+            &request_key,
+            &[
+                b"withdrawal",
+                vault_key.as_ref(),
+                &request.request_id.to_le_bytes(),
+            ],
+            request.bump,
+        )?;
+        require!(!request.executed, ErrorCode::AlreadyExecuted);
+        require!(request.user == user, ErrorCode::Unauthorized);
+        require!(
+            request.vault == vault_key,
+            ErrorCode::InvalidWithdrawalRequest
+        );
+        let (vault_token_key, _) = self.load_token(3)?;
+        require!(
+            vault_token_key == vault.token_account,
+            AnchorErrorCode::ConstraintAddress
+        );
+        let (user_token_key, user_token) = self.load_token(4)?;
+        self.check_token_program(5)?;
+
+        require!(
+            self.now >= request.available_at,
+            ErrorCode::WithdrawalDelayNotMet
+        );
+        require!(
+            vault.available_balance >= request.amount,
+            ErrorCode::InsufficientFunds
+        );
+        require!(vault.locked_balance == 0, ErrorCode::ActivePosition);
+        require!(user_token.owner == user, ErrorCode::InvalidAuthority);
+        self.token_transfer(
+            &vault_token_key,
+            &user_token_key,
+            &vault_key,
+            request.amount,
+        )?;
+
+        vault.total_balance = vault
+            .total_balance
+            .checked_sub(request.amount)
+            .ok_or(ErrorCode::Underflow)?;
+        vault.available_balance = vault
+            .available_balance
+            .checked_sub(request.amount)
+            .ok_or(ErrorCode::Underflow)?;
+        vault.total_withdrawn = vault
+            .total_withdrawn
+            .checked_add(request.amount)
+            .ok_or(ErrorCode::Overflow)?;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+        // `close = user`
+        self.accounts.remove(&request_key);
+
+        self.emit(WithdrawalExecuted {
+            user,
+            vault: vault_key,
+            actor: user,
+            caller_program: None,
+            sequence,
+            amount: request.amount,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn settle_pnl(&mut self, args: ix::SettlePnl) -> ProgramResult {
+        let ix::SettlePnl {
+            position_id,
+            loser_margin,
+            winner_margin,
+            pnl,
+            debit_bucket,
+            credit_bucket,
+        } = args;
+        let (loser_key, mut loser) = self.load::<CollateralVault>(0)?;
+        self.check_seeds(
+            &loser_key,
+            &[b"vault", loser.original_owner.as_ref()],
+            loser.bump,
+        )?;
+        let (_, loser_authority) = self.load_vault_authority(1, &loser_key, &loser)?;
+        let (loser_token_key, _) = self.load_token(2)?;
+        require!(
+            loser_token_key == loser.token_account,
+            AnchorErrorCode::ConstraintAddress
+        );
+        let (winner_key, mut winner) = self.load::<CollateralVault>(3)?;
+        self.check_seeds(
+            &winner_key,
+            &[b"vault", winner.original_owner.as_ref()],
+            winner.bump,
+        )?;
+        let (_, winner_authority) = self.load_vault_authority(4, &winner_key, &winner)?;
+        let (winner_token_key, _) = self.load_token(5)?;
+        require!(
+            winner_token_key == winner.token_account,
+            AnchorErrorCode::ConstraintAddress
+        );
+        let authority_program = self.key(6)?;
+        let caller = self.check_caller(7, &authority_program)?;
+        self.check_token_program(8)?;
+
+        require!(loser_key != winner_key, ErrorCode::SameVaultSettlement);
+        require!(
+            loser_authority.is_authorized(&authority_program),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            winner_authority.is_authorized(&authority_program),
+            ErrorCode::Unauthorized
+        );
+        require!(self.executable(6)?, ErrorCode::Unauthorized);
+        require!(
+            loser.locked_balance >= loser_margin,
+            ErrorCode::InsufficientLockedFunds
+        );
+        require!(
+            winner.locked_balance >= winner_margin,
+            ErrorCode::InsufficientLockedFunds
+        );
+
+        let loser_released = match debit_bucket {
+            SettlementBucket::Locked => {
+                require!(pnl <= loser_margin, ErrorCode::PnlExceedsMargin);
+                loser.locked_balance = loser
+                    .locked_balance
+                    .checked_sub(pnl)
+                    .ok_or(ErrorCode::Underflow)?;
+                loser_margin - pnl
+            }
+            SettlementBucket::Available => {
+                require!(loser.available_balance >= pnl, ErrorCode::InsufficientFunds);
+                loser.available_balance = loser
+                    .available_balance
+                    .checked_sub(pnl)
+                    .ok_or(ErrorCode::Underflow)?;
+                loser_margin
+            }
+        };
+        loser.locked_balance = loser
+            .locked_balance
+            .checked_sub(loser_released)
+            .ok_or(ErrorCode::Underflow)?;
+        loser.available_balance = loser
+            .available_balance
+            .checked_add(loser_released)
+            .ok_or(ErrorCode::Overflow)?;
+        loser.total_balance = loser
+            .total_balance
+            .checked_sub(pnl)
+            .ok_or(ErrorCode::Underflow)?;
+
+        winner.locked_balance = winner
+            .locked_balance
+            .checked_sub(winner_margin)
+            .ok_or(ErrorCode::Underflow)?;
+        winner.available_balance = winner
+            .available_balance
+            .checked_add(winner_margin)
+            .ok_or(ErrorCode::Overflow)?;
+        match credit_bucket {
+            SettlementBucket::Locked => {
+                winner.locked_balance = winner
+                    .locked_balance
+                    .checked_add(pnl)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            SettlementBucket::Available => {
+                winner.available_balance = winner
+                    .available_balance
+                    .checked_add(pnl)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+        }
+        winner.total_balance = winner
+            .total_balance
+            .checked_add(pnl)
+            .ok_or(ErrorCode::Overflow)?;
+
+        if pnl > 0 {
+            self.token_transfer(&loser_token_key, &winner_token_key, &loser_key, pnl)?;
+        }
+
+        loser.last_position_activity = self.now;
+        winner.last_position_activity = self.now;
+        let loser_sequence = loser.next_sequence()?;
+        let winner_sequence = winner.next_sequence()?;
+        self.store(&loser_key, &loser)?;
+        self.store(&winner_key, &winner)?;
+
+        self.emit(PositionSettled {
+            position_id,
+            actor: caller,
+            caller_program: Some(authority_program),
+            pnl,
+            loser: SettlementLeg {
+                user: loser.owner,
+                vault: loser_key,
+                sequence: loser_sequence,
+                margin_released: loser_released,
+                bucket: debit_bucket,
+                new_locked_balance: loser.locked_balance,
+                new_available_balance: loser.available_balance,
+            },
+            winner: SettlementLeg {
+                user: winner.owner,
+                vault: winner_key,
+                sequence: winner_sequence,
+                margin_released: winner_margin,
+                bucket: credit_bucket,
+                new_locked_balance: winner.locked_balance,
+                new_available_balance: winner.available_balance,
+            },
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    /// `configure_recovery` when `create`, `update_recovery` otherwise.
+    fn configure_recovery(
+        &mut self,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+        create: bool,
+    ) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &owner)?;
+        let config_key = self.key(2)?;
+        let config = if create {
+            let (expected, bump) = self.pda(&[b"recovery", vault_key.as_ref()]);
+            require!(config_key == expected, AnchorErrorCode::ConstraintSeeds);
+            RecoveryConfig {
+                vault: vault_key,
+                guardians,
+                threshold,
+                bump,
+            }
+        } else {
+            let (_, existing) = self.load::<RecoveryConfig>(2)?;
+            self.check_seeds(
+                &config_key,
+                &[b"recovery", vault_key.as_ref()],
+                existing.bump,
+            )?;
+            require!(
+                existing.vault == vault_key,
+                ErrorCode::InvalidVaultAuthority
+            );
+            RecoveryConfig {
+                guardians,
+                threshold,
+                ..existing
+            }
+        };
+        if create {
+            self.init(&config_key, 8 + RecoveryConfig::MAX_SIZE, &config)?;
+        }
+
+        validate_guardians(&config.guardians, threshold, &owner)?;
+        if !create {
+            self.store(&config_key, &config)?;
+        }
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(RecoveryConfigured {
+            user: owner,
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence,
+            guardians: config.guardians,
+            threshold,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn initiate_recovery(&mut self, new_owner: Pubkey) -> ProgramResult {
+        let guardian = self.signer(0)?;
+        let (vault_key, mut vault) = self.load::<CollateralVault>(1)?;
+        self.check_seeds(
+            &vault_key,
+            &[b"vault", vault.original_owner.as_ref()],
+            vault.bump,
+        )?;
+        let config = self.load_recovery_config(2, &vault_key, &guardian)?;
+        let request_key = self.key(3)?;
+        let (expected_request, request_bump) = self.pda(&[b"recovery_request", vault_key.as_ref()]);
+        require!(
+            request_key == expected_request,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        let request = RecoveryRequest {
+            vault: vault_key,
+            proposed_owner: new_owner,
+            initiator: guardian,
+            approvals: vec![guardian],
+            initiated_at: self.now,
+            available_at: self.now + RECOVERY_DELAY_SECONDS,
+            bump: request_bump,
+        };
+        self.init(&request_key, 8 + RecoveryRequest::MAX_SIZE, &request)?;
+
+        require!(
+            new_owner != Pubkey::default() && new_owner != vault.owner,
+            ErrorCode::InvalidAuthority
+        );
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(RecoveryInitiated {
+            user: vault.owner,
+            vault: vault_key,
+            actor: guardian,
+            caller_program: None,
+            sequence,
+            proposed_owner: new_owner,
+            approvals: 1,
+            threshold: config.threshold,
+            available_at: request.available_at,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn approve_recovery(&mut self) -> ProgramResult {
+        let guardian = self.signer(0)?;
+        let (vault_key, mut vault) = self.load::<CollateralVault>(1)?;
+        self.check_seeds(
+            &vault_key,
+            &[b"vault", vault.original_owner.as_ref()],
+            vault.bump,
+        )?;
+        let config = self.load_recovery_config(2, &vault_key, &guardian)?;
+        let (request_key, mut request) = self.load_recovery_request(3, &vault_key)?;
+
+        require!(
+            !request.approvals.contains(&guardian),
+            ErrorCode::RecoveryAlreadyApproved
+        );
+        request.approvals.push(guardian);
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+        self.store(&request_key, &request)?;
+
+        self.emit(RecoveryApproved {
+            user: vault.owner,
+            vault: vault_key,
+            actor: guardian,
+            caller_program: None,
+            sequence,
+            proposed_owner: request.proposed_owner,
+            approvals: request.approvals.len() as u8,
+            threshold: config.threshold,
+            available_at: request.available_at,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn veto_recovery(&mut self) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &owner)?;
+        let (request_key, request) = self.load_recovery_request(2, &vault_key)?;
+        require!(
+            self.key(3)? == request.initiator,
+            ErrorCode::InvalidAuthority
+        );
+
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+        // `close = initiator`
+        self.accounts.remove(&request_key);
+
+        self.emit(RecoveryVetoed {
+            user: vault.owner,
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence,
+            proposed_owner: request.proposed_owner,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn execute_recovery(&mut self) -> ProgramResult {
+        let guardian = self.signer(0)?;
+        let (vault_key, mut vault) = self.load::<CollateralVault>(1)?;
+        self.check_seeds(
+            &vault_key,
+            &[b"vault", vault.original_owner.as_ref()],
+            vault.bump,
+        )?;
+        let config = self.load_recovery_config(2, &vault_key, &guardian)?;
+        let (request_key, request) = self.load_recovery_request(3, &vault_key)?;
+        require!(
+            self.key(4)? == request.initiator,
+            ErrorCode::InvalidAuthority
+        );
+
+        require!(
+            self.now >= request.available_at,
+            ErrorCode::RecoveryDelayNotMet
+        );
+        let approvals = request
+            .approvals
+            .iter()
+            .filter(|g| config.is_guardian(g))
+            .count();
+        require!(
+            approvals >= config.threshold as usize,
+            ErrorCode::RecoveryThresholdNotMet
+        );
+
+        let previous_owner = vault.owner;
+        vault.owner = request.proposed_owner;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+        // `close = initiator`
+        self.accounts.remove(&request_key);
+
+        self.emit(RecoveryExecuted {
+            previous_owner,
+            new_owner: vault.owner,
+            vault: vault_key,
+            actor: guardian,
+            caller_program: None,
+            sequence,
+            approvals: approvals as u8,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn request_forced_unlock(&mut self) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &owner)?;
+        let request_key = self.key(2)?;
+        let (expected_request, request_bump) = self.pda(&[b"forced_unlock", vault_key.as_ref()]);
+        require!(
+            request_key == expected_request,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        // `init_if_needed`: a stale request is reused.
+        let existing = if self.accounts.contains_key(&request_key) {
+            Some(self.load::<ForcedUnlockRequest>(2)?.1)
+        } else {
+            None
+        };
+
+        require!(
+            existing
+                .as_ref()
+                .is_none_or(|r| vault.last_position_activity > r.requested_at),
+            ErrorCode::ForcedUnlockPending
+        );
+        require!(vault.locked_balance > 0, ErrorCode::InsufficientLockedFunds);
+        require!(
+            self.now - vault.last_position_activity >= FORCED_UNLOCK_INACTIVITY_SECONDS,
+            ErrorCode::ForcedUnlockInactivityNotMet
+        );
+
+        let request = ForcedUnlockRequest {
+            vault: vault_key,
+            owner,
+            requested_at: self.now,
+            available_at: self.now + FORCED_UNLOCK_DELAY_SECONDS,
+            bump: request_bump,
+        };
+        if existing.is_some() {
+            self.store(&request_key, &request)?;
+        } else {
+            self.init(
+                &request_key,
+                8 + std::mem::size_of::<ForcedUnlockRequest>(),
+                &request,
+            )?;
+        }
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(ForcedUnlockRequested {
+            user: owner,
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence,
+            locked_balance: vault.locked_balance,
+            available_at: request.available_at,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn execute_forced_unlock(&mut self) -> ProgramResult {
+        let owner = self.signer(0)?;
+        let (vault_key, mut vault) = self.load_owned_vault(1, &owner)?;
+        let (request_key, request) = self.load::<ForcedUnlockRequest>(2)?;
+        self.check_seeds(
+            &request_key,
+            &[b"forced_unlock", vault_key.as_ref()],
+            request.bump,
+        )?;
+        require!(request.vault == vault_key, ErrorCode::InvalidVaultAuthority);
+
+        require!(
+            self.now >= request.available_at,
+            ErrorCode::ForcedUnlockDelayNotMet
+        );
+        require!(
+            vault.last_position_activity <= request.requested_at,
+            ErrorCode::ForcedUnlockInactivityNotMet
+        );
+
+        let amount = vault.locked_balance;
+        vault.locked_balance = 0;
+        vault.available_balance = vault
+            .available_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+        // `close = owner`
+        self.accounts.remove(&request_key);
+
+        self.emit(ForcedUnlockExecuted {
+            user: owner,
+            vault: vault_key,
+            actor: owner,
+            caller_program: None,
+            sequence,
+            amount,
+            new_available_balance: vault.available_balance,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+
+    fn heartbeat(&mut self) -> ProgramResult {
+        let (vault_key, mut vault) = self.load::<CollateralVault>(0)?;
+        self.check_seeds(
+            &vault_key,
+            &[b"vault", vault.original_owner.as_ref()],
+            vault.bump,
+        )?;
+        let (_, authority) = self.load_vault_authority(1, &vault_key, &vault)?;
+        let authority_program = self.key(2)?;
+        let caller = self.check_caller(3, &authority_program)?;
+        let request_key = self.key(4)?;
+        let (expected_request, _) = self.pda(&[b"forced_unlock", vault_key.as_ref()]);
+        require!(
+            request_key == expected_request,
+            AnchorErrorCode::ConstraintSeeds
+        );
+        require!(self.key(5)? == vault.owner, ErrorCode::InvalidAuthority);
+
+        require!(
+            authority.is_authorized(&authority_program),
+            ErrorCode::Unauthorized
+        );
+        require!(self.executable(2)?, ErrorCode::Unauthorized);
+
+        let cancelled = self.accounts.get(&request_key).is_some_and(|a| {
+            a.owner == self.program_id && a.data.starts_with(ForcedUnlockRequest::DISCRIMINATOR)
+        });
+        if cancelled {
+            self.accounts.remove(&request_key);
+        }
+        vault.last_position_activity = self.now;
+        let sequence = vault.next_sequence()?;
+        self.store(&vault_key, &vault)?;
+
+        self.emit(PositionHeartbeat {
+            vault: vault_key,
+            actor: caller,
+            caller_program: Some(authority_program),
+            sequence,
+            cancelled_forced_unlock: cancelled,
+            timestamp: self.now,
+        });
+        Ok(())
+    }
+}
+
+fn decode<T: AnchorDeserialize>(mut args: &[u8]) -> ProgramResult<T> {
+    T::deserialize(&mut args).map_err(|_| AnchorErrorCode::InstructionDidNotDeserialize.into())
+}
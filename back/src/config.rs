@@ -1,77 +1,522 @@
-use anchor_client::solana_sdk::signature::{read_keypair_file, Keypair};
+//! Backend configuration, layered lowest to highest precedence:
+//!
+//! 1. built-in defaults, including per-cluster RPC URLs,
+//! 2. a TOML file (`--config <path>`, `CONFIG_PATH`, or `./config.toml` if present),
+//! 3. environment variables (`CLUSTER`, `RPC_URL`, `PROGRAM_ID`, `USDT_MINT`,
+//!    `DATABASE_URL`, `BIND_ADDR`, the signer variables and `USER_KEYPAIR`),
+//! 4. command line flags (`--cluster`, `--rpc-url`, `--program-id`,
+//!    `--usdt-mint`, `--database-url`, `--bind-addr`, `--keypair`).
+//!
+//! Cluster specific values live under `[profiles.<cluster>]` in the file. Alert
+//! thresholds, monitor intervals, feature toggles, sign-in settings, admin API keys
+//! and sender tuning only come from the file.
+
+use crate::admin::AdminConfig;
+use crate::auth::AuthConfig;
+use crate::balance_tracker::AlertThresholds;
+use crate::idempotency::IdempotencyConfig;
+use crate::multisig::ProposalLimits;
+use crate::sender::{PriorityFee, SenderConfig};
+use crate::signer::{parse_keypair, SignerConfig};
+use anchor_client::solana_sdk::signature::Keypair;
 use anchor_lang::prelude::Pubkey;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cluster {
+    Localnet,
+    Devnet,
+    Mainnet,
+}
+
+impl Cluster {
+    fn default_rpc_url(self) -> &'static str {
+        match self {
+            Cluster::Localnet => "http://127.0.0.1:8899",
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Mainnet => "https://api.mainnet-beta.solana.com",
+        }
+    }
+
+    /// The devnet test USDT mint; other clusters must configure one.
+    fn default_usdt_mint(self) -> Option<&'static str> {
+        match self {
+            Cluster::Devnet => Some("36YuSPgXkeWCKQcyoibHgdHzF91CMPyhbRo22EiaWFZD"),
+            Cluster::Localnet | Cluster::Mainnet => None,
+        }
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "localnet" | "localhost" => Ok(Cluster::Localnet),
+            "devnet" => Ok(Cluster::Devnet),
+            "mainnet" | "mainnet-beta" => Ok(Cluster::Mainnet),
+            other => bail!(
+                "unknown cluster '{}', expected localnet, devnet or mainnet",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Cluster::Localnet => "localnet",
+            Cluster::Devnet => "devnet",
+            Cluster::Mainnet => "mainnet",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonitorIntervals {
+    pub balance_tracker_secs: u64,
+    pub metrics_secs: u64,
+    pub security_secs: u64,
+    /// How often pending and confirmed transactions are re-checked.
+    pub confirmer_secs: u64,
+    /// How often every vault is reconciled against the database and chain.
+    pub reconciler_secs: u64,
+}
+
+impl Default for MonitorIntervals {
+    fn default() -> Self {
+        Self {
+            balance_tracker_secs: 30,
+            metrics_secs: 60,
+            security_secs: 30,
+            confirmer_secs: 2,
+            reconciler_secs: 300,
+        }
+    }
+}
+
+impl MonitorIntervals {
+    pub fn balance_tracker(&self) -> Duration {
+        Duration::from_secs(self.balance_tracker_secs)
+    }
+
+    pub fn metrics(&self) -> Duration {
+        Duration::from_secs(self.metrics_secs)
+    }
+
+    pub fn security(&self) -> Duration {
+        Duration::from_secs(self.security_secs)
+    }
+
+    pub fn confirmer(&self) -> Duration {
+        Duration::from_secs(self.confirmer_secs)
+    }
+
+    pub fn reconciler(&self) -> Duration {
+        Duration::from_secs(self.reconciler_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureToggles {
+    /// Connect to `database_url` and serve `/analytics/*`.
+    pub analytics: bool,
+    pub balance_tracker: bool,
+    pub vault_monitor: bool,
+    /// Index program events from the log subscription into the database.
+    pub indexer: bool,
+    /// Catch up on events missed while the server was down before serving.
+    pub backfill: bool,
+    /// Periodically reconcile every vault from a program account scan.
+    pub reconciler: bool,
+}
+
+impl Default for FeatureToggles {
+    fn default() -> Self {
+        Self {
+            analytics: true,
+            balance_tracker: true,
+            vault_monitor: true,
+            indexer: true,
+            backfill: true,
+            reconciler: true,
+        }
+    }
+}
+
+/// `[sender]` in the file; durations are in milliseconds.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SenderSection {
+    priority_fee: Option<PriorityFee>,
+    compute_unit_margin_percent: Option<u32>,
+    fallback_compute_units: Option<u32>,
+    resend_interval_ms: Option<u64>,
+    poll_interval_ms: Option<u64>,
+    max_rebuilds: Option<u32>,
+    status_error_timeout_ms: Option<u64>,
+}
+
+impl SenderSection {
+    fn into_config(self) -> SenderConfig {
+        let defaults = SenderConfig::default();
+        SenderConfig {
+            priority_fee: self.priority_fee.unwrap_or(defaults.priority_fee),
+            compute_unit_margin_percent: self
+                .compute_unit_margin_percent
+                .unwrap_or(defaults.compute_unit_margin_percent),
+            fallback_compute_units: self
+                .fallback_compute_units
+                .unwrap_or(defaults.fallback_compute_units),
+            resend_interval: self
+                .resend_interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.resend_interval),
+            poll_interval: self
+                .poll_interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.poll_interval),
+            max_rebuilds: self.max_rebuilds.unwrap_or(defaults.max_rebuilds),
+            status_error_timeout: self
+                .status_error_timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.status_error_timeout),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Profile {
+    rpc_url: Option<String>,
+    program_id: Option<String>,
+    usdt_mint: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    cluster: Option<String>,
+    bind_addr: Option<String>,
+    database_url: Option<String>,
+    signer: Option<SignerConfig>,
+    profiles: HashMap<Cluster, Profile>,
+    alerts: AlertThresholds,
+    monitor: MonitorIntervals,
+    features: FeatureToggles,
+    auth: AuthConfig,
+    admin: AdminConfig,
+    idempotency: IdempotencyConfig,
+    multisig: ProposalLimits,
+    sender: SenderSection,
+}
+
+/// Values that env vars or CLI flags can override.
+#[derive(Debug, Default)]
+struct Overrides {
+    config_path: Option<PathBuf>,
+    cluster: Option<String>,
+    rpc_url: Option<String>,
+    program_id: Option<String>,
+    usdt_mint: Option<String>,
+    database_url: Option<String>,
+    bind_addr: Option<String>,
+    signer: Option<SignerConfig>,
+    user_keypair: Option<String>,
+}
+
+impl Overrides {
+    fn from_env() -> Self {
+        Self {
+            config_path: env::var("CONFIG_PATH").ok().map(PathBuf::from),
+            cluster: env::var("CLUSTER").ok(),
+            rpc_url: env::var("RPC_URL").ok(),
+            program_id: env::var("PROGRAM_ID").ok(),
+            usdt_mint: env::var("USDT_MINT").ok(),
+            database_url: env::var("DATABASE_URL").ok(),
+            bind_addr: env::var("BIND_ADDR").ok(),
+            signer: SignerConfig::from_env(),
+            user_keypair: env::var("USER_KEYPAIR").ok(),
+        }
+    }
+
+    fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut overrides = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("{} needs a value", flag))
+            };
+
+            match flag.as_str() {
+                "--config" => overrides.config_path = Some(PathBuf::from(value()?)),
+                "--cluster" => overrides.cluster = Some(value()?),
+                "--rpc-url" => overrides.rpc_url = Some(value()?),
+                "--program-id" => overrides.program_id = Some(value()?),
+                "--usdt-mint" => overrides.usdt_mint = Some(value()?),
+                "--database-url" => overrides.database_url = Some(value()?),
+                "--bind-addr" => overrides.bind_addr = Some(value()?),
+                "--keypair" => overrides.signer = Some(SignerConfig::File { path: value()? }),
+                other => bail!("unknown argument '{}'", other),
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    /// `self` wins wherever it is set.
+    fn over(self, lower: Overrides) -> Overrides {
+        Overrides {
+            config_path: self.config_path.or(lower.config_path),
+            cluster: self.cluster.or(lower.cluster),
+            rpc_url: self.rpc_url.or(lower.rpc_url),
+            program_id: self.program_id.or(lower.program_id),
+            usdt_mint: self.usdt_mint.or(lower.usdt_mint),
+            database_url: self.database_url.or(lower.database_url),
+            bind_addr: self.bind_addr.or(lower.bind_addr),
+            signer: self.signer.or(lower.signer),
+            user_keypair: self.user_keypair.or(lower.user_keypair),
+        }
+    }
+}
 
 pub struct AppConfig {
+    pub cluster: Cluster,
     pub rpc_url: String,
     pub program_id: Pubkey,
     pub usdt_mint: Pubkey,
-    pub database_url: String,
+    pub database_url: Option<String>,
     pub bind_addr: SocketAddr,
-    pub payer: Keypair,
+    pub signer: SignerConfig,
     pub user: Option<Keypair>,
+    pub alerts: AlertThresholds,
+    pub monitor: MonitorIntervals,
+    pub features: FeatureToggles,
+    pub auth: AuthConfig,
+    pub admin: AdminConfig,
+    pub idempotency: IdempotencyConfig,
+    pub multisig: ProposalLimits,
+    pub sender: SenderConfig,
 }
 
 impl AppConfig {
-    pub fn from_env() -> Result<Self> {
+    /// Load from the process environment and command line (without argv[0]).
+    pub fn load() -> Result<Self> {
         dotenvy::dotenv().ok();
+        let cli = Overrides::from_args(env::args().skip(1))?;
+        let overrides = cli.over(Overrides::from_env());
 
-        let rpc_url =
-            env::var("RPC_URL").unwrap_or_else(|_| "https://api.devnet.solana.com".to_string());
-        let payer = if let Ok(secret) = env::var("SOLANA_KEYPAIR") {
-            Keypair::from_base58_string(&secret)
-        } else {
-            let keypair_path = env::var("SOLANA_KEYPAIR_PATH")
-                .or_else(|_| env::var("ANCHOR_WALLET"))
-                .context("Set SOLANA_KEYPAIR (base58 secret) or SOLANA_KEYPAIR_PATH")?;
-            read_keypair_file(&keypair_path)
-                .map_err(|err| anyhow!("Failed to read keypair file {}: {}", keypair_path, err))?
+        let file = match &overrides.config_path {
+            Some(path) => Some(read_file(path)?),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Some(read_file(Path::new(DEFAULT_CONFIG_PATH))?)
+            }
+            None => None,
         };
 
-        let program_id = env::var("PROGRAM_ID")
-            .context("PROGRAM_ID must be set")?
-            .parse::<Pubkey>()
-            .context("PROGRAM_ID is not a valid pubkey")?;
+        Self::resolve(file.unwrap_or_default(), overrides)
+    }
 
-        let usdt_mint = env::var("USDT_MINT")
-            .context("USDT_MINT must be set")?
-            .parse::<Pubkey>()
-            .context("USDT_MINT is not a valid pubkey")?;
+    /// Parse a TOML document and apply CLI-style `args` on top of it, ignoring
+    /// the environment.
+    pub fn from_toml_and_args<I: IntoIterator<Item = String>>(toml: &str, args: I) -> Result<Self> {
+        let file = parse_file(toml, "<inline>")?;
+        Self::resolve(file, Overrides::from_args(args)?)
+    }
 
-        let database_url =
-            env::var("DATABASE_URL").context("DATABASE_URL must be set for persistent storage")?;
+    fn resolve(file: FileConfig, overrides: Overrides) -> Result<Self> {
+        let cluster: Cluster = overrides
+            .cluster
+            .or(file.cluster)
+            .as_deref()
+            .unwrap_or("devnet")
+            .parse()?;
+        let profile = file.profiles.get(&cluster).cloned().unwrap_or_default();
 
-        let bind_addr = env::var("BIND_ADDR")
-            .unwrap_or_else(|_| "0.0.0.0:8080".to_string())
-            .parse::<SocketAddr>()
-            .context("BIND_ADDR must be a valid socket address, e.g. 0.0.0.0:8080")?;
+        let rpc_url = overrides
+            .rpc_url
+            .or(profile.rpc_url)
+            .unwrap_or_else(|| cluster.default_rpc_url().to_string());
+        if !(rpc_url.starts_with("http://") || rpc_url.starts_with("https://")) {
+            bail!("rpc_url must be an http(s) URL, got '{}'", rpc_url);
+        }
 
-        // Optional user keypair for testing (when backend needs to sign as user)
-        let user = if let Ok(user_key) = env::var("USER_KEYPAIR") {
-            if user_key.starts_with('[') {
-                // Parse JSON array format
-                let bytes: Vec<u8> = serde_json::from_str(&user_key)
-                    .context("USER_KEYPAIR must be valid JSON array")?;
-                Some(Keypair::from_bytes(&bytes)
-                    .map_err(|e| anyhow!("Invalid USER_KEYPAIR bytes: {}", e))?)
-            } else {
-                // Parse base58 format
-                Some(Keypair::from_base58_string(&user_key))
-            }
-        } else {
-            None
+        let program_id = match overrides.program_id.or(profile.program_id) {
+            Some(value) => parse_pubkey("program_id", &value)?,
+            None => collateral_vault::ID,
         };
 
+        let usdt_mint = overrides
+            .usdt_mint
+            .or(profile.usdt_mint)
+            .or_else(|| cluster.default_usdt_mint().map(str::to_string))
+            .ok_or_else(|| {
+                anyhow!(
+                    "usdt_mint must be set for {} (USDT_MINT, --usdt-mint or [profiles.{}])",
+                    cluster,
+                    cluster
+                )
+            })?;
+        let usdt_mint = parse_pubkey("usdt_mint", &usdt_mint)?;
+
+        let bind_addr = overrides
+            .bind_addr
+            .or(file.bind_addr)
+            .unwrap_or_else(|| "0.0.0.0:8080".to_string());
+        let bind_addr = bind_addr.parse::<SocketAddr>().with_context(|| {
+            format!(
+                "bind_addr must be a valid socket address, e.g. 0.0.0.0:8080, got '{}'",
+                bind_addr
+            )
+        })?;
+
+        let database_url = overrides.database_url.or(file.database_url);
+        if file.features.analytics && database_url.is_none() {
+            bail!("database_url must be set when features.analytics is enabled");
+        }
+
+        // Optional user keypair for testing (when backend needs to sign as user)
+        let user = overrides
+            .user_keypair
+            .map(|key| parse_keypair(&key).context("USER_KEYPAIR is not a valid secret key"))
+            .transpose()?;
+
+        validate_alerts(&file.alerts)?;
+        validate_monitor(&file.monitor)?;
+        validate_auth(&file.auth)?;
+        validate_admin(&file.admin)?;
+        if file.idempotency.ttl_secs == 0 || file.idempotency.lease_secs == 0 {
+            bail!("idempotency.ttl_secs and idempotency.lease_secs must be greater than zero");
+        }
+        if file.multisig.max_open_per_vault == 0 || file.multisig.ttl_secs == 0 {
+            bail!("multisig.max_open_per_vault and multisig.ttl_secs must be greater than zero");
+        }
+        let sender = file.sender.into_config();
+        validate_sender(&sender)?;
+
         Ok(Self {
+            cluster,
             rpc_url,
             program_id,
             usdt_mint,
             database_url,
             bind_addr,
-            payer,
+            signer: overrides.signer.or(file.signer).unwrap_or_default(),
             user,
+            alerts: file.alerts,
+            monitor: file.monitor,
+            features: file.features,
+            auth: file.auth,
+            admin: file.admin,
+            idempotency: file.idempotency,
+            multisig: file.multisig,
+            sender,
         })
     }
 }
+
+fn read_file(path: &Path) -> Result<FileConfig> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file {}", path.display()))?;
+    parse_file(&contents, &path.display().to_string())
+}
+
+fn parse_file(contents: &str, name: &str) -> Result<FileConfig> {
+    toml::from_str(contents).with_context(|| format!("invalid config file {}", name))
+}
+
+fn parse_pubkey(field: &str, value: &str) -> Result<Pubkey> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("{} is not a valid pubkey: '{}'", field, value))
+}
+
+fn validate_alerts(alerts: &AlertThresholds) -> Result<()> {
+    if !(0.0..=100.0).contains(&alerts.high_locked_ratio_percent) {
+        bail!(
+            "alerts.high_locked_ratio_percent must be between 0 and 100, got {}",
+            alerts.high_locked_ratio_percent
+        );
+    }
+    Ok(())
+}
+
+fn validate_monitor(monitor: &MonitorIntervals) -> Result<()> {
+    for (name, secs) in [
+        ("balance_tracker_secs", monitor.balance_tracker_secs),
+        ("metrics_secs", monitor.metrics_secs),
+        ("security_secs", monitor.security_secs),
+        ("confirmer_secs", monitor.confirmer_secs),
+        ("reconciler_secs", monitor.reconciler_secs),
+    ] {
+        if secs == 0 {
+            bail!("monitor.{} must be greater than zero", name);
+        }
+    }
+    Ok(())
+}
+
+fn validate_auth(auth: &AuthConfig) -> Result<()> {
+    if auth.domain.trim().is_empty() {
+        bail!("auth.domain must not be empty");
+    }
+    if auth.nonce_ttl_secs == 0 || auth.session_ttl_secs == 0 {
+        bail!("auth.nonce_ttl_secs and auth.session_ttl_secs must be greater than zero");
+    }
+    if auth.max_pending_challenges == 0 {
+        bail!("auth.max_pending_challenges must be greater than zero");
+    }
+    Ok(())
+}
+
+fn validate_admin(admin: &AdminConfig) -> Result<()> {
+    for (i, key) in admin.keys.iter().enumerate() {
+        if key.name.trim().is_empty() {
+            bail!("admin.keys[{}].name must not be empty", i);
+        }
+        if key.key.len() < 16 {
+            bail!("admin.keys[{}].key must be at least 16 characters", i);
+        }
+        if admin.keys[..i].iter().any(|other| other.key == key.key) {
+            bail!("admin.keys[{}].key is already used by another entry", i);
+        }
+    }
+    Ok(())
+}
+
+fn validate_sender(sender: &SenderConfig) -> Result<()> {
+    if let PriorityFee::Estimate { percentile, .. } = sender.priority_fee {
+        if percentile > 100 {
+            bail!(
+                "sender.priority_fee.estimate.percentile must be at most 100, got {}",
+                percentile
+            );
+        }
+    }
+    if sender.resend_interval.is_zero() || sender.poll_interval.is_zero() {
+        bail!("sender.resend_interval_ms and sender.poll_interval_ms must be greater than zero");
+    }
+    Ok(())
+}
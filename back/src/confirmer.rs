@@ -0,0 +1,372 @@
+//! Transaction lifecycle: rows are written as `Pending` when the sender signs
+//! a transaction and moved to `Confirmed`, `Finalized` or `Failed` from
+//! signature statuses. Every change is pushed over WebSocket.
+
+use crate::chain::{ChainClient, Commitment};
+use crate::db::{Database, TransactionRecord, TransactionStatus, TransactionType};
+use crate::metrics::Metrics;
+use crate::sender::SendObserver;
+use crate::websocket::{WebSocketManager, WsMessage};
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use collateral_vault::errors::ErrorCode;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// A pending transaction the node has never seen is failed after this long.
+/// It is well past the sender's last rebuild, so the transaction can no
+/// longer land.
+const PENDING_TIMEOUT_SECS: i64 = 600;
+
+/// `getSignatureStatuses` accepts at most this many signatures per call.
+const STATUS_BATCH: usize = 256;
+
+/// A collateral_vault instruction recognised in a transaction.
+pub struct VaultOp {
+    pub user: Pubkey,
+    pub tx_type: TransactionType,
+    pub amount: u64,
+    pub action: &'static str,
+}
+
+pub fn decode_vault_ops(program_id: &Pubkey, tx: &Transaction) -> Vec<VaultOp> {
+    use collateral_vault::instruction as ix;
+
+    let keys = &tx.message.account_keys;
+    tx.message
+        .instructions
+        .iter()
+        .filter(|ci| keys.get(ci.program_id_index as usize) == Some(program_id))
+        .filter_map(|ci| {
+            // Every vault instruction lists the acting user first.
+            let user = *keys.get(*ci.accounts.first()? as usize)?;
+            let data = &ci.data;
+            let args = data.get(8..)?;
+            let (tx_type, amount, action) = if data.starts_with(ix::InitializeVault::DISCRIMINATOR)
+            {
+                (TransactionType::Initialize, 0, "INITIALIZE_VAULT")
+            } else if data.starts_with(ix::Deposit::DISCRIMINATOR) {
+                let amount = ix::Deposit::try_from_slice(args).ok()?.amount;
+                (TransactionType::Deposit, amount, "DEPOSIT")
+            } else if data.starts_with(ix::Withdraw::DISCRIMINATOR) {
+                let amount = ix::Withdraw::try_from_slice(args).ok()?.amount;
+                (TransactionType::Withdraw, amount, "WITHDRAW")
+            } else if data.starts_with(ix::LockCollateral::DISCRIMINATOR) {
+                let amount = ix::LockCollateral::try_from_slice(args).ok()?.amount;
+                (TransactionType::Lock, amount, "LOCK")
+            } else if data.starts_with(ix::UnlockCollateral::DISCRIMINATOR) {
+                let amount = ix::UnlockCollateral::try_from_slice(args).ok()?.amount;
+                (TransactionType::Unlock, amount, "UNLOCK")
+            } else if data.starts_with(ix::TransferCollateral::DISCRIMINATOR) {
+                let amount = ix::TransferCollateral::try_from_slice(args).ok()?.amount;
+                (TransactionType::Transfer, amount, "TRANSFER")
+            } else if data.starts_with(ix::RequestWithdrawal::DISCRIMINATOR) {
+                let amount = ix::RequestWithdrawal::try_from_slice(args).ok()?.amount;
+                (
+                    TransactionType::WithdrawalRequest,
+                    amount,
+                    "WITHDRAWAL_REQUEST",
+                )
+            } else if data.starts_with(ix::ExecuteWithdrawal::DISCRIMINATOR) {
+                (TransactionType::WithdrawalExecute, 0, "WITHDRAWAL_EXECUTE")
+            } else {
+                return None;
+            };
+            Some(VaultOp {
+                user,
+                tx_type,
+                amount,
+                action,
+            })
+        })
+        .collect()
+}
+
+/// An `add_authorized_program` or `remove_authorized_program` instruction
+/// recognised in a transaction. These change configuration, not balances, so
+/// they are not `VaultOp`s.
+pub struct AuthorizationChange {
+    pub user: Pubkey,
+    pub program: Pubkey,
+    pub authorized: bool,
+}
+
+pub fn decode_authorization_changes(
+    program_id: &Pubkey,
+    tx: &Transaction,
+) -> Vec<AuthorizationChange> {
+    use collateral_vault::instruction as ix;
+
+    let keys = &tx.message.account_keys;
+    tx.message
+        .instructions
+        .iter()
+        .filter(|ci| keys.get(ci.program_id_index as usize) == Some(program_id))
+        .filter_map(|ci| {
+            let user = *keys.get(*ci.accounts.first()? as usize)?;
+            let data = &ci.data;
+            let args = data.get(8..)?;
+            let (program, authorized) = if data.starts_with(ix::AddAuthorizedProgram::DISCRIMINATOR)
+            {
+                (
+                    ix::AddAuthorizedProgram::try_from_slice(args).ok()?.program,
+                    true,
+                )
+            } else if data.starts_with(ix::RemoveAuthorizedProgram::DISCRIMINATOR) {
+                (
+                    ix::RemoveAuthorizedProgram::try_from_slice(args)
+                        .ok()?
+                        .program,
+                    false,
+                )
+            } else {
+                return None;
+            };
+            Some(AuthorizationChange {
+                user,
+                program,
+                authorized,
+            })
+        })
+        .collect()
+}
+
+/// Every `ErrorCode` in declaration order. `anchor_lang` offers no way to
+/// turn a code back into a variant; a test checks this against the program.
+const VAULT_ERRORS: [ErrorCode; 28] = [
+    ErrorCode::InvalidMint,
+    ErrorCode::InvalidAmount,
+    ErrorCode::AuthorizationAlreadyExists,
+    ErrorCode::AuthorizedProgramsCapacity,
+    ErrorCode::Unauthorized,
+    ErrorCode::InsufficientFunds,
+    ErrorCode::ActivePosition,
+    ErrorCode::InsufficientLockedFunds,
+    ErrorCode::Overflow,
+    ErrorCode::Underflow,
+    ErrorCode::InvalidAuthority,
+    ErrorCode::WithdrawalDelayNotMet,
+    ErrorCode::AlreadyExecuted,
+    ErrorCode::InvalidVaultAuthority,
+    ErrorCode::InvalidWithdrawalRequest,
+    ErrorCode::ForcedUnlockInactivityNotMet,
+    ErrorCode::ForcedUnlockDelayNotMet,
+    ErrorCode::SameVaultSettlement,
+    ErrorCode::PnlExceedsMargin,
+    ErrorCode::InvalidGuardianSet,
+    ErrorCode::NotGuardian,
+    ErrorCode::RecoveryAlreadyApproved,
+    ErrorCode::RecoveryThresholdNotMet,
+    ErrorCode::RecoveryDelayNotMet,
+    ErrorCode::InvalidSignerSet,
+    ErrorCode::AlreadyMigrated,
+    ErrorCode::ForcedUnlockPending,
+    ErrorCode::MultisigThresholdNotMet,
+];
+
+/// The vault `ErrorCode` in an RPC error such as
+/// `Error processing Instruction 2: custom program error: 0x1775`.
+pub fn decode_vault_error(error: &str) -> Option<ErrorCode> {
+    let hex = error.split("custom program error: 0x").nth(1)?;
+    let hex: String = hex.chars().take_while(|c| c.is_ascii_hexdigit()).collect();
+    let code = u32::from_str_radix(&hex, 16).ok()?;
+    VAULT_ERRORS.into_iter().find(|e| u32::from(*e) == code)
+}
+
+/// What gets stored as the row's error: `Name: message` for vault errors,
+/// the RPC error otherwise.
+pub fn describe_failure(error: &str) -> String {
+    match decode_vault_error(error) {
+        Some(code) => format!("{}: {}", code.name(), code),
+        None => error.to_string(),
+    }
+}
+
+pub struct TransactionConfirmer {
+    chain: Arc<dyn ChainClient>,
+    database: Arc<Database>,
+    ws: Arc<WebSocketManager>,
+    program_id: Pubkey,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl TransactionConfirmer {
+    pub fn new(
+        chain: Arc<dyn ChainClient>,
+        database: Arc<Database>,
+        ws: Arc<WebSocketManager>,
+        program_id: Pubkey,
+    ) -> Self {
+        Self {
+            chain,
+            database,
+            ws,
+            program_id,
+            metrics: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn start(self: Arc<Self>, every: Duration) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.poll().await {
+                    eprintln!("❌ Transaction confirmer poll failed: {}", e);
+                }
+            }
+        });
+
+        println!("📡 Transaction confirmer started");
+    }
+
+    /// Check every unsettled row once. Returns how many rows changed.
+    pub async fn poll(&self) -> Result<usize> {
+        let rows = self.database.get_unsettled_transactions().await?;
+        let mut signatures: Vec<Signature> = rows
+            .iter()
+            .filter_map(|row| row.signature.parse().ok())
+            .collect();
+        signatures.sort();
+        signatures.dedup();
+
+        let now = Utc::now().timestamp();
+        let mut changed = 0;
+        for batch in signatures.chunks(STATUS_BATCH) {
+            let statuses = self.chain.get_signature_statuses(batch).await?;
+            for (signature, status) in batch.iter().zip(statuses) {
+                let signature = signature.to_string();
+                let current: Vec<&TransactionRecord> =
+                    rows.iter().filter(|r| r.signature == signature).collect();
+
+                let (status, slot, error) = match status {
+                    Some(s) => match (s.err, s.commitment) {
+                        (Some(err), _) => (
+                            TransactionStatus::Failed,
+                            Some(s.slot),
+                            Some(describe_failure(&err)),
+                        ),
+                        (None, Commitment::Finalized) => {
+                            (TransactionStatus::Finalized, Some(s.slot), None)
+                        }
+                        (None, Commitment::Confirmed) => {
+                            (TransactionStatus::Confirmed, Some(s.slot), None)
+                        }
+                        (None, Commitment::Processed) => continue,
+                    },
+                    None if current.iter().all(|r| {
+                        r.status == TransactionStatus::Pending
+                            && now - r.timestamp > PENDING_TIMEOUT_SECS
+                    }) =>
+                    {
+                        (
+                            TransactionStatus::Failed,
+                            None,
+                            Some("Transaction expired before it landed".to_string()),
+                        )
+                    }
+                    None => continue,
+                };
+                if current.iter().all(|r| r.status == status) {
+                    continue;
+                }
+
+                let updated = self
+                    .database
+                    .update_transaction_status(&signature, status.clone(), slot, error)
+                    .await?;
+                self.count(&status);
+                changed += updated.len();
+                self.push(&updated);
+            }
+        }
+        Ok(changed)
+    }
+
+    fn count(&self, status: &TransactionStatus) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_status_change(status);
+        }
+    }
+
+    fn push(&self, rows: &[TransactionRecord]) {
+        for row in rows {
+            self.ws.broadcast(WsMessage::TransactionStatus {
+                signature: row.signature.clone(),
+                user: row.user.clone(),
+                tx_type: row.tx_type.clone(),
+                status: row.status.clone(),
+                slot: row.slot,
+                error: row.error.clone(),
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl SendObserver for TransactionConfirmer {
+    async fn signed(&self, tx: &Transaction, replaces: Option<&Signature>) {
+        let signature = tx.signatures[0].to_string();
+        if let Some(old) = replaces {
+            match self
+                .database
+                .repoint_transactions(&old.to_string(), &signature)
+                .await
+            {
+                Ok(rows) if !rows.is_empty() => return self.push(&rows),
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to repoint {} to {}: {}", old, signature, e),
+            }
+        }
+
+        let now = Utc::now().timestamp();
+        for op in decode_vault_ops(&self.program_id, tx) {
+            let record = TransactionRecord {
+                id: Uuid::new_v4().to_string(),
+                user: op.user.to_string(),
+                tx_type: op.tx_type,
+                amount: op.amount,
+                signature: signature.clone(),
+                status: TransactionStatus::Pending,
+                timestamp: now,
+                slot: None,
+                block_time: None,
+                log_index: None,
+                error: None,
+                confirmed_at: None,
+            };
+            match self.database.insert_transaction(record.clone()).await {
+                Ok(()) => self.push(&[record]),
+                Err(e) => eprintln!("Failed to store transaction: {}", e),
+            }
+        }
+    }
+
+    async fn failed(&self, signature: &Signature, error: &str) {
+        match self
+            .database
+            .update_transaction_status(
+                &signature.to_string(),
+                TransactionStatus::Failed,
+                None,
+                Some(describe_failure(error)),
+            )
+            .await
+        {
+            Ok(rows) => {
+                self.count(&TransactionStatus::Failed);
+                self.push(&rows)
+            }
+            Err(e) => eprintln!("Failed to mark {} failed: {}", signature, e),
+        }
+    }
+}
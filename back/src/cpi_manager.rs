@@ -24,7 +24,7 @@ impl CPIManager {
         // Call the lock instruction
         let sig = self
             .vault_manager
-            .lock(user, self.vault_manager.program.id(), amount)
+            .lock(user, self.vault_manager.program_id, amount)
             .await?;
 
         println!("✅ Locked successfully: {}", sig);
@@ -45,7 +45,7 @@ impl CPIManager {
 
         let sig = self
             .vault_manager
-            .unlock(user, self.vault_manager.program.id(), amount)
+            .unlock(user, self.vault_manager.program_id, amount)
             .await?;
 
         println!("✅ Unlocked successfully: {}", sig);
@@ -67,7 +67,7 @@ impl CPIManager {
 
         let sig = self
             .vault_manager
-            .transfer(from, to, self.vault_manager.program.id(), amount)
+            .transfer(from, to, self.vault_manager.program_id, amount)
             .await?;
 
         println!("✅ Liquidation transfer successful: {}", sig);
@@ -95,7 +95,7 @@ impl CPIManager {
     pub async fn safe_lock(&self, user: Pubkey, amount: u64) -> Result<Option<String>> {
         match self
             .vault_manager
-            .lock(user, self.vault_manager.program.id(), amount)
+            .lock(user, self.vault_manager.program_id, amount)
             .await
         {
             Ok(sig) => Ok(Some(sig)),
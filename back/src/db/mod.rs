@@ -1,6 +1,7 @@
 use crate::db::postgres::Alert;
 use anchor_client::solana_sdk::pubkey::Pubkey;
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
@@ -9,7 +10,10 @@ pub mod models;
 pub mod postgres;
 
 pub use models::*;
-pub use postgres::PostgresDatabase;
+pub use postgres::{PostgresDatabase, VaultAccount};
+
+/// The vaults each indexed event touched, by signature and log index.
+type EventVaults = HashMap<(String, u32), Vec<ChainEventVault>>;
 
 #[derive(Clone)]
 pub struct Database {
@@ -17,6 +21,13 @@ pub struct Database {
     transactions: Arc<RwLock<Vec<TransactionRecord>>>,
     balance_snapshots: Arc<RwLock<Vec<BalanceSnapshot>>>,
     audit_logs: Arc<RwLock<Vec<AuditLog>>>,
+    transaction_attempts: Arc<RwLock<Vec<TransactionAttempt>>>,
+    chain_events: Arc<RwLock<Vec<ChainEvent>>>,
+    chain_event_vaults: Arc<RwLock<EventVaults>>,
+    checkpoints: Arc<RwLock<HashMap<String, IndexerCheckpoint>>>,
+    reconciliation_logs: Arc<RwLock<Vec<ReconciliationLog>>>,
+    multisig_operations: Arc<RwLock<Vec<MultisigOperation>>>,
+    idempotency_keys: Arc<RwLock<HashMap<(String, String), IdempotencyRecord>>>,
 }
 
 impl Database {
@@ -26,6 +37,13 @@ impl Database {
             transactions: Arc::new(RwLock::new(Vec::new())),
             balance_snapshots: Arc::new(RwLock::new(Vec::new())),
             audit_logs: Arc::new(RwLock::new(Vec::new())),
+            transaction_attempts: Arc::new(RwLock::new(Vec::new())),
+            chain_events: Arc::new(RwLock::new(Vec::new())),
+            chain_event_vaults: Arc::new(RwLock::new(HashMap::new())),
+            checkpoints: Arc::new(RwLock::new(HashMap::new())),
+            reconciliation_logs: Arc::new(RwLock::new(Vec::new())),
+            multisig_operations: Arc::new(RwLock::new(Vec::new())),
+            idempotency_keys: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -33,6 +51,14 @@ impl Database {
         self.postgres.is_some()
     }
 
+    /// Check the Postgres connection; the in-memory store is always up.
+    pub async fn ping(&self) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            pg.ping().await.context("postgres is unreachable")?;
+        }
+        Ok(())
+    }
+
     pub async fn register_vault(
         &self,
         owner: &Pubkey,
@@ -51,6 +77,35 @@ impl Database {
         Ok(())
     }
 
+    /// Like `register_vault`, but tolerates a vault that is already known.
+    pub async fn ensure_vault(
+        &self,
+        owner: &Pubkey,
+        vault_pda: &Pubkey,
+        token_account: &Pubkey,
+    ) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            pg.ensure_vault(
+                &owner.to_string(),
+                &vault_pda.to_string(),
+                &token_account.to_string(),
+            )
+            .await
+            .context("failed to persist vault metadata")?;
+        }
+        Ok(())
+    }
+
+    /// Record that a recovery moved `vault_pda` to `owner`.
+    pub async fn set_vault_owner(&self, vault_pda: &Pubkey, owner: &Pubkey) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            pg.set_vault_owner(&vault_pda.to_string(), &owner.to_string())
+                .await
+                .context("failed to update vault owner")?;
+        }
+        Ok(())
+    }
+
     pub async fn update_vault_balances(
         &self,
         owner: &str,
@@ -101,6 +156,99 @@ impl Database {
             .collect())
     }
 
+    /// One page of `user`'s history; see [`TransactionQuery`].
+    pub async fn get_transaction_page(
+        &self,
+        user: &str,
+        query: &TransactionQuery,
+    ) -> Result<TransactionPage> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_transaction_page(user, query)
+                .await
+                .context("failed to load transaction page from postgres");
+        }
+
+        // Second timestamps, so ties within a second fall back to the id.
+        let position = |tx: &TransactionRecord| (tx.timestamp * 1_000_000, tx.id.clone());
+        let transactions = self.transactions.read().await;
+        let mut matching: Vec<&TransactionRecord> = transactions
+            .iter()
+            .filter(|tx| tx.user == user)
+            .filter(|tx| query.tx_type.as_ref().is_none_or(|t| &tx.tx_type == t))
+            .filter(|tx| query.status.as_ref().is_none_or(|s| &tx.status == s))
+            .filter(|tx| query.min_amount.is_none_or(|min| tx.amount >= min))
+            .filter(|tx| query.max_amount.is_none_or(|max| tx.amount <= max))
+            .filter(|tx| query.from.is_none_or(|from| tx.timestamp >= from))
+            .filter(|tx| query.to.is_none_or(|to| tx.timestamp < to))
+            .collect();
+        matching.sort_by_key(|tx| position(tx));
+        if query.order == SortOrder::Desc {
+            matching.reverse();
+        }
+        let total = matching.len() as u64;
+
+        let mut page: Vec<&TransactionRecord> = matching
+            .into_iter()
+            .filter(|tx| {
+                query.after.as_ref().is_none_or(|after| {
+                    let key = (after.created_at, after.id.clone());
+                    match query.order {
+                        SortOrder::Asc => position(tx) > key,
+                        SortOrder::Desc => position(tx) < key,
+                    }
+                })
+            })
+            .take(query.limit + 1)
+            .collect();
+        let more = page.len() > query.limit;
+        page.truncate(query.limit);
+        let next_cursor = page.last().filter(|_| more).map(|tx| {
+            let (created_at, id) = position(tx);
+            TransactionCursor { created_at, id }.encode()
+        });
+
+        Ok(TransactionPage {
+            transactions: page.into_iter().cloned().collect(),
+            total,
+            next_cursor,
+        })
+    }
+
+    pub async fn count_user_transactions(&self, user: &str) -> Result<u64> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .count_user_transactions(user)
+                .await
+                .context("failed to count user transactions");
+        }
+
+        let transactions = self.transactions.read().await;
+        Ok(transactions.iter().filter(|tx| tx.user == user).count() as u64)
+    }
+
+    /// Totals for the metrics history, counted by the database rather than
+    /// loaded row by row.
+    pub async fn get_transaction_stats(&self, active_since: i64) -> Result<TransactionStats> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_transaction_stats(active_since)
+                .await
+                .context("failed to count transactions");
+        }
+
+        let transactions = self.transactions.read().await;
+        let active: std::collections::HashSet<&str> = transactions
+            .iter()
+            .filter(|tx| tx.timestamp >= active_since)
+            .map(|tx| tx.user.as_str())
+            .collect();
+        Ok(TransactionStats {
+            total_transactions: transactions.len() as u64,
+            active_users: active.len() as u64,
+        })
+    }
+
     pub async fn get_all_transactions(&self) -> Result<Vec<TransactionRecord>> {
         if let Some(pg) = &self.postgres {
             return pg
@@ -113,6 +261,346 @@ impl Database {
         Ok(transactions.clone())
     }
 
+    pub async fn get_transactions_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<Vec<TransactionRecord>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_transactions_by_signature(signature)
+                .await
+                .context("failed to load transactions from postgres");
+        }
+
+        let transactions = self.transactions.read().await;
+        Ok(transactions
+            .iter()
+            .filter(|tx| tx.signature == signature)
+            .cloned()
+            .collect())
+    }
+
+    /// Pending and Confirmed rows, which the confirmer still has to settle.
+    pub async fn get_unsettled_transactions(&self) -> Result<Vec<TransactionRecord>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_unsettled_transactions()
+                .await
+                .context("failed to load unsettled transactions");
+        }
+
+        let transactions = self.transactions.read().await;
+        Ok(transactions
+            .iter()
+            .filter(|tx| !is_settled(&tx.status))
+            .cloned()
+            .collect())
+    }
+
+    /// Move every unsettled row of `signature` to `status` and return the
+    /// rows that changed. Finalized and Failed rows are left alone.
+    pub async fn update_transaction_status(
+        &self,
+        signature: &str,
+        status: TransactionStatus,
+        slot: Option<u64>,
+        error: Option<String>,
+    ) -> Result<Vec<TransactionRecord>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .update_transaction_status(signature, &status, slot, error.as_deref())
+                .await
+                .context("failed to update transaction status");
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let confirmed = matches!(
+            status,
+            TransactionStatus::Confirmed | TransactionStatus::Finalized
+        );
+        let mut transactions = self.transactions.write().await;
+        Ok(transactions
+            .iter_mut()
+            .filter(|tx| tx.signature == signature && !is_settled(&tx.status))
+            .map(|tx| {
+                tx.status = status.clone();
+                tx.slot = slot.or(tx.slot);
+                tx.error = error.clone();
+                if confirmed {
+                    tx.confirmed_at = tx.confirmed_at.or(Some(now));
+                }
+                tx.clone()
+            })
+            .collect())
+    }
+
+    /// Point the pending rows of `old` at the transaction that replaced it.
+    pub async fn repoint_transactions(
+        &self,
+        old: &str,
+        new: &str,
+    ) -> Result<Vec<TransactionRecord>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .repoint_transactions(old, new)
+                .await
+                .context("failed to repoint transactions");
+        }
+
+        let mut transactions = self.transactions.write().await;
+        Ok(transactions
+            .iter_mut()
+            .filter(|tx| tx.signature == old && matches!(tx.status, TransactionStatus::Pending))
+            .map(|tx| {
+                tx.signature = new.to_string();
+                tx.clone()
+            })
+            .collect())
+    }
+
+    /// Store an indexed event with the vaults it touched and its
+    /// `transactions` rows. Rows the REST handlers already wrote for the same
+    /// signature are completed rather than duplicated. Returns false if the
+    /// event was indexed before.
+    pub async fn record_chain_event(
+        &self,
+        event: ChainEvent,
+        vaults: Vec<ChainEventVault>,
+        rows: Vec<TransactionRecord>,
+    ) -> Result<bool> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .record_chain_event(&event, &vaults, &rows)
+                .await
+                .context("failed to persist chain event");
+        }
+
+        let mut events = self.chain_events.write().await;
+        if events
+            .iter()
+            .any(|e| e.signature == event.signature && e.log_index == event.log_index)
+        {
+            return Ok(false);
+        }
+        self.chain_event_vaults
+            .write()
+            .await
+            .insert((event.signature.clone(), event.log_index), vaults);
+        events.push(event);
+
+        let mut transactions = self.transactions.write().await;
+        for row in rows {
+            let existing = transactions.iter_mut().find(|tx| {
+                tx.signature == row.signature
+                    && tx.user == row.user
+                    && std::mem::discriminant(&tx.tx_type) == std::mem::discriminant(&row.tx_type)
+                    && (tx.log_index.is_none() || tx.log_index == row.log_index)
+            });
+            match existing {
+                Some(tx) => {
+                    tx.amount = row.amount;
+                    if !matches!(tx.status, TransactionStatus::Finalized) {
+                        tx.status = row.status;
+                    }
+                    tx.confirmed_at = tx.confirmed_at.or(Some(chrono::Utc::now().timestamp()));
+                    tx.slot = row.slot;
+                    tx.block_time = row.block_time;
+                    tx.log_index = row.log_index;
+                }
+                None => transactions.push(row),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Every indexed event, oldest first.
+    pub async fn get_chain_events(&self) -> Result<Vec<ChainEvent>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_chain_events()
+                .await
+                .context("failed to load chain events");
+        }
+        Ok(self.chain_events.read().await.clone())
+    }
+
+    /// `vault`'s indexed events within `range`, oldest first.
+    pub async fn get_vault_chain_events(
+        &self,
+        vault: &str,
+        range: VaultEventRange,
+    ) -> Result<Vec<ChainEvent>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_vault_chain_events(vault, range)
+                .await
+                .context("failed to load vault chain events");
+        }
+
+        let links = self.chain_event_vaults.read().await;
+        let mut events: Vec<ChainEvent> = self
+            .chain_events
+            .read()
+            .await
+            .iter()
+            .filter(|e| {
+                links
+                    .get(&(e.signature.clone(), e.log_index))
+                    .is_some_and(|vaults| {
+                        vaults
+                            .iter()
+                            .any(|link| link.vault == vault && in_range(&range, link))
+                    })
+            })
+            .cloned()
+            .collect();
+        events.sort_by_key(|e| (e.slot, e.log_index));
+        Ok(events)
+    }
+
+    /// The last sequence among `vault`'s indexed events at or before
+    /// `cutoff`, and the first among those after it.
+    pub async fn get_vault_sequence_bounds(
+        &self,
+        vault: &str,
+        cutoff: EventCutoff,
+    ) -> Result<(Option<u64>, Option<u64>)> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_vault_sequence_bounds(vault, cutoff)
+                .await
+                .context("failed to load vault sequence bounds");
+        }
+
+        let links = self.chain_event_vaults.read().await;
+        let (mut last, mut first_after) = (None, None);
+        for event in self.chain_events.read().await.iter() {
+            let Some(vaults) = links.get(&(event.signature.clone(), event.log_index)) else {
+                continue;
+            };
+            for link in vaults.iter().filter(|link| link.vault == vault) {
+                let Some(sequence) = link.sequence else {
+                    continue;
+                };
+                let included = match cutoff {
+                    EventCutoff::Time(at) => link.timestamp <= at,
+                    EventCutoff::Slot(slot) => event.slot <= slot,
+                };
+                if included {
+                    last = last.max(Some(sequence));
+                } else {
+                    first_after = Some(first_after.map_or(sequence, |f: u64| f.min(sequence)));
+                }
+            }
+        }
+        Ok((last, first_after))
+    }
+
+    /// Indexed events with no vaults recorded, from before vaults were.
+    pub async fn get_unlinked_chain_events(&self) -> Result<Vec<ChainEvent>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_unlinked_chain_events()
+                .await
+                .context("failed to load unlinked chain events");
+        }
+
+        let links = self.chain_event_vaults.read().await;
+        Ok(self
+            .chain_events
+            .read()
+            .await
+            .iter()
+            .filter(|e| !links.contains_key(&(e.signature.clone(), e.log_index)))
+            .cloned()
+            .collect())
+    }
+
+    /// Record the vaults an already indexed event touched.
+    pub async fn link_chain_event(
+        &self,
+        event: &ChainEvent,
+        vaults: Vec<ChainEventVault>,
+    ) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .link_chain_event(event, &vaults)
+                .await
+                .context("failed to link chain event");
+        }
+
+        self.chain_event_vaults
+            .write()
+            .await
+            .insert((event.signature.clone(), event.log_index), vaults);
+        Ok(())
+    }
+
+    /// Totals of the `vault_accounts` rows, or `None` without Postgres.
+    pub async fn get_tvl_summary(&self) -> Result<Option<TvlSummary>> {
+        match &self.postgres {
+            Some(pg) => Ok(Some(
+                pg.get_tvl_summary()
+                    .await
+                    .context("failed to load tvl_summary")?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Registered vault rows; always empty without Postgres.
+    pub async fn get_all_vaults(&self) -> Result<Vec<VaultAccount>> {
+        if let Some(pg) = &self.postgres {
+            return pg.get_all_vaults().await.context("failed to load vaults");
+        }
+        Ok(Vec::new())
+    }
+
+    /// Vault PDAs to walk during backfill: registered vaults with Postgres,
+    /// otherwise every vault an indexed event mentioned.
+    pub async fn get_vault_pdas(&self) -> Result<Vec<String>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_vault_pdas()
+                .await
+                .context("failed to load vault PDAs");
+        }
+
+        let mut vaults: Vec<String> = self
+            .chain_events
+            .read()
+            .await
+            .iter()
+            .filter_map(|e| e.vault.clone())
+            .collect();
+        vaults.sort();
+        vaults.dedup();
+        Ok(vaults)
+    }
+
+    pub async fn get_checkpoint(&self, address: &str) -> Result<Option<IndexerCheckpoint>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_checkpoint(address)
+                .await
+                .context("failed to load indexer checkpoint");
+        }
+        Ok(self.checkpoints.read().await.get(address).cloned())
+    }
+
+    pub async fn set_checkpoint(&self, checkpoint: IndexerCheckpoint) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            pg.set_checkpoint(&checkpoint)
+                .await
+                .context("failed to persist indexer checkpoint")?;
+        }
+        self.checkpoints
+            .write()
+            .await
+            .insert(checkpoint.address.clone(), checkpoint);
+        Ok(())
+    }
+
     // Balance snapshot operations
     pub async fn insert_snapshot(&self, snapshot: BalanceSnapshot) -> Result<()> {
         if let Some(pg) = &self.postgres {
@@ -142,6 +630,44 @@ impl Database {
             .collect())
     }
 
+    /// The latest snapshot of `vault` read at or before its sequence `sequence`.
+    pub async fn get_snapshot_at_sequence(
+        &self,
+        vault: &str,
+        sequence: u64,
+    ) -> Result<Option<BalanceSnapshot>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_snapshot_at_sequence(vault, sequence)
+                .await
+                .context("failed to load snapshot from postgres");
+        }
+
+        let snapshots = self.balance_snapshots.read().await;
+        Ok(snapshots
+            .iter()
+            .filter(|s| s.vault == vault && s.sequence.is_some_and(|seq| seq <= sequence))
+            .max_by_key(|s| (s.sequence, s.timestamp))
+            .cloned())
+    }
+
+    /// The latest snapshot of `vault` taken at or before `at`.
+    pub async fn get_snapshot_at(&self, vault: &str, at: i64) -> Result<Option<BalanceSnapshot>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_snapshot_at(vault, at)
+                .await
+                .context("failed to load snapshot from postgres");
+        }
+
+        let snapshots = self.balance_snapshots.read().await;
+        Ok(snapshots
+            .iter()
+            .filter(|s| s.vault == vault && s.timestamp <= at)
+            .max_by_key(|s| s.timestamp)
+            .cloned())
+    }
+
     // Audit log operations
     pub async fn insert_audit_log(&self, log: AuditLog) -> Result<()> {
         if let Some(pg) = &self.postgres {
@@ -170,17 +696,374 @@ impl Database {
         })
     }
 
-    pub async fn log_reconciliation(
+    // Transaction sender attempts
+    pub async fn insert_transaction_attempt(&self, attempt: TransactionAttempt) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            pg.insert_transaction_attempt(&attempt)
+                .await
+                .context("failed to persist transaction attempt")?;
+        }
+
+        let mut attempts = self.transaction_attempts.write().await;
+        attempts.push(attempt);
+        Ok(())
+    }
+
+    pub async fn get_all_transaction_attempts(&self) -> Vec<TransactionAttempt> {
+        self.transaction_attempts.read().await.clone()
+    }
+
+    /// Every attempt of the send that produced `signature`, including rebuilds.
+    pub async fn get_transaction_attempts(&self, signature: &str) -> Vec<TransactionAttempt> {
+        let attempts = self.transaction_attempts.read().await;
+        let send_ids: std::collections::HashSet<&str> = attempts
+            .iter()
+            .filter(|a| a.signature == signature)
+            .map(|a| a.send_id.as_str())
+            .collect();
+        attempts
+            .iter()
+            .filter(|a| send_ids.contains(a.send_id.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn log_reconciliation(&self, log: ReconciliationLog) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            pg.log_reconciliation(&log)
+                .await
+                .context("failed to persist reconciliation log")?;
+            return Ok(());
+        }
+        self.reconciliation_logs.write().await.push(log);
+        Ok(())
+    }
+
+    /// Reconciliation results, newest first, optionally only one status.
+    pub async fn get_reconciliation_logs(
         &self,
-        user: &str,
-        onchain: u64,
-        offchain: u64,
-        status: &str,
+        status: Option<ReconciliationStatus>,
+    ) -> Result<Vec<ReconciliationLog>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_reconciliation_logs(status, 1000)
+                .await
+                .context("failed to load reconciliation logs");
+        }
+
+        let logs = self.reconciliation_logs.read().await;
+        Ok(logs
+            .iter()
+            .rev()
+            .filter(|log| status.is_none_or(|s| log.status == s))
+            .cloned()
+            .collect())
+    }
+
+    /// Mark a `MISMATCH` as `RESOLVED` with the action taken. Returns `None`
+    /// if there is no open mismatch with that id.
+    pub async fn resolve_reconciliation(
+        &self,
+        id: &str,
+        action: &str,
+    ) -> Result<Option<ReconciliationLog>> {
+        if let Some(pg) = &self.postgres {
+            let Ok(id) = Uuid::parse_str(id) else {
+                return Ok(None);
+            };
+            return pg
+                .resolve_reconciliation(id, action)
+                .await
+                .context("failed to resolve reconciliation log");
+        }
+
+        let mut logs = self.reconciliation_logs.write().await;
+        let Some(log) = logs
+            .iter_mut()
+            .find(|log| log.id == id && log.status == ReconciliationStatus::Mismatch)
+        else {
+            return Ok(None);
+        };
+        log.status = ReconciliationStatus::Resolved;
+        log.resolution_action = Some(action.to_string());
+        log.resolved_at = Some(chrono::Utc::now().timestamp());
+        Ok(Some(log.clone()))
+    }
+
+    pub async fn insert_multisig_operation(&self, op: MultisigOperation) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            pg.insert_multisig_operation(&op)
+                .await
+                .context("failed to persist multisig operation")?;
+            return Ok(());
+        }
+        self.multisig_operations.write().await.push(op);
+        Ok(())
+    }
+
+    pub async fn get_multisig_operation(&self, id: &str) -> Result<Option<MultisigOperation>> {
+        if let Some(pg) = &self.postgres {
+            let Ok(id) = Uuid::parse_str(id) else {
+                return Ok(None);
+            };
+            return pg
+                .get_multisig_operation(id)
+                .await
+                .context("failed to load multisig operation");
+        }
+
+        let operations = self.multisig_operations.read().await;
+        Ok(operations.iter().find(|op| op.id == id).cloned())
+    }
+
+    /// Pending operations, oldest first, optionally only those `approver`
+    /// can sign.
+    pub async fn get_pending_multisig_operations(
+        &self,
+        approver: Option<&str>,
+    ) -> Result<Vec<MultisigOperation>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .get_pending_multisig_operations(approver)
+                .await
+                .context("failed to load multisig operations");
+        }
+
+        let operations = self.multisig_operations.read().await;
+        Ok(operations
+            .iter()
+            .filter(|op| op.status == MultisigStatus::Pending)
+            .filter(|op| {
+                approver.is_none_or(|a| {
+                    op.owner == a
+                        || op.signers.iter().any(|key| key == a)
+                        || op.signing.iter().any(|key| key == a)
+                })
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Pending operations on `vault` proposed at or after `created_after`.
+    pub async fn count_pending_multisig_operations(
+        &self,
+        vault: &str,
+        created_after: i64,
+    ) -> Result<usize> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .count_pending_multisig_operations(vault, created_after)
+                .await
+                .context("failed to count multisig operations");
+        }
+
+        let operations = self.multisig_operations.read().await;
+        Ok(operations
+            .iter()
+            .filter(|op| op.status == MultisigStatus::Pending)
+            .filter(|op| op.vault == vault && op.created_at >= created_after)
+            .count())
+    }
+
+    /// Returns false if the signer already approved, or there is no such
+    /// operation.
+    pub async fn add_multisig_approval(
+        &self,
+        id: &str,
+        approval: MultisigApproval,
+    ) -> Result<bool> {
+        if let Some(pg) = &self.postgres {
+            let Ok(id) = Uuid::parse_str(id) else {
+                return Ok(false);
+            };
+            return pg
+                .add_multisig_approval(id, &approval)
+                .await
+                .context("failed to persist multisig approval");
+        }
+
+        let mut operations = self.multisig_operations.write().await;
+        let Some(op) = operations.iter_mut().find(|op| op.id == id) else {
+            return Ok(false);
+        };
+        if op.approval(&approval.signer).is_some() {
+            return Ok(false);
+        }
+        op.approvals.push(approval);
+        Ok(true)
+    }
+
+    /// Store the transaction built for a pending operation's approvers.
+    /// Returns false if it already has one or is no longer pending.
+    pub async fn set_multisig_transaction(
+        &self,
+        id: &str,
+        transaction: &str,
+        message: &str,
+        signing: &[String],
+    ) -> Result<bool> {
+        if let Some(pg) = &self.postgres {
+            let Ok(id) = Uuid::parse_str(id) else {
+                return Ok(false);
+            };
+            return pg
+                .set_multisig_transaction(id, transaction, message, signing)
+                .await
+                .context("failed to store multisig transaction");
+        }
+
+        let mut operations = self.multisig_operations.write().await;
+        let Some(op) = operations.iter_mut().find(|op| {
+            op.id == id && op.status == MultisigStatus::Pending && op.transaction.is_none()
+        }) else {
+            return Ok(false);
+        };
+        op.transaction = Some(transaction.to_string());
+        op.transaction_message = Some(message.to_string());
+        op.signing = signing.to_vec();
+        Ok(true)
+    }
+
+    /// Returns false if the signer already signed the operation's
+    /// transaction, or there is no such operation.
+    pub async fn add_multisig_signature(
+        &self,
+        id: &str,
+        signature: MultisigApproval,
+    ) -> Result<bool> {
+        if let Some(pg) = &self.postgres {
+            let Ok(id) = Uuid::parse_str(id) else {
+                return Ok(false);
+            };
+            return pg
+                .add_multisig_signature(id, &signature)
+                .await
+                .context("failed to persist multisig signature");
+        }
+
+        let mut operations = self.multisig_operations.write().await;
+        let Some(op) = operations.iter_mut().find(|op| op.id == id) else {
+            return Ok(false);
+        };
+        if op.signature(&signature.signer).is_some() {
+            return Ok(false);
+        }
+        op.signatures.push(signature);
+        Ok(true)
+    }
+
+    /// Record how a pending operation ended. Returns false if it had already
+    /// left `pending`, so only one caller submits or expires it.
+    pub async fn finish_multisig_operation(
+        &self,
+        id: &str,
+        status: MultisigStatus,
+        tx_signature: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<bool> {
+        if let Some(pg) = &self.postgres {
+            let Ok(id) = Uuid::parse_str(id) else {
+                return Ok(false);
+            };
+            return pg
+                .finish_multisig_operation(id, status, tx_signature, error)
+                .await
+                .context("failed to update multisig operation");
+        }
+
+        let mut operations = self.multisig_operations.write().await;
+        let Some(op) = operations
+            .iter_mut()
+            .find(|op| op.id == id && op.status == MultisigStatus::Pending)
+        else {
+            return Ok(false);
+        };
+        op.status = status;
+        op.tx_signature = tx_signature.map(str::to_string);
+        op.error = error.map(str::to_string);
+        if status == MultisigStatus::Executed {
+            op.executed_at = Some(chrono::Utc::now().timestamp());
+        }
+        Ok(true)
+    }
+
+    /// Claim a wallet's idempotency key for a new request, forgetting keys
+    /// created before `expires_before` and taking over claims still in
+    /// progress since before `lease_expires_before`. Returns the existing
+    /// record if the key is already taken.
+    pub async fn claim_idempotency_key(
+        &self,
+        record: IdempotencyRecord,
+        expires_before: i64,
+        lease_expires_before: i64,
+    ) -> Result<Option<IdempotencyRecord>> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .claim_idempotency_key(&record, expires_before, lease_expires_before)
+                .await
+                .context("failed to claim idempotency key");
+        }
+
+        let mut keys = self.idempotency_keys.write().await;
+        keys.retain(|_, r| r.created_at >= expires_before);
+        let id = (record.wallet.clone(), record.key.clone());
+        if let Some(existing) = keys.get(&id) {
+            let abandoned = existing.status == IdempotencyStatus::InProgress
+                && existing.created_at < lease_expires_before;
+            if !abandoned {
+                return Ok(Some(existing.clone()));
+            }
+        }
+        keys.insert(id, record);
+        Ok(None)
+    }
+
+    /// Store the response for `claim`, unless its lease was taken over.
+    pub async fn complete_idempotency_key(
+        &self,
+        claim: &IdempotencyRecord,
+        response_status: u16,
+        response_body: &str,
+        tx_signature: Option<&str>,
     ) -> Result<()> {
         if let Some(pg) = &self.postgres {
-            pg.log_reconciliation(user, onchain as i64, offchain as i64, status)
+            return pg
+                .complete_idempotency_key(claim, response_status, response_body, tx_signature)
                 .await
-                .context("failed to persist reconciliation log")?;
+                .context("failed to store idempotent response");
+        }
+
+        let mut keys = self.idempotency_keys.write().await;
+        let id = (claim.wallet.clone(), claim.key.clone());
+        if let Some(record) = keys
+            .get_mut(&id)
+            .filter(|record| record.created_at == claim.created_at)
+        {
+            record.status = IdempotencyStatus::Completed;
+            record.response_status = Some(response_status);
+            record.response_body = Some(response_body.to_string());
+            record.tx_signature = tx_signature.map(str::to_string);
+            record.completed_at = Some(chrono::Utc::now().timestamp());
+        }
+        Ok(())
+    }
+
+    /// Forget `claim` after a failed request, unless its lease was taken over.
+    pub async fn release_idempotency_key(&self, claim: &IdempotencyRecord) -> Result<()> {
+        if let Some(pg) = &self.postgres {
+            return pg
+                .release_idempotency_key(claim)
+                .await
+                .context("failed to release idempotency key");
+        }
+
+        let mut keys = self.idempotency_keys.write().await;
+        let id = (claim.wallet.clone(), claim.key.clone());
+        if keys
+            .get(&id)
+            .is_some_and(|record| record.created_at == claim.created_at)
+        {
+            keys.remove(&id);
         }
         Ok(())
     }
@@ -200,6 +1083,20 @@ impl Database {
         Ok(())
     }
 
+    /// Close an active alert. Alerts only exist in Postgres, so without it
+    /// there is never one to resolve.
+    pub async fn resolve_alert(&self, id: &str) -> Result<bool> {
+        let Some(pg) = &self.postgres else {
+            return Ok(false);
+        };
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(false);
+        };
+        pg.resolve_alert(id)
+            .await
+            .context("failed to resolve alert")
+    }
+
     pub async fn get_active_alerts(&self) -> Result<Vec<Alert>> {
         if let Some(pg) = &self.postgres {
             return pg
@@ -225,3 +1122,23 @@ impl Database {
             .collect())
     }
 }
+
+fn is_settled(status: &TransactionStatus) -> bool {
+    matches!(
+        status,
+        TransactionStatus::Finalized | TransactionStatus::Failed
+    )
+}
+
+/// The in-memory equivalent of the range filter `get_vault_chain_events`
+/// applies in SQL; a bound on sequence excludes events without one.
+fn in_range(range: &VaultEventRange, link: &ChainEventVault) -> bool {
+    range
+        .after_sequence
+        .is_none_or(|after| link.sequence.is_some_and(|seq| seq > after))
+        && range
+            .through_sequence
+            .is_none_or(|through| link.sequence.is_some_and(|seq| seq <= through))
+        && range.from_time.is_none_or(|from| link.timestamp >= from)
+        && range.to_time.is_none_or(|to| link.timestamp < to)
+}
@@ -1,3 +1,4 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,9 +10,17 @@ pub struct TransactionRecord {
     pub signature: String,
     pub status: TransactionStatus,
     pub timestamp: i64,
+    /// Where the indexer saw this on chain; `None` until the event is indexed.
+    pub slot: Option<u64>,
+    pub block_time: Option<i64>,
+    pub log_index: Option<u32>,
+    /// Decoded program error when the transaction failed.
+    pub error: Option<String>,
+    /// When the confirmer first saw it confirmed.
+    pub confirmed_at: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransactionType {
     Initialize,
     Deposit,
@@ -21,22 +30,174 @@ pub enum TransactionType {
     Transfer,
     WithdrawalRequest,
     WithdrawalExecute,
+    Settlement,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransactionStatus {
     Pending,
     Confirmed,
+    Finalized,
     Failed,
 }
 
+/// Filters, order and position for one page of a user's transaction history.
+#[derive(Debug, Clone)]
+pub struct TransactionQuery {
+    pub tx_type: Option<TransactionType>,
+    pub status: Option<TransactionStatus>,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    /// Unix time, inclusive.
+    pub from: Option<i64>,
+    /// Unix time, exclusive.
+    pub to: Option<i64>,
+    pub order: SortOrder,
+    pub limit: usize,
+    /// Continue after this row, from a previous page's `next_cursor`.
+    pub after: Option<TransactionCursor>,
+}
+
+impl Default for TransactionQuery {
+    fn default() -> Self {
+        Self {
+            tx_type: None,
+            status: None,
+            min_amount: None,
+            max_amount: None,
+            from: None,
+            to: None,
+            order: SortOrder::Desc,
+            limit: 50,
+            after: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Position in the `(created_at, id)` order. `created_at` is in
+/// microseconds, so rows created in the same second keep their order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionCursor {
+    pub created_at: i64,
+    pub id: String,
+}
+
+impl TransactionCursor {
+    /// Opaque form handed to clients.
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.created_at, self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let text = String::from_utf8(bytes).ok()?;
+        let (created_at, id) = text.split_once(':')?;
+        let id = uuid::Uuid::parse_str(id).ok()?.to_string();
+        Some(Self {
+            created_at: created_at.parse().ok()?,
+            id,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<TransactionRecord>,
+    /// Rows matching the filters, across all pages.
+    pub total: u64,
+    pub next_cursor: Option<String>,
+}
+
+/// Counts over the whole `transactions` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionStats {
+    pub total_transactions: u64,
+    /// Users with a transaction since the time asked about.
+    pub active_users: u64,
+}
+
+/// A program event as it appeared in a transaction's logs. `data` is the
+/// base64 event payload, discriminator included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainEvent {
+    pub signature: String,
+    pub log_index: u32,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub event_name: String,
+    pub vault: Option<String>,
+    pub sequence: Option<u64>,
+    pub data: String,
+}
+
+/// A vault an indexed event touched; a transfer or settlement touches two.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainEventVault {
+    pub vault: String,
+    /// The vault's sequence after the event.
+    pub sequence: Option<u64>,
+    /// Block time, or the event's own clock reading if the block's is unknown.
+    pub timestamp: i64,
+}
+
+/// Which of a vault's indexed events to load. Unset bounds are open.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VaultEventRange {
+    /// Only sequences above this one.
+    pub after_sequence: Option<u64>,
+    /// Only sequences up to and including this one.
+    pub through_sequence: Option<u64>,
+    /// Only timestamps at or after this one.
+    pub from_time: Option<i64>,
+    /// Only timestamps before this one.
+    pub to_time: Option<i64>,
+}
+
+/// A point in a vault's indexed history: everything at or before a unix
+/// time, or at or before a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCutoff {
+    Time(i64),
+    Slot(u64),
+}
+
+/// How far the backfill has walked an address's signature history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexerCheckpoint {
+    pub address: String,
+    pub signature: String,
+    pub slot: u64,
+}
+
+/// Totals over the `vault_accounts` rows, from the `tvl_summary` view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TvlSummary {
+    pub total_vaults: u64,
+    pub total_value_locked: u64,
+    pub total_locked: u64,
+    pub total_available: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BalanceSnapshot {
     pub id: String,
     pub user: String,
+    /// The vault PDA; `user` is whoever owned it when it was read.
+    pub vault: String,
     pub total_balance: u64,
     pub locked_balance: u64,
     pub available_balance: u64,
+    /// The vault's `sequence` when the balances were read; `None` for rows
+    /// from before it was recorded.
+    pub sequence: Option<u64>,
     pub timestamp: i64,
 }
 
@@ -49,3 +210,293 @@ pub struct AuditLog {
     pub ip_address: Option<String>,
     pub timestamp: i64,
 }
+
+/// One step of the transaction sender: a send, a resend or a terminal outcome.
+/// Rebuilds change the signature, so `send_id` groups one logical send.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionAttempt {
+    pub id: String,
+    pub send_id: String,
+    pub signature: String,
+    pub attempt: u32,
+    pub rebuild: u32,
+    pub blockhash: String,
+    pub compute_unit_limit: Option<u32>,
+    pub compute_unit_price: Option<u64>,
+    pub outcome: AttemptOutcome,
+    pub error: Option<String>,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttemptOutcome {
+    Sent,
+    SendFailed,
+    SimulationFailed,
+    Expired,
+    Confirmed,
+    Failed,
+    /// Status lookups kept failing; the confirmer settles the transaction.
+    Unknown,
+}
+
+/// One comparison of a vault's on-chain `total_balance` with another source.
+/// `discrepancy` is on-chain minus the other source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationLog {
+    pub id: String,
+    pub user: String,
+    pub vault: Option<String>,
+    pub source: ReconciliationSource,
+    pub onchain_balance: u64,
+    pub offchain_balance: u64,
+    pub discrepancy: i64,
+    pub status: ReconciliationStatus,
+    /// Slot of the program account scan, when the check came from one.
+    pub slot: Option<u64>,
+    pub resolution_action: Option<String>,
+    pub checked_at: i64,
+    pub resolved_at: Option<i64>,
+}
+
+/// What the on-chain balance was compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconciliationSource {
+    /// The balance tracker's in-memory cache.
+    Cache,
+    /// The vault's `vault_accounts` row.
+    VaultAccounts,
+    /// The SPL balance of the vault's token account.
+    VaultToken,
+    /// The sum of the vault's indexed events.
+    Events,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReconciliationStatus {
+    Match,
+    Mismatch,
+    Resolved,
+}
+
+impl ReconciliationLog {
+    /// A fresh comparison, `MATCH` or `MISMATCH` depending on the balances.
+    pub fn compare(
+        user: String,
+        vault: Option<String>,
+        source: ReconciliationSource,
+        onchain_balance: u64,
+        offchain_balance: u64,
+        slot: Option<u64>,
+    ) -> Self {
+        let discrepancy = onchain_balance as i64 - offchain_balance as i64;
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user,
+            vault,
+            source,
+            onchain_balance,
+            offchain_balance,
+            discrepancy,
+            status: if discrepancy == 0 {
+                ReconciliationStatus::Match
+            } else {
+                ReconciliationStatus::Mismatch
+            },
+            slot,
+            resolution_action: None,
+            checked_at: chrono::Utc::now().timestamp(),
+            resolved_at: None,
+        }
+    }
+}
+
+impl ReconciliationSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReconciliationSource::Cache => "cache",
+            ReconciliationSource::VaultAccounts => "vault_accounts",
+            ReconciliationSource::VaultToken => "vault_token",
+            ReconciliationSource::Events => "events",
+        }
+    }
+}
+
+impl ReconciliationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReconciliationStatus::Match => "MATCH",
+            ReconciliationStatus::Mismatch => "MISMATCH",
+            ReconciliationStatus::Resolved => "RESOLVED",
+        }
+    }
+}
+
+/// A vault operation waiting on its multisig co-signers. Any `threshold` of
+/// them approve `message`; the transaction is then built for those approvers
+/// and the owner, who each sign `transaction_message`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultisigOperation {
+    pub id: String,
+    pub owner: String,
+    pub vault: String,
+    pub action: MultisigAction,
+    /// Base64 of the plain-text operation message co-signers approve.
+    pub message: String,
+    /// The vault's co-signers when the operation was proposed; any
+    /// `threshold` of them can approve it.
+    pub signers: Vec<String>,
+    pub threshold: u8,
+    pub approvals: Vec<MultisigApproval>,
+    /// Base64 bincode transaction, fee payer signature included. Built on a
+    /// durable nonce once `threshold` co-signers have approved.
+    pub transaction: Option<String>,
+    /// Base64 of the transaction message every key in `signing` signs.
+    pub transaction_message: Option<String>,
+    /// The approvers the transaction was built for, then the owner if not
+    /// among them. It needs all of their signatures.
+    pub signing: Vec<String>,
+    /// Signatures of `transaction_message`.
+    pub signatures: Vec<MultisigApproval>,
+    pub status: MultisigStatus,
+    pub tx_signature: Option<String>,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub executed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MultisigAction {
+    Withdraw { amount: u64 },
+    AddAuthorizedProgram { program: String },
+    RemoveAuthorizedProgram { program: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultisigApproval {
+    pub signer: String,
+    /// Base58 ed25519 signature of the operation's message, or of its
+    /// transaction message.
+    pub signature: String,
+    pub approved_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MultisigStatus {
+    Pending,
+    Executed,
+    Failed,
+    /// Still pending after the TTL, or the transaction's durable nonce
+    /// advanced before every signature arrived.
+    Expired,
+    /// The owner withdrew the operation.
+    Cancelled,
+}
+
+impl MultisigOperation {
+    pub fn approval(&self, signer: &str) -> Option<&MultisigApproval> {
+        self.approvals.iter().find(|a| a.signer == signer)
+    }
+
+    pub fn signature(&self, signer: &str) -> Option<&MultisigApproval> {
+        self.signatures.iter().find(|a| a.signer == signer)
+    }
+
+    /// Approvals from co-signers; the owner's only counts if listed.
+    pub fn signer_approvals(&self) -> usize {
+        self.approvals
+            .iter()
+            .filter(|a| self.signers.contains(&a.signer))
+            .count()
+    }
+
+    /// The base64 message `signer` signs next: the transaction message once
+    /// the transaction is built for them, before that the operation message
+    /// for co-signers. `None` if they have nothing to sign.
+    pub fn message_for(&self, signer: &str) -> Option<&str> {
+        match &self.transaction_message {
+            Some(message) => self
+                .signing
+                .iter()
+                .any(|key| key == signer)
+                .then_some(message.as_str()),
+            None => self
+                .signers
+                .iter()
+                .any(|key| key == signer)
+                .then_some(self.message.as_str()),
+        }
+    }
+
+    /// Keys whose signature would move the operation on: co-signers yet to
+    /// approve, or once the transaction is built, its signers yet to sign.
+    pub fn awaiting(&self) -> Vec<String> {
+        if self.transaction.is_some() {
+            return self
+                .signing
+                .iter()
+                .filter(|key| self.signature(key).is_none())
+                .cloned()
+                .collect();
+        }
+        self.signers
+            .iter()
+            .filter(|key| self.approval(key).is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// The transaction is built and everyone it needs has signed it.
+    pub fn is_ready(&self) -> bool {
+        self.transaction.is_some() && self.awaiting().is_empty()
+    }
+}
+
+impl MultisigStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MultisigStatus::Pending => "pending",
+            MultisigStatus::Executed => "executed",
+            MultisigStatus::Failed => "failed",
+            MultisigStatus::Expired => "expired",
+            MultisigStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A POST made with an `Idempotency-Key`: a hash of the request the key was
+/// first used for and, once the handler has answered, the response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    /// Session wallet the key belongs to.
+    pub wallet: String,
+    pub key: String,
+    pub request_hash: String,
+    pub status: IdempotencyStatus,
+    pub response_status: Option<u16>,
+    pub response_body: Option<String>,
+    /// `tx_signature` from the response, when it sent a transaction.
+    pub tx_signature: Option<String>,
+    pub created_at: i64,
+    pub completed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdempotencyStatus {
+    InProgress,
+    Completed,
+}
+
+impl IdempotencyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdempotencyStatus::InProgress => "in_progress",
+            IdempotencyStatus::Completed => "completed",
+        }
+    }
+}
@@ -16,14 +16,35 @@ impl PostgresDatabase {
         Ok(Self { pool })
     }
 
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
     pub async fn init_schema(&self) -> Result<()> {
         // Run migrations or create tables if they don't exist
         println!("🔧 Running database migrations...");
-        let sql_content = include_str!("../../migrations/001_initial_schema.sql");
+        let migrations = [
+            include_str!("../../migrations/001_initial_schema.sql"),
+            include_str!("../../migrations/002_transaction_attempts.sql"),
+            include_str!("../../migrations/003_chain_events.sql"),
+            include_str!("../../migrations/004_indexer_checkpoints.sql"),
+            include_str!("../../migrations/005_transaction_lifecycle.sql"),
+            include_str!("../../migrations/006_reconciliation_workflow.sql"),
+            include_str!("../../migrations/007_tvl_summary.sql"),
+            include_str!("../../migrations/008_multisig_operations.sql"),
+            include_str!("../../migrations/009_idempotency_keys.sql"),
+            include_str!("../../migrations/010_transaction_history.sql"),
+            include_str!("../../migrations/011_snapshot_sequence.sql"),
+            include_str!("../../migrations/012_chain_event_vaults.sql"),
+            include_str!("../../migrations/013_multisig_rounds.sql"),
+            include_str!("../../migrations/014_utc_timestamps.sql"),
+        ];
 
         // Remove comments and split into statements more robustly
-        let cleaned_sql: String = sql_content
-            .lines()
+        let cleaned_sql: String = migrations
+            .iter()
+            .flat_map(|sql| sql.lines())
             .filter(|line| !line.trim().starts_with("--"))
             .collect::<Vec<_>>()
             .join("\n");
@@ -89,10 +110,70 @@ impl PostgresDatabase {
         .bind(token_account)
         .fetch_one(&self.pool)
         .await?;
+        self.attach_transactions(owner).await?;
 
         Ok(row.get("id"))
     }
 
+    /// Register a vault seen on chain; a no-op if the owner already has one.
+    pub async fn ensure_vault(
+        &self,
+        owner: &str,
+        vault_pda: &str,
+        token_account: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO vault_accounts
+            (owner_pubkey, vault_pda, token_account, status)
+            VALUES ($1, $2, $3, 'ACTIVE')
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(owner)
+        .bind(vault_pda)
+        .bind(token_account)
+        .execute(&self.pool)
+        .await?;
+        self.attach_transactions(owner).await?;
+
+        Ok(())
+    }
+
+    /// Point a vault's row at its new owner after a recovery.
+    pub async fn set_vault_owner(&self, vault_pda: &str, owner: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE vault_accounts
+            SET owner_pubkey = $2, updated_at = NOW() AT TIME ZONE 'UTC'
+            WHERE vault_pda = $1
+            "#,
+        )
+        .bind(vault_pda)
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+        self.attach_transactions(owner).await?;
+
+        Ok(())
+    }
+
+    /// Link rows written before the owner's vault was registered.
+    async fn attach_transactions(&self, owner: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE transactions
+            SET vault_id = (SELECT id FROM vault_accounts WHERE owner_pubkey = $1)
+            WHERE user_pubkey = $1 AND vault_id IS NULL
+            "#,
+        )
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn get_vault_by_owner(&self, owner: &str) -> Result<Option<VaultAccount>> {
         let result = sqlx::query_as::<_, VaultAccount>(
             r#"
@@ -120,7 +201,7 @@ impl PostgresDatabase {
             SET total_balance = $1,
                 locked_balance = $2,
                 available_balance = $3,
-                updated_at = NOW()
+                updated_at = NOW() AT TIME ZONE 'UTC'
             WHERE id = $4
             "#,
         )
@@ -139,26 +220,20 @@ impl PostgresDatabase {
     // ============================================
 
     pub async fn insert_transaction(&self, tx: &TransactionRecord) -> Result<Uuid> {
-        // First get vault_id from user_pubkey
-        let vault = self.get_vault_by_owner(&tx.user).await?;
-        let vault_id = vault
-            .map(|v| v.id)
-            .ok_or_else(|| anyhow::anyhow!("Vault not found"))?;
-
         let row = sqlx::query(
             r#"
             INSERT INTO transactions 
-            (vault_id, user_pubkey, tx_type, amount, signature, status)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            (vault_id, user_pubkey, tx_type, amount, signature, status, error_message)
+            VALUES ((SELECT id FROM vault_accounts WHERE owner_pubkey = $1), $1, $2, $3, $4, $5, $6)
             RETURNING id
             "#,
         )
-        .bind(vault_id)
         .bind(&tx.user)
         .bind(format!("{:?}", tx.tx_type))
         .bind(tx.amount as i64)
         .bind(&tx.signature)
         .bind(format!("{:?}", tx.status))
+        .bind(&tx.error)
         .fetch_one(&self.pool)
         .await?;
 
@@ -168,7 +243,8 @@ impl PostgresDatabase {
     pub async fn get_user_transactions(&self, user: &str) -> Result<Vec<TransactionRecord>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, user_pubkey, tx_type, amount, signature, status, created_at
+            SELECT id, user_pubkey, tx_type, amount, signature, status, created_at,
+                   slot, block_time, log_index, error_message, confirmed_at
             FROM transactions
             WHERE user_pubkey = $1
             ORDER BY created_at DESC
@@ -178,45 +254,126 @@ impl PostgresDatabase {
         .fetch_all(&self.pool)
         .await?;
 
-        let mut transactions = Vec::new();
-        for row in rows {
-            let tx_type_str: String = row.get("tx_type");
-            let status_str: String = row.get("status");
-            let created_at: chrono::NaiveDateTime = row.get("created_at");
+        Ok(rows.iter().map(transaction_from_row).collect())
+    }
+
+    /// One page of `user`'s history in `(created_at, id)` order, with the
+    /// number of rows matching the filters.
+    pub async fn get_transaction_page(
+        &self,
+        user: &str,
+        query: &TransactionQuery,
+    ) -> Result<TransactionPage> {
+        // Shared by the page and the count; $1..$7 bind the same way in both.
+        const FILTERS: &str = r#"
+            WHERE user_pubkey = $1
+              AND ($2::text IS NULL OR tx_type = $2)
+              AND ($3::text IS NULL OR status = $3)
+              AND ($4::bigint IS NULL OR amount >= $4)
+              AND ($5::bigint IS NULL OR amount <= $5)
+              AND ($6::float8 IS NULL OR created_at >= to_timestamp($6) AT TIME ZONE 'UTC')
+              AND ($7::float8 IS NULL OR created_at < to_timestamp($7) AT TIME ZONE 'UTC')
+        "#;
+        let (direction, past) = match query.order {
+            SortOrder::Asc => ("ASC", ">"),
+            SortOrder::Desc => ("DESC", "<"),
+        };
+        let page_sql = format!(
+            r#"
+            SELECT id, user_pubkey, tx_type, amount, signature, status, created_at,
+                   slot, block_time, log_index, error_message, confirmed_at,
+                   (EXTRACT(EPOCH FROM created_at) * 1000000)::BIGINT AS created_us
+            FROM transactions
+            {FILTERS}
+              AND ($8::bigint IS NULL
+                   OR (created_at, id) {past} (TIMESTAMP 'epoch' + $8 * INTERVAL '1 microsecond', $9::uuid))
+            ORDER BY created_at {direction}, id {direction}
+            LIMIT $10
+            "#
+        );
+        let count_sql = format!("SELECT COUNT(*) AS total FROM transactions {FILTERS}");
+
+        let tx_type = query.tx_type.as_ref().map(|t| format!("{:?}", t));
+        let status = query.status.as_ref().map(|s| format!("{:?}", s));
+        let (after_us, after_id) = match &query.after {
+            Some(cursor) => (Some(cursor.created_at), Some(Uuid::parse_str(&cursor.id)?)),
+            None => (None, None),
+        };
+
+        let rows = sqlx::query(&page_sql)
+            .bind(user)
+            .bind(&tx_type)
+            .bind(&status)
+            .bind(query.min_amount.map(|a| a as i64))
+            .bind(query.max_amount.map(|a| a as i64))
+            .bind(query.from.map(|t| t as f64))
+            .bind(query.to.map(|t| t as f64))
+            .bind(after_us)
+            .bind(after_id)
+            .bind(query.limit as i64 + 1)
+            .fetch_all(&self.pool)
+            .await?;
+        let total: i64 = sqlx::query(&count_sql)
+            .bind(user)
+            .bind(&tx_type)
+            .bind(&status)
+            .bind(query.min_amount.map(|a| a as i64))
+            .bind(query.max_amount.map(|a| a as i64))
+            .bind(query.from.map(|t| t as f64))
+            .bind(query.to.map(|t| t as f64))
+            .fetch_one(&self.pool)
+            .await?
+            .get("total");
 
-            transactions.push(TransactionRecord {
+        let more = rows.len() > query.limit;
+        let rows = &rows[..rows.len().min(query.limit)];
+        let next_cursor = rows.last().filter(|_| more).map(|row| {
+            TransactionCursor {
+                created_at: row.get("created_us"),
                 id: row.get::<Uuid, _>("id").to_string(),
-                user: row.get("user_pubkey"),
-                tx_type: match tx_type_str.as_str() {
-                    "Initialize" => TransactionType::Initialize,
-                    "Deposit" => TransactionType::Deposit,
-                    "Withdraw" => TransactionType::Withdraw,
-                    "Lock" => TransactionType::Lock,
-                    "Unlock" => TransactionType::Unlock,
-                    "Transfer" => TransactionType::Transfer,
-                    "WithdrawalRequest" => TransactionType::WithdrawalRequest,
-                    "WithdrawalExecute" => TransactionType::WithdrawalExecute,
-                    _ => TransactionType::Deposit,
-                },
-                amount: row.get::<i64, _>("amount") as u64,
-                signature: row.get("signature"),
-                status: match status_str.as_str() {
-                    "Pending" => TransactionStatus::Pending,
-                    "Confirmed" => TransactionStatus::Confirmed,
-                    "Failed" => TransactionStatus::Failed,
-                    _ => TransactionStatus::Pending,
-                },
-                timestamp: created_at.and_utc().timestamp(),
-            });
-        }
+            }
+            .encode()
+        });
+
+        Ok(TransactionPage {
+            transactions: rows.iter().map(transaction_from_row).collect(),
+            total: total as u64,
+            next_cursor,
+        })
+    }
+
+    pub async fn get_transaction_stats(&self, active_since: i64) -> Result<TransactionStats> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS total,
+                   COUNT(DISTINCT user_pubkey)
+                       FILTER (WHERE created_at >= to_timestamp($1) AT TIME ZONE 'UTC') AS active
+            FROM transactions
+            "#,
+        )
+        .bind(active_since as f64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(TransactionStats {
+            total_transactions: row.get::<i64, _>("total") as u64,
+            active_users: row.get::<i64, _>("active") as u64,
+        })
+    }
 
-        Ok(transactions)
+    pub async fn count_user_transactions(&self, user: &str) -> Result<u64> {
+        let row = sqlx::query("SELECT COUNT(*) AS total FROM transactions WHERE user_pubkey = $1")
+            .bind(user)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get::<i64, _>("total") as u64)
     }
 
     pub async fn get_all_transactions(&self) -> Result<Vec<TransactionRecord>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, user_pubkey, tx_type, amount, signature, status, created_at
+            SELECT id, user_pubkey, tx_type, amount, signature, status, created_at,
+                   slot, block_time, log_index, error_message, confirmed_at
             FROM transactions
             ORDER BY created_at DESC
             LIMIT 1000
@@ -225,39 +382,99 @@ impl PostgresDatabase {
         .fetch_all(&self.pool)
         .await?;
 
-        let mut transactions = Vec::new();
-        for row in rows {
-            let tx_type_str: String = row.get("tx_type");
-            let status_str: String = row.get("status");
-            let created_at: chrono::NaiveDateTime = row.get("created_at");
+        Ok(rows.iter().map(transaction_from_row).collect())
+    }
 
-            transactions.push(TransactionRecord {
-                id: row.get::<Uuid, _>("id").to_string(),
-                user: row.get("user_pubkey"),
-                tx_type: match tx_type_str.as_str() {
-                    "Initialize" => TransactionType::Initialize,
-                    "Deposit" => TransactionType::Deposit,
-                    "Withdraw" => TransactionType::Withdraw,
-                    "Lock" => TransactionType::Lock,
-                    "Unlock" => TransactionType::Unlock,
-                    "Transfer" => TransactionType::Transfer,
-                    "WithdrawalRequest" => TransactionType::WithdrawalRequest,
-                    "WithdrawalExecute" => TransactionType::WithdrawalExecute,
-                    _ => TransactionType::Deposit,
-                },
-                amount: row.get::<i64, _>("amount") as u64,
-                signature: row.get("signature"),
-                status: match status_str.as_str() {
-                    "Pending" => TransactionStatus::Pending,
-                    "Confirmed" => TransactionStatus::Confirmed,
-                    "Failed" => TransactionStatus::Failed,
-                    _ => TransactionStatus::Pending,
-                },
-                timestamp: created_at.and_utc().timestamp(),
-            });
-        }
+    pub async fn get_transactions_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<Vec<TransactionRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_pubkey, tx_type, amount, signature, status, created_at,
+                   slot, block_time, log_index, error_message, confirmed_at
+            FROM transactions
+            WHERE signature = $1
+            "#,
+        )
+        .bind(signature)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(transaction_from_row).collect())
+    }
+
+    /// Rows that can still change status, oldest first.
+    pub async fn get_unsettled_transactions(&self) -> Result<Vec<TransactionRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_pubkey, tx_type, amount, signature, status, created_at,
+                   slot, block_time, log_index, error_message, confirmed_at
+            FROM transactions
+            WHERE status IN ('Pending', 'Confirmed')
+            ORDER BY created_at
+            LIMIT 1000
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(transaction_from_row).collect())
+    }
+
+    /// Move every unsettled row of `signature` to `status`; returns the rows
+    /// that changed.
+    pub async fn update_transaction_status(
+        &self,
+        signature: &str,
+        status: &TransactionStatus,
+        slot: Option<u64>,
+        error: Option<&str>,
+    ) -> Result<Vec<TransactionRecord>> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE transactions
+            SET status = $2,
+                slot = COALESCE($3, slot),
+                error_message = $4,
+                confirmed_at = CASE WHEN $2 IN ('Confirmed', 'Finalized')
+                                    THEN COALESCE(confirmed_at, NOW() AT TIME ZONE 'UTC')
+                                    ELSE confirmed_at END
+            WHERE signature = $1 AND status IN ('Pending', 'Confirmed')
+            RETURNING id, user_pubkey, tx_type, amount, signature, status, created_at,
+                      slot, block_time, log_index, error_message, confirmed_at
+            "#,
+        )
+        .bind(signature)
+        .bind(format!("{:?}", status))
+        .bind(slot.map(|s| s as i64))
+        .bind(error)
+        .fetch_all(&self.pool)
+        .await?;
 
-        Ok(transactions)
+        Ok(rows.iter().map(transaction_from_row).collect())
+    }
+
+    /// Point pending rows at the rebuilt transaction that replaced `old`.
+    pub async fn repoint_transactions(
+        &self,
+        old: &str,
+        new: &str,
+    ) -> Result<Vec<TransactionRecord>> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE transactions SET signature = $2
+            WHERE signature = $1 AND status = 'Pending'
+            RETURNING id, user_pubkey, tx_type, amount, signature, status, created_at,
+                      slot, block_time, log_index, error_message, confirmed_at
+            "#,
+        )
+        .bind(old)
+        .bind(new)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(transaction_from_row).collect())
     }
 
     // ============================================
@@ -265,36 +482,39 @@ impl PostgresDatabase {
     // ============================================
 
     pub async fn create_snapshot(&self, snapshot: &BalanceSnapshot) -> Result<()> {
-        let vault = self.get_vault_by_owner(&snapshot.user).await?;
-        let vault_id = vault
-            .map(|v| v.id)
-            .ok_or_else(|| anyhow::anyhow!("Vault not found"))?;
-
-        sqlx::query(
+        let result = sqlx::query(
             r#"
             INSERT INTO balance_snapshots 
-            (vault_id, user_pubkey, total_balance, locked_balance, available_balance, snapshot_type, snapshot_time)
-            VALUES ($1, $2, $3, $4, $5, 'HOURLY', NOW())
+            (vault_id, user_pubkey, total_balance, locked_balance, available_balance, sequence, snapshot_type, snapshot_time)
+            SELECT id, $2, $3, $4, $5, $6, 'HOURLY', NOW() AT TIME ZONE 'UTC'
+            FROM vault_accounts
+            WHERE vault_pda = $1
             "#
         )
-        .bind(vault_id)
+        .bind(&snapshot.vault)
         .bind(&snapshot.user)
         .bind(snapshot.total_balance as i64)
         .bind(snapshot.locked_balance as i64)
         .bind(snapshot.available_balance as i64)
+        .bind(snapshot.sequence.map(|s| s as i64))
         .execute(&self.pool)
         .await?;
 
+        if result.rows_affected() == 0 {
+            anyhow::bail!("Vault not found");
+        }
         Ok(())
     }
 
     pub async fn get_snapshots(&self, user: &str, limit: i64) -> Result<Vec<BalanceSnapshot>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, user_pubkey, total_balance, locked_balance, available_balance, snapshot_time
-            FROM balance_snapshots
-            WHERE user_pubkey = $1
-            ORDER BY snapshot_time DESC
+            SELECT s.id, s.user_pubkey, v.vault_pda, s.total_balance, s.locked_balance,
+                   s.available_balance, s.sequence, s.snapshot_time
+            FROM balance_snapshots s
+            JOIN vault_accounts v ON v.id = s.vault_id
+            WHERE s.user_pubkey = $1
+            ORDER BY s.snapshot_time DESC
             LIMIT $2
             "#,
         )
@@ -303,20 +523,51 @@ impl PostgresDatabase {
         .fetch_all(&self.pool)
         .await?;
 
-        let mut snapshots = Vec::new();
-        for row in rows {
-            let snapshot_time: chrono::NaiveDateTime = row.get("snapshot_time");
-            snapshots.push(BalanceSnapshot {
-                id: row.get::<Uuid, _>("id").to_string(),
-                user: row.get("user_pubkey"),
-                total_balance: row.get::<i64, _>("total_balance") as u64,
-                locked_balance: row.get::<i64, _>("locked_balance") as u64,
-                available_balance: row.get::<i64, _>("available_balance") as u64,
-                timestamp: snapshot_time.and_utc().timestamp(),
-            });
-        }
+        Ok(rows.iter().map(snapshot_from_row).collect())
+    }
 
-        Ok(snapshots)
+    pub async fn get_snapshot_at_sequence(
+        &self,
+        vault: &str,
+        sequence: u64,
+    ) -> Result<Option<BalanceSnapshot>> {
+        let row = sqlx::query(
+            r#"
+            SELECT s.id, s.user_pubkey, v.vault_pda, s.total_balance, s.locked_balance,
+                   s.available_balance, s.sequence, s.snapshot_time
+            FROM balance_snapshots s
+            JOIN vault_accounts v ON v.id = s.vault_id
+            WHERE v.vault_pda = $1 AND s.sequence <= $2
+            ORDER BY s.sequence DESC, s.snapshot_time DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(vault)
+        .bind(sequence as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(snapshot_from_row))
+    }
+
+    pub async fn get_snapshot_at(&self, vault: &str, at: i64) -> Result<Option<BalanceSnapshot>> {
+        let row = sqlx::query(
+            r#"
+            SELECT s.id, s.user_pubkey, v.vault_pda, s.total_balance, s.locked_balance,
+                   s.available_balance, s.sequence, s.snapshot_time
+            FROM balance_snapshots s
+            JOIN vault_accounts v ON v.id = s.vault_id
+            WHERE v.vault_pda = $1 AND s.snapshot_time <= to_timestamp($2) AT TIME ZONE 'UTC'
+            ORDER BY s.snapshot_time DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(vault)
+        .bind(at as f64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(snapshot_from_row))
     }
 
     // ============================================
@@ -386,6 +637,283 @@ impl PostgresDatabase {
         Ok(logs)
     }
 
+    // ============================================
+    // CHAIN EVENTS
+    // ============================================
+
+    /// Store `event`, the vaults it touched and its rows in `transactions` in
+    /// one database transaction. A row the REST handlers already recorded for
+    /// the same signature, user and type is completed in place instead of
+    /// duplicated. Returns false if the event was already indexed.
+    pub async fn record_chain_event(
+        &self,
+        event: &ChainEvent,
+        vaults: &[ChainEventVault],
+        rows: &[TransactionRecord],
+    ) -> Result<bool> {
+        let mut db_tx = self.pool.begin().await?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO chain_events
+            (signature, log_index, slot, block_time, event_name, vault_pda, sequence, data)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (signature, log_index) DO NOTHING
+            "#,
+        )
+        .bind(&event.signature)
+        .bind(event.log_index as i32)
+        .bind(event.slot as i64)
+        .bind(event.block_time)
+        .bind(&event.event_name)
+        .bind(&event.vault)
+        .bind(event.sequence.map(|s| s as i64))
+        .bind(&event.data)
+        .execute(&mut *db_tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if !inserted {
+            return Ok(false);
+        }
+
+        for link in vaults {
+            insert_event_vault(&mut db_tx, event, link).await?;
+        }
+        for row in rows {
+            sqlx::query(
+                r#"
+                WITH claimed AS (
+                    UPDATE transactions
+                    SET amount = $3,
+                        status = CASE WHEN status = 'Finalized' THEN status ELSE $5 END,
+                        slot = $6, block_time = $7, log_index = $8,
+                        confirmed_at = COALESCE(confirmed_at, NOW() AT TIME ZONE 'UTC')
+                    WHERE id = (
+                        SELECT id FROM transactions
+                        WHERE signature = $4 AND user_pubkey = $1 AND tx_type = $2
+                          AND log_index IS NULL
+                        LIMIT 1
+                    )
+                    RETURNING id
+                )
+                INSERT INTO transactions
+                (vault_id, user_pubkey, tx_type, amount, signature, status,
+                 slot, block_time, log_index, created_at)
+                SELECT
+                    (SELECT id FROM vault_accounts WHERE owner_pubkey = $1),
+                    $1, $2, $3, $4, $5, $6, $7, $8,
+                    COALESCE(to_timestamp($7) AT TIME ZONE 'UTC', NOW() AT TIME ZONE 'UTC')
+                WHERE NOT EXISTS (SELECT 1 FROM claimed)
+                ON CONFLICT (signature, log_index, user_pubkey) DO NOTHING
+                "#,
+            )
+            .bind(&row.user)
+            .bind(format!("{:?}", row.tx_type))
+            .bind(row.amount as i64)
+            .bind(&row.signature)
+            .bind(format!("{:?}", row.status))
+            .bind(row.slot.map(|s| s as i64))
+            .bind(row.block_time)
+            .bind(row.log_index.map(|i| i as i32))
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn get_chain_events(&self) -> Result<Vec<ChainEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT signature, log_index, slot, block_time, event_name, vault_pda, sequence, data
+            FROM chain_events
+            ORDER BY slot, log_index
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(chain_event_from_row).collect())
+    }
+
+    pub async fn get_vault_chain_events(
+        &self,
+        vault: &str,
+        range: VaultEventRange,
+    ) -> Result<Vec<ChainEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT e.signature, e.log_index, e.slot, e.block_time, e.event_name, e.vault_pda,
+                   e.sequence, e.data
+            FROM chain_event_vaults v
+            JOIN chain_events e ON e.signature = v.signature AND e.log_index = v.log_index
+            WHERE v.vault_pda = $1
+              AND ($2::bigint IS NULL OR v.sequence > $2)
+              AND ($3::bigint IS NULL OR v.sequence <= $3)
+              AND ($4::bigint IS NULL OR v.event_time >= $4)
+              AND ($5::bigint IS NULL OR v.event_time < $5)
+            ORDER BY e.slot, e.log_index
+            "#,
+        )
+        .bind(vault)
+        .bind(range.after_sequence.map(|s| s as i64))
+        .bind(range.through_sequence.map(|s| s as i64))
+        .bind(range.from_time)
+        .bind(range.to_time)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(chain_event_from_row).collect())
+    }
+
+    pub async fn get_vault_sequence_bounds(
+        &self,
+        vault: &str,
+        cutoff: EventCutoff,
+    ) -> Result<(Option<u64>, Option<u64>)> {
+        let (at, slot) = match cutoff {
+            EventCutoff::Time(at) => (Some(at), None),
+            EventCutoff::Slot(slot) => (None, Some(slot as i64)),
+        };
+        let row = sqlx::query(
+            r#"
+            SELECT MAX(sequence) FILTER (WHERE included) AS last_included,
+                   MIN(sequence) FILTER (WHERE NOT included) AS first_excluded
+            FROM (
+                SELECT sequence,
+                       CASE WHEN $2::bigint IS NOT NULL THEN event_time <= $2
+                            ELSE slot <= $3 END AS included
+                FROM chain_event_vaults
+                WHERE vault_pda = $1 AND sequence IS NOT NULL
+            ) v
+            "#,
+        )
+        .bind(vault)
+        .bind(at)
+        .bind(slot)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((
+            row.get::<Option<i64>, _>("last_included").map(|s| s as u64),
+            row.get::<Option<i64>, _>("first_excluded")
+                .map(|s| s as u64),
+        ))
+    }
+
+    pub async fn get_unlinked_chain_events(&self) -> Result<Vec<ChainEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT signature, log_index, slot, block_time, event_name, vault_pda, sequence, data
+            FROM chain_events e
+            WHERE NOT EXISTS (
+                SELECT 1 FROM chain_event_vaults v
+                WHERE v.signature = e.signature AND v.log_index = e.log_index
+            )
+            ORDER BY slot, log_index
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(chain_event_from_row).collect())
+    }
+
+    pub async fn link_chain_event(
+        &self,
+        event: &ChainEvent,
+        vaults: &[ChainEventVault],
+    ) -> Result<()> {
+        let mut db_tx = self.pool.begin().await?;
+        for link in vaults {
+            insert_event_vault(&mut db_tx, event, link).await?;
+        }
+        db_tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_all_vaults(&self) -> Result<Vec<VaultAccount>> {
+        let result = sqlx::query_as::<_, VaultAccount>("SELECT * FROM vault_accounts")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(result)
+    }
+
+    /// PDAs of every vault the database knows about.
+    pub async fn get_vault_pdas(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT vault_pda FROM vault_accounts")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| row.get("vault_pda")).collect())
+    }
+
+    pub async fn get_checkpoint(&self, address: &str) -> Result<Option<IndexerCheckpoint>> {
+        let row = sqlx::query(
+            r#"
+            SELECT address, signature, slot FROM indexer_checkpoints
+            WHERE address = $1
+            "#,
+        )
+        .bind(address)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| IndexerCheckpoint {
+            address: row.get("address"),
+            signature: row.get("signature"),
+            slot: row.get::<i64, _>("slot") as u64,
+        }))
+    }
+
+    pub async fn set_checkpoint(&self, checkpoint: &IndexerCheckpoint) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO indexer_checkpoints (address, signature, slot)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (address) DO UPDATE
+            SET signature = EXCLUDED.signature, slot = EXCLUDED.slot, updated_at = NOW() AT TIME ZONE 'UTC'
+            "#,
+        )
+        .bind(&checkpoint.address)
+        .bind(&checkpoint.signature)
+        .bind(checkpoint.slot as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // ============================================
+    // TRANSACTION ATTEMPTS
+    // ============================================
+
+    pub async fn insert_transaction_attempt(&self, attempt: &TransactionAttempt) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO transaction_attempts
+            (send_id, signature, attempt, rebuild, blockhash, compute_unit_limit,
+             compute_unit_price, outcome, error_message)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(Uuid::parse_str(&attempt.send_id)?)
+        .bind(&attempt.signature)
+        .bind(attempt.attempt as i32)
+        .bind(attempt.rebuild as i32)
+        .bind(&attempt.blockhash)
+        .bind(attempt.compute_unit_limit.map(|u| u as i32))
+        .bind(attempt.compute_unit_price.map(|p| p as i64))
+        .bind(format!("{:?}", attempt.outcome))
+        .bind(&attempt.error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     // ============================================
     // ANALYTICS
     // ============================================
@@ -404,6 +932,27 @@ impl PostgresDatabase {
         Ok(row.get("tvl"))
     }
 
+    pub async fn get_tvl_summary(&self) -> Result<TvlSummary> {
+        let row = sqlx::query(
+            r#"
+            SELECT total_vaults,
+                   COALESCE(total_value_locked, 0)::BIGINT as total_value_locked,
+                   COALESCE(total_locked, 0)::BIGINT as total_locked,
+                   COALESCE(total_available, 0)::BIGINT as total_available
+            FROM tvl_summary
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(TvlSummary {
+            total_vaults: row.get::<i64, _>("total_vaults") as u64,
+            total_value_locked: row.get::<i64, _>("total_value_locked") as u64,
+            total_locked: row.get::<i64, _>("total_locked") as u64,
+            total_available: row.get::<i64, _>("total_available") as u64,
+        })
+    }
+
     pub async fn get_active_vaults_count(&self) -> Result<i64> {
         let row = sqlx::query(
             r#"
@@ -422,39 +971,422 @@ impl PostgresDatabase {
     // RECONCILIATION
     // ============================================
 
-    pub async fn log_reconciliation(
+    pub async fn log_reconciliation(&self, log: &ReconciliationLog) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO reconciliation_logs
+            (id, vault_id, vault_pda, user_pubkey, source, onchain_balance, offchain_balance,
+             discrepancy, status, slot, checked_at)
+            VALUES (
+                $1,
+                (SELECT id FROM vault_accounts WHERE vault_pda = $2 OR owner_pubkey = $3 LIMIT 1),
+                $2, $3, $4, $5, $6, $7, $8, $9, to_timestamp($10) AT TIME ZONE 'UTC'
+            )
+            "#,
+        )
+        .bind(Uuid::parse_str(&log.id)?)
+        .bind(&log.vault)
+        .bind(&log.user)
+        .bind(log.source.as_str())
+        .bind(log.onchain_balance as i64)
+        .bind(log.offchain_balance as i64)
+        .bind(log.discrepancy)
+        .bind(log.status.as_str())
+        .bind(log.slot.map(|s| s as i64))
+        .bind(log.checked_at as f64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_reconciliation_logs(
         &self,
-        user: &str,
-        onchain: i64,
-        offchain: i64,
-        status: &str,
-    ) -> Result<()> {
-        let vault = self.get_vault_by_owner(user).await?;
-        let vault_id = vault
-            .map(|v| v.id)
-            .ok_or_else(|| anyhow::anyhow!("Vault not found"))?;
+        status: Option<ReconciliationStatus>,
+        limit: i64,
+    ) -> Result<Vec<ReconciliationLog>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM reconciliation_logs
+            WHERE $1::text IS NULL OR status = $1
+            ORDER BY checked_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(status.map(|s| s.as_str()))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(reconciliation_from_row).collect())
+    }
 
-        let discrepancy = onchain - offchain;
+    /// Close an open mismatch. Returns `None` if `id` is not a `MISMATCH` row.
+    pub async fn resolve_reconciliation(
+        &self,
+        id: Uuid,
+        action: &str,
+    ) -> Result<Option<ReconciliationLog>> {
+        let row = sqlx::query(
+            r#"
+            UPDATE reconciliation_logs
+            SET status = 'RESOLVED', resolution_action = $2, resolved_at = NOW() AT TIME ZONE 'UTC'
+            WHERE id = $1 AND status = 'MISMATCH'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(action)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(reconciliation_from_row))
+    }
+
+    // ============================================
+    // MULTISIG
+    // ============================================
 
+    pub async fn insert_multisig_operation(&self, op: &MultisigOperation) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO reconciliation_logs 
-            (vault_id, user_pubkey, onchain_balance, offchain_balance, discrepancy, status)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO multisig_operations
+            (id, owner_pubkey, vault_pda, action, transaction, transaction_message, message,
+             signers, signing, threshold, status, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, to_timestamp($12) AT TIME ZONE 'UTC')
             "#,
         )
-        .bind(vault_id)
-        .bind(user)
-        .bind(onchain)
-        .bind(offchain)
-        .bind(discrepancy)
-        .bind(status)
+        .bind(Uuid::parse_str(&op.id)?)
+        .bind(&op.owner)
+        .bind(&op.vault)
+        .bind(serde_json::to_string(&op.action)?)
+        .bind(&op.transaction)
+        .bind(&op.transaction_message)
+        .bind(&op.message)
+        .bind(&op.signers)
+        .bind(&op.signing)
+        .bind(op.threshold as i16)
+        .bind(op.status.as_str())
+        .bind(op.created_at as f64)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    pub async fn get_multisig_operation(&self, id: Uuid) -> Result<Option<MultisigOperation>> {
+        let row = sqlx::query("SELECT * FROM multisig_operations WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let mut op = multisig_from_row(&row)?;
+        op.approvals = self.get_multisig_approvals(id).await?;
+        op.signatures = self.get_multisig_signatures(id).await?;
+        Ok(Some(op))
+    }
+
+    /// Pending operations, oldest first, optionally only those `approver`
+    /// can sign (as a co-signer, the owner or a transaction signer).
+    pub async fn get_pending_multisig_operations(
+        &self,
+        approver: Option<&str>,
+    ) -> Result<Vec<MultisigOperation>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM multisig_operations
+            WHERE status = 'pending'
+              AND ($1::text IS NULL OR owner_pubkey = $1 OR $1 = ANY(signers)
+                   OR $1 = ANY(signing))
+            ORDER BY created_at
+            LIMIT 1000
+            "#,
+        )
+        .bind(approver)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut operations = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let mut op = multisig_from_row(row)?;
+            let id = Uuid::parse_str(&op.id)?;
+            op.approvals = self.get_multisig_approvals(id).await?;
+            op.signatures = self.get_multisig_signatures(id).await?;
+            operations.push(op);
+        }
+        Ok(operations)
+    }
+
+    pub async fn count_pending_multisig_operations(
+        &self,
+        vault: &str,
+        created_after: i64,
+    ) -> Result<usize> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS total FROM multisig_operations
+            WHERE vault_pda = $1 AND status = 'pending'
+              AND created_at >= to_timestamp($2) AT TIME ZONE 'UTC'
+            "#,
+        )
+        .bind(vault)
+        .bind(created_after as f64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.get::<i64, _>("total") as usize)
+    }
+
+    async fn get_multisig_approvals(&self, id: Uuid) -> Result<Vec<MultisigApproval>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT signer_pubkey, signature, approved_at FROM multisig_approvals
+            WHERE operation_id = $1
+            ORDER BY approved_at
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| MultisigApproval {
+                signer: row.get("signer_pubkey"),
+                signature: row.get("signature"),
+                approved_at: row
+                    .get::<chrono::NaiveDateTime, _>("approved_at")
+                    .and_utc()
+                    .timestamp(),
+            })
+            .collect())
+    }
+
+    async fn get_multisig_signatures(&self, id: Uuid) -> Result<Vec<MultisigApproval>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT signer_pubkey, signature, signed_at FROM multisig_signatures
+            WHERE operation_id = $1
+            ORDER BY signed_at
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| MultisigApproval {
+                signer: row.get("signer_pubkey"),
+                signature: row.get("signature"),
+                approved_at: row
+                    .get::<chrono::NaiveDateTime, _>("signed_at")
+                    .and_utc()
+                    .timestamp(),
+            })
+            .collect())
+    }
+
+    /// Returns false if `approval.signer` already approved this operation.
+    pub async fn add_multisig_approval(
+        &self,
+        id: Uuid,
+        approval: &MultisigApproval,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO multisig_approvals (operation_id, signer_pubkey, signature, approved_at)
+            VALUES ($1, $2, $3, to_timestamp($4) AT TIME ZONE 'UTC')
+            ON CONFLICT (operation_id, signer_pubkey) DO NOTHING
+            "#,
+        )
+        .bind(id)
+        .bind(&approval.signer)
+        .bind(&approval.signature)
+        .bind(approval.approved_at as f64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns false if the operation already has a transaction or is no
+    /// longer pending.
+    pub async fn set_multisig_transaction(
+        &self,
+        id: Uuid,
+        transaction: &str,
+        message: &str,
+        signing: &[String],
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE multisig_operations
+            SET transaction = $2, transaction_message = $3, signing = $4
+            WHERE id = $1 AND status = 'pending' AND transaction IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(transaction)
+        .bind(message)
+        .bind(signing)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns false if `signature.signer` already signed this operation's
+    /// transaction.
+    pub async fn add_multisig_signature(
+        &self,
+        id: Uuid,
+        signature: &MultisigApproval,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO multisig_signatures (operation_id, signer_pubkey, signature, signed_at)
+            VALUES ($1, $2, $3, to_timestamp($4) AT TIME ZONE 'UTC')
+            ON CONFLICT (operation_id, signer_pubkey) DO NOTHING
+            "#,
+        )
+        .bind(id)
+        .bind(&signature.signer)
+        .bind(&signature.signature)
+        .bind(signature.approved_at as f64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Move a pending operation to its outcome. Returns false if it was no
+    /// longer pending.
+    pub async fn finish_multisig_operation(
+        &self,
+        id: Uuid,
+        status: MultisigStatus,
+        tx_signature: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE multisig_operations
+            SET status = $2, tx_signature = $3, error_message = $4,
+                executed_at = CASE WHEN $2 = 'executed' THEN NOW() AT TIME ZONE 'UTC' END
+            WHERE id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(id)
+        .bind(status.as_str())
+        .bind(tx_signature)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ============================================
+    // IDEMPOTENCY KEYS
+    // ============================================
+
+    /// Drop keys created before `expires_before`, then claim `record.key`
+    /// for `record.wallet`, taking over a claim still in progress since
+    /// before `lease_expires_before`. Returns the existing record if the key
+    /// is taken.
+    pub async fn claim_idempotency_key(
+        &self,
+        record: &IdempotencyRecord,
+        expires_before: i64,
+        lease_expires_before: i64,
+    ) -> Result<Option<IdempotencyRecord>> {
+        sqlx::query(
+            "DELETE FROM idempotency_keys WHERE created_at < to_timestamp($1) AT TIME ZONE 'UTC'",
+        )
+        .bind(expires_before as f64)
+        .execute(&self.pool)
+        .await?;
+
+        let claimed = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (wallet, idempotency_key, request_hash, status, created_at)
+            VALUES ($1, $2, $3, $4, to_timestamp($5) AT TIME ZONE 'UTC')
+            ON CONFLICT (wallet, idempotency_key) DO UPDATE
+            SET request_hash = EXCLUDED.request_hash, created_at = EXCLUDED.created_at
+            WHERE idempotency_keys.status = 'in_progress'
+              AND idempotency_keys.created_at < to_timestamp($6) AT TIME ZONE 'UTC'
+            "#,
+        )
+        .bind(&record.wallet)
+        .bind(&record.key)
+        .bind(&record.request_hash)
+        .bind(record.status.as_str())
+        .bind(record.created_at as f64)
+        .bind(lease_expires_before as f64)
+        .execute(&self.pool)
+        .await?;
+        if claimed.rows_affected() > 0 {
+            return Ok(None);
+        }
+
+        let row = sqlx::query(
+            "SELECT * FROM idempotency_keys WHERE wallet = $1 AND idempotency_key = $2",
+        )
+        .bind(&record.wallet)
+        .bind(&record.key)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.as_ref().map(idempotency_from_row))
+    }
+
+    pub async fn complete_idempotency_key(
+        &self,
+        claim: &IdempotencyRecord,
+        response_status: u16,
+        response_body: &str,
+        tx_signature: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = 'completed', response_status = $4, response_body = $5,
+                tx_signature = $6, completed_at = NOW() AT TIME ZONE 'UTC'
+            WHERE wallet = $1 AND idempotency_key = $2
+              AND created_at = to_timestamp($3) AT TIME ZONE 'UTC'
+            "#,
+        )
+        .bind(&claim.wallet)
+        .bind(&claim.key)
+        .bind(claim.created_at as f64)
+        .bind(response_status as i16)
+        .bind(response_body)
+        .bind(tx_signature)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn release_idempotency_key(&self, claim: &IdempotencyRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE wallet = $1 AND idempotency_key = $2
+              AND created_at = to_timestamp($3) AT TIME ZONE 'UTC'
+            "#,
+        )
+        .bind(&claim.wallet)
+        .bind(&claim.key)
+        .bind(claim.created_at as f64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     // ============================================
     // ALERTS
     // ============================================
@@ -490,6 +1422,21 @@ impl PostgresDatabase {
         Ok(())
     }
 
+    /// Returns false when no active alert has this id.
+    pub async fn resolve_alert(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE alerts SET status = 'RESOLVED'
+            WHERE id = $1 AND status = 'ACTIVE'
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn get_active_alerts(&self) -> Result<Vec<Alert>> {
         let rows = sqlx::query(
             r#"
@@ -540,7 +1487,7 @@ pub struct VaultAccount {
     pub updated_at: chrono::NaiveDateTime,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Alert {
     pub id: String,
     pub alert_type: String,
@@ -549,3 +1496,184 @@ pub struct Alert {
     pub message: String,
     pub timestamp: i64,
 }
+
+fn snapshot_from_row(row: &sqlx::postgres::PgRow) -> BalanceSnapshot {
+    let snapshot_time: chrono::NaiveDateTime = row.get("snapshot_time");
+    BalanceSnapshot {
+        id: row.get::<Uuid, _>("id").to_string(),
+        user: row.get("user_pubkey"),
+        vault: row.get("vault_pda"),
+        total_balance: row.get::<i64, _>("total_balance") as u64,
+        locked_balance: row.get::<i64, _>("locked_balance") as u64,
+        available_balance: row.get::<i64, _>("available_balance") as u64,
+        sequence: row.get::<Option<i64>, _>("sequence").map(|s| s as u64),
+        timestamp: snapshot_time.and_utc().timestamp(),
+    }
+}
+
+fn transaction_from_row(row: &sqlx::postgres::PgRow) -> TransactionRecord {
+    let tx_type_str: String = row.get("tx_type");
+    let status_str: String = row.get("status");
+    let created_at: chrono::NaiveDateTime = row.get("created_at");
+
+    TransactionRecord {
+        id: row.get::<Uuid, _>("id").to_string(),
+        user: row.get("user_pubkey"),
+        tx_type: match tx_type_str.as_str() {
+            "Initialize" => TransactionType::Initialize,
+            "Deposit" => TransactionType::Deposit,
+            "Withdraw" => TransactionType::Withdraw,
+            "Lock" => TransactionType::Lock,
+            "Unlock" => TransactionType::Unlock,
+            "Transfer" => TransactionType::Transfer,
+            "WithdrawalRequest" => TransactionType::WithdrawalRequest,
+            "WithdrawalExecute" => TransactionType::WithdrawalExecute,
+            "Settlement" => TransactionType::Settlement,
+            _ => TransactionType::Deposit,
+        },
+        amount: row.get::<i64, _>("amount") as u64,
+        signature: row.get("signature"),
+        status: match status_str.as_str() {
+            "Pending" => TransactionStatus::Pending,
+            "Confirmed" => TransactionStatus::Confirmed,
+            "Finalized" => TransactionStatus::Finalized,
+            "Failed" => TransactionStatus::Failed,
+            _ => TransactionStatus::Pending,
+        },
+        timestamp: created_at.and_utc().timestamp(),
+        slot: row.get::<Option<i64>, _>("slot").map(|s| s as u64),
+        block_time: row.get("block_time"),
+        log_index: row.get::<Option<i32>, _>("log_index").map(|i| i as u32),
+        error: row.get("error_message"),
+        confirmed_at: row
+            .get::<Option<chrono::NaiveDateTime>, _>("confirmed_at")
+            .map(|at| at.and_utc().timestamp()),
+    }
+}
+
+fn reconciliation_from_row(row: &sqlx::postgres::PgRow) -> ReconciliationLog {
+    let source_str: String = row.get("source");
+    let status_str: String = row.get("status");
+    let checked_at: chrono::NaiveDateTime = row.get("checked_at");
+
+    ReconciliationLog {
+        id: row.get::<Uuid, _>("id").to_string(),
+        user: row.get("user_pubkey"),
+        vault: row.get("vault_pda"),
+        source: match source_str.as_str() {
+            "vault_accounts" => ReconciliationSource::VaultAccounts,
+            "vault_token" => ReconciliationSource::VaultToken,
+            "events" => ReconciliationSource::Events,
+            _ => ReconciliationSource::Cache,
+        },
+        onchain_balance: row.get::<i64, _>("onchain_balance") as u64,
+        offchain_balance: row.get::<i64, _>("offchain_balance") as u64,
+        discrepancy: row.get("discrepancy"),
+        status: match status_str.as_str() {
+            "MATCH" => ReconciliationStatus::Match,
+            "RESOLVED" => ReconciliationStatus::Resolved,
+            _ => ReconciliationStatus::Mismatch,
+        },
+        slot: row.get::<Option<i64>, _>("slot").map(|s| s as u64),
+        resolution_action: row.get("resolution_action"),
+        checked_at: checked_at.and_utc().timestamp(),
+        resolved_at: row
+            .get::<Option<chrono::NaiveDateTime>, _>("resolved_at")
+            .map(|at| at.and_utc().timestamp()),
+    }
+}
+
+fn multisig_from_row(row: &sqlx::postgres::PgRow) -> Result<MultisigOperation> {
+    let action: String = row.get("action");
+    let status_str: String = row.get("status");
+    let created_at: chrono::NaiveDateTime = row.get("created_at");
+
+    Ok(MultisigOperation {
+        id: row.get::<Uuid, _>("id").to_string(),
+        owner: row.get("owner_pubkey"),
+        vault: row.get("vault_pda"),
+        action: serde_json::from_str(&action)?,
+        transaction: row.get("transaction"),
+        transaction_message: row.get("transaction_message"),
+        message: row.get("message"),
+        signers: row.get("signers"),
+        signing: row.get("signing"),
+        threshold: row.get::<i16, _>("threshold") as u8,
+        approvals: Vec::new(),
+        signatures: Vec::new(),
+        status: match status_str.as_str() {
+            "executed" => MultisigStatus::Executed,
+            "failed" => MultisigStatus::Failed,
+            "expired" => MultisigStatus::Expired,
+            "cancelled" => MultisigStatus::Cancelled,
+            _ => MultisigStatus::Pending,
+        },
+        tx_signature: row.get("tx_signature"),
+        error: row.get("error_message"),
+        created_at: created_at.and_utc().timestamp(),
+        executed_at: row
+            .get::<Option<chrono::NaiveDateTime>, _>("executed_at")
+            .map(|at| at.and_utc().timestamp()),
+    })
+}
+
+fn chain_event_from_row(row: &sqlx::postgres::PgRow) -> ChainEvent {
+    ChainEvent {
+        signature: row.get("signature"),
+        log_index: row.get::<i32, _>("log_index") as u32,
+        slot: row.get::<i64, _>("slot") as u64,
+        block_time: row.get("block_time"),
+        event_name: row.get("event_name"),
+        vault: row.get("vault_pda"),
+        sequence: row.get::<Option<i64>, _>("sequence").map(|s| s as u64),
+        data: row.get("data"),
+    }
+}
+
+async fn insert_event_vault(
+    db_tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    event: &ChainEvent,
+    link: &ChainEventVault,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO chain_event_vaults
+        (signature, log_index, vault_pda, sequence, slot, event_time)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (signature, log_index, vault_pda) DO NOTHING
+        "#,
+    )
+    .bind(&event.signature)
+    .bind(event.log_index as i32)
+    .bind(&link.vault)
+    .bind(link.sequence.map(|s| s as i64))
+    .bind(event.slot as i64)
+    .bind(link.timestamp)
+    .execute(&mut **db_tx)
+    .await?;
+    Ok(())
+}
+
+fn idempotency_from_row(row: &sqlx::postgres::PgRow) -> IdempotencyRecord {
+    let status_str: String = row.get("status");
+    let created_at: chrono::NaiveDateTime = row.get("created_at");
+
+    IdempotencyRecord {
+        wallet: row.get("wallet"),
+        key: row.get("idempotency_key"),
+        request_hash: row.get("request_hash"),
+        status: match status_str.as_str() {
+            "completed" => IdempotencyStatus::Completed,
+            _ => IdempotencyStatus::InProgress,
+        },
+        response_status: row
+            .get::<Option<i16>, _>("response_status")
+            .map(|s| s as u16),
+        response_body: row.get("response_body"),
+        tx_signature: row.get("tx_signature"),
+        created_at: created_at.and_utc().timestamp(),
+        completed_at: row
+            .get::<Option<chrono::NaiveDateTime>, _>("completed_at")
+            .map(|at| at.and_utc().timestamp()),
+    }
+}
@@ -1,16 +1,34 @@
+use crate::admin::{AdminConfig, Operator};
 use crate::analytics::AnalyticsService;
+use crate::auth::{AuthService, AuthenticatedWallet, OwnedRequest, OwnerJson};
+use crate::backfill::{Backfill, BackfillProgress};
+use crate::balance_history::{self, BalanceAt, BalanceInconsistency, IndexGap, PointInTime};
 use crate::balance_tracker::{BalanceAlert, BalanceTracker, VaultBalance};
-use crate::db::{AuditLog, Database, TransactionRecord, TransactionStatus, TransactionType};
-use crate::vault_manager::VaultManager;
+use crate::confirmer::{decode_authorization_changes, decode_vault_ops};
+use crate::db::{
+    AuditLog, Database, MultisigAction, MultisigOperation, MultisigStatus, ReconciliationStatus,
+    SortOrder, TransactionCursor, TransactionQuery, TransactionRecord, TransactionStatus,
+    TransactionType,
+};
+use crate::multisig::{verify_approval, MultisigCoordinator};
+use crate::reconciler::Reconciler;
+use crate::statement;
+use crate::vault_manager::{UnsignedTransaction, VaultManager};
+use crate::vault_monitor::VaultMonitor;
 use crate::websocket::{WebSocketManager, WsMessage};
-use anchor_lang::prelude::Pubkey;
+use anchor_client::solana_sdk::{instruction::Instruction, signature::Signature};
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
 use axum::{
-    extract::Path,
-    http::StatusCode,
+    extract::{Path, Query},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
     response::{IntoResponse, Response},
     Extension, Json,
 };
 use chrono::Utc;
+use collateral_vault::state::{CollateralVault, MultisigConfig, VaultAuthority};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
@@ -22,6 +40,97 @@ pub struct VaultRequest {
     pub to_pubkey: Option<String>,
     pub request_id: Option<u64>,
     pub authorized_programs: Option<Vec<String>>,
+    /// Return a transaction for the user's wallet to sign instead of sending it.
+    pub unsigned: Option<bool>,
+    /// In unsigned mode, have the backend payer cover (and sign for) fees.
+    pub sponsor_fees: Option<bool>,
+}
+
+impl OwnedRequest for VaultRequest {
+    fn owner(&self) -> &str {
+        &self.user_pubkey
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NonceRequest {
+    pub pubkey: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyRequest {
+    pub pubkey: String,
+    pub nonce: String,
+    /// Base58 ed25519 signature of the challenge message.
+    pub signature: String,
+}
+
+#[derive(Deserialize)]
+pub struct SubmitTxRequest {
+    pub transaction: String,
+}
+
+#[derive(Deserialize)]
+pub struct AuthorizedProgramRequest {
+    pub program: String,
+    /// Return a transaction for the owner's wallet to sign instead of sending it.
+    pub unsigned: Option<bool>,
+    /// In unsigned mode, have the backend payer cover (and sign for) fees.
+    pub sponsor_fees: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct AuthorizedProgramsResponse {
+    pub user: String,
+    pub authorized_programs: Vec<String>,
+    /// Most programs a vault can authorize.
+    pub capacity: usize,
+}
+
+#[derive(Deserialize)]
+pub struct MultisigInitRequest {
+    pub signers: Vec<String>,
+    pub threshold: u8,
+    /// Return a transaction for the owner's wallet to sign instead of sending it.
+    pub unsigned: Option<bool>,
+    /// In unsigned mode, have the backend payer cover (and sign for) fees.
+    pub sponsor_fees: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct MultisigConfigResponse {
+    pub user: String,
+    pub vault: String,
+    pub signers: Vec<String>,
+    pub threshold: u8,
+    /// Most co-signers a vault can have.
+    pub max_signers: usize,
+}
+
+#[derive(Deserialize)]
+pub struct MultisigOperationsQuery {
+    /// Only operations this key can approve.
+    pub signer: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MultisigOperationsResponse {
+    pub operations: Vec<MultisigOperation>,
+    pub count: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ProposeMultisigRequest {
+    #[serde(flatten)]
+    pub action: MultisigAction,
+}
+
+#[derive(Deserialize)]
+pub struct ApproveMultisigRequest {
+    pub signer: String,
+    /// Base58 ed25519 signature of the operation's `message`, or of its
+    /// `transaction_message` once the transaction is built for the signer.
+    pub signature: String,
 }
 
 #[derive(Serialize)]
@@ -48,14 +157,71 @@ pub struct BalanceResponse {
 #[derive(Serialize)]
 pub struct TvlResponse {
     pub total_value_locked: u64,
+    pub total_locked: u64,
+    pub total_available: u64,
     pub total_vaults: usize,
+    /// Vault accounts left out of the totals because they did not decode.
+    pub unreadable_vaults: usize,
+    /// Slot of the program account scan the figures come from.
+    pub slot: u64,
+    /// Whether the `tvl_summary` view agrees; `None` without Postgres.
+    pub matches_database: Option<bool>,
     pub timestamp: i64,
 }
 
+/// Query string of `GET /vault/transactions/{user}`.
+#[derive(Deserialize)]
+pub struct TransactionHistoryQuery {
+    pub tx_type: Option<TransactionType>,
+    pub status: Option<TransactionStatus>,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    /// Unix time, inclusive.
+    pub from: Option<i64>,
+    /// Unix time, exclusive.
+    pub to: Option<i64>,
+    /// `desc` (newest first, the default) or `asc`.
+    pub order: Option<SortOrder>,
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementFormat {
+    Csv,
+    #[default]
+    Json,
+}
+
+#[derive(Deserialize)]
+pub struct StatementQuery {
+    /// Unix time, inclusive.
+    pub from: i64,
+    /// Unix time, exclusive; defaults to now.
+    pub to: Option<i64>,
+    pub format: Option<StatementFormat>,
+}
+
+#[derive(Deserialize)]
+pub struct BalanceAtQuery {
+    /// A unix timestamp, an RFC 3339 time, or `slot:<n>`.
+    pub at: String,
+}
+
+/// Largest page `GET /vault/transactions/{user}` returns.
+pub const MAX_TRANSACTION_PAGE: usize = 500;
+
 #[derive(Serialize)]
 pub struct TransactionsResponse {
     pub transactions: Vec<TransactionRecord>,
+    /// Rows on this page.
     pub count: usize,
+    /// Rows matching the filters, across all pages.
+    pub total: u64,
+    /// Pass as `cursor` for the next page; `None` on the last one.
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -69,34 +235,41 @@ pub struct HealthResponse {
     pub status: &'static str,
 }
 
+#[derive(Serialize)]
+pub struct DependencyCheck {
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub status: &'static str,
+    pub rpc: DependencyCheck,
+    pub database: DependencyCheck,
+    /// Reported only; a running backfill does not hold readiness back.
+    pub backfill: BackfillProgress,
+}
+
+#[derive(Serialize)]
+pub struct WithdrawalRequestResponse {
+    pub address: String,
+    pub request_id: u64,
+    pub amount: u64,
+    pub requested_at: i64,
+    /// Unix time from which the request can be executed.
+    pub available_at: i64,
+}
+
 fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
     s.parse::<Pubkey>()
         .map_err(|e| format!("Invalid pubkey '{}': {}", s, e))
 }
 
-async fn record_transaction(
-    db: &Arc<Database>,
-    user: &str,
-    tx_type: TransactionType,
-    amount: u64,
-    signature: &str,
-    action: &str,
-    details: &str,
-) {
+/// Transaction rows are written by the confirmer as the sender signs; the
+/// handlers only add the audit trail.
+async fn record_audit(db: &Arc<Database>, user: &str, action: &str, details: &str) {
     let timestamp = Utc::now().timestamp();
-    let record = TransactionRecord {
-        id: Uuid::new_v4().to_string(),
-        user: user.to_string(),
-        tx_type,
-        amount,
-        signature: signature.to_string(),
-        status: TransactionStatus::Confirmed,
-        timestamp,
-    };
-    if let Err(err) = db.insert_transaction(record).await {
-        eprintln!("Failed to store transaction: {}", err);
-    }
-
     let audit = AuditLog {
         id: Uuid::new_v4().to_string(),
         user: user.to_string(),
@@ -110,6 +283,28 @@ async fn record_transaction(
     }
 }
 
+/// Record `user`'s vault, with the addresses read from the vault account.
+async fn record_vault(vm: &VaultManager, db: &Arc<Database>, user: Pubkey) {
+    let vault = match vm.vault_address(&user).await {
+        Ok(vault) => vault,
+        Err(e) => {
+            eprintln!("Failed to resolve vault of {}: {}", user, e);
+            return;
+        }
+    };
+    match vm.chain.get_account(&vault).await.and_then(|account| {
+        let mut data: &[u8] = &account.data;
+        Ok(CollateralVault::try_deserialize(&mut data)?)
+    }) {
+        Ok(state) => {
+            let _ = db
+                .register_vault(&state.owner, &vault, &state.token_account)
+                .await;
+        }
+        Err(e) => eprintln!("Failed to load vault {}: {}", vault, e),
+    }
+}
+
 async fn refresh_balance(tracker: &Arc<BalanceTracker>, ws: &Arc<WebSocketManager>, user: Pubkey) {
     if let Ok(balance) = tracker.get_vault_balance(user).await {
         ws.broadcast(WsMessage::BalanceUpdate {
@@ -119,16 +314,176 @@ async fn refresh_balance(tracker: &Arc<BalanceTracker>, ws: &Arc<WebSocketManage
     }
 }
 
+async fn announce_authorization(
+    db: &Arc<Database>,
+    ws: &Arc<WebSocketManager>,
+    user: Pubkey,
+    program: Pubkey,
+    authorized: bool,
+    signature: &str,
+) {
+    let action = if authorized {
+        "AUTHORIZED_PROGRAM_ADDED"
+    } else {
+        "AUTHORIZED_PROGRAM_REMOVED"
+    };
+    let details = serde_json::json!({
+        "program": program.to_string(),
+        "signature": signature,
+    })
+    .to_string();
+    record_audit(db, &user.to_string(), action, &details).await;
+    ws.broadcast(WsMessage::AuthorizationChanged {
+        user: user.to_string(),
+        program: program.to_string(),
+        authorized,
+        signature: signature.to_string(),
+    });
+}
+
+/// Vaults with a `MultisigConfig` need their co-signers for withdrawals and
+/// authorization changes, which only `/vault/{user}/multisig/operations`
+/// gathers; the owner alone would be rejected by the program.
+async fn require_single_owner(vm: &VaultManager, user: Pubkey) -> Option<Response> {
+    vm.get_multisig_config(user).await.ok().map(|_| {
+        (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: format!(
+                    "Vault requires multisig approval; propose the operation at \
+                     /vault/{}/multisig/operations",
+                    user
+                ),
+            }),
+        )
+            .into_response()
+    })
+}
+
+async fn unsigned_response(
+    vm: &VaultManager,
+    signer: Pubkey,
+    instruction: anyhow::Result<Instruction>,
+    sponsor_fees: Option<bool>,
+) -> Response {
+    let built = match instruction {
+        Ok(ix) => {
+            vm.build_unsigned(signer, &[ix], sponsor_fees.unwrap_or(false))
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match built {
+        Ok(tx) => (StatusCode::OK, Json::<UnsignedTransaction>(tx)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Liveness: the process is up and serving.
 pub async fn health_check() -> Response {
     (StatusCode::OK, Json(HealthResponse { status: "ok" })).into_response()
 }
 
+async fn check<F>(probe: F) -> DependencyCheck
+where
+    F: std::future::Future<Output = anyhow::Result<()>>,
+{
+    let started = std::time::Instant::now();
+    let result = probe.await;
+    DependencyCheck {
+        ok: result.is_ok(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        error: result.err().map(|e| format!("{:#}", e)),
+    }
+}
+
+/// Readiness: the RPC node and the database both answer.
+pub async fn readiness_check(
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(backfill): Extension<Arc<Backfill>>,
+) -> Response {
+    let rpc = check(async { vm.chain.get_latest_blockhash().await.map(|_| ()) }).await;
+    let database = check(db.ping()).await;
+
+    let ready = rpc.ok && database.ok;
+    let response = ReadinessResponse {
+        status: if ready { "ready" } else { "unavailable" },
+        rpc,
+        database,
+        backfill: backfill.progress().await,
+    };
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(response)).into_response()
+}
+
+pub async fn request_nonce(
+    Extension(auth): Extension<Arc<AuthService>>,
+    Json(req): Json<NonceRequest>,
+) -> Response {
+    match parse_pubkey(&req.pubkey) {
+        Ok(pubkey) => match auth.challenge(pubkey).await {
+            Ok(challenge) => (StatusCode::OK, Json(challenge)).into_response(),
+            Err(e) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+                .into_response(),
+        },
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response(),
+    }
+}
+
+pub async fn verify_signature(
+    Extension(auth): Extension<Arc<AuthService>>,
+    Json(req): Json<VerifyRequest>,
+) -> Response {
+    let pubkey = match parse_pubkey(&req.pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+        }
+    };
+    let Ok(signature) = req.signature.parse::<Signature>() else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid signature encoding".to_string(),
+            }),
+        )
+            .into_response();
+    };
+
+    match auth.verify(pubkey, &req.nonce, &signature).await {
+        Ok(session) => (StatusCode::OK, Json(session)).into_response(),
+        Err(e) => (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
 pub async fn initialize_vault(
     Extension(vm): Extension<Arc<VaultManager>>,
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let user = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -139,25 +494,23 @@ pub async fn initialize_vault(
 
     let authorized_programs = req
         .authorized_programs
-        .unwrap_or_else(|| vec![vm.program.id().to_string()])
+        .unwrap_or_else(|| vec![vm.program_id.to_string()])
         .into_iter()
         .filter_map(|p| parse_pubkey(&p).ok())
         .collect::<Vec<_>>();
 
+    if req.unsigned.unwrap_or(false) {
+        let ix = Ok(vm.initialize_vault_ix(user, authorized_programs));
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
+
     match vm.initialize_vault(user, authorized_programs.clone()).await {
         Ok(sig) => {
-            let (vault_pda, _) =
-                Pubkey::find_program_address(&[b"vault", user.as_ref()], &vm.program.id());
-            let (vault_token, _) =
-                Pubkey::find_program_address(&[b"vault_token", user.as_ref()], &vm.program.id());
-            let _ = db.register_vault(&user, &vault_pda, &vault_token).await;
+            record_vault(&vm, &db, user).await;
 
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::Initialize,
-                0,
-                &sig,
                 "INITIALIZE_VAULT",
                 &format!("Vault initialized: {}", sig),
             )
@@ -182,7 +535,7 @@ pub async fn deposit_collateral(
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let user = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -203,14 +556,16 @@ pub async fn deposit_collateral(
         }
     };
 
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm.deposit_ix(user, amount).await;
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
+
     match vm.deposit(user, amount).await {
         Ok(sig) => {
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::Deposit,
-                amount,
-                &sig,
                 "DEPOSIT",
                 &format!("Deposit successful: {}", sig),
             )
@@ -240,7 +595,7 @@ pub async fn withdraw_collateral(
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let user = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -261,14 +616,20 @@ pub async fn withdraw_collateral(
         }
     };
 
+    if let Some(response) = require_single_owner(&vm, user).await {
+        return response;
+    }
+
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm.withdraw_ix(user, amount).await;
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
+
     match vm.withdraw(user, amount).await {
         Ok(sig) => {
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::Withdraw,
-                amount,
-                &sig,
                 "WITHDRAW",
                 &format!("Withdrawal successful: {}", sig),
             )
@@ -298,7 +659,7 @@ pub async fn request_withdrawal(
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let user = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -331,14 +692,20 @@ pub async fn request_withdrawal(
         }
     };
 
+    if let Some(response) = require_single_owner(&vm, user).await {
+        return response;
+    }
+
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm.request_withdrawal_ix(user, request_id, amount).await;
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
+
     match vm.request_withdrawal(user, request_id, amount).await {
         Ok(sig) => {
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::WithdrawalRequest,
-                amount,
-                &sig,
                 "WITHDRAWAL_REQUEST",
                 &format!("Withdrawal request created: {}", sig),
             )
@@ -363,7 +730,7 @@ pub async fn execute_withdrawal(
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let user = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -384,14 +751,16 @@ pub async fn execute_withdrawal(
         }
     };
 
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm.execute_withdrawal_ix(user, request_id).await;
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
+
     match vm.execute_withdrawal(user, request_id).await {
         Ok(sig) => {
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::WithdrawalExecute,
-                0,
-                &sig,
                 "WITHDRAWAL_EXECUTE",
                 &format!("Withdrawal executed: {}", sig),
             )
@@ -411,12 +780,47 @@ pub async fn execute_withdrawal(
     }
 }
 
+pub async fn get_withdrawal_requests(
+    Path(user_pubkey): Path<String>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+) -> Response {
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+        }
+    };
+
+    match vm.get_withdrawal_requests(user).await {
+        Ok(requests) => {
+            let requests: Vec<_> = requests
+                .into_iter()
+                .map(|(address, request)| WithdrawalRequestResponse {
+                    address: address.to_string(),
+                    request_id: request.request_id,
+                    amount: request.amount,
+                    requested_at: request.requested_at,
+                    available_at: request.available_at,
+                })
+                .collect();
+            (StatusCode::OK, Json(requests)).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
 pub async fn lock_collateral(
     Extension(vm): Extension<Arc<VaultManager>>,
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let user = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -437,16 +841,18 @@ pub async fn lock_collateral(
         }
     };
 
-    let authority_program = vm.program.id();
+    let authority_program = vm.program_id;
+
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm.lock_ix(user, authority_program, amount).await;
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
 
     match vm.lock(user, authority_program, amount).await {
         Ok(sig) => {
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::Lock,
-                amount,
-                &sig,
                 "LOCK",
                 &format!("Collateral locked: {}", sig),
             )
@@ -471,7 +877,7 @@ pub async fn unlock_collateral(
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let user = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -492,16 +898,18 @@ pub async fn unlock_collateral(
         }
     };
 
-    let authority_program = vm.program.id();
+    let authority_program = vm.program_id;
+
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm.unlock_ix(user, authority_program, amount).await;
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
 
     match vm.unlock(user, authority_program, amount).await {
         Ok(sig) => {
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::Unlock,
-                amount,
-                &sig,
                 "UNLOCK",
                 &format!("Collateral unlocked: {}", sig),
             )
@@ -526,7 +934,7 @@ pub async fn transfer_collateral(
     Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
     Extension(ws): Extension<Arc<WebSocketManager>>,
-    Json(req): Json<VaultRequest>,
+    OwnerJson(req): OwnerJson<VaultRequest>,
 ) -> Response {
     let from = match parse_pubkey(&req.user_pubkey) {
         Ok(pk) => pk,
@@ -564,19 +972,23 @@ pub async fn transfer_collateral(
         }
     };
 
-    let authority_program = vm.program.id();
+    let authority_program = vm.program_id;
+
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm
+            .transfer_ix(from, from, to_pubkey, authority_program, amount)
+            .await;
+        return unsigned_response(&vm, from, ix, req.sponsor_fees).await;
+    }
 
     match vm
         .transfer(from, to_pubkey, authority_program, amount)
         .await
     {
         Ok(sig) => {
-            record_transaction(
+            record_audit(
                 &db,
                 &req.user_pubkey,
-                TransactionType::Transfer,
-                amount,
-                &sig,
                 "TRANSFER",
                 &format!("Internal transfer: {}", sig),
             )
@@ -597,19 +1009,71 @@ pub async fn transfer_collateral(
     }
 }
 
-pub async fn get_balance(
-    Path(user_pubkey): Path<String>,
+pub async fn submit_transaction(
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(db): Extension<Arc<Database>>,
     Extension(tracker): Extension<Arc<BalanceTracker>>,
+    Extension(ws): Extension<Arc<WebSocketManager>>,
+    Json(req): Json<SubmitTxRequest>,
 ) -> Response {
-    let user = match parse_pubkey(&user_pubkey) {
-        Ok(pk) => pk,
+    let tx = match vm.decode_signed(&req.transaction) {
+        Ok(tx) => tx,
         Err(e) => {
-            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+                .into_response()
         }
     };
 
-    match tracker.get_vault_balance(user).await {
-        Ok(balance) => (StatusCode::OK, Json(balance)).into_response(),
+    match vm.submit_signed(&tx).await {
+        Ok(sig) => {
+            for op in decode_vault_ops(&vm.program_id, &tx) {
+                let user = op.user.to_string();
+                if let TransactionType::Initialize = op.tx_type {
+                    record_vault(&vm, &db, op.user).await;
+                }
+
+                record_audit(
+                    &db,
+                    &user,
+                    op.action,
+                    &format!("Relayed wallet-signed transaction: {}", sig),
+                )
+                .await;
+
+                refresh_balance(&tracker, &ws, op.user).await;
+                match op.tx_type {
+                    TransactionType::Deposit => ws.broadcast(WsMessage::DepositNotification {
+                        user,
+                        amount: op.amount,
+                        signature: sig.clone(),
+                    }),
+                    TransactionType::Withdraw => ws.broadcast(WsMessage::WithdrawNotification {
+                        user,
+                        amount: op.amount,
+                        signature: sig.clone(),
+                    }),
+                    _ => {}
+                }
+            }
+            for change in decode_authorization_changes(&vm.program_id, &tx) {
+                announce_authorization(
+                    &db,
+                    &ws,
+                    change.user,
+                    change.program,
+                    change.authorized,
+                    &sig,
+                )
+                .await;
+            }
+
+            (StatusCode::OK, Json(TxResponse { tx_signature: sig })).into_response()
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
@@ -620,74 +1084,842 @@ pub async fn get_balance(
     }
 }
 
-pub async fn get_transactions(
+pub async fn get_authorized_programs(
     Path(user_pubkey): Path<String>,
-    Extension(db): Extension<Arc<Database>>,
+    Extension(vm): Extension<Arc<VaultManager>>,
 ) -> Response {
-    match db.get_user_transactions(&user_pubkey).await {
-        Ok(transactions) => {
-            let count = transactions.len();
-            (
-                StatusCode::OK,
-                Json(TransactionsResponse {
-                    transactions,
-                    count,
-                }),
-            )
-                .into_response()
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
         }
+    };
+
+    match vm.get_authorized_programs(user).await {
+        Ok(programs) => (
+            StatusCode::OK,
+            Json(AuthorizedProgramsResponse {
+                user: user_pubkey,
+                authorized_programs: programs.iter().map(Pubkey::to_string).collect(),
+                capacity: VaultAuthority::MAX_AUTHORIZED_PROGRAMS,
+            }),
+        )
+            .into_response(),
         Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::NOT_FOUND,
             Json(ErrorResponse {
-                error: e.to_string(),
+                error: format!("Vault not found: {}", e),
             }),
         )
             .into_response(),
     }
 }
 
-pub async fn get_tvl(Extension(tracker): Extension<Arc<BalanceTracker>>) -> Response {
-    let tvl = tracker.calculate_tvl().await;
-    (
-        StatusCode::OK,
-        Json(TvlResponse {
-            total_value_locked: tvl,
-            total_vaults: 0,
-            timestamp: Utc::now().timestamp(),
-        }),
-    )
-        .into_response()
-}
-
-pub async fn get_alerts(Extension(tracker): Extension<Arc<BalanceTracker>>) -> Response {
-    let alerts = tracker.get_alerts().await;
-    let count = alerts.len();
-    (StatusCode::OK, Json(AlertsResponse { alerts, count })).into_response()
+pub async fn add_authorized_program(
+    Path(user_pubkey): Path<String>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(ws): Extension<Arc<WebSocketManager>>,
+    AuthenticatedWallet(wallet): AuthenticatedWallet,
+    Json(req): Json<AuthorizedProgramRequest>,
+) -> Response {
+    change_authorized_program(true, &user_pubkey, &vm, &db, &ws, wallet, req).await
 }
 
-pub async fn get_vault_status(
+pub async fn remove_authorized_program(
     Path(user_pubkey): Path<String>,
-    Extension(tracker): Extension<Arc<BalanceTracker>>,
+    Extension(vm): Extension<Arc<VaultManager>>,
     Extension(db): Extension<Arc<Database>>,
+    Extension(ws): Extension<Arc<WebSocketManager>>,
+    AuthenticatedWallet(wallet): AuthenticatedWallet,
+    Json(req): Json<AuthorizedProgramRequest>,
 ) -> Response {
-    let user = match parse_pubkey(&user_pubkey) {
+    change_authorized_program(false, &user_pubkey, &vm, &db, &ws, wallet, req).await
+}
+
+/// Checks the change against the current `VaultAuthority` first, so a
+/// duplicate or unknown program is a 409/404 instead of a failed transaction.
+async fn change_authorized_program(
+    authorized: bool,
+    user_pubkey: &str,
+    vm: &Arc<VaultManager>,
+    db: &Arc<Database>,
+    ws: &Arc<WebSocketManager>,
+    wallet: Pubkey,
+    req: AuthorizedProgramRequest,
+) -> Response {
+    let reject =
+        |status: StatusCode, error: String| (status, Json(ErrorResponse { error })).into_response();
+    let user = match parse_pubkey(user_pubkey) {
         Ok(pk) => pk,
-        Err(e) => {
+        Err(e) => return reject(StatusCode::BAD_REQUEST, e),
+    };
+    if wallet != user {
+        return reject(
+            StatusCode::FORBIDDEN,
+            "Session wallet does not own this vault".to_string(),
+        );
+    }
+    let program = match parse_pubkey(&req.program) {
+        Ok(pk) => pk,
+        Err(e) => return reject(StatusCode::BAD_REQUEST, e),
+    };
+
+    let current = match vm.get_authorized_programs(user).await {
+        Ok(programs) => programs,
+        Err(e) => return reject(StatusCode::NOT_FOUND, format!("Vault not found: {}", e)),
+    };
+    if let Some(response) = require_single_owner(vm, user).await {
+        return response;
+    }
+    let listed = current.contains(&program);
+    if authorized && listed {
+        return reject(
+            StatusCode::CONFLICT,
+            format!("Program {} is already authorized", program),
+        );
+    }
+    if authorized && current.len() >= VaultAuthority::MAX_AUTHORIZED_PROGRAMS {
+        return reject(
+            StatusCode::CONFLICT,
+            format!(
+                "Vault already authorizes the maximum of {} programs",
+                VaultAuthority::MAX_AUTHORIZED_PROGRAMS
+            ),
+        );
+    }
+    if !authorized && !listed {
+        return reject(
+            StatusCode::NOT_FOUND,
+            format!("Program {} is not authorized", program),
+        );
+    }
+
+    if req.unsigned.unwrap_or(false) {
+        let ix = if authorized {
+            vm.add_authorized_program_ix(user, program).await
+        } else {
+            vm.remove_authorized_program_ix(user, program).await
+        };
+        return unsigned_response(vm, user, ix, req.sponsor_fees).await;
+    }
+
+    let sent = if authorized {
+        vm.add_authorized_program(user, program).await
+    } else {
+        vm.remove_authorized_program(user, program).await
+    };
+    match sent {
+        Ok(sig) => {
+            announce_authorization(db, ws, user, program, authorized, &sig).await;
+            (StatusCode::OK, Json(TxResponse { tx_signature: sig })).into_response()
+        }
+        Err(e) => reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+pub async fn get_multisig_config(
+    Path(user_pubkey): Path<String>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+) -> Response {
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+        }
+    };
+
+    match vm.get_multisig_config(user).await {
+        Ok(config) => (
+            StatusCode::OK,
+            Json(MultisigConfigResponse {
+                user: user_pubkey,
+                vault: config.vault.to_string(),
+                signers: config.signers.iter().map(Pubkey::to_string).collect(),
+                threshold: config.threshold,
+                max_signers: MultisigConfig::MAX_SIGNERS,
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Multisig not configured: {}", e),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Validates the signer set the way the program does, so a bad set is a 400
+/// instead of a failed transaction.
+pub async fn initialize_multisig(
+    Path(user_pubkey): Path<String>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(db): Extension<Arc<Database>>,
+    AuthenticatedWallet(wallet): AuthenticatedWallet,
+    Json(req): Json<MultisigInitRequest>,
+) -> Response {
+    let reject =
+        |status: StatusCode, error: String| (status, Json(ErrorResponse { error })).into_response();
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => return reject(StatusCode::BAD_REQUEST, e),
+    };
+    if wallet != user {
+        return reject(
+            StatusCode::FORBIDDEN,
+            "Session wallet does not own this vault".to_string(),
+        );
+    }
+    let signers = match req
+        .signers
+        .iter()
+        .map(|s| parse_pubkey(s))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(signers) => signers,
+        Err(e) => return reject(StatusCode::BAD_REQUEST, e),
+    };
+    if signers.is_empty() || signers.len() > MultisigConfig::MAX_SIGNERS {
+        return reject(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Between 1 and {} signers are required",
+                MultisigConfig::MAX_SIGNERS
+            ),
+        );
+    }
+    if req.threshold == 0 || req.threshold as usize > signers.len() {
+        return reject(
+            StatusCode::BAD_REQUEST,
+            format!("threshold must be between 1 and {}", signers.len()),
+        );
+    }
+    let mut seen = std::collections::HashSet::new();
+    if let Some(duplicate) = signers.iter().find(|s| !seen.insert(**s)) {
+        return reject(
+            StatusCode::BAD_REQUEST,
+            format!("Signer {} is listed twice", duplicate),
+        );
+    }
+    if vm.get_multisig_config(user).await.is_ok() {
+        return reject(
+            StatusCode::CONFLICT,
+            "Multisig is already configured for this vault".to_string(),
+        );
+    }
+
+    if req.unsigned.unwrap_or(false) {
+        let ix = vm
+            .initialize_multisig_ix(user, signers, req.threshold)
+            .await;
+        return unsigned_response(&vm, user, ix, req.sponsor_fees).await;
+    }
+
+    let details = serde_json::json!({
+        "signers": req.signers,
+        "threshold": req.threshold,
+    });
+    match vm.initialize_multisig(user, signers, req.threshold).await {
+        Ok(sig) => {
+            let mut details = details;
+            details["signature"] = sig.clone().into();
+            record_audit(
+                &db,
+                &user_pubkey,
+                "MULTISIG_CONFIGURED",
+                &details.to_string(),
+            )
+            .await;
+            (StatusCode::OK, Json(TxResponse { tx_signature: sig })).into_response()
+        }
+        Err(e) => reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Start an operation that needs the vault's co-signers; it is submitted by
+/// the approval that meets the threshold.
+pub async fn propose_multisig_operation(
+    Path(user_pubkey): Path<String>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(multisig): Extension<Arc<MultisigCoordinator>>,
+    AuthenticatedWallet(wallet): AuthenticatedWallet,
+    Json(req): Json<ProposeMultisigRequest>,
+) -> Response {
+    let action = req.action;
+    let reject =
+        |status: StatusCode, error: String| (status, Json(ErrorResponse { error })).into_response();
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => return reject(StatusCode::BAD_REQUEST, e),
+    };
+    if wallet != user {
+        return reject(
+            StatusCode::FORBIDDEN,
+            "Session wallet does not own this vault".to_string(),
+        );
+    }
+    match &action {
+        MultisigAction::Withdraw { amount: 0 } => {
+            return reject(StatusCode::BAD_REQUEST, "amount is required".to_string())
+        }
+        MultisigAction::AddAuthorizedProgram { program }
+        | MultisigAction::RemoveAuthorizedProgram { program } => {
+            if let Err(e) = parse_pubkey(program) {
+                return reject(StatusCode::BAD_REQUEST, e);
+            }
+        }
+        MultisigAction::Withdraw { .. } => {}
+    }
+    let config = match vm.get_multisig_config(user).await {
+        Ok(config) => config,
+        Err(e) => {
+            return reject(
+                StatusCode::NOT_FOUND,
+                format!("Multisig not configured: {}", e),
+            )
+        }
+    };
+
+    match multisig.propose(user, &config, action).await {
+        Ok(Some(op)) => {
+            let details = serde_json::json!({
+                "operation_id": op.id,
+                "action": op.action,
+            })
+            .to_string();
+            record_audit(&db, &user_pubkey, "MULTISIG_PROPOSED", &details).await;
+            (StatusCode::OK, Json(op)).into_response()
+        }
+        Ok(None) => reject(
+            StatusCode::CONFLICT,
+            format!(
+                "Vault already has {} pending operations; cancel one or let it expire",
+                multisig.limits().max_open_per_vault
+            ),
+        ),
+        Err(e) => reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+pub async fn list_multisig_operations(
+    Query(query): Query<MultisigOperationsQuery>,
+    Extension(multisig): Extension<Arc<MultisigCoordinator>>,
+) -> Response {
+    match multisig.pending(query.signer.as_deref()).await {
+        Ok(operations) => {
+            let count = operations.len();
+            (
+                StatusCode::OK,
+                Json(MultisigOperationsResponse { operations, count }),
+            )
+                .into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn get_multisig_operation(
+    Path(id): Path<String>,
+    Extension(multisig): Extension<Arc<MultisigCoordinator>>,
+) -> Response {
+    match multisig.get(&id).await {
+        Ok(Some(op)) => (StatusCode::OK, Json(op)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No multisig operation {}", id),
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Approvals prove themselves by signing the operation's message, or its
+/// transaction message, so no session is needed. The approval that meets the
+/// threshold builds the transaction; the signature that completes it submits
+/// the transaction and the response carries the outcome.
+pub async fn approve_multisig_operation(
+    Path(id): Path<String>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(tracker): Extension<Arc<BalanceTracker>>,
+    Extension(ws): Extension<Arc<WebSocketManager>>,
+    Extension(multisig): Extension<Arc<MultisigCoordinator>>,
+    Json(req): Json<ApproveMultisigRequest>,
+) -> Response {
+    let reject =
+        |status: StatusCode, error: String| (status, Json(ErrorResponse { error })).into_response();
+    let signer = match parse_pubkey(&req.signer) {
+        Ok(pk) => pk,
+        Err(e) => return reject(StatusCode::BAD_REQUEST, e),
+    };
+    let Ok(signature) = req.signature.parse::<Signature>() else {
+        return reject(
+            StatusCode::BAD_REQUEST,
+            "Invalid signature encoding".to_string(),
+        );
+    };
+    let op = match multisig.get(&id).await {
+        Ok(Some(op)) => op,
+        Ok(None) => {
+            return reject(
+                StatusCode::NOT_FOUND,
+                format!("No multisig operation {}", id),
+            )
+        }
+        Err(e) => return reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    if op.status != MultisigStatus::Pending {
+        return reject(
+            StatusCode::CONFLICT,
+            format!("Operation is {}", op.status.as_str()),
+        );
+    }
+    let Some(message) = op.message_for(&req.signer) else {
+        return reject(
+            StatusCode::FORBIDDEN,
+            format!("{} has nothing to sign for this operation", signer),
+        );
+    };
+    if !verify_approval(message, &signer, &signature) {
+        return reject(
+            StatusCode::BAD_REQUEST,
+            "Signature does not match the operation message".to_string(),
+        );
+    }
+
+    let op = match multisig.approve(&op, signer, signature).await {
+        Ok(Some(op)) => op,
+        Ok(None) => {
+            return reject(
+                StatusCode::CONFLICT,
+                format!("{} already signed this operation", signer),
+            )
+        }
+        Err(e) => return reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let details = serde_json::json!({
+        "operation_id": op.id,
+        "signer": req.signer,
+        "status": op.status,
+    })
+    .to_string();
+    record_audit(&db, &op.owner, "MULTISIG_APPROVED", &details).await;
+
+    if let (MultisigStatus::Executed, Some(sig)) = (op.status, &op.tx_signature) {
+        if let Ok(owner) = parse_pubkey(&op.owner) {
+            announce_multisig_execution(&db, &tracker, &ws, owner, &op.action, sig).await;
+        }
+    }
+    (StatusCode::OK, Json(op)).into_response()
+}
+
+/// The owner withdraws a pending operation; its nonce account is closed.
+pub async fn cancel_multisig_operation(
+    Path(id): Path<String>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(multisig): Extension<Arc<MultisigCoordinator>>,
+    AuthenticatedWallet(wallet): AuthenticatedWallet,
+) -> Response {
+    let reject =
+        |status: StatusCode, error: String| (status, Json(ErrorResponse { error })).into_response();
+    let op = match multisig.get(&id).await {
+        Ok(Some(op)) => op,
+        Ok(None) => {
+            return reject(
+                StatusCode::NOT_FOUND,
+                format!("No multisig operation {}", id),
+            )
+        }
+        Err(e) => return reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    if op.owner != wallet.to_string() {
+        return reject(
+            StatusCode::FORBIDDEN,
+            "Session wallet does not own this operation".to_string(),
+        );
+    }
+    if op.status != MultisigStatus::Pending {
+        return reject(
+            StatusCode::CONFLICT,
+            format!("Operation is {}", op.status.as_str()),
+        );
+    }
+
+    match multisig.cancel(&op).await {
+        Ok(op) if op.status == MultisigStatus::Cancelled => {
+            let details = serde_json::json!({ "operation_id": op.id }).to_string();
+            record_audit(&db, &op.owner, "MULTISIG_CANCELLED", &details).await;
+            (StatusCode::OK, Json(op)).into_response()
+        }
+        Ok(op) => reject(
+            StatusCode::CONFLICT,
+            format!("Operation is {}", op.status.as_str()),
+        ),
+        Err(e) => reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+async fn announce_multisig_execution(
+    db: &Arc<Database>,
+    tracker: &Arc<BalanceTracker>,
+    ws: &Arc<WebSocketManager>,
+    owner: Pubkey,
+    action: &MultisigAction,
+    signature: &str,
+) {
+    match action {
+        MultisigAction::Withdraw { amount } => {
+            refresh_balance(tracker, ws, owner).await;
+            ws.broadcast(WsMessage::WithdrawNotification {
+                user: owner.to_string(),
+                amount: *amount,
+                signature: signature.to_string(),
+            });
+        }
+        MultisigAction::AddAuthorizedProgram { program }
+        | MultisigAction::RemoveAuthorizedProgram { program } => {
+            if let Ok(program) = parse_pubkey(program) {
+                let authorized = matches!(action, MultisigAction::AddAuthorizedProgram { .. });
+                announce_authorization(db, ws, owner, program, authorized, signature).await;
+            }
+        }
+    }
+}
+
+pub async fn get_balance(
+    Path(user_pubkey): Path<String>,
+    Extension(tracker): Extension<Arc<BalanceTracker>>,
+) -> Response {
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+        }
+    };
+
+    match tracker.get_vault_balance(user).await {
+        Ok(balance) => (StatusCode::OK, Json(balance)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn get_transactions(
+    Path(user_pubkey): Path<String>,
+    Query(query): Query<TransactionHistoryQuery>,
+    Extension(db): Extension<Arc<Database>>,
+) -> Response {
+    let reject = |error: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+            }),
+        )
+            .into_response()
+    };
+    let after = match query.cursor.as_deref().map(TransactionCursor::decode) {
+        Some(None) => return reject("Invalid cursor"),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    if let (Some(min), Some(max)) = (query.min_amount, query.max_amount) {
+        if min > max {
+            return reject("min_amount must not exceed max_amount");
+        }
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return reject("from must be before to");
+        }
+    }
+    let limit = query.limit.unwrap_or(50);
+    if limit == 0 || limit > MAX_TRANSACTION_PAGE {
+        return reject(&format!(
+            "limit must be between 1 and {}",
+            MAX_TRANSACTION_PAGE
+        ));
+    }
+
+    let query = TransactionQuery {
+        tx_type: query.tx_type,
+        status: query.status,
+        min_amount: query.min_amount,
+        max_amount: query.max_amount,
+        from: query.from,
+        to: query.to,
+        order: query.order.unwrap_or_default(),
+        limit,
+        after,
+    };
+    match db.get_transaction_page(&user_pubkey, &query).await {
+        Ok(page) => (
+            StatusCode::OK,
+            Json(TransactionsResponse {
+                count: page.transactions.len(),
+                transactions: page.transactions,
+                total: page.total,
+                next_cursor: page.next_cursor,
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn get_tvl(Extension(tracker): Extension<Arc<BalanceTracker>>) -> Response {
+    match tracker.tvl().await {
+        Ok(tvl) => (
+            StatusCode::OK,
+            Json(TvlResponse {
+                total_value_locked: tvl.total_value_locked,
+                total_locked: tvl.total_locked,
+                total_available: tvl.total_available,
+                total_vaults: tvl.total_vaults,
+                unreadable_vaults: tvl.unreadable_vaults,
+                slot: tvl.slot,
+                matches_database: tvl.matches_database,
+                timestamp: tvl.observed_at,
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn get_alerts(Extension(tracker): Extension<Arc<BalanceTracker>>) -> Response {
+    let alerts = tracker.get_alerts().await;
+    let count = alerts.len();
+    (StatusCode::OK, Json(AlertsResponse { alerts, count })).into_response()
+}
+
+/// `GET /vault/{user}/balance?at=`: balances at a past time or slot, replayed
+/// from indexed events. Refuses with 409 while the index has gaps.
+pub async fn get_balance_at(
+    Path(user_pubkey): Path<String>,
+    Query(query): Query<BalanceAtQuery>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(db): Extension<Arc<Database>>,
+) -> Response {
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+        }
+    };
+    let at: PointInTime = match query.at.parse() {
+        Ok(at) => at,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid at: {}", e),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let vault = match vm.vault_address(&user).await {
+        Ok(vault) => vault,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    match balance_history::balance_at(&db, vm.chain.as_ref(), user, vault, at).await {
+        Ok(BalanceAt::Balance(balance)) => (StatusCode::OK, Json(balance)).into_response(),
+        Ok(BalanceAt::NotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Vault had no indexed events at that point".to_string(),
+            }),
+        )
+            .into_response(),
+        Ok(BalanceAt::Gap(gap)) => {
+            #[derive(Serialize)]
+            struct GapResponse {
+                error: String,
+                #[serde(flatten)]
+                gap: IndexGap,
+            }
+
+            (
+                StatusCode::CONFLICT,
+                Json(GapResponse {
+                    error: "Vault events are missing from the index; run a backfill first"
+                        .to_string(),
+                    gap,
+                }),
+            )
+                .into_response()
+        }
+        Ok(BalanceAt::Inconsistent(inconsistency)) => {
+            #[derive(Serialize)]
+            struct InconsistencyResponse {
+                error: String,
+                #[serde(flatten)]
+                inconsistency: BalanceInconsistency,
+            }
+
+            (
+                StatusCode::CONFLICT,
+                Json(InconsistencyResponse {
+                    error: "Indexed events do not add up from the snapshot; reindex the vault"
+                        .to_string(),
+                    inconsistency,
+                }),
+            )
+                .into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// `GET /vault/{user}/statement`: movements over a period between an opening
+/// and closing balance, from indexed events, as JSON or CSV.
+pub async fn get_statement(
+    Path(user_pubkey): Path<String>,
+    Query(query): Query<StatementQuery>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(db): Extension<Arc<Database>>,
+) -> Response {
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+        }
+    };
+    let to = query.to.unwrap_or_else(|| Utc::now().timestamp());
+    if query.from >= to {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "from must be before to".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let vault = match vm.vault_address(&user).await {
+        Ok(vault) => vault,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let statement =
+        match statement::build(&db, vm.chain.as_ref(), user, vault, query.from, to).await {
+            Ok(statement) => statement,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+    if !statement.reconciliation.balanced {
+        eprintln!(
+            "⚠️ Statement for {} over [{}, {}) does not reconcile",
+            user, query.from, to
+        );
+    }
+
+    match query.format.unwrap_or_default() {
+        StatementFormat::Json => (StatusCode::OK, Json(statement)).into_response(),
+        StatementFormat::Csv => {
+            let filename = format!(
+                "attachment; filename=\"statement-{}-{}-{}.csv\"",
+                user, query.from, to
+            );
+            (
+                StatusCode::OK,
+                [
+                    (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                    (CONTENT_DISPOSITION, filename),
+                ],
+                statement.to_csv(),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn get_vault_status(
+    Path(user_pubkey): Path<String>,
+    Extension(tracker): Extension<Arc<BalanceTracker>>,
+    Extension(db): Extension<Arc<Database>>,
+) -> Response {
+    let user = match parse_pubkey(&user_pubkey) {
+        Ok(pk) => pk,
+        Err(e) => {
             return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
         }
     };
 
     match tracker.get_vault_balance(user).await {
         Ok(balance) => {
-            let transactions = db
-                .get_user_transactions(&user_pubkey)
+            let transaction_count = db
+                .count_user_transactions(&user_pubkey)
                 .await
                 .unwrap_or_default();
 
             #[derive(Serialize)]
             struct StatusResponse {
                 balance: VaultBalance,
-                transaction_count: usize,
+                transaction_count: u64,
                 health_score: f64,
             }
 
@@ -701,7 +1933,7 @@ pub async fn get_vault_status(
                 StatusCode::OK,
                 Json(StatusResponse {
                     balance,
-                    transaction_count: transactions.len(),
+                    transaction_count,
                     health_score,
                 }),
             )
@@ -748,10 +1980,264 @@ pub async fn get_tvl_history(
     }
 }
 
-// Aliases for route handlers to match main.rs
-pub use initialize_vault as register_vault;
+#[derive(Deserialize, Default)]
+pub struct BackfillRequest {
+    /// Rescan from this slot instead of resuming from the checkpoints.
+    pub from_slot: Option<u64>,
+}
+
+pub async fn start_backfill(
+    Extension(backfill): Extension<Arc<Backfill>>,
+    request: Option<Json<BackfillRequest>>,
+) -> Response {
+    let Json(request) = request.unwrap_or_default();
+    match backfill.start(request.from_slot).await {
+        Ok(progress) => (StatusCode::ACCEPTED, Json(progress)).into_response(),
+        Err(e) => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn get_backfill_progress(Extension(backfill): Extension<Arc<Backfill>>) -> Response {
+    (StatusCode::OK, Json(backfill.progress().await)).into_response()
+}
+
+pub async fn run_reconciliation(Extension(reconciler): Extension<Arc<Reconciler>>) -> Response {
+    match reconciler.run().await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("{:#}", e),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReconciliationQuery {
+    /// `MATCH`, `MISMATCH` or `RESOLVED`.
+    pub status: Option<ReconciliationStatus>,
+}
+
+pub async fn get_reconciliation_logs(
+    Extension(db): Extension<Arc<Database>>,
+    Query(query): Query<ReconciliationQuery>,
+) -> Response {
+    match db.get_reconciliation_logs(query.status).await {
+        Ok(logs) => (StatusCode::OK, Json(logs)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ResolveRequest {
+    /// What was done about the mismatch, kept with the log.
+    pub action: String,
+}
+
+pub async fn resolve_reconciliation(
+    Path(id): Path<String>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(operator): Extension<Operator>,
+    Json(req): Json<ResolveRequest>,
+) -> Response {
+    if req.action.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "A resolution action is required".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    match db.resolve_reconciliation(&id, &req.action).await {
+        Ok(Some(log)) => {
+            let details = serde_json::json!({
+                "reconciliation_id": log.id,
+                "source": log.source,
+                "discrepancy": log.discrepancy,
+                "action": req.action,
+                "operator": operator.name,
+            })
+            .to_string();
+            record_audit(&db, &log.user, "RECONCILIATION_RESOLVED", &details).await;
+            (StatusCode::OK, Json(log)).into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No open mismatch with id {}", id),
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn whoami(Extension(operator): Extension<Operator>) -> Response {
+    (StatusCode::OK, Json(operator)).into_response()
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyInfo {
+    pub name: String,
+    pub role: &'static str,
+}
+
+/// Holders and roles of the configured keys, never the keys themselves.
+pub async fn list_api_keys(Extension(config): Extension<Arc<AdminConfig>>) -> Response {
+    let keys: Vec<ApiKeyInfo> = config
+        .keys
+        .iter()
+        .map(|key| ApiKeyInfo {
+            name: key.name.clone(),
+            role: key.role.as_str(),
+        })
+        .collect();
+    (StatusCode::OK, Json(keys)).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct MetricsQuery {
+    pub limit: Option<usize>,
+}
+
+pub async fn get_metrics_history(
+    Extension(monitor): Extension<Arc<VaultMonitor>>,
+    Query(query): Query<MetricsQuery>,
+) -> Response {
+    let history = monitor.get_metrics_history(query.limit.unwrap_or(60)).await;
+    (StatusCode::OK, Json(history)).into_response()
+}
+
+pub async fn get_security_alerts(Extension(monitor): Extension<Arc<VaultMonitor>>) -> Response {
+    (StatusCode::OK, Json(monitor.get_security_alerts().await)).into_response()
+}
+
+pub async fn get_active_alerts(Extension(db): Extension<Arc<Database>>) -> Response {
+    match db.get_active_alerts().await {
+        Ok(alerts) => (StatusCode::OK, Json(alerts)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn resolve_alert(
+    Path(id): Path<String>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(operator): Extension<Operator>,
+) -> Response {
+    match db.resolve_alert(&id).await {
+        Ok(true) => {
+            let details = serde_json::json!({
+                "alert_id": id,
+                "operator": operator.name,
+            })
+            .to_string();
+            record_audit(&db, &operator.name, "ALERT_RESOLVED", &details).await;
+            (StatusCode::OK, Json(serde_json::json!({ "id": id }))).into_response()
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No active alert with id {}", id),
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    pub user: Option<String>,
+}
+
+pub async fn get_audit_logs(
+    Extension(db): Extension<Arc<Database>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Response {
+    match db.get_audit_logs(query.user.as_deref()).await {
+        Ok(logs) => (StatusCode::OK, Json(logs)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReconcileVaultResponse {
+    pub user: String,
+    pub matches: bool,
+}
+
+/// Compare one vault's cached balance with the chain now.
+pub async fn reconcile_vault(
+    Path(user): Path<String>,
+    Extension(tracker): Extension<Arc<BalanceTracker>>,
+) -> Response {
+    let user_pubkey = match parse_pubkey(&user) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response()
+        }
+    };
+
+    match tracker.reconcile(user_pubkey).await {
+        Ok(matches) => (
+            StatusCode::OK,
+            Json(ReconcileVaultResponse { user, matches }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+// Aliases for route handlers to match the router
 pub use deposit_collateral as deposit;
-pub use withdraw_collateral as withdraw;
+pub use initialize_vault as register_vault;
 pub use lock_collateral as lock;
-pub use unlock_collateral as unlock;
 pub use transfer_collateral as transfer;
+pub use unlock_collateral as unlock;
+pub use withdraw_collateral as withdraw;
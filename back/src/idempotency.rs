@@ -0,0 +1,241 @@
+//! `Idempotency-Key` support for POST routes signed in with a session. A
+//! retried request with the same key gets the first response back instead
+//! of sending another transaction:
+//!
+//! - the session is checked first, and the key is scoped to its wallet, so
+//!   one wallet can neither replay nor block another's key,
+//! - the first request with a key claims it with a hash of its method, path
+//!   and body, runs, and its response is stored,
+//! - a repeat with the same payload replays that response, or gets a 409
+//!   while the first is still running,
+//! - a repeat with a different payload is rejected with a 422.
+//!
+//! A 4xx or 5xx from a request that sent nothing releases the key for a
+//! retry. Once a transaction has been broadcast it may land whatever the
+//! handler answered, so an error is kept like a success, with the last
+//! signature sent added to its body, and a retry replays it rather than
+//! sending again. A claim still in progress after `[idempotency] lease_secs`
+//! is taken to be abandoned and can be claimed again. Keys are forgotten
+//! after `[idempotency] ttl_secs`.
+
+use crate::auth::{reject, AuthenticatedWallet};
+use crate::db::{Database, IdempotencyRecord, IdempotencyStatus};
+use crate::sender::track_submissions;
+use anchor_client::solana_sdk::hash::hashv;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{FromRequestParts, Request, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderMap, HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Set on responses served from a stored result.
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Longest key a client may send; the column is `VARCHAR(255)`.
+const MAX_KEY_LEN: usize = 255;
+/// Same limit axum's `Json` extractor applies.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// `[idempotency]` in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdempotencyConfig {
+    /// How long a key is remembered after its first use.
+    pub ttl_secs: u64,
+    /// How long a claim may stay in progress before a retry can take it
+    /// over; longer than the sender takes to land a transaction.
+    pub lease_secs: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 86_400,
+            lease_secs: 120,
+        }
+    }
+}
+
+pub struct Idempotency {
+    database: Arc<Database>,
+    config: IdempotencyConfig,
+}
+
+impl Idempotency {
+    pub fn new(database: Arc<Database>, config: IdempotencyConfig) -> Self {
+        Self { database, config }
+    }
+}
+
+/// Middleware for the whole router; requests without the header or a
+/// bearer session, and anything but POST, pass straight through.
+pub async fn guard(
+    State(idempotency): State<Arc<Idempotency>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.method() != Method::POST {
+        return next.run(req).await;
+    }
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY) else {
+        return next.run(req).await;
+    };
+    let key = match key.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => key.to_string(),
+        _ => {
+            return reject(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "Idempotency-Key must be 1 to {} visible ASCII characters",
+                    MAX_KEY_LEN
+                ),
+            )
+        }
+    };
+
+    if !has_bearer(req.headers()) {
+        return next.run(req).await;
+    }
+
+    let (mut parts, body) = req.into_parts();
+    let wallet = match AuthenticatedWallet::from_request_parts(&mut parts, &()).await {
+        Ok(AuthenticatedWallet(wallet)) => wallet.to_string(),
+        Err(rejection) => return rejection,
+    };
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return reject(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large");
+    };
+    let request_hash = hashv(&[
+        parts.method.as_str().as_bytes(),
+        parts.uri.path().as_bytes(),
+        parts.uri.query().unwrap_or_default().as_bytes(),
+        &body,
+    ])
+    .to_string();
+
+    let now = Utc::now().timestamp();
+    let claim = IdempotencyRecord {
+        wallet,
+        key: key.clone(),
+        request_hash: request_hash.clone(),
+        status: IdempotencyStatus::InProgress,
+        response_status: None,
+        response_body: None,
+        tx_signature: None,
+        created_at: now,
+        completed_at: None,
+    };
+    let expires_before = now - idempotency.config.ttl_secs as i64;
+    let lease_expires_before = now - idempotency.config.lease_secs as i64;
+    match idempotency
+        .database
+        .claim_idempotency_key(claim.clone(), expires_before, lease_expires_before)
+        .await
+    {
+        Ok(None) => {}
+        Ok(Some(existing)) if existing.request_hash != request_hash => {
+            return reject(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency-Key was already used for a different request",
+            )
+        }
+        Ok(Some(existing)) => return replay(existing),
+        Err(e) => return reject(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+
+    let (response, submitted) =
+        track_submissions(next.run(Request::from_parts(parts, Body::from(body)))).await;
+    let (mut parts, body) = response.into_parts();
+    let (status, body) = match to_bytes(body, usize::MAX).await {
+        Ok(body) => (parts.status, body.to_vec()),
+        Err(e) => {
+            let error = format!("Response could not be read: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({ "error": error })
+                    .to_string()
+                    .into_bytes(),
+            )
+        }
+    };
+    let failed = status.is_client_error() || status.is_server_error();
+    let body = match submitted.last() {
+        Some(signature) if failed => with_signature(body, signature),
+        _ => body,
+    };
+
+    let stored = if failed && submitted.is_empty() {
+        idempotency.database.release_idempotency_key(&claim).await
+    } else {
+        let text = String::from_utf8_lossy(&body);
+        idempotency
+            .database
+            .complete_idempotency_key(
+                &claim,
+                status.as_u16(),
+                &text,
+                tx_signature(&text).as_deref(),
+            )
+            .await
+    };
+    if let Err(e) = stored {
+        eprintln!("Failed to store result for Idempotency-Key {}: {}", key, e);
+    }
+    if status != parts.status {
+        parts.status = status;
+        parts
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// `body` with `tx_signature` set to `signature`, if it is a JSON object
+/// without one.
+fn with_signature(body: Vec<u8>, signature: &str) -> Vec<u8> {
+    match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Object(mut object)) if !object.contains_key("tx_signature") => {
+            object.insert("tx_signature".to_string(), signature.into());
+            serde_json::Value::Object(object).to_string().into_bytes()
+        }
+        _ => body,
+    }
+}
+
+fn has_bearer(headers: &HeaderMap) -> bool {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Bearer "))
+}
+
+fn replay(record: IdempotencyRecord) -> Response {
+    let (IdempotencyStatus::Completed, Some(status)) = (record.status, record.response_status)
+    else {
+        return reject(
+            StatusCode::CONFLICT,
+            "A request with this Idempotency-Key is still in progress",
+        );
+    };
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = (status, record.response_body.unwrap_or_default()).into_response();
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+fn tx_signature(body: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    value.get("tx_signature")?.as_str().map(str::to_string)
+}
@@ -0,0 +1,682 @@
+//! Indexes `collateral_vault` events from program logs into the database, so
+//! history includes operations that never went through the REST handlers
+//! (direct wallet transactions, CPI calls from position programs).
+
+use crate::chain::{ChainClient, ProgramLogs};
+use crate::db::{
+    ChainEvent, ChainEventVault, Database, TransactionRecord, TransactionStatus, TransactionType,
+    VaultEventRange,
+};
+use crate::metrics::Metrics;
+use crate::websocket::{WebSocketManager, WsMessage};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use collateral_vault::events::*;
+use collateral_vault::state::{CollateralVault, SettlementBucket};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Notify, RwLock};
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+macro_rules! vault_events {
+    ($($event:ident),* $(,)?) => {
+        /// Every event `collateral_vault` emits.
+        pub enum VaultEvent {
+            $($event($event)),*
+        }
+
+        impl VaultEvent {
+            /// Decode one `Program data:` payload (discriminator + borsh body).
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                $(
+                    if let Some(mut body) = data.strip_prefix($event::DISCRIMINATOR) {
+                        return $event::deserialize(&mut body).ok().map(VaultEvent::$event);
+                    }
+                )*
+                None
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(VaultEvent::$event(_) => stringify!($event)),*
+                }
+            }
+        }
+    };
+}
+
+vault_events!(
+    VaultInitialized,
+    VaultMigrated,
+    DepositEvent,
+    WithdrawEvent,
+    CollateralLocked,
+    CollateralUnlocked,
+    CollateralTransferred,
+    AuthorizedProgramAdded,
+    AuthorizedProgramRemoved,
+    WithdrawalRequested,
+    WithdrawalExecuted,
+    MultisigInitialized,
+    ForcedUnlockRequested,
+    ForcedUnlockExecuted,
+    PositionHeartbeat,
+    PositionSettled,
+    RecoveryConfigured,
+    RecoveryInitiated,
+    RecoveryApproved,
+    RecoveryVetoed,
+    RecoveryExecuted,
+);
+
+/// A balance movement an event implies for one vault. `user` is `None` when
+/// the event only names the vault and the owner has to be looked up.
+pub struct Movement {
+    pub vault: Pubkey,
+    pub user: Option<Pubkey>,
+    pub tx_type: TransactionType,
+    pub amount: u64,
+}
+
+impl VaultEvent {
+    /// The vault the event is about (the source vault for transfers).
+    pub fn vault(&self) -> Option<Pubkey> {
+        Some(match self {
+            VaultEvent::VaultInitialized(e) => e.vault,
+            VaultEvent::VaultMigrated(e) => e.vault,
+            VaultEvent::DepositEvent(e) => e.vault,
+            VaultEvent::WithdrawEvent(e) => e.vault,
+            VaultEvent::CollateralLocked(e) => e.vault,
+            VaultEvent::CollateralUnlocked(e) => e.vault,
+            VaultEvent::CollateralTransferred(e) => e.from_vault,
+            VaultEvent::AuthorizedProgramAdded(e) => e.vault,
+            VaultEvent::AuthorizedProgramRemoved(e) => e.vault,
+            VaultEvent::WithdrawalRequested(e) => e.vault,
+            VaultEvent::WithdrawalExecuted(e) => e.vault,
+            VaultEvent::MultisigInitialized(e) => e.vault,
+            VaultEvent::ForcedUnlockRequested(e) => e.vault,
+            VaultEvent::ForcedUnlockExecuted(e) => e.vault,
+            VaultEvent::PositionHeartbeat(e) => e.vault,
+            VaultEvent::PositionSettled(_) => return None,
+            VaultEvent::RecoveryConfigured(e) => e.vault,
+            VaultEvent::RecoveryInitiated(e) => e.vault,
+            VaultEvent::RecoveryApproved(e) => e.vault,
+            VaultEvent::RecoveryVetoed(e) => e.vault,
+            VaultEvent::RecoveryExecuted(e) => e.vault,
+        })
+    }
+
+    /// The vault's sequence after the event (the source vault for transfers).
+    pub fn sequence(&self) -> Option<u64> {
+        Some(match self {
+            VaultEvent::VaultInitialized(e) => e.sequence,
+            VaultEvent::VaultMigrated(e) => e.sequence,
+            VaultEvent::DepositEvent(e) => e.sequence,
+            VaultEvent::WithdrawEvent(e) => e.sequence,
+            VaultEvent::CollateralLocked(e) => e.sequence,
+            VaultEvent::CollateralUnlocked(e) => e.sequence,
+            VaultEvent::CollateralTransferred(e) => e.from_sequence,
+            VaultEvent::AuthorizedProgramAdded(e) => e.sequence,
+            VaultEvent::AuthorizedProgramRemoved(e) => e.sequence,
+            VaultEvent::WithdrawalRequested(e) => e.sequence,
+            VaultEvent::WithdrawalExecuted(e) => e.sequence,
+            VaultEvent::MultisigInitialized(e) => e.sequence,
+            VaultEvent::ForcedUnlockRequested(e) => e.sequence,
+            VaultEvent::ForcedUnlockExecuted(e) => e.sequence,
+            VaultEvent::PositionHeartbeat(e) => e.sequence,
+            VaultEvent::PositionSettled(_) => return None,
+            VaultEvent::RecoveryConfigured(e) => e.sequence,
+            VaultEvent::RecoveryInitiated(e) => e.sequence,
+            VaultEvent::RecoveryApproved(e) => e.sequence,
+            VaultEvent::RecoveryVetoed(e) => e.sequence,
+            VaultEvent::RecoveryExecuted(e) => e.sequence,
+        })
+    }
+
+    /// The on-chain clock when the event was emitted.
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            VaultEvent::VaultInitialized(e) => e.timestamp,
+            VaultEvent::VaultMigrated(e) => e.timestamp,
+            VaultEvent::DepositEvent(e) => e.timestamp,
+            VaultEvent::WithdrawEvent(e) => e.timestamp,
+            VaultEvent::CollateralLocked(e) => e.timestamp,
+            VaultEvent::CollateralUnlocked(e) => e.timestamp,
+            VaultEvent::CollateralTransferred(e) => e.timestamp,
+            VaultEvent::AuthorizedProgramAdded(e) => e.timestamp,
+            VaultEvent::AuthorizedProgramRemoved(e) => e.timestamp,
+            VaultEvent::WithdrawalRequested(e) => e.timestamp,
+            VaultEvent::WithdrawalExecuted(e) => e.timestamp,
+            VaultEvent::MultisigInitialized(e) => e.timestamp,
+            VaultEvent::ForcedUnlockRequested(e) => e.timestamp,
+            VaultEvent::ForcedUnlockExecuted(e) => e.timestamp,
+            VaultEvent::PositionHeartbeat(e) => e.timestamp,
+            VaultEvent::PositionSettled(e) => e.timestamp,
+            VaultEvent::RecoveryConfigured(e) => e.timestamp,
+            VaultEvent::RecoveryInitiated(e) => e.timestamp,
+            VaultEvent::RecoveryApproved(e) => e.timestamp,
+            VaultEvent::RecoveryVetoed(e) => e.timestamp,
+            VaultEvent::RecoveryExecuted(e) => e.timestamp,
+        }
+    }
+
+    /// Rows this event contributes to transaction history. Configuration
+    /// events (authorizations, recovery, multisig, heartbeats) have none.
+    pub fn movements(&self) -> Vec<Movement> {
+        let one = |vault, user, tx_type, amount| {
+            vec![Movement {
+                vault,
+                user: Some(user),
+                tx_type,
+                amount,
+            }]
+        };
+        match self {
+            VaultEvent::VaultInitialized(e) => {
+                one(e.vault, e.user, TransactionType::Initialize, e.amount)
+            }
+            VaultEvent::DepositEvent(e) => one(e.vault, e.user, TransactionType::Deposit, e.amount),
+            VaultEvent::WithdrawEvent(e) => {
+                one(e.vault, e.user, TransactionType::Withdraw, e.amount)
+            }
+            VaultEvent::CollateralLocked(e) => {
+                one(e.vault, e.user, TransactionType::Lock, e.amount)
+            }
+            VaultEvent::CollateralUnlocked(e) => {
+                one(e.vault, e.user, TransactionType::Unlock, e.amount)
+            }
+            VaultEvent::ForcedUnlockExecuted(e) => {
+                one(e.vault, e.user, TransactionType::Unlock, e.amount)
+            }
+            VaultEvent::WithdrawalRequested(e) => one(
+                e.vault,
+                e.user,
+                TransactionType::WithdrawalRequest,
+                e.amount,
+            ),
+            VaultEvent::WithdrawalExecuted(e) => one(
+                e.vault,
+                e.user,
+                TransactionType::WithdrawalExecute,
+                e.amount,
+            ),
+            VaultEvent::CollateralTransferred(e) => [e.from_vault, e.to_vault]
+                .into_iter()
+                .map(|vault| Movement {
+                    vault,
+                    user: None,
+                    tx_type: TransactionType::Transfer,
+                    amount: e.amount,
+                })
+                .collect(),
+            VaultEvent::PositionSettled(e) => [&e.loser, &e.winner]
+                .into_iter()
+                .map(|leg| Movement {
+                    vault: leg.vault,
+                    user: Some(leg.user),
+                    tx_type: TransactionType::Settlement,
+                    amount: e.pnl,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// `vault`'s sequence after the event, if the event touched `vault`. Unlike
+    /// [`VaultEvent::sequence`] this covers the receiving side of transfers and
+    /// both legs of a settlement.
+    pub fn sequence_for(&self, vault: &Pubkey) -> Option<u64> {
+        match self {
+            VaultEvent::CollateralTransferred(e) if e.from_vault == *vault => Some(e.from_sequence),
+            VaultEvent::CollateralTransferred(e) if e.to_vault == *vault => Some(e.to_sequence),
+            VaultEvent::PositionSettled(e) => [&e.loser, &e.winner]
+                .into_iter()
+                .find(|leg| leg.vault == *vault)
+                .map(|leg| leg.sequence),
+            _ if self.vault() == Some(*vault) => self.sequence(),
+            _ => None,
+        }
+    }
+
+    /// Every vault the event touched: both sides of a transfer or settlement.
+    pub fn vaults(&self) -> Vec<Pubkey> {
+        match self {
+            VaultEvent::CollateralTransferred(e) => vec![e.from_vault, e.to_vault],
+            VaultEvent::PositionSettled(e) => vec![e.loser.vault, e.winner.vault],
+            _ => self.vault().into_iter().collect(),
+        }
+    }
+
+    /// The `chain_event_vaults` rows for this event, emitted at `timestamp`.
+    pub fn vault_links(&self, timestamp: i64) -> Vec<ChainEventVault> {
+        self.vaults()
+            .into_iter()
+            .map(|vault| ChainEventVault {
+                vault: vault.to_string(),
+                sequence: self.sequence_for(&vault),
+                timestamp,
+            })
+            .collect()
+    }
+
+    /// How the event moved each vault's balances. Summed over a vault's full
+    /// history this gives its expected balances. Locks and unlocks move funds
+    /// between available and locked without changing the total.
+    pub fn balance_changes(&self) -> Vec<BalanceChange> {
+        let amount = |amount: u64| amount as i128;
+        match self {
+            // Migrated vaults have no indexed history before the migration.
+            VaultEvent::VaultMigrated(e) => vec![BalanceChange {
+                vault: e.vault,
+                total: amount(e.total_balance),
+                locked: amount(e.locked_balance),
+                available: amount(e.available_balance),
+            }],
+            VaultEvent::DepositEvent(e) => vec![BalanceChange::free(e.vault, amount(e.amount))],
+            VaultEvent::WithdrawEvent(e) => vec![BalanceChange::free(e.vault, -amount(e.amount))],
+            VaultEvent::WithdrawalExecuted(e) => {
+                vec![BalanceChange::free(e.vault, -amount(e.amount))]
+            }
+            VaultEvent::CollateralLocked(e) => vec![BalanceChange::lock(e.vault, amount(e.amount))],
+            VaultEvent::CollateralUnlocked(e) => {
+                vec![BalanceChange::lock(e.vault, -amount(e.amount))]
+            }
+            VaultEvent::ForcedUnlockExecuted(e) => {
+                vec![BalanceChange::lock(e.vault, -amount(e.amount))]
+            }
+            VaultEvent::CollateralTransferred(e) => vec![
+                BalanceChange::free(e.from_vault, -amount(e.amount)),
+                BalanceChange::free(e.to_vault, amount(e.amount)),
+            ],
+            VaultEvent::PositionSettled(e) => {
+                // Each leg releases its margin, and the pnl moves out of or
+                // into the bucket the leg names.
+                let leg = |leg: &SettlementLeg, pnl: i128| {
+                    let released = amount(leg.margin_released);
+                    let (locked, available) = match leg.bucket {
+                        SettlementBucket::Locked => (pnl, 0),
+                        SettlementBucket::Available => (0, pnl),
+                    };
+                    BalanceChange {
+                        vault: leg.vault,
+                        total: pnl,
+                        locked: locked - released,
+                        available: available + released,
+                    }
+                };
+                vec![leg(&e.loser, -amount(e.pnl)), leg(&e.winner, amount(e.pnl))]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// How one event moved one vault's balances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub vault: Pubkey,
+    pub total: i128,
+    pub locked: i128,
+    pub available: i128,
+}
+
+impl BalanceChange {
+    /// Funds entering or leaving the available balance.
+    fn free(vault: Pubkey, change: i128) -> Self {
+        Self {
+            vault,
+            total: change,
+            locked: 0,
+            available: change,
+        }
+    }
+
+    /// Funds moving from available to locked; negative to unlock.
+    fn lock(vault: Pubkey, change: i128) -> Self {
+        Self {
+            vault,
+            total: 0,
+            locked: change,
+            available: -change,
+        }
+    }
+}
+
+/// An event from `chain_events`, decoded, with where and when it happened.
+pub struct IndexedEvent {
+    /// Block time, or the event's own clock reading if the block's is unknown.
+    pub timestamp: i64,
+    pub signature: String,
+    pub slot: u64,
+    pub log_index: u32,
+    pub event: VaultEvent,
+}
+
+impl IndexedEvent {
+    /// Decode a `chain_events` row; `None` if it no longer decodes.
+    fn from_row(row: ChainEvent) -> Option<IndexedEvent> {
+        let event = BASE64
+            .decode(&row.data)
+            .ok()
+            .and_then(|data| VaultEvent::decode(&data))?;
+        Some(IndexedEvent {
+            timestamp: row.block_time.unwrap_or_else(|| event.timestamp()),
+            signature: row.signature,
+            slot: row.slot,
+            log_index: row.log_index,
+            event,
+        })
+    }
+
+    /// `vault`'s indexed events within `range`, in chain order. Rows that no
+    /// longer decode are skipped.
+    pub async fn load_for_vault(
+        database: &Database,
+        vault: &Pubkey,
+        range: VaultEventRange,
+    ) -> Result<Vec<IndexedEvent>> {
+        Ok(database
+            .get_vault_chain_events(&vault.to_string(), range)
+            .await?
+            .into_iter()
+            .filter_map(IndexedEvent::from_row)
+            .collect())
+    }
+}
+
+/// A decoded event with its position among the transaction's
+/// `Program data:` lines and its raw payload.
+pub struct LoggedEvent {
+    pub log_index: u32,
+    pub event: VaultEvent,
+    pub data: Vec<u8>,
+}
+
+/// Decode the events `program_id` emitted in one transaction's logs. The
+/// invoke stack is tracked so data logged by other programs in the same
+/// transaction is never mistaken for ours.
+pub fn decode_logs(program_id: &Pubkey, logs: &[String]) -> Vec<LoggedEvent> {
+    let program_id = program_id.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    let mut log_index = 0u32;
+
+    for line in logs {
+        if let Some(data) = line.strip_prefix("Program data: ") {
+            let index = log_index;
+            log_index += 1;
+            if stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let Ok(data) = BASE64.decode(data.trim()) else {
+                continue;
+            };
+            if let Some(event) = VaultEvent::decode(&data) {
+                events.push(LoggedEvent {
+                    log_index: index,
+                    event,
+                    data,
+                });
+            }
+        } else if let Some(rest) = line.strip_prefix("Program ") {
+            let mut parts = rest.split_whitespace();
+            let (Some(program), Some(word)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            match word {
+                "invoke" => stack.push(program),
+                "success" | "failed:" => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    events
+}
+
+/// Subscribes to the program's logs and writes every event it sees.
+pub struct EventIndexer {
+    chain: Arc<dyn ChainClient>,
+    database: Arc<Database>,
+    program_id: Pubkey,
+    /// Vaults already registered in the database, with their owners.
+    owners: RwLock<HashMap<Pubkey, Pubkey>>,
+    metrics: Option<Arc<Metrics>>,
+    websocket: Option<Arc<WebSocketManager>>,
+    /// Notified when the subscription drops transactions.
+    lagged: Notify,
+}
+
+impl EventIndexer {
+    pub fn new(chain: Arc<dyn ChainClient>, database: Arc<Database>, program_id: Pubkey) -> Self {
+        Self {
+            chain,
+            database,
+            program_id,
+            owners: RwLock::new(HashMap::new()),
+            metrics: None,
+            websocket: None,
+            lagged: Notify::new(),
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Push owner-facing events (recovery attempts) to WebSocket clients.
+    pub fn with_websocket(mut self, websocket: Arc<WebSocketManager>) -> Self {
+        self.websocket = Some(websocket);
+        self
+    }
+
+    /// Start the subscription loop; it resubscribes if the stream drops.
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                match self.chain.subscribe_logs(self.program_id).await {
+                    Ok(receiver) => self.run(receiver).await,
+                    Err(e) => eprintln!("❌ Indexer subscription failed: {}", e),
+                }
+                sleep(Duration::from_secs(5)).await;
+            }
+        });
+
+        println!("📚 Event indexer started");
+    }
+
+    /// Index everything `receiver` delivers until it closes.
+    pub async fn run(&self, mut receiver: broadcast::Receiver<ProgramLogs>) {
+        loop {
+            match receiver.recv().await {
+                Ok(logs) => {
+                    if let Err(e) = self.index_logs(&logs).await {
+                        eprintln!("❌ Failed to index {}: {}", logs.signature, e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    eprintln!(
+                        "⚠️ Indexer fell behind, {} transactions skipped; backfilling",
+                        missed
+                    );
+                    self.lagged.notify_one();
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+
+    /// Wait until the subscription next drops transactions. A lag that
+    /// happens while nobody waits is remembered.
+    pub async fn lagged(&self) {
+        self.lagged.notified().await
+    }
+
+    /// Index one transaction's logs. Returns how many events were new;
+    /// replaying the same logs is a no-op.
+    pub async fn index_logs(&self, logs: &ProgramLogs) -> Result<usize> {
+        if logs.err.is_some() {
+            // A failed transaction's state changes and events were rolled back.
+            return Ok(0);
+        }
+        let events = decode_logs(&self.program_id, &logs.logs);
+        if events.is_empty() {
+            return Ok(0);
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_indexed_slot(logs.slot);
+        }
+        let block_time = self.chain.get_block_time(logs.slot).await.unwrap_or(None);
+
+        let mut indexed = 0;
+        for logged in events {
+            if let VaultEvent::RecoveryExecuted(e) = &logged.event {
+                // Ownership moved; later movements belong to the new owner.
+                self.owners.write().await.insert(e.vault, e.new_owner);
+                if let Err(err) = self.database.set_vault_owner(&e.vault, &e.new_owner).await {
+                    eprintln!(
+                        "⚠️ Could not move vault {} to its new owner: {}",
+                        e.vault, err
+                    );
+                }
+            }
+            let mut rows = Vec::new();
+            for movement in logged.event.movements() {
+                let user = match movement.user {
+                    Some(user) => {
+                        self.ensure_vault(&movement.vault).await;
+                        user
+                    }
+                    None => match self.ensure_vault(&movement.vault).await {
+                        Some(owner) => owner,
+                        None => continue,
+                    },
+                };
+                rows.push(TransactionRecord {
+                    id: Uuid::new_v4().to_string(),
+                    user: user.to_string(),
+                    tx_type: movement.tx_type,
+                    amount: movement.amount,
+                    signature: logs.signature.clone(),
+                    status: TransactionStatus::Confirmed,
+                    timestamp: block_time.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+                    slot: Some(logs.slot),
+                    block_time,
+                    log_index: Some(logged.log_index),
+                    error: None,
+                    confirmed_at: None,
+                });
+            }
+
+            let timestamp = block_time.unwrap_or_else(|| logged.event.timestamp());
+            let vaults = logged.event.vault_links(timestamp);
+            let event = ChainEvent {
+                signature: logs.signature.clone(),
+                log_index: logged.log_index,
+                slot: logs.slot,
+                block_time,
+                event_name: logged.event.name().to_string(),
+                vault: logged.event.vault().map(|v| v.to_string()),
+                sequence: logged.event.sequence(),
+                data: BASE64.encode(&logged.data),
+            };
+            if self
+                .database
+                .record_chain_event(event, vaults, rows)
+                .await?
+            {
+                indexed += 1;
+                if let VaultEvent::RecoveryInitiated(e) = &logged.event {
+                    self.recovery_initiated(e, &logs.signature).await;
+                }
+            }
+        }
+
+        Ok(indexed)
+    }
+
+    /// Record the vaults of events indexed before `chain_event_vaults`
+    /// existed. Returns how many events were linked.
+    pub async fn link_vaults(&self) -> Result<usize> {
+        let mut linked = 0;
+        for row in self.database.get_unlinked_chain_events().await? {
+            let Some(indexed) = IndexedEvent::from_row(row.clone()) else {
+                continue;
+            };
+            let vaults = indexed.event.vault_links(indexed.timestamp);
+            self.database.link_chain_event(&row, vaults).await?;
+            linked += 1;
+        }
+        Ok(linked)
+    }
+
+    /// A recovery takes the vault away from its owner unless they veto it in
+    /// time, so tell them as soon as one starts. Only runs for newly indexed
+    /// events, so replays do not alert twice.
+    async fn recovery_initiated(&self, e: &RecoveryInitiated, signature: &str) {
+        let message = format!(
+            "Guardian {} started recovering vault {} to {}; veto before {}",
+            e.actor, e.vault, e.proposed_owner, e.available_at
+        );
+        if let Err(err) = self
+            .database
+            .create_alert(
+                "RECOVERY_INITIATED",
+                "CRITICAL",
+                Some(&e.user.to_string()),
+                &message,
+            )
+            .await
+        {
+            eprintln!("⚠️ Could not record recovery alert: {}", err);
+        }
+        if let Some(websocket) = &self.websocket {
+            websocket.broadcast(WsMessage::RecoveryInitiated {
+                user: e.user.to_string(),
+                vault: e.vault.to_string(),
+                proposed_owner: e.proposed_owner.to_string(),
+                guardian: e.actor.to_string(),
+                approvals: e.approvals,
+                threshold: e.threshold,
+                available_at: e.available_at,
+                signature: signature.to_string(),
+            });
+        }
+    }
+
+    /// Make sure the vault has a `vault_accounts` row and return its owner,
+    /// read from the vault account on chain.
+    async fn ensure_vault(&self, vault: &Pubkey) -> Option<Pubkey> {
+        if let Some(owner) = self.owners.read().await.get(vault) {
+            return Some(*owner);
+        }
+
+        let state = match self.chain.get_account(vault).await {
+            Ok(account) => {
+                let mut data: &[u8] = &account.data;
+                CollateralVault::try_deserialize(&mut data).ok()?
+            }
+            Err(e) => {
+                eprintln!("⚠️ Could not load vault {}: {}", vault, e);
+                return None;
+            }
+        };
+        if let Err(e) = self
+            .database
+            .ensure_vault(&state.owner, vault, &state.token_account)
+            .await
+        {
+            eprintln!("⚠️ Could not register vault {}: {}", vault, e);
+            return None;
+        }
+
+        self.owners.write().await.insert(*vault, state.owner);
+        Some(state.owner)
+    }
+}
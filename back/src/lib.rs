@@ -0,0 +1,24 @@
+pub mod admin;
+pub mod analytics;
+pub mod auth;
+pub mod backfill;
+pub mod balance_history;
+pub mod balance_tracker;
+pub mod chain;
+pub mod config;
+pub mod confirmer;
+pub mod cpi_manager;
+pub mod db;
+pub mod handlers;
+pub mod idempotency;
+pub mod indexer;
+pub mod metrics;
+pub mod multisig;
+pub mod reconciler;
+pub mod router;
+pub mod sender;
+pub mod signer;
+pub mod statement;
+pub mod vault_manager;
+pub mod vault_monitor;
+pub mod websocket;
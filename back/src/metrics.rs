@@ -0,0 +1,367 @@
+//! Prometheus metrics, served as text at `GET /metrics`.
+//!
+//! Counters and histograms are recorded where things happen: the HTTP
+//! middleware below, [`InstrumentedChain`](crate::chain::InstrumentedChain)
+//! for RPC calls, the sender, the confirmer and the indexer. Gauges (TVL,
+//! alerts, WebSocket subscribers, indexer lag) are read from the services
+//! that own them when the endpoint is scraped.
+
+use crate::balance_tracker::BalanceTracker;
+use crate::db::{AttemptOutcome, Database, TransactionStatus};
+use crate::vault_manager::VaultManager;
+use crate::websocket::WebSocketManager;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bounds, in seconds, of the latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+#[derive(Default)]
+struct Histogram {
+    /// Cumulative: `buckets[i]` counts observations <= `LATENCY_BUCKETS[i]`.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        for (count, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    /// By method, route template and status code.
+    http: Mutex<BTreeMap<(String, String, u16), Histogram>>,
+    /// By JSON-RPC method.
+    rpc: Mutex<BTreeMap<&'static str, Histogram>>,
+    rpc_errors: Mutex<BTreeMap<&'static str, u64>>,
+    /// Sender attempts by outcome.
+    tx_attempts: Mutex<BTreeMap<String, u64>>,
+    /// Confirmer status changes by new status.
+    tx_statuses: Mutex<BTreeMap<String, u64>>,
+    /// Highest slot the indexer has indexed events from; 0 before the first.
+    indexed_slot: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.http
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    pub fn observe_rpc(&self, method: &'static str, elapsed: Duration, ok: bool) {
+        self.rpc
+            .lock()
+            .unwrap()
+            .entry(method)
+            .or_default()
+            .observe(elapsed);
+        if !ok {
+            *self.rpc_errors.lock().unwrap().entry(method).or_default() += 1;
+        }
+    }
+
+    pub fn record_send_attempt(&self, outcome: AttemptOutcome) {
+        *self
+            .tx_attempts
+            .lock()
+            .unwrap()
+            .entry(snake_case(&format!("{:?}", outcome)))
+            .or_default() += 1;
+    }
+
+    pub fn record_status_change(&self, status: &TransactionStatus) {
+        *self
+            .tx_statuses
+            .lock()
+            .unwrap()
+            .entry(snake_case(&format!("{:?}", status)))
+            .or_default() += 1;
+    }
+
+    pub fn record_indexed_slot(&self, slot: u64) {
+        self.indexed_slot.fetch_max(slot, Ordering::Relaxed);
+    }
+
+    /// The exposition text, with `gauges` read for this scrape.
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "vault_http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency by method, route and status.",
+        );
+        for ((method, route, status), histogram) in self.http.lock().unwrap().iter() {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\",status=\"{}\"",
+                method,
+                escape(route),
+                status
+            );
+            histogram.render(&mut out, "vault_http_request_duration_seconds", &labels);
+        }
+
+        header(
+            &mut out,
+            "vault_rpc_request_duration_seconds",
+            "histogram",
+            "Solana RPC call latency by method.",
+        );
+        for (method, histogram) in self.rpc.lock().unwrap().iter() {
+            let labels = format!("method=\"{}\"", method);
+            histogram.render(&mut out, "vault_rpc_request_duration_seconds", &labels);
+        }
+        header(
+            &mut out,
+            "vault_rpc_errors_total",
+            "counter",
+            "Solana RPC calls that returned an error, by method.",
+        );
+        for (method, count) in self.rpc_errors.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "vault_rpc_errors_total{{method=\"{}\"}} {}",
+                method, count
+            );
+        }
+
+        header(
+            &mut out,
+            "vault_tx_send_attempts_total",
+            "counter",
+            "Transaction send attempts by outcome.",
+        );
+        for (outcome, count) in self.tx_attempts.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "vault_tx_send_attempts_total{{outcome=\"{}\"}} {}",
+                outcome, count
+            );
+        }
+        header(
+            &mut out,
+            "vault_tx_status_changes_total",
+            "counter",
+            "Transactions moved to a new status by the confirmer.",
+        );
+        for (status, count) in self.tx_statuses.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "vault_tx_status_changes_total{{status=\"{}\"}} {}",
+                status, count
+            );
+        }
+
+        let indexed = self.indexed_slot.load(Ordering::Relaxed);
+        if indexed > 0 {
+            gauge(
+                &mut out,
+                "vault_indexer_last_slot",
+                "Highest slot the indexer has indexed events from.",
+                indexed as f64,
+            );
+        }
+        if let Some(slot) = gauges.chain_slot {
+            gauge(
+                &mut out,
+                "vault_chain_slot",
+                "Slot the RPC node has processed up to.",
+                slot as f64,
+            );
+            if indexed > 0 {
+                gauge(
+                    &mut out,
+                    "vault_indexer_lag_slots",
+                    "Slots between the node and the last indexed event; also grows while the program is idle.",
+                    slot.saturating_sub(indexed) as f64,
+                );
+            }
+        }
+
+        if let Some((tvl, locked)) = gauges.tvl {
+            gauge(
+                &mut out,
+                "vault_tvl",
+                "Total collateral held by all vaults, in base units.",
+                tvl as f64,
+            );
+            gauge(
+                &mut out,
+                "vault_locked",
+                "Collateral locked for positions, in base units.",
+                locked as f64,
+            );
+            let ratio = if tvl > 0 {
+                locked as f64 / tvl as f64
+            } else {
+                0.0
+            };
+            gauge(
+                &mut out,
+                "vault_locked_ratio",
+                "Locked share of the TVL, from 0 to 1.",
+                ratio,
+            );
+        }
+
+        header(
+            &mut out,
+            "vault_active_alerts",
+            "gauge",
+            "Active alerts by severity.",
+        );
+        for (severity, count) in &gauges.alerts {
+            let _ = writeln!(
+                out,
+                "vault_active_alerts{{severity=\"{}\"}} {}",
+                escape(severity),
+                count
+            );
+        }
+
+        gauge(
+            &mut out,
+            "vault_websocket_subscribers",
+            "Open WebSocket connections.",
+            gauges.websocket_subscribers as f64,
+        );
+
+        out
+    }
+}
+
+/// Readings taken from other services for one scrape. Each is left out of
+/// the output if it could not be read.
+#[derive(Debug, Default)]
+pub struct Gauges {
+    pub chain_slot: Option<u64>,
+    /// TVL and total locked, in base units.
+    pub tvl: Option<(u64, u64)>,
+    pub alerts: BTreeMap<String, u64>,
+    pub websocket_subscribers: usize,
+}
+
+/// Middleware for the whole router: times every request under its route
+/// template, so path parameters do not create a series per vault.
+pub async fn track(State(metrics): State<Arc<Metrics>>, req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str())
+        .to_string();
+    let start = Instant::now();
+    let response = next.run(req).await;
+    metrics.observe_http(&method, &route, response.status().as_u16(), start.elapsed());
+    response
+}
+
+/// `GET /metrics`.
+pub async fn serve(
+    Extension(metrics): Extension<Arc<Metrics>>,
+    Extension(vm): Extension<Arc<VaultManager>>,
+    Extension(tracker): Extension<Arc<BalanceTracker>>,
+    Extension(db): Extension<Arc<Database>>,
+    Extension(ws): Extension<Arc<WebSocketManager>>,
+) -> Response {
+    let mut gauges = Gauges {
+        chain_slot: vm.chain.get_slot().await.ok(),
+        tvl: tracker
+            .tvl()
+            .await
+            .ok()
+            .map(|tvl| (tvl.total_value_locked, tvl.total_locked)),
+        websocket_subscribers: ws.subscriber_count(),
+        ..Gauges::default()
+    };
+    for alert in db.get_active_alerts().await.unwrap_or_default() {
+        *gauges
+            .alerts
+            .entry(alert.severity.to_lowercase())
+            .or_default() += 1;
+    }
+
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        metrics.render(&gauges),
+    )
+        .into_response()
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, "gauge", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Label values may not contain raw quotes, backslashes or newlines.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `SendFailed` -> `send_failed`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
@@ -0,0 +1,503 @@
+//! Multisig approval for vault operations. Once a vault has a
+//! `MultisigConfig`, the program refuses withdrawals and authorization
+//! changes unless `threshold` of its co-signers sign the transaction too.
+//! A transaction's signers are fixed before anyone signs it, so this gathers
+//! those signatures in two rounds:
+//!
+//! 1. The owner proposes an operation. Any of the vault's co-signers approve
+//!    it by signing its `message`, a plain-text description of it. Each
+//!    signature is verified before it is stored.
+//! 2. The approval that meets the threshold has the transaction built for
+//!    the first `threshold` approvers and the owner. It is built on a fresh
+//!    durable nonce account and has the backend payer covering fees.
+//! 3. Each of them signs the transaction message. Once all have, their
+//!    signatures go into the transaction and it is submitted. The nonce
+//!    account is closed after.
+//!
+//! No nonce account exists until enough co-signers have approved, so one
+//! who never shows up does not hold anything open. A vault has at most
+//! `[multisig] max_open_per_vault` pending operations. The owner can cancel
+//! one, and one still pending after `[multisig] ttl_secs` expires; either
+//! way its nonce account is closed. The durable nonce keeps the transaction
+//! valid however long signing takes until then.
+
+use crate::chain::durable_nonce;
+use crate::db::{Database, MultisigAction, MultisigApproval, MultisigOperation, MultisigStatus};
+use crate::vault_manager::VaultManager;
+use crate::websocket::{WebSocketManager, WsMessage};
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::Transaction,
+};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use collateral_vault::state::MultisigConfig;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const NONCE_ADVANCED: &str =
+    "Durable nonce advanced before the operation was signed; propose it again";
+
+/// How often operations past their TTL are expired and their nonce
+/// accounts closed.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `[multisig]` in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProposalLimits {
+    /// Most operations a vault may have pending at once.
+    pub max_open_per_vault: usize,
+    /// How long an operation may stay pending before it expires.
+    pub ttl_secs: u64,
+}
+
+impl Default for ProposalLimits {
+    fn default() -> Self {
+        Self {
+            max_open_per_vault: 5,
+            ttl_secs: 86_400,
+        }
+    }
+}
+
+pub struct MultisigCoordinator {
+    vault_manager: Arc<VaultManager>,
+    database: Arc<Database>,
+    websocket: Arc<WebSocketManager>,
+    limits: ProposalLimits,
+    /// One proposal at a time, so two cannot both take a vault's last slot.
+    proposing: Mutex<()>,
+    /// One transition at a time, so two approvals cannot both build the
+    /// transaction and two final signatures cannot both send it.
+    submitting: Mutex<()>,
+}
+
+impl MultisigCoordinator {
+    pub fn new(
+        vault_manager: Arc<VaultManager>,
+        database: Arc<Database>,
+        websocket: Arc<WebSocketManager>,
+    ) -> Self {
+        Self {
+            vault_manager,
+            database,
+            websocket,
+            limits: ProposalLimits::default(),
+            proposing: Mutex::new(()),
+            submitting: Mutex::new(()),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: ProposalLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Expire operations past their TTL in the background, so their nonce
+    /// accounts are closed even if nobody looks at them again.
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.pending(None).await {
+                    eprintln!("❌ Multisig expiry sweep failed: {:#}", e);
+                }
+            }
+        });
+    }
+
+    /// Store `action` for `config`'s co-signers to approve and ask them to.
+    /// Returns `None` if the vault already has its limit of pending
+    /// operations.
+    pub async fn propose(
+        &self,
+        owner: Pubkey,
+        config: &MultisigConfig,
+        action: MultisigAction,
+    ) -> Result<Option<MultisigOperation>> {
+        // Fail on an action the program cannot take before asking anyone.
+        self.instruction(owner, &action).await?;
+
+        let _proposing = self.proposing.lock().await;
+        let vault = config.vault.to_string();
+        let now = Utc::now().timestamp();
+        let open = self
+            .database
+            .count_pending_multisig_operations(&vault, now - self.limits.ttl_secs as i64)
+            .await?;
+        if open >= self.limits.max_open_per_vault {
+            return Ok(None);
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let op = MultisigOperation {
+            message: BASE64.encode(operation_message(&id, &vault, &action)),
+            id,
+            owner: owner.to_string(),
+            vault,
+            action,
+            signers: config.signers.iter().map(Pubkey::to_string).collect(),
+            threshold: config.threshold,
+            approvals: Vec::new(),
+            transaction: None,
+            transaction_message: None,
+            signing: Vec::new(),
+            signatures: Vec::new(),
+            status: MultisigStatus::Pending,
+            tx_signature: None,
+            error: None,
+            created_at: now,
+            executed_at: None,
+        };
+        self.database.insert_multisig_operation(op.clone()).await?;
+
+        self.websocket
+            .broadcast(WsMessage::MultisigSignatureRequested {
+                operation_id: op.id.clone(),
+                owner: op.owner.clone(),
+                action: op.action.clone(),
+                message: op.message.clone(),
+                awaiting: op.awaiting(),
+            });
+        Ok(Some(op))
+    }
+
+    /// The `[multisig]` limits in force.
+    pub fn limits(&self) -> &ProposalLimits {
+        &self.limits
+    }
+
+    async fn instruction(&self, owner: Pubkey, action: &MultisigAction) -> Result<Instruction> {
+        let vm = &self.vault_manager;
+        match action {
+            MultisigAction::Withdraw { amount } => vm.withdraw_ix(owner, *amount).await,
+            MultisigAction::AddAuthorizedProgram { program } => {
+                vm.add_authorized_program_ix(owner, program.parse()?).await
+            }
+            MultisigAction::RemoveAuthorizedProgram { program } => {
+                vm.remove_authorized_program_ix(owner, program.parse()?)
+                    .await
+            }
+        }
+    }
+
+    /// An operation by id, marked `expired` first if it is past its TTL or
+    /// its nonce has advanced.
+    pub async fn get(&self, id: &str) -> Result<Option<MultisigOperation>> {
+        match self.database.get_multisig_operation(id).await? {
+            Some(op) => Ok(Some(self.expire_if_stale(op).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Pending operations, optionally only those `approver` can sign.
+    pub async fn pending(&self, approver: Option<&str>) -> Result<Vec<MultisigOperation>> {
+        let mut pending = Vec::new();
+        for op in self
+            .database
+            .get_pending_multisig_operations(approver)
+            .await?
+        {
+            let op = self.expire_if_stale(op).await?;
+            if op.status == MultisigStatus::Pending {
+                pending.push(op);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Store `signer`'s signature of the message [`MultisigOperation::message_for`]
+    /// gives them: an approval of the operation, or a signature of its
+    /// transaction. Builds the transaction at the threshold and submits it
+    /// once signed. The caller checks the signature with [`verify_approval`]
+    /// first. Returns `None` if `signer` had already signed that message.
+    pub async fn approve(
+        &self,
+        op: &MultisigOperation,
+        signer: Pubkey,
+        signature: Signature,
+    ) -> Result<Option<MultisigOperation>> {
+        let approval = MultisigApproval {
+            signer: signer.to_string(),
+            signature: signature.to_string(),
+            approved_at: Utc::now().timestamp(),
+        };
+        let added = if op.transaction.is_some() {
+            self.database
+                .add_multisig_signature(&op.id, approval)
+                .await?
+        } else {
+            self.database
+                .add_multisig_approval(&op.id, approval)
+                .await?
+        };
+        if !added {
+            return Ok(None);
+        }
+
+        let op = self.reload(op).await?;
+        if op.status == MultisigStatus::Pending {
+            if op.transaction.is_none() && op.signer_approvals() >= op.threshold as usize {
+                return self.build(op).await.map(Some);
+            }
+            if op.is_ready() {
+                return self.execute(op).await.map(Some);
+            }
+        }
+        self.announce(&op);
+        Ok(Some(op))
+    }
+
+    /// Withdraw a pending operation and close its nonce account. Returns the
+    /// operation as it ended up, which is only `cancelled` if it was still
+    /// pending.
+    pub async fn cancel(&self, op: &MultisigOperation) -> Result<MultisigOperation> {
+        let _submitting = self.submitting.lock().await;
+        let op = self.reload(op).await?;
+        if op.status != MultisigStatus::Pending {
+            return Ok(op);
+        }
+        let op = self
+            .finish(&op, MultisigStatus::Cancelled, None, None)
+            .await?;
+        self.close_nonce(&op).await;
+        Ok(op)
+    }
+
+    /// Build the transaction for the first `threshold` co-signers who
+    /// approved and the owner, and ask them to sign it.
+    async fn build(&self, op: MultisigOperation) -> Result<MultisigOperation> {
+        let _submitting = self.submitting.lock().await;
+        let op = self.reload(&op).await?;
+        if op.status != MultisigStatus::Pending || op.transaction.is_some() {
+            return Ok(op);
+        }
+
+        let approvers = op
+            .approvals
+            .iter()
+            .filter(|a| op.signers.contains(&a.signer))
+            .take(op.threshold as usize)
+            .map(|a| a.signer.parse())
+            .collect::<Result<Vec<Pubkey>, _>>()?;
+        let owner: Pubkey = op.owner.parse()?;
+        let mut instruction = self.instruction(owner, &op.action).await?;
+        instruction.accounts.extend(
+            approvers
+                .iter()
+                .map(|key| AccountMeta::new_readonly(*key, true)),
+        );
+        let nonce = self
+            .vault_manager
+            .create_nonce_account(&Uuid::parse_str(&op.id)?.simple().to_string())
+            .await?;
+        let unsigned = self
+            .vault_manager
+            .build_unsigned_on_nonce(nonce, &[instruction])
+            .await?;
+        let tx = decode(&unsigned.transaction)?;
+
+        let mut signing: Vec<String> = approvers.iter().map(Pubkey::to_string).collect();
+        if !signing.contains(&op.owner) {
+            signing.push(op.owner.clone());
+        }
+        self.database
+            .set_multisig_transaction(
+                &op.id,
+                &unsigned.transaction,
+                &BASE64.encode(tx.message_data()),
+                &signing,
+            )
+            .await?;
+
+        let op = self.reload(&op).await?;
+        self.announce(&op);
+        if let Some(message) = &op.transaction_message {
+            self.websocket
+                .broadcast(WsMessage::MultisigSignatureRequested {
+                    operation_id: op.id.clone(),
+                    owner: op.owner.clone(),
+                    action: op.action.clone(),
+                    message: message.clone(),
+                    awaiting: op.awaiting(),
+                });
+        }
+        Ok(op)
+    }
+
+    async fn execute(&self, op: MultisigOperation) -> Result<MultisigOperation> {
+        let _submitting = self.submitting.lock().await;
+        let op = self.reload(&op).await?;
+        if op.status != MultisigStatus::Pending {
+            return Ok(op);
+        }
+        let Some(transaction) = &op.transaction else {
+            return Ok(op);
+        };
+
+        let mut tx = decode(transaction)?;
+        for signer in &op.signing {
+            let signature = op
+                .signature(signer)
+                .ok_or_else(|| anyhow!("{} has not signed operation {}", signer, op.id))?;
+            let key: Pubkey = signer.parse()?;
+            let position = tx
+                .message
+                .account_keys
+                .iter()
+                .position(|k| *k == key)
+                .filter(|&i| i < tx.signatures.len())
+                .ok_or_else(|| anyhow!("{} does not sign operation {}", signer, op.id))?;
+            tx.signatures[position] = signature.signature.parse()?;
+        }
+
+        if !self.vault_manager.chain.is_transaction_live(&tx).await? {
+            return self.expire(op, NONCE_ADVANCED).await;
+        }
+
+        let result = self.vault_manager.submit_signed(&tx).await;
+        self.close_nonce(&op).await;
+        match result {
+            Ok(sig) => {
+                self.finish(&op, MultisigStatus::Executed, Some(&sig), None)
+                    .await
+            }
+            Err(e) => {
+                self.finish(&op, MultisigStatus::Failed, None, Some(&e.to_string()))
+                    .await
+            }
+        }
+    }
+
+    async fn expire_if_stale(&self, op: MultisigOperation) -> Result<MultisigOperation> {
+        if op.status != MultisigStatus::Pending {
+            return Ok(op);
+        }
+        if Utc::now().timestamp() - op.created_at >= self.limits.ttl_secs as i64 {
+            // Not while a submission is under way.
+            let _submitting = self.submitting.lock().await;
+            let op = self.reload(&op).await?;
+            if op.status != MultisigStatus::Pending {
+                return Ok(op);
+            }
+            let op = self
+                .expire(op, "Not signed in time; propose it again")
+                .await?;
+            self.close_nonce(&op).await;
+            return Ok(op);
+        }
+        let Some(transaction) = &op.transaction else {
+            return Ok(op);
+        };
+        if self
+            .vault_manager
+            .chain
+            .is_transaction_live(&decode(transaction)?)
+            .await?
+        {
+            return Ok(op);
+        }
+        self.close_nonce(&op).await;
+        self.expire(op, NONCE_ADVANCED).await
+    }
+
+    async fn expire(&self, op: MultisigOperation, reason: &str) -> Result<MultisigOperation> {
+        self.finish(&op, MultisigStatus::Expired, None, Some(reason))
+            .await
+    }
+
+    /// Return the operation's nonce account rent to the payer. A failure
+    /// only leaks the rent, so it is logged and otherwise ignored.
+    async fn close_nonce(&self, op: &MultisigOperation) {
+        let Some(nonce) = op
+            .transaction
+            .as_deref()
+            .and_then(|tx| decode(tx).ok())
+            .as_ref()
+            .and_then(durable_nonce)
+        else {
+            return;
+        };
+        if self.vault_manager.chain.get_account(&nonce).await.is_err() {
+            return;
+        }
+        if let Err(e) = self.vault_manager.close_nonce_account(nonce).await {
+            eprintln!("Failed to close nonce account {}: {}", nonce, e);
+        }
+    }
+
+    async fn finish(
+        &self,
+        op: &MultisigOperation,
+        status: MultisigStatus,
+        tx_signature: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<MultisigOperation> {
+        if self
+            .database
+            .finish_multisig_operation(&op.id, status, tx_signature, error)
+            .await?
+        {
+            let op = self.reload(op).await?;
+            self.announce(&op);
+            return Ok(op);
+        }
+        self.reload(op).await
+    }
+
+    async fn reload(&self, op: &MultisigOperation) -> Result<MultisigOperation> {
+        self.database
+            .get_multisig_operation(&op.id)
+            .await?
+            .ok_or_else(|| anyhow!("multisig operation {} disappeared", op.id))
+    }
+
+    fn announce(&self, op: &MultisigOperation) {
+        self.websocket
+            .broadcast(WsMessage::MultisigOperationUpdated {
+                operation_id: op.id.clone(),
+                owner: op.owner.clone(),
+                status: op.status,
+                approvals: op.signer_approvals(),
+                threshold: op.threshold,
+                tx_signature: op.tx_signature.clone(),
+            });
+    }
+}
+
+/// Whether `signature` is `signer`'s signature of `message`, base64.
+pub fn verify_approval(message: &str, signer: &Pubkey, signature: &Signature) -> bool {
+    BASE64
+        .decode(message)
+        .is_ok_and(|message| signature.verify(signer.as_ref(), &message))
+}
+
+/// What co-signers approve: the operation spelled out, so a wallet can show
+/// it before signing.
+fn operation_message(id: &str, vault: &str, action: &MultisigAction) -> String {
+    let action = match action {
+        MultisigAction::Withdraw { amount } => format!("withdraw {}", amount),
+        MultisigAction::AddAuthorizedProgram { program } => {
+            format!("authorize program {}", program)
+        }
+        MultisigAction::RemoveAuthorizedProgram { program } => {
+            format!("deauthorize program {}", program)
+        }
+    };
+    format!(
+        "Approve multisig operation {}\nVault: {}\nAction: {}",
+        id, vault, action
+    )
+}
+
+fn decode(encoded: &str) -> Result<Transaction> {
+    Ok(bincode::deserialize(&BASE64.decode(encoded)?)?)
+}
@@ -0,0 +1,241 @@
+//! Full-program reconciliation. Every `CollateralVault` is loaded with one
+//! `getProgramAccounts` scan and its `total_balance` is compared with:
+//!
+//! - its `vault_accounts` row (only with Postgres),
+//! - the SPL balance of its `vault_token` account,
+//! - the sum of its indexed events.
+//!
+//! Each comparison is written to `reconciliation_logs`. Mismatches stay open
+//! until someone resolves them through the API. Accounts that do not decode
+//! as the current layout, such as vaults not yet migrated, are skipped and
+//! listed in the report.
+//!
+//! Event sums are kept per vault between runs. Each run only reads the events
+//! after the last sequence summed, so the cache only advances over a gapless
+//! run of sequences starting at the vault's initialization or migration.
+
+use crate::chain::ChainClient;
+use crate::db::{
+    Database, EventCutoff, ReconciliationLog, ReconciliationSource, ReconciliationStatus,
+    VaultEventRange,
+};
+use crate::indexer::VaultEvent;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_spl::token::TokenAccount;
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use collateral_vault::state::CollateralVault;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciliationReport {
+    /// Slot of the program account scan.
+    pub slot: u64,
+    pub vaults: usize,
+    pub checks: usize,
+    pub mismatches: Vec<ReconciliationLog>,
+    /// Program accounts that did not decode as a current vault.
+    pub unreadable_vaults: Vec<String>,
+}
+
+/// A vault's summed events up to and including `through_sequence`.
+#[derive(Debug, Clone, Copy)]
+struct EventTotal {
+    through_sequence: u64,
+    total: i128,
+}
+
+pub struct Reconciler {
+    chain: Arc<dyn ChainClient>,
+    database: Arc<Database>,
+    program_id: Pubkey,
+    event_totals: Mutex<HashMap<Pubkey, EventTotal>>,
+}
+
+impl Reconciler {
+    pub fn new(chain: Arc<dyn ChainClient>, database: Arc<Database>, program_id: Pubkey) -> Self {
+        Self {
+            chain,
+            database,
+            program_id,
+            event_totals: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn start(self: Arc<Self>, every: Duration) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                match self.run().await {
+                    Ok(report) => {
+                        if !report.mismatches.is_empty() {
+                            eprintln!(
+                                "⚠️  Reconciliation at slot {}: {} mismatches across {} vaults",
+                                report.slot,
+                                report.mismatches.len(),
+                                report.vaults
+                            );
+                        }
+                        if !report.unreadable_vaults.is_empty() {
+                            eprintln!(
+                                "⚠️  Reconciliation at slot {}: skipped {} unreadable vaults",
+                                report.slot,
+                                report.unreadable_vaults.len()
+                            );
+                        }
+                    }
+                    Err(e) => eprintln!("❌ Reconciliation failed: {:#}", e),
+                }
+            }
+        });
+
+        println!("🧮 Reconciliation job started");
+    }
+
+    pub async fn run(&self) -> Result<ReconciliationReport> {
+        let (slot, accounts) = self
+            .chain
+            .get_program_accounts(&self.program_id, CollateralVault::DISCRIMINATOR)
+            .await
+            .context("program account scan failed")?;
+
+        // Without Postgres there are no rows to compare with.
+        let rows: Option<HashMap<String, u64>> = if self.database.has_persistence() {
+            Some(
+                self.database
+                    .get_all_vaults()
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.vault_pda, row.total_balance as u64))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        let mut report = ReconciliationReport {
+            slot,
+            vaults: 0,
+            checks: 0,
+            mismatches: Vec::new(),
+            unreadable_vaults: Vec::new(),
+        };
+        for (address, account) in accounts {
+            let mut data: &[u8] = &account.data;
+            let Ok(vault) = CollateralVault::try_deserialize(&mut data) else {
+                report.unreadable_vaults.push(address.to_string());
+                continue;
+            };
+            report.vaults += 1;
+
+            let token = self.chain.get_account(&vault.token_account).await?;
+            let token = TokenAccount::try_deserialize(&mut token.data.as_slice())
+                .with_context(|| format!("invalid vault token account {}", vault.token_account))?;
+
+            let mut checks = vec![
+                (ReconciliationSource::VaultToken, token.amount),
+                (
+                    ReconciliationSource::Events,
+                    self.event_balance(&address, slot)
+                        .await?
+                        .clamp(0, u64::MAX as i128) as u64,
+                ),
+            ];
+            if let Some(rows) = &rows {
+                let row = rows.get(&address.to_string()).copied().unwrap_or(0);
+                checks.insert(0, (ReconciliationSource::VaultAccounts, row));
+            }
+
+            for (source, offchain) in checks {
+                let log = ReconciliationLog::compare(
+                    vault.owner.to_string(),
+                    Some(address.to_string()),
+                    source,
+                    vault.total_balance,
+                    offchain,
+                    Some(slot),
+                );
+                self.database.log_reconciliation(log.clone()).await?;
+                report.checks += 1;
+                if log.status == ReconciliationStatus::Mismatch {
+                    report.mismatches.push(log);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Net balance change of `vault` from its events indexed up to `slot`.
+    /// Events the indexer has not caught up on yet show up as mismatches.
+    async fn event_balance(&self, vault: &Pubkey, slot: u64) -> Result<i128> {
+        let key = vault.to_string();
+        let (last, _) = self
+            .database
+            .get_vault_sequence_bounds(&key, EventCutoff::Slot(slot))
+            .await?;
+        let Some(last) = last else {
+            return Ok(0);
+        };
+
+        let mut totals = self.event_totals.lock().await;
+        let cached = totals.get(vault).copied();
+        if cached.is_some_and(|c| c.through_sequence >= last) {
+            return Ok(cached.map_or(0, |c| c.total));
+        }
+
+        let range = VaultEventRange {
+            after_sequence: cached.map(|c| c.through_sequence),
+            through_sequence: Some(last),
+            ..Default::default()
+        };
+        let mut events: Vec<(u64, VaultEvent)> = self
+            .database
+            .get_vault_chain_events(&key, range)
+            .await?
+            .into_iter()
+            .filter_map(|event| {
+                let decoded = VaultEvent::decode(&BASE64.decode(&event.data).ok()?)?;
+                Some((decoded.sequence_for(vault)?, decoded))
+            })
+            .collect();
+        events.sort_by_key(|(sequence, _)| *sequence);
+
+        let mut total = cached.map_or(0, |c| c.total);
+        let mut contiguous = cached;
+        let mut gapless = true;
+        for (sequence, event) in events {
+            total += event
+                .balance_changes()
+                .iter()
+                .filter(|change| change.vault == *vault)
+                .map(|change| change.total)
+                .sum::<i128>();
+
+            let follows = match contiguous {
+                Some(c) => sequence == c.through_sequence + 1,
+                None => matches!(
+                    event,
+                    VaultEvent::VaultInitialized(_) | VaultEvent::VaultMigrated(_)
+                ),
+            };
+            gapless &= follows;
+            if gapless {
+                contiguous = Some(EventTotal {
+                    through_sequence: sequence,
+                    total,
+                });
+            }
+        }
+        if let Some(c) = contiguous {
+            totals.insert(*vault, c);
+        }
+        Ok(total)
+    }
+}
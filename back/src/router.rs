@@ -0,0 +1,150 @@
+//! The HTTP API. Every route is declared here once; `/analytics/*` is only
+//! added when an `AnalyticsService` (and so Postgres) is available. Every
+//! POST honours an `Idempotency-Key` header, and every request is timed for
+//! `/metrics`.
+
+use crate::admin::{self, AdminConfig};
+use crate::analytics::AnalyticsService;
+use crate::auth::AuthService;
+use crate::backfill::Backfill;
+use crate::balance_tracker::BalanceTracker;
+use crate::db::Database;
+use crate::handlers;
+use crate::idempotency::{self, Idempotency, IdempotencyConfig};
+use crate::metrics::{self, Metrics};
+use crate::multisig::MultisigCoordinator;
+use crate::reconciler::Reconciler;
+use crate::vault_manager::VaultManager;
+use crate::vault_monitor::VaultMonitor;
+use crate::websocket::{self, WebSocketManager};
+use axum::{
+    middleware,
+    routing::{get, post},
+    Extension, Router,
+};
+use std::sync::Arc;
+
+/// Everything the handlers take as an `Extension`.
+pub struct Services {
+    pub vault_manager: Arc<VaultManager>,
+    pub balance_tracker: Arc<BalanceTracker>,
+    pub vault_monitor: Arc<VaultMonitor>,
+    pub database: Arc<Database>,
+    pub analytics: Option<Arc<AnalyticsService>>,
+    pub backfill: Arc<Backfill>,
+    pub reconciler: Arc<Reconciler>,
+    pub multisig: Arc<MultisigCoordinator>,
+    pub auth: Arc<AuthService>,
+    pub websocket: Arc<WebSocketManager>,
+    pub admin: AdminConfig,
+    pub idempotency: IdempotencyConfig,
+    pub metrics: Arc<Metrics>,
+}
+
+pub fn router(services: Services) -> Router {
+    let mut router = Router::new()
+        // Health
+        .route("/health", get(handlers::health_check))
+        .route("/health/live", get(handlers::health_check))
+        .route("/health/ready", get(handlers::readiness_check))
+        .route("/metrics", get(metrics::serve))
+        // Sign-in
+        .route("/auth/nonce", post(handlers::request_nonce))
+        .route("/auth/verify", post(handlers::verify_signature))
+        // POST endpoints
+        .route("/register", post(handlers::register_vault))
+        .route("/deposit", post(handlers::deposit))
+        .route("/withdraw", post(handlers::withdraw))
+        .route("/withdraw/request", post(handlers::request_withdrawal))
+        .route("/withdraw/execute", post(handlers::execute_withdrawal))
+        .route("/lock", post(handlers::lock))
+        .route("/unlock", post(handlers::unlock))
+        .route("/transfer", post(handlers::transfer))
+        .route("/tx/submit", post(handlers::submit_transaction))
+        // GET endpoints
+        .route("/vault/balance/{user}", get(handlers::get_balance))
+        .route(
+            "/vault/transactions/{user}",
+            get(handlers::get_transactions),
+        )
+        .route("/vault/status/{user}", get(handlers::get_vault_status))
+        .route(
+            "/vault/{user}/authorized-programs",
+            get(handlers::get_authorized_programs)
+                .post(handlers::add_authorized_program)
+                .delete(handlers::remove_authorized_program),
+        )
+        .route("/vault/{user}/balance", get(handlers::get_balance_at))
+        .route("/vault/{user}/statement", get(handlers::get_statement))
+        .route(
+            "/vault/{user}/withdrawal-requests",
+            get(handlers::get_withdrawal_requests),
+        )
+        .route(
+            "/vault/{user}/multisig",
+            get(handlers::get_multisig_config).post(handlers::initialize_multisig),
+        )
+        .route(
+            "/vault/{user}/multisig/operations",
+            post(handlers::propose_multisig_operation),
+        )
+        .route(
+            "/multisig/operations",
+            get(handlers::list_multisig_operations),
+        )
+        .route(
+            "/multisig/operations/{id}",
+            get(handlers::get_multisig_operation),
+        )
+        .route(
+            "/multisig/operations/{id}/approve",
+            post(handlers::approve_multisig_operation),
+        )
+        .route(
+            "/multisig/operations/{id}/cancel",
+            post(handlers::cancel_multisig_operation),
+        )
+        .route("/vault/tvl", get(handlers::get_tvl))
+        .route("/vault/alerts", get(handlers::get_alerts))
+        // Admin endpoints, behind API keys
+        .nest("/admin", admin::router(services.admin))
+        // WebSocket endpoint
+        .route("/ws", get(websocket::ws_handler));
+
+    if let Some(analytics) = services.analytics {
+        router = router
+            .route(
+                "/analytics/dashboard",
+                get(handlers::get_dashboard_analytics),
+            )
+            .route(
+                "/analytics/tvl-history/{days}",
+                get(handlers::get_tvl_history),
+            )
+            .layer(Extension(analytics));
+    }
+
+    let idempotency = Arc::new(Idempotency::new(
+        services.database.clone(),
+        services.idempotency,
+    ));
+    router
+        .layer(middleware::from_fn_with_state(
+            idempotency,
+            idempotency::guard,
+        ))
+        .layer(middleware::from_fn_with_state(
+            services.metrics.clone(),
+            metrics::track,
+        ))
+        .layer(Extension(services.metrics))
+        .layer(Extension(services.vault_manager))
+        .layer(Extension(services.balance_tracker))
+        .layer(Extension(services.vault_monitor))
+        .layer(Extension(services.database))
+        .layer(Extension(services.backfill))
+        .layer(Extension(services.reconciler))
+        .layer(Extension(services.multisig))
+        .layer(Extension(services.auth))
+        .layer(Extension(services.websocket))
+}
@@ -0,0 +1,582 @@
+use crate::chain::{ChainClient, Commitment, SignatureStatus};
+use crate::db::{AttemptOutcome, Database, TransactionAttempt};
+use crate::metrics::Metrics;
+use crate::signer::{sign_transaction, TransactionSigner};
+use anchor_client::solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Signature,
+    transaction::Transaction,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Hard cap the runtime places on a transaction.
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+tokio::task_local! {
+    static SUBMITTED: RefCell<Vec<String>>;
+}
+
+/// Run `future` and return, with its output, the signatures of the
+/// transactions it broadcast in order, whatever became of them. Callers use
+/// this to tell a failure before anything was sent from one after.
+pub async fn track_submissions<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    SUBMITTED
+        .scope(RefCell::new(Vec::new()), async move {
+            let output = future.await;
+            (output, SUBMITTED.with(|submitted| submitted.take()))
+        })
+        .await
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityFee {
+    /// Always pay this many micro-lamports per compute unit.
+    Fixed(u64),
+    /// Pay the given percentile of recent fees on the written accounts, capped.
+    Estimate { percentile: u8, max: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct SenderConfig {
+    pub priority_fee: PriorityFee,
+    /// Headroom added to the simulated compute units, in percent.
+    pub compute_unit_margin_percent: u32,
+    /// Limit used when simulation does not report units consumed.
+    pub fallback_compute_units: u32,
+    /// How often an unconfirmed transaction is sent again.
+    pub resend_interval: Duration,
+    pub poll_interval: Duration,
+    /// Fresh blockhash + re-sign cycles after the first one expires.
+    pub max_rebuilds: u32,
+    /// How long status and liveness lookups may keep failing before a sent
+    /// transaction is returned as pending for the confirmer to settle.
+    pub status_error_timeout: Duration,
+}
+
+impl Default for SenderConfig {
+    fn default() -> Self {
+        Self {
+            priority_fee: PriorityFee::Estimate {
+                percentile: 75,
+                max: 1_000_000,
+            },
+            compute_unit_margin_percent: 20,
+            fallback_compute_units: 200_000,
+            resend_interval: Duration::from_secs(2),
+            poll_interval: Duration::from_millis(500),
+            max_rebuilds: 3,
+            status_error_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Told about every transaction the sender signs, so it can be tracked from
+/// before it is sent until it lands or fails.
+#[async_trait]
+pub trait SendObserver: Send + Sync {
+    /// `tx` is about to be simulated or sent. `replaces` is the transaction it
+    /// supersedes: the simulation probe, or the attempt before a rebuild.
+    async fn signed(&self, tx: &Transaction, replaces: Option<&Signature>);
+
+    /// The send ended without the transaction landing successfully.
+    async fn failed(&self, signature: &Signature, error: &str);
+}
+
+/// Terminal state of one blockhash's worth of sending.
+enum Landing {
+    Confirmed,
+    Failed(String),
+    Expired,
+    /// Sent, but the node could not say whether it landed.
+    Pending,
+}
+
+/// Shared sender: sizes compute, prices priority fees, resends until the
+/// blockhash expires and rebuilds a bounded number of times. Every attempt is
+/// written to the transaction attempt log.
+pub struct TransactionSender {
+    chain: Arc<dyn ChainClient>,
+    config: SenderConfig,
+    log: Option<Arc<Database>>,
+    observer: Option<Arc<dyn SendObserver>>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl TransactionSender {
+    pub fn new(chain: Arc<dyn ChainClient>, config: SenderConfig) -> Self {
+        Self {
+            chain,
+            config,
+            log: None,
+            observer: None,
+            metrics: None,
+        }
+    }
+
+    pub fn with_log(mut self, database: Arc<Database>) -> Self {
+        self.log = Some(database);
+        self
+    }
+
+    pub fn with_observer(mut self, observer: Arc<dyn SendObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn config(&self) -> &SenderConfig {
+        &self.config
+    }
+
+    /// Build, sign and land `instructions` with `signers[0]` as fee payer.
+    pub async fn send(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&dyn TransactionSigner],
+    ) -> Result<String> {
+        let payer = signers
+            .first()
+            .ok_or_else(|| anyhow!("a fee payer signer is required"))?
+            .pubkey();
+        let send_id = Uuid::new_v4().to_string();
+        let price = self.compute_unit_price(instructions).await;
+        let mut attempt = 0;
+        let mut previous: Option<Signature> = None;
+
+        for rebuild in 0..=self.config.max_rebuilds {
+            let (blockhash, _) = self.chain.get_latest_blockhash().await?;
+
+            // Simulate at the cap to learn how many units the instructions use.
+            let probe = self
+                .sign(
+                    instructions,
+                    &payer,
+                    signers,
+                    blockhash,
+                    MAX_COMPUTE_UNIT_LIMIT,
+                    price,
+                )
+                .await?;
+            self.notify_signed(&probe, previous.as_ref()).await;
+            previous = Some(probe.signatures[0]);
+            let simulation = self.chain.simulate_transaction(&probe).await?;
+            if let Some(err) = simulation.err {
+                self.notify_failed(&probe.signatures[0], &err).await;
+                attempt += 1;
+                self.record(
+                    &send_id,
+                    &probe.signatures[0],
+                    attempt,
+                    rebuild,
+                    &blockhash,
+                    Some((MAX_COMPUTE_UNIT_LIMIT, price)),
+                    AttemptOutcome::SimulationFailed,
+                    Some(err.clone()),
+                )
+                .await;
+                return Err(anyhow!("Transaction simulation failed: {}", err));
+            }
+            let limit = self.compute_unit_limit(simulation.units_consumed);
+
+            let tx = self
+                .sign(instructions, &payer, signers, blockhash, limit, price)
+                .await?;
+            self.notify_signed(&tx, previous.as_ref()).await;
+            previous = Some(tx.signatures[0]);
+            match self
+                .land(&send_id, &tx, &mut attempt, rebuild, Some((limit, price)))
+                .await?
+            {
+                Landing::Confirmed => return Ok(tx.signatures[0].to_string()),
+                Landing::Pending => return Ok(pending(&tx)),
+                Landing::Failed(err) => {
+                    self.notify_failed(&tx.signatures[0], &err).await;
+                    return Err(anyhow!("Transaction failed: {}", err));
+                }
+                Landing::Expired => continue,
+            }
+        }
+
+        let err = format!(
+            "Transaction expired after {} rebuilds",
+            self.config.max_rebuilds
+        );
+        if let Some(signature) = previous {
+            self.notify_failed(&signature, &err).await;
+        }
+        Err(anyhow!(err))
+    }
+
+    /// Land a transaction someone else signed. It cannot be rebuilt, so this
+    /// resends until its blockhash expires.
+    pub async fn send_signed(&self, tx: &Transaction) -> Result<String> {
+        let send_id = Uuid::new_v4().to_string();
+        let mut attempt = 0;
+
+        self.notify_signed(tx, None).await;
+        let simulation = self.chain.simulate_transaction(tx).await?;
+        if let Some(err) = simulation.err {
+            self.notify_failed(&tx.signatures[0], &err).await;
+            attempt += 1;
+            self.record(
+                &send_id,
+                &tx.signatures[0],
+                attempt,
+                0,
+                &tx.message.recent_blockhash,
+                None,
+                AttemptOutcome::SimulationFailed,
+                Some(err.clone()),
+            )
+            .await;
+            return Err(anyhow!("Transaction simulation failed: {}", err));
+        }
+
+        match self.land(&send_id, tx, &mut attempt, 0, None).await? {
+            Landing::Confirmed => Ok(tx.signatures[0].to_string()),
+            Landing::Pending => Ok(pending(tx)),
+            Landing::Failed(err) => {
+                self.notify_failed(&tx.signatures[0], &err).await;
+                Err(anyhow!("Transaction failed: {}", err))
+            }
+            Landing::Expired => {
+                let err = "Transaction expired before it was confirmed; sign a new one";
+                self.notify_failed(&tx.signatures[0], err).await;
+                Err(anyhow!(err))
+            }
+        }
+    }
+
+    /// Send, resend every `resend_interval` and poll until the transaction
+    /// lands or its blockhash (or durable nonce) stops being valid. Failed
+    /// lookups are retried; after `status_error_timeout` of them the outcome
+    /// is left to the confirmer.
+    async fn land(
+        &self,
+        send_id: &str,
+        tx: &Transaction,
+        attempt: &mut u32,
+        rebuild: u32,
+        budget: Option<(u32, u64)>,
+    ) -> Result<Landing> {
+        let signature = tx.signatures[0];
+        let blockhash = tx.message.recent_blockhash;
+        let mut last_send: Option<Instant> = None;
+        let mut failing_since: Option<Instant> = None;
+
+        loop {
+            if last_send.is_none_or(|at| at.elapsed() >= self.config.resend_interval) {
+                *attempt += 1;
+                submitted(&signature);
+                let (outcome, error) = match self.chain.send_transaction(tx).await {
+                    Ok(_) => (AttemptOutcome::Sent, None),
+                    Err(e) => (AttemptOutcome::SendFailed, Some(e.to_string())),
+                };
+                self.record(
+                    send_id, &signature, *attempt, rebuild, &blockhash, budget, outcome, error,
+                )
+                .await;
+                last_send = Some(Instant::now());
+            }
+
+            let status = match self.chain.get_signature_status(&signature).await {
+                Ok(status) => status,
+                Err(e) => {
+                    if self.lookup_failed(&mut failing_since) {
+                        return Ok(self
+                            .unknown(send_id, tx, *attempt, rebuild, budget, e)
+                            .await);
+                    }
+                    tokio::time::sleep(self.config.poll_interval).await;
+                    continue;
+                }
+            };
+            match status {
+                Some(Ok(())) => {
+                    self.record(
+                        send_id,
+                        &signature,
+                        *attempt,
+                        rebuild,
+                        &blockhash,
+                        budget,
+                        AttemptOutcome::Confirmed,
+                        None,
+                    )
+                    .await;
+                    return Ok(Landing::Confirmed);
+                }
+                Some(Err(err)) => {
+                    self.record(
+                        send_id,
+                        &signature,
+                        *attempt,
+                        rebuild,
+                        &blockhash,
+                        budget,
+                        AttemptOutcome::Failed,
+                        Some(err.clone()),
+                    )
+                    .await;
+                    return Ok(Landing::Failed(err));
+                }
+                None => {}
+            }
+
+            let live = match self.chain.is_transaction_live(tx).await {
+                Ok(live) => live,
+                Err(e) => {
+                    if self.lookup_failed(&mut failing_since) {
+                        return Ok(self
+                            .unknown(send_id, tx, *attempt, rebuild, budget, e)
+                            .await);
+                    }
+                    tokio::time::sleep(self.config.poll_interval).await;
+                    continue;
+                }
+            };
+            if !live {
+                // The transaction may have landed between the last poll and the
+                // expiry check; rebuilding it then would execute it twice.
+                let status = match self.chain.get_signature_statuses(&[signature]).await {
+                    Ok(status) => status,
+                    Err(e) => {
+                        if self.lookup_failed(&mut failing_since) {
+                            return Ok(self
+                                .unknown(send_id, tx, *attempt, rebuild, budget, e)
+                                .await);
+                        }
+                        tokio::time::sleep(self.config.poll_interval).await;
+                        continue;
+                    }
+                };
+                failing_since = None;
+                match status.into_iter().next().flatten() {
+                    Some(SignatureStatus { err: Some(err), .. }) => {
+                        self.record(
+                            send_id,
+                            &signature,
+                            *attempt,
+                            rebuild,
+                            &blockhash,
+                            budget,
+                            AttemptOutcome::Failed,
+                            Some(err.clone()),
+                        )
+                        .await;
+                        return Ok(Landing::Failed(err));
+                    }
+                    Some(status) if status.commitment >= Commitment::Confirmed => {
+                        self.record(
+                            send_id,
+                            &signature,
+                            *attempt,
+                            rebuild,
+                            &blockhash,
+                            budget,
+                            AttemptOutcome::Confirmed,
+                            None,
+                        )
+                        .await;
+                        return Ok(Landing::Confirmed);
+                    }
+                    // Processed but not yet confirmed: it can no longer be
+                    // resent, so wait for it to confirm or drop out.
+                    Some(_) => {
+                        tokio::time::sleep(self.config.poll_interval).await;
+                        continue;
+                    }
+                    None => {}
+                }
+                self.record(
+                    send_id,
+                    &signature,
+                    *attempt,
+                    rebuild,
+                    &blockhash,
+                    budget,
+                    AttemptOutcome::Expired,
+                    None,
+                )
+                .await;
+                return Ok(Landing::Expired);
+            }
+
+            failing_since = None;
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// Note a failed lookup. True once lookups have kept failing for
+    /// `status_error_timeout`.
+    fn lookup_failed(&self, failing_since: &mut Option<Instant>) -> bool {
+        failing_since.get_or_insert_with(Instant::now).elapsed() >= self.config.status_error_timeout
+    }
+
+    async fn unknown(
+        &self,
+        send_id: &str,
+        tx: &Transaction,
+        attempt: u32,
+        rebuild: u32,
+        budget: Option<(u32, u64)>,
+        error: anyhow::Error,
+    ) -> Landing {
+        self.record(
+            send_id,
+            &tx.signatures[0],
+            attempt,
+            rebuild,
+            &tx.message.recent_blockhash,
+            budget,
+            AttemptOutcome::Unknown,
+            Some(error.to_string()),
+        )
+        .await;
+        Landing::Pending
+    }
+
+    async fn sign(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &[&dyn TransactionSigner],
+        blockhash: Hash,
+        compute_unit_limit: u32,
+        compute_unit_price: u64,
+    ) -> Result<Transaction> {
+        let mut all = Vec::with_capacity(instructions.len() + 2);
+        all.push(ComputeBudgetInstruction::set_compute_unit_limit(
+            compute_unit_limit,
+        ));
+        all.push(ComputeBudgetInstruction::set_compute_unit_price(
+            compute_unit_price,
+        ));
+        all.extend_from_slice(instructions);
+
+        let mut tx = Transaction::new_with_payer(&all, Some(payer));
+        sign_transaction(&mut tx, signers, blockhash).await?;
+        Ok(tx)
+    }
+
+    fn compute_unit_limit(&self, units_consumed: Option<u64>) -> u32 {
+        match units_consumed {
+            Some(units) if units > 0 => {
+                let padded = units * (100 + self.config.compute_unit_margin_percent as u64) / 100;
+                padded.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+            }
+            _ => self.config.fallback_compute_units,
+        }
+    }
+
+    async fn compute_unit_price(&self, instructions: &[Instruction]) -> u64 {
+        let (percentile, max) = match self.config.priority_fee {
+            PriorityFee::Fixed(price) => return price,
+            PriorityFee::Estimate { percentile, max } => (percentile, max),
+        };
+
+        let mut writable: Vec<Pubkey> = instructions
+            .iter()
+            .flat_map(|ix| ix.accounts.iter())
+            .filter(|meta| meta.is_writable)
+            .map(|meta| meta.pubkey)
+            .collect();
+        writable.sort();
+        writable.dedup();
+
+        let mut fees = match self.chain.get_recent_prioritization_fees(&writable).await {
+            Ok(fees) => fees,
+            Err(e) => {
+                eprintln!("Priority fee estimate failed, paying none: {}", e);
+                return 0;
+            }
+        };
+        if fees.is_empty() {
+            return 0;
+        }
+        fees.sort_unstable();
+        let index = (fees.len() - 1) * percentile.min(100) as usize / 100;
+        fees[index].min(max)
+    }
+
+    async fn notify_signed(&self, tx: &Transaction, replaces: Option<&Signature>) {
+        if let Some(observer) = &self.observer {
+            observer.signed(tx, replaces).await;
+        }
+    }
+
+    async fn notify_failed(&self, signature: &Signature, error: &str) {
+        if let Some(observer) = &self.observer {
+            observer.failed(signature, error).await;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn record(
+        &self,
+        send_id: &str,
+        signature: &Signature,
+        attempt: u32,
+        rebuild: u32,
+        blockhash: &Hash,
+        budget: Option<(u32, u64)>,
+        outcome: AttemptOutcome,
+        error: Option<String>,
+    ) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_send_attempt(outcome);
+        }
+        let Some(log) = &self.log else {
+            return;
+        };
+        let record = TransactionAttempt {
+            id: Uuid::new_v4().to_string(),
+            send_id: send_id.to_string(),
+            signature: signature.to_string(),
+            attempt,
+            rebuild,
+            blockhash: blockhash.to_string(),
+            compute_unit_limit: budget.map(|(limit, _)| limit),
+            compute_unit_price: budget.map(|(_, price)| price),
+            outcome,
+            error,
+            timestamp: Utc::now().timestamp(),
+        };
+        if let Err(err) = log.insert_transaction_attempt(record).await {
+            eprintln!("Failed to store transaction attempt: {}", err);
+        }
+    }
+}
+
+/// The signature of a sent transaction whose outcome is still unknown. Its
+/// row stays `Pending` until the confirmer reads a status for it.
+fn pending(tx: &Transaction) -> String {
+    let signature = tx.signatures[0].to_string();
+    eprintln!("⚠️ Status of {} unknown; left to the confirmer", signature);
+    signature
+}
+
+/// Note a broadcast for [`track_submissions`]. A failed send counts too, as
+/// the node may have received it.
+fn submitted(signature: &Signature) {
+    let signature = signature.to_string();
+    let _ = SUBMITTED.try_with(|submitted| {
+        let mut submitted = submitted.borrow_mut();
+        if submitted.last() != Some(&signature) {
+            submitted.push(signature);
+        }
+    });
+}
@@ -0,0 +1,278 @@
+//! Signing backends for the fee payer.
+//!
+//! The remote signer speaks a small JSON protocol so hot keys can live outside
+//! the API process:
+//!
+//! ```text
+//! GET  {url}/pubkey  -> {"pubkey": "<base58>"}
+//! POST {url}/sign    <- {"pubkey": "<base58>", "message": "<base64 message bytes>"}
+//!                    -> {"signature": "<base58>"}
+//! ```
+//!
+//! When a token is configured it is sent as `Authorization: Bearer <token>`.
+//! Every signature returned by the service is verified before it is used.
+
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Something that can sign transaction messages for one pubkey.
+#[async_trait]
+pub trait TransactionSigner: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    /// Sign serialized transaction message bytes.
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature>;
+}
+
+/// Add `signers`' signatures to `tx` for `blockhash`. Signatures already on
+/// the transaction for other keys are kept, so this also works for partial
+/// signing.
+pub async fn sign_transaction(
+    tx: &mut Transaction,
+    signers: &[&dyn TransactionSigner],
+    blockhash: anchor_client::solana_sdk::hash::Hash,
+) -> Result<()> {
+    if tx.message.recent_blockhash != blockhash {
+        tx.message.recent_blockhash = blockhash;
+        tx.signatures = vec![Signature::default(); tx.signatures.len()];
+    }
+
+    let message = tx.message_data();
+    let required = tx.message.header.num_required_signatures as usize;
+    for signer in signers {
+        let pubkey = signer.pubkey();
+        let position = tx.message.account_keys[..required]
+            .iter()
+            .position(|key| *key == pubkey)
+            .ok_or_else(|| anyhow!("{} is not a required signer", pubkey))?;
+        tx.signatures[position] = signer.sign_message(&message).await?;
+    }
+    Ok(())
+}
+
+/// A keypair held in this process.
+pub struct LocalSigner {
+    keypair: Keypair,
+}
+
+impl LocalSigner {
+    pub fn new(keypair: Keypair) -> Self {
+        Self { keypair }
+    }
+
+    /// Read a `solana-keygen` JSON keypair file.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let keypair = read_keypair_file(path)
+            .map_err(|err| anyhow!("Failed to read keypair file {}: {}", path, err))?;
+        Ok(Self::new(keypair))
+    }
+
+    /// Read a secret key from `var`, as a base58 string or a JSON byte array.
+    pub fn from_env(var: &str) -> Result<Self> {
+        let secret = env::var(var).with_context(|| format!("{} must be set", var))?;
+        Ok(Self::new(parse_keypair(&secret).with_context(|| {
+            format!("{} is not a valid secret key", var)
+        })?))
+    }
+
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        Ok(self.keypair.sign_message(message))
+    }
+}
+
+/// Parse a secret key given as a base58 string or a JSON byte array.
+pub fn parse_keypair(secret: &str) -> Result<Keypair> {
+    let secret = secret.trim();
+    let bytes = if secret.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(secret).context("expected a JSON byte array")?
+    } else {
+        bs58::decode(secret)
+            .into_vec()
+            .context("expected a base58 string")?
+    };
+    Keypair::try_from(bytes.as_slice()).map_err(|e| anyhow!("invalid keypair bytes: {}", e))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PubkeyResponse {
+    pub pubkey: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub pubkey: String,
+    /// Base64 serialized transaction message.
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignResponse {
+    pub signature: String,
+}
+
+/// Signs through an external HTTP signing service.
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    pubkey: Pubkey,
+}
+
+impl RemoteSigner {
+    /// Ask the service which key it signs for.
+    pub async fn connect(url: &str, token: Option<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        let url = url.trim_end_matches('/').to_string();
+
+        let mut request = client.get(format!("{}/pubkey", url));
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+        let response: PubkeyResponse = request
+            .send()
+            .await
+            .with_context(|| format!("remote signer {} is unreachable", url))?
+            .error_for_status()?
+            .json()
+            .await
+            .context("remote signer returned an invalid pubkey response")?;
+        let pubkey = response
+            .pubkey
+            .parse()
+            .context("remote signer returned an invalid pubkey")?;
+
+        Ok(Self {
+            client,
+            url,
+            token,
+            pubkey,
+        })
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        let mut request = self
+            .client
+            .post(format!("{}/sign", self.url))
+            .json(&SignRequest {
+                pubkey: self.pubkey.to_string(),
+                message: BASE64.encode(message),
+            });
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response: SignResponse = request
+            .send()
+            .await
+            .context("remote signer request failed")?
+            .error_for_status()
+            .context("remote signer refused to sign")?
+            .json()
+            .await
+            .context("remote signer returned an invalid sign response")?;
+        let signature: Signature = response
+            .signature
+            .parse()
+            .context("remote signer returned an invalid signature")?;
+
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(anyhow!(
+                "remote signer returned a signature that does not verify"
+            ));
+        }
+        Ok(signature)
+    }
+}
+
+/// Where the fee payer key comes from. In TOML this is a `[signer]` table
+/// tagged by `type`, e.g. `type = "remote"` with `url` and optional `token`.
+#[derive(Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SignerConfig {
+    File { path: String },
+    Env { var: String },
+    Remote { url: String, token: Option<String> },
+}
+
+impl std::fmt::Debug for SignerConfig {
+    // Keep the remote signer token out of logs.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignerConfig::File { path } => write!(f, "file {}", path),
+            SignerConfig::Env { var } => write!(f, "env {}", var),
+            SignerConfig::Remote { url, .. } => write!(f, "remote {}", url),
+        }
+    }
+}
+
+impl Default for SignerConfig {
+    /// The Solana CLI default keypair.
+    fn default() -> Self {
+        let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        SignerConfig::File {
+            path: format!("{}/.config/solana/id.json", home),
+        }
+    }
+}
+
+impl SignerConfig {
+    /// `REMOTE_SIGNER_URL` (+ optional `REMOTE_SIGNER_TOKEN`), then
+    /// `SOLANA_KEYPAIR`, then `SOLANA_KEYPAIR_PATH` / `ANCHOR_WALLET`.
+    pub fn from_env() -> Option<Self> {
+        if let Ok(url) = env::var("REMOTE_SIGNER_URL") {
+            return Some(SignerConfig::Remote {
+                url,
+                token: env::var("REMOTE_SIGNER_TOKEN").ok(),
+            });
+        }
+        if env::var("SOLANA_KEYPAIR").is_ok() {
+            return Some(SignerConfig::Env {
+                var: "SOLANA_KEYPAIR".to_string(),
+            });
+        }
+        env::var("SOLANA_KEYPAIR_PATH")
+            .or_else(|_| env::var("ANCHOR_WALLET"))
+            .ok()
+            .map(|path| SignerConfig::File { path })
+    }
+
+    pub async fn build(&self) -> Result<Arc<dyn TransactionSigner>> {
+        Ok(match self {
+            SignerConfig::File { path } => Arc::new(LocalSigner::from_file(path)?),
+            SignerConfig::Env { var } => Arc::new(LocalSigner::from_env(var)?),
+            SignerConfig::Remote { url, token } => {
+                Arc::new(RemoteSigner::connect(url, token.clone()).await?)
+            }
+        })
+    }
+}
@@ -0,0 +1,457 @@
+//! Account statements for one vault over a period, built from the events the
+//! indexer decoded rather than the rows the handlers recorded, so direct
+//! wallet and CPI activity is included.
+//!
+//! The opening balance starts from the latest `balance_snapshots` row before
+//! the period (or from zero at the vault's creation) and adds the indexed
+//! events after it. The closing balance is the opening balance plus the
+//! period's entries. A statement is `balanced` when that closing balance
+//! agrees with one read independently at the period's end: the vault account
+//! itself when the period runs to the present, otherwise the latest snapshot
+//! taken during the period, carried forward by the entries after it.
+//!
+//! Network fees the owner paid for the period's transactions are listed as
+//! `fee` lines. They are paid in SOL, so they carry `fee_lamports` instead of
+//! a token `amount`, are totalled apart from the token amounts and never
+//! change the vault's balance. A transaction whose fee can no longer be read
+//! (pruned history, or an RPC error) gets a `fee_unavailable` line instead.
+
+use crate::chain::{ChainClient, TransactionFee};
+use crate::db::{BalanceSnapshot, Database, VaultEventRange};
+use crate::indexer::{IndexedEvent, VaultEvent};
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anchor_lang::AccountDeserialize;
+use anyhow::Result;
+use chrono::DateTime;
+use collateral_vault::state::CollateralVault;
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Fee lookups in flight at once while building a statement.
+const FEE_LOOKUPS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementEntryType {
+    /// Balance carried over when a vault moved to the current layout.
+    Migration,
+    Deposit,
+    Withdrawal,
+    Lock,
+    Unlock,
+    TransferIn,
+    TransferOut,
+    SettlementIn,
+    SettlementOut,
+    /// Network fee the owner paid for a transaction.
+    Fee,
+    /// A transaction whose fee could not be read.
+    FeeUnavailable,
+}
+
+impl StatementEntryType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StatementEntryType::Migration => "migration",
+            StatementEntryType::Deposit => "deposit",
+            StatementEntryType::Withdrawal => "withdrawal",
+            StatementEntryType::Lock => "lock",
+            StatementEntryType::Unlock => "unlock",
+            StatementEntryType::TransferIn => "transfer_in",
+            StatementEntryType::TransferOut => "transfer_out",
+            StatementEntryType::SettlementIn => "settlement_in",
+            StatementEntryType::SettlementOut => "settlement_out",
+            StatementEntryType::Fee => "fee",
+            StatementEntryType::FeeUnavailable => "fee_unavailable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementEntry {
+    pub timestamp: i64,
+    pub signature: String,
+    pub slot: u64,
+    pub log_index: u32,
+    pub entry_type: StatementEntryType,
+    /// Token amount; zero on fee lines.
+    pub amount: u64,
+    /// Lamports, on `fee` lines only.
+    pub fee_lamports: Option<u64>,
+    /// Change to `total_balance`; zero for locks and unlocks, which only move
+    /// funds between available and locked, and for fees.
+    pub balance_change: i64,
+    /// `total_balance` after this entry.
+    pub balance: u64,
+}
+
+/// Token amounts per entry type over the period, and the fees paid in SOL.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatementTotals {
+    pub migrated: u64,
+    pub deposits: u64,
+    pub withdrawals: u64,
+    pub locked: u64,
+    pub unlocked: u64,
+    pub transfers_in: u64,
+    pub transfers_out: u64,
+    pub settlements_in: u64,
+    pub settlements_out: u64,
+    pub fees_lamports: u64,
+    /// Transactions whose fee could not be read, so `fees_lamports` is short.
+    pub fees_unavailable: u64,
+}
+
+/// Where the period-end balance was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosingSource {
+    /// The vault account, read now; the period runs to the present.
+    Chain,
+    /// The latest `balance_snapshots` row taken during the period.
+    Snapshot,
+}
+
+/// The closing balance compared with one read independently of the events.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosingCheck {
+    pub source: ClosingSource,
+    /// When the balance was read.
+    pub read_at: i64,
+    pub read_balance: u64,
+    /// `read_balance` carried forward by the entries after `read_at`.
+    pub expected_balance: u64,
+    pub matches: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementReconciliation {
+    /// The closing balance matches the period-end check.
+    pub balanced: bool,
+    /// Where the opening balance was replayed from; `None` when it was
+    /// replayed from the vault's creation.
+    pub opening_snapshot: Option<BalanceSnapshot>,
+    /// `None` when the period ended in the past and no snapshot was taken
+    /// during it.
+    pub closing_check: Option<ClosingCheck>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Statement {
+    pub user: String,
+    pub vault: String,
+    /// Unix time, inclusive.
+    pub from: i64,
+    /// Unix time, exclusive.
+    pub to: i64,
+    pub opening_balance: u64,
+    pub closing_balance: u64,
+    pub totals: StatementTotals,
+    pub entries: Vec<StatementEntry>,
+    pub reconciliation: StatementReconciliation,
+}
+
+/// Build `user`'s statement for `vault` over `[from, to)`.
+pub async fn build(
+    database: &Database,
+    chain: &dyn ChainClient,
+    user: Pubkey,
+    vault: Pubkey,
+    from: i64,
+    to: i64,
+) -> Result<Statement> {
+    // Opening: the latest snapshot before the period, then the events after it.
+    let opening_snapshot = database
+        .get_snapshot_at(&vault.to_string(), from - 1)
+        .await?;
+    let mut before = VaultEventRange {
+        to_time: Some(from),
+        ..Default::default()
+    };
+    if let Some(snapshot) = &opening_snapshot {
+        match snapshot.sequence {
+            Some(sequence) => before.after_sequence = Some(sequence),
+            None => before.from_time = Some(snapshot.timestamp + 1),
+        }
+    }
+    let opening = opening_snapshot
+        .as_ref()
+        .map_or(0, |s| s.total_balance as i128)
+        + IndexedEvent::load_for_vault(database, &vault, before)
+            .await?
+            .iter()
+            .flat_map(|e| e.event.balance_changes())
+            .filter(|change| change.vault == vault)
+            .map(|change| change.total)
+            .sum::<i128>();
+
+    let events = IndexedEvent::load_for_vault(
+        database,
+        &vault,
+        VaultEventRange {
+            from_time: Some(from),
+            to_time: Some(to),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // One fee per transaction, read ahead of time; events of a transaction
+    // are adjacent.
+    let mut signatures: Vec<String> = events.iter().map(|e| e.signature.clone()).collect();
+    signatures.dedup();
+    let mut fees = stream::iter(signatures)
+        .map(|signature| async move {
+            let fee = match signature.parse::<Signature>() {
+                Ok(parsed) => chain.get_transaction_fee(&parsed).await,
+                Err(e) => Err(e.into()),
+            };
+            fee.unwrap_or_else(|e| {
+                eprintln!("⚠️ Could not read the fee of {}: {}", signature, e);
+                None
+            })
+        })
+        .buffered(FEE_LOOKUPS)
+        .collect::<Vec<Option<TransactionFee>>>()
+        .await
+        .into_iter();
+
+    let mut totals = StatementTotals::default();
+    let mut entries = Vec::new();
+    // Total balance change of each event, to carry a snapshot forward.
+    let mut changes = Vec::new();
+    let mut balance = opening;
+    for (index, dated) in events.iter().enumerate() {
+        let mut change_total = 0;
+        for (entry_type, amount, change) in entries_for(&dated.event, &vault) {
+            let total = match entry_type {
+                StatementEntryType::Migration => &mut totals.migrated,
+                StatementEntryType::Deposit => &mut totals.deposits,
+                StatementEntryType::Withdrawal => &mut totals.withdrawals,
+                StatementEntryType::Lock => &mut totals.locked,
+                StatementEntryType::Unlock => &mut totals.unlocked,
+                StatementEntryType::TransferIn => &mut totals.transfers_in,
+                StatementEntryType::TransferOut => &mut totals.transfers_out,
+                StatementEntryType::SettlementIn => &mut totals.settlements_in,
+                StatementEntryType::SettlementOut => &mut totals.settlements_out,
+                StatementEntryType::Fee | StatementEntryType::FeeUnavailable => {
+                    unreachable!("fee lines are not events")
+                }
+            };
+            *total += amount;
+            balance += change as i128;
+            change_total += change as i128;
+            entries.push(entry(dated, entry_type, amount, None, change, balance));
+        }
+        changes.push(change_total);
+
+        // One fee line per transaction, after its last event.
+        let last_of_transaction = events
+            .get(index + 1)
+            .is_none_or(|next| next.signature != dated.signature);
+        if last_of_transaction {
+            match fees.next().flatten() {
+                Some(fee) if fee.payer == user => {
+                    totals.fees_lamports += fee.lamports;
+                    entries.push(entry(
+                        dated,
+                        StatementEntryType::Fee,
+                        0,
+                        Some(fee.lamports),
+                        0,
+                        balance,
+                    ));
+                }
+                // Someone else paid.
+                Some(_) => {}
+                None => {
+                    totals.fees_unavailable += 1;
+                    entries.push(entry(
+                        dated,
+                        StatementEntryType::FeeUnavailable,
+                        0,
+                        None,
+                        0,
+                        balance,
+                    ));
+                }
+            }
+        }
+    }
+    let closing = balance;
+
+    // Closing: checked against the vault itself, or a snapshot from the period.
+    let now = match chain.get_block_time(chain.get_slot().await?).await? {
+        Some(now) => now,
+        None => chrono::Utc::now().timestamp(),
+    };
+    let closing_check = if to > now {
+        let account = chain.get_account(&vault).await?;
+        let mut data: &[u8] = &account.data;
+        let read_balance = CollateralVault::try_deserialize(&mut data)?.total_balance;
+        Some(ClosingCheck {
+            source: ClosingSource::Chain,
+            read_at: now,
+            read_balance,
+            expected_balance: read_balance,
+            matches: closing == read_balance as i128,
+        })
+    } else {
+        database
+            .get_snapshot_at(&vault.to_string(), to - 1)
+            .await?
+            .filter(|snapshot| snapshot.timestamp >= from)
+            .map(|snapshot| {
+                let after = |e: &IndexedEvent| match snapshot.sequence {
+                    Some(sequence) => e.event.sequence_for(&vault).is_some_and(|s| s > sequence),
+                    None => e.timestamp > snapshot.timestamp,
+                };
+                let expected = snapshot.total_balance as i128
+                    + events
+                        .iter()
+                        .zip(&changes)
+                        .filter(|(e, _)| after(e))
+                        .map(|(_, change)| change)
+                        .sum::<i128>();
+                ClosingCheck {
+                    source: ClosingSource::Snapshot,
+                    read_at: snapshot.timestamp,
+                    read_balance: snapshot.total_balance,
+                    expected_balance: clamp(expected),
+                    matches: closing == expected,
+                }
+            })
+    };
+
+    Ok(Statement {
+        user: user.to_string(),
+        vault: vault.to_string(),
+        from,
+        to,
+        opening_balance: clamp(opening),
+        closing_balance: clamp(closing),
+        totals,
+        entries,
+        reconciliation: StatementReconciliation {
+            balanced: closing_check.as_ref().is_some_and(|check| check.matches),
+            opening_snapshot,
+            closing_check,
+        },
+    })
+}
+
+fn entry(
+    dated: &IndexedEvent,
+    entry_type: StatementEntryType,
+    amount: u64,
+    fee_lamports: Option<u64>,
+    balance_change: i64,
+    balance: i128,
+) -> StatementEntry {
+    StatementEntry {
+        timestamp: dated.timestamp,
+        signature: dated.signature.clone(),
+        slot: dated.slot,
+        log_index: dated.log_index,
+        entry_type,
+        amount,
+        fee_lamports,
+        balance_change,
+        balance: clamp(balance),
+    }
+}
+
+/// The statement lines `event` puts on `vault`'s statement: one per
+/// balance change to `vault`, typed by the event and sized by the change.
+fn entries_for(event: &VaultEvent, vault: &Pubkey) -> Vec<(StatementEntryType, u64, i64)> {
+    event
+        .balance_changes()
+        .into_iter()
+        .filter(|change| change.vault == *vault)
+        .filter_map(|change| {
+            let moved = change.total.unsigned_abs() as u64;
+            let (entry_type, amount) = match event {
+                VaultEvent::VaultMigrated(_) => (StatementEntryType::Migration, moved),
+                VaultEvent::DepositEvent(_) => (StatementEntryType::Deposit, moved),
+                VaultEvent::WithdrawEvent(_) | VaultEvent::WithdrawalExecuted(_) => {
+                    (StatementEntryType::Withdrawal, moved)
+                }
+                VaultEvent::CollateralLocked(_) => (
+                    StatementEntryType::Lock,
+                    change.locked.unsigned_abs() as u64,
+                ),
+                VaultEvent::CollateralUnlocked(_) | VaultEvent::ForcedUnlockExecuted(_) => (
+                    StatementEntryType::Unlock,
+                    change.locked.unsigned_abs() as u64,
+                ),
+                VaultEvent::CollateralTransferred(e) if e.from_vault == *vault => {
+                    (StatementEntryType::TransferOut, moved)
+                }
+                VaultEvent::CollateralTransferred(_) => (StatementEntryType::TransferIn, moved),
+                VaultEvent::PositionSettled(e) if e.loser.vault == *vault => {
+                    (StatementEntryType::SettlementOut, moved)
+                }
+                VaultEvent::PositionSettled(_) => (StatementEntryType::SettlementIn, moved),
+                _ => return None,
+            };
+            Some((entry_type, amount, change.total as i64))
+        })
+        .collect()
+}
+
+fn clamp(balance: i128) -> u64 {
+    balance.clamp(0, u64::MAX as i128) as u64
+}
+
+impl Statement {
+    /// One row per entry between an opening and a closing balance row. Fee
+    /// lines leave `amount` empty and fill `fee_lamports`.
+    pub fn to_csv(&self) -> String {
+        let date = |timestamp: i64| {
+            DateTime::from_timestamp(timestamp, 0)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default()
+        };
+        let mut csv = String::from(
+            "date,timestamp,signature,slot,type,amount,fee_lamports,balance_change,balance\n",
+        );
+        let _ = writeln!(
+            csv,
+            "{},{},,,opening_balance,,,,{}",
+            date(self.from),
+            self.from,
+            self.opening_balance
+        );
+        for entry in &self.entries {
+            let amount = match entry.entry_type {
+                StatementEntryType::Fee | StatementEntryType::FeeUnavailable => String::new(),
+                _ => entry.amount.to_string(),
+            };
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{}",
+                date(entry.timestamp),
+                entry.timestamp,
+                entry.signature,
+                entry.slot,
+                entry.entry_type.as_str(),
+                amount,
+                entry
+                    .fee_lamports
+                    .map(|f| f.to_string())
+                    .unwrap_or_default(),
+                entry.balance_change,
+                entry.balance
+            );
+        }
+        let _ = writeln!(
+            csv,
+            "{},{},,,closing_balance,,,,{}",
+            date(self.to),
+            self.to,
+            self.closing_balance
+        );
+        csv
+    }
+}
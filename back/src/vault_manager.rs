@@ -1,130 +1,210 @@
-use anchor_client::{
-    solana_sdk::{
-        commitment_config::CommitmentConfig,
-        signature::{Keypair, Signer},
-        sysvar,
-        transaction::Transaction,
-    },
-    Client, Cluster, Program,
+use crate::chain::{nonce_hash, ChainClient, RpcChainClient};
+use crate::sender::{SenderConfig, TransactionSender};
+use crate::signer::{sign_transaction, LocalSigner, TransactionSigner};
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, instruction::Instruction, rent::Rent,
+    signature::Keypair, sysvar, transaction::Transaction,
 };
-use solana_program::system_program;
-use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use anchor_lang::system_program;
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, Discriminator};
 use anchor_spl::token::TokenAccount;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use collateral_vault::state::{
+    CollateralVault, MultisigConfig, RecoveryRequest, WithdrawalRequest,
+};
+use serde::Serialize;
+use solana_nonce::state::State as NonceState;
+use solana_system_interface::instruction as system_instruction;
 use std::sync::Arc;
 
+/// A transaction built for a wallet to sign. `transaction` is a base64 bincode
+/// `Transaction`; the backend has already signed as fee payer if it sponsors fees.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnsignedTransaction {
+    pub transaction: String,
+    pub fee_payer: String,
+    pub recent_blockhash: String,
+    pub last_valid_block_height: u64,
+    pub required_signers: Vec<String>,
+}
+
+/// Chain access for the vault program. Clones share one `ChainClient`, so RPC
+/// round-trips and confirmation polling never block a tokio worker.
+#[derive(Clone)]
 pub struct VaultManager {
-    pub program: Program<Arc<Keypair>>,
-    pub payer: Arc<Keypair>,
-    pub user: Option<Arc<Keypair>>,
+    pub chain: Arc<dyn ChainClient>,
+    pub sender: Arc<TransactionSender>,
+    pub program_id: Pubkey,
+    pub payer: Arc<dyn TransactionSigner>,
+    pub user: Option<Arc<LocalSigner>>,
     pub usdt_mint: Pubkey,
 }
 
 impl VaultManager {
     pub fn new(
         rpc_url: String,
-        payer: Keypair,
+        payer: Arc<dyn TransactionSigner>,
         user: Option<Keypair>,
         program_id: Pubkey,
         usdt_mint: Pubkey,
     ) -> Result<Self> {
-        let cluster = Cluster::Custom(rpc_url.clone(), rpc_url);
-        let payer = Arc::new(payer);
-        let user = user.map(Arc::new);
-        let client =
-            Client::new_with_options(cluster, payer.clone(), CommitmentConfig::confirmed());
-        let program = client.program(program_id)?;
-        Ok(Self {
-            program,
+        let chain = RpcChainClient::new(rpc_url, CommitmentConfig::confirmed());
+        Ok(Self::with_chain(
+            Arc::new(chain),
             payer,
             user,
+            program_id,
             usdt_mint,
-        })
+        ))
     }
 
-    pub async fn initialize_vault(
-        &self,
-        user: Pubkey,
-        authorized_programs: Vec<Pubkey>,
-    ) -> Result<String> {
-        println!("Initializing vault for user {}", user);
+    /// Use any `ChainClient`, e.g. a `SimulatedChain` in tests.
+    pub fn with_chain(
+        chain: Arc<dyn ChainClient>,
+        payer: Arc<dyn TransactionSigner>,
+        user: Option<Keypair>,
+        program_id: Pubkey,
+        usdt_mint: Pubkey,
+    ) -> Self {
+        let sender = TransactionSender::new(chain.clone(), SenderConfig::default());
+        Self {
+            chain,
+            sender: Arc::new(sender),
+            program_id,
+            payer,
+            user: user.map(|keypair| Arc::new(LocalSigner::new(keypair))),
+            usdt_mint,
+        }
+    }
 
-        let (vault_pda, _) =
-            Pubkey::find_program_address(&[b"vault", user.as_ref()], &self.program.id());
+    /// Replace the default sender, e.g. to set priority fees or log attempts.
+    pub fn with_sender(mut self, sender: TransactionSender) -> Self {
+        self.sender = Arc::new(sender);
+        self
+    }
 
-        let (vault_authority, _) = Pubkey::find_program_address(
-            &[b"vault_authority", vault_pda.as_ref()],
-            &self.program.id(),
-        );
+    /// Vault PDAs are derived from the owner that created them, which a
+    /// recovery leaves in place; use `vault_address` to find a user's vault.
+    fn vault_pda(&self, original_owner: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"vault", original_owner.as_ref()], &self.program_id).0
+    }
 
-        let (vault_token_account, _) =
-            Pubkey::find_program_address(&[b"vault_token", user.as_ref()], &self.program.id());
+    /// The vault `user` currently owns: the one derived from their key while
+    /// they still own it, otherwise one recovered to them, looked up by its
+    /// `owner` field.
+    pub async fn vault_address(&self, user: &Pubkey) -> Result<Pubkey> {
+        let derived = self.vault_pda(user);
+        if let Ok(account) = self.chain.get_account(&derived).await {
+            let mut data: &[u8] = &account.data;
+            if CollateralVault::try_deserialize(&mut data).is_ok_and(|v| v.owner == *user) {
+                return Ok(derived);
+            }
+        }
 
-        let ix_data =
-            anchor_lang::InstructionData::data(&collateral_vault::instruction::InitializeVault {
-                authorized_programs,
-            });
+        // `owner` is the first field, right after the discriminator.
+        let mut prefix = CollateralVault::DISCRIMINATOR.to_vec();
+        prefix.extend_from_slice(user.as_ref());
+        let (_, accounts) = self
+            .chain
+            .get_program_accounts(&self.program_id, &prefix)
+            .await?;
+        Ok(accounts
+            .into_iter()
+            .map(|(address, _)| address)
+            .find(|address| *address != derived)
+            .unwrap_or(derived))
+    }
 
-        let accounts = collateral_vault::accounts::InitializeVault {
-            user,
-            vault: vault_pda,
-            vault_authority,
-            usdt_mint: self.usdt_mint,
-            vault_token_account,
-            system_program: system_program::ID,
-            token_program: anchor_spl::token::ID,
-            rent: sysvar::rent::ID,
-        };
+    fn vault_authority_pda(&self, vault: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"vault_authority", vault.as_ref()], &self.program_id).0
+    }
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    fn multisig_pda(&self, vault: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"multisig", vault.as_ref()], &self.program_id).0
+    }
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+    fn withdrawal_request_pda(&self, vault: &Pubkey, request_id: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"withdrawal", vault.as_ref(), &request_id.to_le_bytes()],
+            &self.program_id,
+        )
+        .0
+    }
 
-        // Build signer list: always include payer, add user if available and matches
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if user == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
-            }
+    fn instruction(
+        &self,
+        accounts: impl anchor_lang::ToAccountMetas,
+        data: impl anchor_lang::InstructionData,
+    ) -> Instruction {
+        Instruction {
+            program_id: self.program_id,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
         }
+    }
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
+    /// Token account recorded in the vault state.
+    async fn vault_token_account(&self, vault_pda: &Pubkey) -> Result<Pubkey> {
+        let account = self.chain.get_account(vault_pda).await?;
+        let mut data: &[u8] = &account.data;
+        let vault = CollateralVault::try_deserialize(&mut data)?;
+        Ok(vault.token_account)
+    }
 
-        println!("Vault initialized: {}", sig);
-        Ok(sig.to_string())
+    /// Move a vault created before the current account layout onto it. Such
+    /// vaults predate recovery, so they are still at their owner's address.
+    pub fn migrate_vault_ix(&self, user: Pubkey) -> Instruction {
+        self.instruction(
+            collateral_vault::accounts::MigrateVault {
+                owner: user,
+                vault: self.vault_pda(&user),
+                system_program: system_program::ID,
+            },
+            collateral_vault::instruction::MigrateVault {},
+        )
     }
 
-    pub async fn deposit(&self, user: Pubkey, amount: u64) -> Result<String> {
-        println!("Depositing {} tokens for {}", amount, user);
+    pub fn initialize_vault_ix(
+        &self,
+        user: Pubkey,
+        authorized_programs: Vec<Pubkey>,
+    ) -> Instruction {
+        let vault_pda = self.vault_pda(&user);
+        let (vault_token_account, _) =
+            Pubkey::find_program_address(&[b"vault_token", user.as_ref()], &self.program_id);
+
+        self.instruction(
+            collateral_vault::accounts::InitializeVault {
+                user,
+                vault: vault_pda,
+                vault_authority: self.vault_authority_pda(&vault_pda),
+                usdt_mint: self.usdt_mint,
+                vault_token_account,
+                system_program: system_program::ID,
+                token_program: anchor_spl::token::ID,
+                rent: sysvar::rent::ID,
+            },
+            collateral_vault::instruction::InitializeVault {
+                authorized_programs,
+            },
+        )
+    }
 
-        let (vault_pda, _) =
-            Pubkey::find_program_address(&[b"vault", user.as_ref()], &self.program.id());
+    pub async fn deposit_ix(&self, user: Pubkey, amount: u64) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
 
         // Fetch vault state to get the correct token_account
-        let account = self.program.rpc().get_account(&vault_pda)?;
-        let mut data: &[u8] = &account.data;
-        let vault = collateral_vault::state::CollateralVault::try_deserialize(&mut data)?;
-        let vault_token_account = vault.token_account; // Use stored token account
+        let vault_token_account = self.vault_token_account(&vault_pda).await?;
 
         // Validate that vault_token_account is associated with the correct mint
-        let vault_token_account_data = self.program.rpc().get_account(&vault_token_account)?;
+        let vault_token_account_data = self.chain.get_account(&vault_token_account).await?;
         let mut vault_token_data: &[u8] = &vault_token_account_data.data;
         let vault_token_state = TokenAccount::try_deserialize(&mut vault_token_data)
-            .map_err(|e| anyhow::anyhow!("Failed to parse vault token account: {}", e))?;
+            .map_err(|e| anyhow!("Failed to parse vault token account: {}", e))?;
 
         if vault_token_state.mint != self.usdt_mint {
-            return Err(anyhow::anyhow!(
+            return Err(anyhow!(
                 "Vault mint mismatch: vault uses {}, but backend expects {}. \
                  The vault was initialized with a different mint. Please use a different user keypair \
                  to create a new vault with the correct mint, or update the backend to use mint {}",
@@ -137,279 +217,693 @@ impl VaultManager {
         let user_token_account =
             anchor_spl::associated_token::get_associated_token_address(&user, &self.usdt_mint);
 
-        let ix_data =
-            anchor_lang::InstructionData::data(&collateral_vault::instruction::Deposit { amount });
+        Ok(self.instruction(
+            collateral_vault::accounts::Deposit {
+                user,
+                vault: vault_pda,
+                user_token_account,
+                vault_token_account,
+                token_program: anchor_spl::token::ID,
+            },
+            collateral_vault::instruction::Deposit { amount },
+        ))
+    }
 
-        let accounts = collateral_vault::accounts::Deposit {
-            user,
-            vault: vault_pda,
-            user_token_account,
-            vault_token_account,
-            token_program: anchor_spl::token::ID,
-        };
+    pub async fn withdraw_ix(&self, user: Pubkey, amount: u64) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+        let vault_token_account = self.vault_token_account(&vault_pda).await?;
+        let user_token_account =
+            anchor_spl::associated_token::get_associated_token_address(&user, &self.usdt_mint);
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+        Ok(self.instruction(
+            collateral_vault::accounts::Withdraw {
+                user,
+                vault: vault_pda,
+                vault_token_account,
+                user_token_account,
+                token_program: anchor_spl::token::ID,
+                multisig_config: self.multisig_pda(&vault_pda),
+            },
+            collateral_vault::instruction::Withdraw { amount },
+        ))
+    }
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+    pub async fn request_withdrawal_ix(
+        &self,
+        user: Pubkey,
+        request_id: u64,
+        amount: u64,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::RequestWithdrawal {
+                user,
+                vault: vault_pda,
+                withdrawal_request: self.withdrawal_request_pda(&vault_pda, request_id),
+                system_program: system_program::ID,
+                multisig_config: self.multisig_pda(&vault_pda),
+            },
+            collateral_vault::instruction::RequestWithdrawal { request_id, amount },
+        ))
+    }
 
-        // Build signer list: always include payer, add user if available and matches
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if user == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
-            }
-        }
+    pub async fn execute_withdrawal_ix(
+        &self,
+        user: Pubkey,
+        request_id: u64,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+        let vault_token_account = self.vault_token_account(&vault_pda).await?;
+        let user_token_account =
+            anchor_spl::associated_token::get_associated_token_address(&user, &self.usdt_mint);
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
+        Ok(self.instruction(
+            collateral_vault::accounts::ExecuteWithdrawal {
+                user,
+                vault: vault_pda,
+                withdrawal_request: self.withdrawal_request_pda(&vault_pda, request_id),
+                vault_token_account,
+                user_token_account,
+                token_program: anchor_spl::token::ID,
+                system_program: system_program::ID,
+            },
+            collateral_vault::instruction::ExecuteWithdrawal {},
+        ))
+    }
 
-        println!("Deposit successful: {}", sig);
-        Ok(sig.to_string())
+    pub async fn lock_ix(
+        &self,
+        user: Pubkey,
+        authority_program: Pubkey,
+        amount: u64,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::LockCollateral {
+                user,
+                vault: vault_pda,
+                vault_authority: self.vault_authority_pda(&vault_pda),
+                authority_program,
+            },
+            collateral_vault::instruction::LockCollateral { amount },
+        ))
     }
 
-    pub async fn withdraw(&self, user: Pubkey, amount: u64) -> Result<String> {
-        println!("Withdrawing {} tokens for {}", amount, user);
+    pub async fn unlock_ix(
+        &self,
+        user: Pubkey,
+        authority_program: Pubkey,
+        amount: u64,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::UnlockCollateral {
+                user,
+                vault: vault_pda,
+                vault_authority: self.vault_authority_pda(&vault_pda),
+                authority_program,
+            },
+            collateral_vault::instruction::UnlockCollateral { amount },
+        ))
+    }
 
-        let (vault_pda, _) =
-            Pubkey::find_program_address(&[b"vault", user.as_ref()], &self.program.id());
+    /// Settlement of a position between two users' vaults. Only valid as a
+    /// CPI from `authority_program`, which signs for its
+    /// `VaultAuthority::CALLER_SEED` PDA.
+    pub async fn settle_pnl_ix(
+        &self,
+        loser: Pubkey,
+        winner: Pubkey,
+        authority_program: Pubkey,
+        settlement: collateral_vault::instruction::SettlePnl,
+    ) -> Result<Instruction> {
+        let loser_vault = self.vault_address(&loser).await?;
+        let winner_vault = self.vault_address(&winner).await?;
+        let caller = Pubkey::find_program_address(
+            &[collateral_vault::state::VaultAuthority::CALLER_SEED],
+            &authority_program,
+        )
+        .0;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::SettlePnl {
+                loser_vault,
+                loser_vault_authority: self.vault_authority_pda(&loser_vault),
+                loser_vault_token_account: self.vault_token_account(&loser_vault).await?,
+                winner_vault,
+                winner_vault_authority: self.vault_authority_pda(&winner_vault),
+                winner_vault_token_account: self.vault_token_account(&winner_vault).await?,
+                authority_program,
+                caller,
+                token_program: anchor_spl::token::ID,
+            },
+            settlement,
+        ))
+    }
 
-        let (vault_token_account, _) =
-            Pubkey::find_program_address(&[b"vault_token", user.as_ref()], &self.program.id());
+    fn forced_unlock_pda(&self, vault: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"forced_unlock", vault.as_ref()], &self.program_id).0
+    }
 
-        let user_token_account =
-            anchor_spl::associated_token::get_associated_token_address(&user, &self.usdt_mint);
+    pub async fn request_forced_unlock_ix(&self, user: Pubkey) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
 
-        let ix_data =
-            anchor_lang::InstructionData::data(&collateral_vault::instruction::Withdraw { amount });
+        Ok(self.instruction(
+            collateral_vault::accounts::RequestForcedUnlock {
+                owner: user,
+                vault: vault_pda,
+                forced_unlock_request: self.forced_unlock_pda(&vault_pda),
+                system_program: system_program::ID,
+            },
+            collateral_vault::instruction::RequestForcedUnlock {},
+        ))
+    }
 
-        let accounts = collateral_vault::accounts::Withdraw {
-            user,
-            vault: vault_pda,
-            vault_token_account,
-            user_token_account,
-            token_program: anchor_spl::token::ID,
-        };
+    pub async fn execute_forced_unlock_ix(&self, user: Pubkey) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+        Ok(self.instruction(
+            collateral_vault::accounts::ExecuteForcedUnlock {
+                owner: user,
+                vault: vault_pda,
+                forced_unlock_request: self.forced_unlock_pda(&vault_pda),
+            },
+            collateral_vault::instruction::ExecuteForcedUnlock {},
+        ))
+    }
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+    /// Only valid as a CPI from `authority_program`, which signs for its
+    /// `VaultAuthority::CALLER_SEED` PDA.
+    pub async fn heartbeat_ix(
+        &self,
+        user: Pubkey,
+        authority_program: Pubkey,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+        let caller = Pubkey::find_program_address(
+            &[collateral_vault::state::VaultAuthority::CALLER_SEED],
+            &authority_program,
+        )
+        .0;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::Heartbeat {
+                vault: vault_pda,
+                vault_authority: self.vault_authority_pda(&vault_pda),
+                authority_program,
+                caller,
+                forced_unlock_request: self.forced_unlock_pda(&vault_pda),
+                owner: user,
+            },
+            collateral_vault::instruction::Heartbeat {},
+        ))
+    }
 
-        // Build signer list: always include payer, add user if available and matches
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if user == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
-            }
-        }
+    fn recovery_config_pda(&self, vault: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"recovery", vault.as_ref()], &self.program_id).0
+    }
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
+    fn recovery_request_pda(&self, vault: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"recovery_request", vault.as_ref()], &self.program_id).0
+    }
 
-        println!("Withdrawal successful: {}", sig);
-        Ok(sig.to_string())
+    /// The recovery in progress on `vault`.
+    pub async fn get_recovery_request(&self, vault: &Pubkey) -> Result<RecoveryRequest> {
+        let account = self
+            .chain
+            .get_account(&self.recovery_request_pda(vault))
+            .await?;
+        let mut data: &[u8] = &account.data;
+        Ok(RecoveryRequest::try_deserialize(&mut data)?)
     }
 
-    pub async fn request_withdrawal(
+    pub async fn configure_recovery_ix(
         &self,
         user: Pubkey,
-        request_id: u64,
-        amount: u64,
-    ) -> Result<String> {
-        println!(
-            "Requesting delayed withdrawal of {} for {} (request #{})",
-            amount, user, request_id
-        );
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::ConfigureRecovery {
+                owner: user,
+                vault: vault_pda,
+                recovery_config: self.recovery_config_pda(&vault_pda),
+                system_program: system_program::ID,
+            },
+            collateral_vault::instruction::ConfigureRecovery {
+                guardians,
+                threshold,
+            },
+        ))
+    }
 
-        let (vault_pda, _) =
-            Pubkey::find_program_address(&[b"vault", user.as_ref()], &self.program.id());
+    pub async fn update_recovery_ix(
+        &self,
+        user: Pubkey,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::UpdateRecovery {
+                owner: user,
+                vault: vault_pda,
+                recovery_config: self.recovery_config_pda(&vault_pda),
+            },
+            collateral_vault::instruction::UpdateRecovery {
+                guardians,
+                threshold,
+            },
+        ))
+    }
 
-        let (withdrawal_request, _) = Pubkey::find_program_address(
-            &[b"withdrawal", vault_pda.as_ref(), &request_id.to_le_bytes()],
-            &self.program.id(),
-        );
+    pub async fn veto_recovery_ix(&self, user: Pubkey) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+        let request = self.get_recovery_request(&vault_pda).await?;
 
-        let ix_data =
-            anchor_lang::InstructionData::data(&collateral_vault::instruction::RequestWithdrawal {
-                request_id,
-                amount,
-            });
+        Ok(self.instruction(
+            collateral_vault::accounts::VetoRecovery {
+                owner: user,
+                vault: vault_pda,
+                recovery_request: self.recovery_request_pda(&vault_pda),
+                initiator: request.initiator,
+            },
+            collateral_vault::instruction::VetoRecovery {},
+        ))
+    }
 
-        let accounts = collateral_vault::accounts::RequestWithdrawal {
-            user,
-            vault: vault_pda,
-            withdrawal_request,
-            system_program: system_program::ID,
-        };
+    /// Guardian instructions name the vault directly: its owner is the one
+    /// who lost access.
+    pub fn initiate_recovery_ix(
+        &self,
+        guardian: Pubkey,
+        vault: Pubkey,
+        new_owner: Pubkey,
+    ) -> Instruction {
+        self.instruction(
+            collateral_vault::accounts::InitiateRecovery {
+                guardian,
+                vault,
+                recovery_config: self.recovery_config_pda(&vault),
+                recovery_request: self.recovery_request_pda(&vault),
+                system_program: system_program::ID,
+            },
+            collateral_vault::instruction::InitiateRecovery { new_owner },
+        )
+    }
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    pub fn approve_recovery_ix(&self, guardian: Pubkey, vault: Pubkey) -> Instruction {
+        self.instruction(
+            collateral_vault::accounts::ApproveRecovery {
+                guardian,
+                vault,
+                recovery_config: self.recovery_config_pda(&vault),
+                recovery_request: self.recovery_request_pda(&vault),
+            },
+            collateral_vault::instruction::ApproveRecovery {},
+        )
+    }
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+    pub async fn execute_recovery_ix(
+        &self,
+        guardian: Pubkey,
+        vault: Pubkey,
+    ) -> Result<Instruction> {
+        let request = self.get_recovery_request(&vault).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::ExecuteRecovery {
+                guardian,
+                vault,
+                recovery_config: self.recovery_config_pda(&vault),
+                recovery_request: self.recovery_request_pda(&vault),
+                initiator: request.initiator,
+            },
+            collateral_vault::instruction::ExecuteRecovery {},
+        ))
+    }
+
+    pub async fn transfer_ix(
+        &self,
+        operator: Pubkey,
+        from: Pubkey,
+        to: Pubkey,
+        authority_program: Pubkey,
+        amount: u64,
+    ) -> Result<Instruction> {
+        let from_vault = self.vault_address(&from).await?;
+        let to_vault = self.vault_address(&to).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::TransferCollateral {
+                operator,
+                from_vault,
+                from_vault_authority: self.vault_authority_pda(&from_vault),
+                to_vault,
+                to_vault_authority: self.vault_authority_pda(&to_vault),
+                authority_program,
+            },
+            collateral_vault::instruction::TransferCollateral {
+                from_vault,
+                to_vault,
+                amount,
+            },
+        ))
+    }
 
-        // Build signer list: always include payer, add user if available and matches
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if user == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
+    /// Delayed withdrawals of `user` that have not been executed yet (executed
+    /// requests are closed), oldest request id first.
+    pub async fn get_withdrawal_requests(
+        &self,
+        user: Pubkey,
+    ) -> Result<Vec<(Pubkey, WithdrawalRequest)>> {
+        let vault_pda = self.vault_address(&user).await?;
+        let (_, accounts) = self
+            .chain
+            .get_program_accounts(&self.program_id, WithdrawalRequest::DISCRIMINATOR)
+            .await?;
+
+        let mut requests = Vec::new();
+        for (address, account) in accounts {
+            let mut data: &[u8] = &account.data;
+            let request = WithdrawalRequest::try_deserialize(&mut data)?;
+            if request.vault == vault_pda {
+                requests.push((address, request));
             }
         }
+        requests.sort_by_key(|(_, request)| request.request_id);
+        Ok(requests)
+    }
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
-
-        println!("Withdrawal request placed: {}", sig);
-        Ok(sig.to_string())
+    pub async fn initialize_multisig_ix(
+        &self,
+        user: Pubkey,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::InitializeMultisig {
+                owner: user,
+                vault: vault_pda,
+                multisig_config: self.multisig_pda(&vault_pda),
+                system_program: system_program::ID,
+            },
+            collateral_vault::instruction::InitializeMultisig { signers, threshold },
+        ))
     }
 
-    pub async fn execute_withdrawal(&self, user: Pubkey, request_id: u64) -> Result<String> {
-        println!(
-            "Executing delayed withdrawal for {} (request #{})",
-            user, request_id
-        );
+    /// The co-signers and threshold configured for `user`'s vault.
+    pub async fn get_multisig_config(&self, user: Pubkey) -> Result<MultisigConfig> {
+        let address = self.multisig_pda(&self.vault_address(&user).await?);
+        let account = self.chain.get_account(&address).await?;
+        let mut data: &[u8] = &account.data;
+        Ok(MultisigConfig::try_deserialize(&mut data)?)
+    }
 
-        let (vault_pda, _) =
-            Pubkey::find_program_address(&[b"vault", user.as_ref()], &self.program.id());
+    pub async fn add_authorized_program_ix(
+        &self,
+        user: Pubkey,
+        program: Pubkey,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::AddAuthorizedProgram {
+                owner: user,
+                vault: vault_pda,
+                vault_authority: self.vault_authority_pda(&vault_pda),
+                multisig_config: self.multisig_pda(&vault_pda),
+            },
+            collateral_vault::instruction::AddAuthorizedProgram { program },
+        ))
+    }
 
-        let (withdrawal_request, _) = Pubkey::find_program_address(
-            &[b"withdrawal", vault_pda.as_ref(), &request_id.to_le_bytes()],
-            &self.program.id(),
-        );
+    pub async fn remove_authorized_program_ix(
+        &self,
+        user: Pubkey,
+        program: Pubkey,
+    ) -> Result<Instruction> {
+        let vault_pda = self.vault_address(&user).await?;
+
+        Ok(self.instruction(
+            collateral_vault::accounts::RemoveAuthorizedProgram {
+                owner: user,
+                vault: vault_pda,
+                vault_authority: self.vault_authority_pda(&vault_pda),
+                multisig_config: self.multisig_pda(&vault_pda),
+            },
+            collateral_vault::instruction::RemoveAuthorizedProgram { program },
+        ))
+    }
 
-        let account = self.program.rpc().get_account(&vault_pda)?;
+    /// Programs allowed to lock, unlock and transfer `user`'s collateral, read
+    /// from the vault's `VaultAuthority` account.
+    pub async fn get_authorized_programs(&self, user: Pubkey) -> Result<Vec<Pubkey>> {
+        let address = self.vault_authority_pda(&self.vault_address(&user).await?);
+        let account = self.chain.get_account(&address).await?;
         let mut data: &[u8] = &account.data;
-        let vault = collateral_vault::state::CollateralVault::try_deserialize(&mut data)?;
-        let vault_token_account = vault.token_account;
+        let authority = collateral_vault::state::VaultAuthority::try_deserialize(&mut data)?;
+        Ok(authority.authorized_programs)
+    }
 
-        let user_token_account =
-            anchor_spl::associated_token::get_associated_token_address(&user, &self.usdt_mint);
+    /// Sign as payer (plus the env user keypair when it matches `user` and the
+    /// instructions need its signature) and send.
+    async fn send(&self, user: Pubkey, instructions: &[Instruction]) -> Result<String> {
+        // Build signer list: always include payer, add user if available and required
+        let mut signers: Vec<&dyn TransactionSigner> = vec![&*self.payer];
+        if let Some(ref user_signer) = self.user {
+            let required = instructions
+                .iter()
+                .flat_map(|ix| ix.accounts.iter())
+                .any(|meta| meta.is_signer && meta.pubkey == user);
+            if user == user_signer.pubkey() && required {
+                signers.push(&**user_signer);
+            }
+        }
 
-        let ix_data = anchor_lang::InstructionData::data(
-            &collateral_vault::instruction::ExecuteWithdrawal {},
-        );
+        self.sender.send(instructions, &signers).await
+    }
 
-        let accounts = collateral_vault::accounts::ExecuteWithdrawal {
-            user,
-            vault: vault_pda,
-            withdrawal_request,
-            vault_token_account,
-            user_token_account,
-            token_program: anchor_spl::token::ID,
-            system_program: system_program::ID,
+    /// Build a transaction for `signer`'s wallet to sign. With `sponsor_fees`
+    /// the backend payer is the fee payer and signs its part; otherwise the
+    /// wallet pays its own fees.
+    pub async fn build_unsigned(
+        &self,
+        signer: Pubkey,
+        instructions: &[Instruction],
+        sponsor_fees: bool,
+    ) -> Result<UnsignedTransaction> {
+        let fee_payer = if sponsor_fees {
+            self.payer.pubkey()
+        } else {
+            signer
         };
+        let (blockhash, last_valid_block_height) = self.chain.get_latest_blockhash().await?;
+
+        let mut tx = Transaction::new_with_payer(instructions, Some(&fee_payer));
+        if sponsor_fees {
+            sign_transaction(&mut tx, &[&*self.payer], blockhash).await?;
+        } else {
+            tx.message.recent_blockhash = blockhash;
+        }
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+        let required = tx.message.header.num_required_signatures as usize;
+        let required_signers = tx.message.account_keys[..required]
+            .iter()
+            .zip(&tx.signatures)
+            .filter(|(_, sig)| **sig == Default::default())
+            .map(|(key, _)| key.to_string())
+            .collect();
+
+        Ok(UnsignedTransaction {
+            transaction: BASE64.encode(bincode::serialize(&tx)?),
+            fee_payer: fee_payer.to_string(),
+            recent_blockhash: blockhash.to_string(),
+            last_valid_block_height,
+            required_signers,
+        })
+    }
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+    /// Like `build_unsigned` with fees sponsored, but on the durable nonce
+    /// held by `nonce` instead of a recent blockhash, so signatures can be
+    /// gathered for as long as it takes. The transaction stays valid until the
+    /// nonce advances, which landing it does.
+    pub async fn build_unsigned_on_nonce(
+        &self,
+        nonce: Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<UnsignedTransaction> {
+        let account = self.chain.get_account(&nonce).await?;
+        let blockhash =
+            nonce_hash(&account).ok_or_else(|| anyhow!("{} is not a nonce account", nonce))?;
+        let mut with_advance = vec![system_instruction::advance_nonce_account(
+            &nonce,
+            &self.payer.pubkey(),
+        )];
+        with_advance.extend_from_slice(instructions);
+
+        let mut tx = Transaction::new_with_payer(&with_advance, Some(&self.payer.pubkey()));
+        sign_transaction(&mut tx, &[&*self.payer], blockhash).await?;
+        let required = tx.message.header.num_required_signatures as usize;
+        let required_signers = tx.message.account_keys[..required]
+            .iter()
+            .zip(&tx.signatures)
+            .filter(|(_, sig)| **sig == Default::default())
+            .map(|(key, _)| key.to_string())
+            .collect();
+
+        Ok(UnsignedTransaction {
+            transaction: BASE64.encode(bincode::serialize(&tx)?),
+            fee_payer: self.payer.pubkey().to_string(),
+            recent_blockhash: blockhash.to_string(),
+            last_valid_block_height: u64::MAX,
+            required_signers,
+        })
+    }
 
-        // Build signer list: always include payer, add user if available and matches
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if user == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
-            }
-        }
+    /// Create a durable nonce account at an address derived from the payer
+    /// and `seed`, with the payer as its authority.
+    pub async fn create_nonce_account(&self, seed: &str) -> Result<Pubkey> {
+        let payer = self.payer.pubkey();
+        let nonce = Pubkey::create_with_seed(&payer, seed, &system_program::ID)?;
+        let instructions = system_instruction::create_nonce_account_with_seed(
+            &payer,
+            &nonce,
+            &payer,
+            seed,
+            &payer,
+            Rent::default().minimum_balance(NonceState::size()),
+        );
+        self.sender.send(&instructions, &[&*self.payer]).await?;
+        Ok(nonce)
+    }
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
+    /// Close a nonce account made by `create_nonce_account`, returning its
+    /// rent to the payer.
+    pub async fn close_nonce_account(&self, nonce: Pubkey) -> Result<String> {
+        let account = self.chain.get_account(&nonce).await?;
+        let payer = self.payer.pubkey();
+        let instruction =
+            system_instruction::withdraw_nonce_account(&nonce, &payer, &payer, account.lamports);
+        self.sender.send(&[instruction], &[&*self.payer]).await
+    }
 
-        println!("Withdrawal executed: {}", sig);
-        Ok(sig.to_string())
+    /// Decode and check a wallet-signed transaction built by `build_unsigned`.
+    pub fn decode_signed(&self, encoded: &str) -> Result<Transaction> {
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| anyhow!("transaction is not valid base64: {}", e))?;
+        let tx: Transaction = bincode::deserialize(&bytes)
+            .map_err(|e| anyhow!("transaction could not be decoded: {}", e))?;
+
+        if tx.message.recent_blockhash == Hash::default() {
+            return Err(anyhow!("transaction has no recent blockhash"));
+        }
+        tx.verify()
+            .map_err(|e| anyhow!("transaction is not fully signed: {}", e))?;
+        Ok(tx)
     }
 
-    pub async fn lock(
+    pub async fn submit_signed(&self, tx: &Transaction) -> Result<String> {
+        self.sender.send_signed(tx).await
+    }
+
+    pub async fn initialize_vault(
         &self,
         user: Pubkey,
-        authority_program: Pubkey,
-        amount: u64,
+        authorized_programs: Vec<Pubkey>,
     ) -> Result<String> {
-        println!("Locking {} collateral for {}", amount, user);
+        println!("Initializing vault for user {}", user);
+
+        let instruction = self.initialize_vault_ix(user, authorized_programs);
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Vault initialized: {}", sig);
+        Ok(sig)
+    }
+
+    pub async fn migrate_vault(&self, user: Pubkey) -> Result<String> {
+        println!("Migrating vault layout for {}", user);
 
-        let (vault_pda, _) =
-            Pubkey::find_program_address(&[b"vault", user.as_ref()], &self.program.id());
+        let instruction = self.migrate_vault_ix(user);
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Vault migrated: {}", sig);
+        Ok(sig)
+    }
 
-        let (vault_authority, _) = Pubkey::find_program_address(
-            &[b"vault_authority", vault_pda.as_ref()],
-            &self.program.id(),
+    pub async fn deposit(&self, user: Pubkey, amount: u64) -> Result<String> {
+        println!("Depositing {} tokens for {}", amount, user);
+
+        let instruction = self.deposit_ix(user, amount).await?;
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Deposit successful: {}", sig);
+        Ok(sig)
+    }
+
+    pub async fn withdraw(&self, user: Pubkey, amount: u64) -> Result<String> {
+        println!("Withdrawing {} tokens for {}", amount, user);
+
+        let instruction = self.withdraw_ix(user, amount).await?;
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Withdrawal successful: {}", sig);
+        Ok(sig)
+    }
+
+    pub async fn request_withdrawal(
+        &self,
+        user: Pubkey,
+        request_id: u64,
+        amount: u64,
+    ) -> Result<String> {
+        println!(
+            "Requesting delayed withdrawal of {} for {} (request #{})",
+            amount, user, request_id
         );
 
-        let ix_data =
-            anchor_lang::InstructionData::data(&collateral_vault::instruction::LockCollateral {
-                amount,
-            });
+        let instruction = self.request_withdrawal_ix(user, request_id, amount).await?;
+        let sig = self.send(user, &[instruction]).await?;
 
-        let accounts = collateral_vault::accounts::LockCollateral {
-            user,
-            vault: vault_pda,
-            vault_authority,
-            authority_program,
-        };
+        println!("Withdrawal request placed: {}", sig);
+        Ok(sig)
+    }
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    pub async fn execute_withdrawal(&self, user: Pubkey, request_id: u64) -> Result<String> {
+        println!(
+            "Executing delayed withdrawal for {} (request #{})",
+            user, request_id
+        );
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+        let instruction = self.execute_withdrawal_ix(user, request_id).await?;
+        let sig = self.send(user, &[instruction]).await?;
 
-        // Build signer list: always include payer, add user if available and matches
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if user == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
-            }
-        }
+        println!("Withdrawal executed: {}", sig);
+        Ok(sig)
+    }
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
+    pub async fn lock(
+        &self,
+        user: Pubkey,
+        authority_program: Pubkey,
+        amount: u64,
+    ) -> Result<String> {
+        println!("Locking {} collateral for {}", amount, user);
+
+        let instruction = self.lock_ix(user, authority_program, amount).await?;
+        let sig = self.send(user, &[instruction]).await?;
 
         println!("Collateral locked: {}", sig);
-        Ok(sig.to_string())
+        Ok(sig)
     }
 
     pub async fn unlock(
@@ -420,53 +914,78 @@ impl VaultManager {
     ) -> Result<String> {
         println!("Unlocking {} collateral for {}", amount, user);
 
-        let (vault_pda, _) =
-            Pubkey::find_program_address(&[b"vault", user.as_ref()], &self.program.id());
+        let instruction = self.unlock_ix(user, authority_program, amount).await?;
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Collateral unlocked: {}", sig);
+        Ok(sig)
+    }
+
+    pub async fn request_forced_unlock(&self, user: Pubkey) -> Result<String> {
+        println!("Requesting forced unlock for {}", user);
+
+        let instruction = self.request_forced_unlock_ix(user).await?;
+        let sig = self.send(user, &[instruction]).await?;
 
-        let (vault_authority, _) = Pubkey::find_program_address(
-            &[b"vault_authority", vault_pda.as_ref()],
-            &self.program.id(),
+        println!("Forced unlock requested: {}", sig);
+        Ok(sig)
+    }
+
+    pub async fn execute_forced_unlock(&self, user: Pubkey) -> Result<String> {
+        println!("Executing forced unlock for {}", user);
+
+        let instruction = self.execute_forced_unlock_ix(user).await?;
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Forced unlock executed: {}", sig);
+        Ok(sig)
+    }
+
+    pub async fn configure_recovery(
+        &self,
+        user: Pubkey,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<String> {
+        println!(
+            "Configuring {}-of-{} recovery for {}",
+            threshold,
+            guardians.len(),
+            user
         );
 
-        let ix_data =
-            anchor_lang::InstructionData::data(&collateral_vault::instruction::UnlockCollateral {
-                amount,
-            });
+        let instruction = self
+            .configure_recovery_ix(user, guardians, threshold)
+            .await?;
+        let sig = self.send(user, &[instruction]).await?;
 
-        let accounts = collateral_vault::accounts::UnlockCollateral {
-            user,
-            vault: vault_pda,
-            vault_authority,
-            authority_program,
-        };
+        println!("Recovery configured: {}", sig);
+        Ok(sig)
+    }
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    pub async fn update_recovery(
+        &self,
+        user: Pubkey,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<String> {
+        println!("Rotating guardians for {}", user);
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+        let instruction = self.update_recovery_ix(user, guardians, threshold).await?;
+        let sig = self.send(user, &[instruction]).await?;
 
-        // Build signer list: always include payer, add user if available and matches
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if user == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
-            }
-        }
+        println!("Guardians rotated: {}", sig);
+        Ok(sig)
+    }
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
+    pub async fn veto_recovery(&self, user: Pubkey) -> Result<String> {
+        println!("Vetoing recovery of {}'s vault", user);
 
-        println!("Collateral unlocked: {}", sig);
-        Ok(sig.to_string())
+        let instruction = self.veto_recovery_ix(user).await?;
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Recovery vetoed: {}", sig);
+        Ok(sig)
     }
 
     pub async fn transfer(
@@ -478,87 +997,54 @@ impl VaultManager {
     ) -> Result<String> {
         println!("Transferring {} from {} to {}", amount, from, to);
 
-        let (from_vault, _) =
-            Pubkey::find_program_address(&[b"vault", from.as_ref()], &self.program.id());
-
-        let (to_vault, _) =
-            Pubkey::find_program_address(&[b"vault", to.as_ref()], &self.program.id());
-
-        let (from_vault_authority, _) = Pubkey::find_program_address(
-            &[b"vault_authority", from_vault.as_ref()],
-            &self.program.id(),
-        );
-        let (to_vault_authority, _) = Pubkey::find_program_address(
-            &[b"vault_authority", to_vault.as_ref()],
-            &self.program.id(),
-        );
+        let instruction = self
+            .transfer_ix(self.payer.pubkey(), from, to, authority_program, amount)
+            .await?;
+        let sig = self.send(from, &[instruction]).await?;
 
-        let ix_data = anchor_lang::InstructionData::data(
-            &collateral_vault::instruction::TransferCollateral {
-                from_vault,
-                to_vault,
-                amount,
-            },
-        );
+        println!("Transfer successful: {}", sig);
+        Ok(sig)
+    }
 
-        let accounts = collateral_vault::accounts::TransferCollateral {
-            operator: self.payer.pubkey(),
-            from_vault,
-            from_vault_authority,
-            to_vault,
-            to_vault_authority,
-            authority_program,
-        };
+    pub async fn add_authorized_program(&self, user: Pubkey, program: Pubkey) -> Result<String> {
+        println!("Authorizing program {} for {}", program, user);
 
-        let account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+        let instruction = self.add_authorized_program_ix(user, program).await?;
+        let sig = self.send(user, &[instruction]).await?;
 
-        let instruction = anchor_lang::solana_program::instruction::Instruction {
-            program_id: self.program.id(),
-            accounts: account_metas,
-            data: ix_data,
-        };
+        println!("Program authorized: {}", sig);
+        Ok(sig)
+    }
 
-        // Build signer list: always include payer, add user if available and matches from user
-        let mut signers: Vec<&Keypair> = vec![&*self.payer];
-        if let Some(ref user_keypair) = self.user {
-            if from == user_keypair.pubkey() {
-                signers.push(&**user_keypair);
-            }
-        }
+    pub async fn remove_authorized_program(&self, user: Pubkey, program: Pubkey) -> Result<String> {
+        println!("Revoking program {} for {}", program, user);
 
-        let sig = self.program.rpc().send_and_confirm_transaction(
-            &Transaction::new_signed_with_payer(
-                &[instruction],
-                Some(&self.payer.pubkey()),
-                &signers,
-                self.program.rpc().get_latest_blockhash()?,
-            ),
-        )?;
+        let instruction = self.remove_authorized_program_ix(user, program).await?;
+        let sig = self.send(user, &[instruction]).await?;
 
-        println!("Transfer successful: {}", sig);
-        Ok(sig.to_string())
+        println!("Program revoked: {}", sig);
+        Ok(sig)
     }
-}
 
-impl Clone for VaultManager {
-    fn clone(&self) -> Self {
-        let payer = self.payer.clone();
-        let user = self.user.clone();
-        let cluster = Cluster::Custom(
-            self.program.rpc().url().to_string(),
-            self.program.rpc().url().to_string(),
+    pub async fn initialize_multisig(
+        &self,
+        user: Pubkey,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<String> {
+        println!(
+            "Configuring {}-of-{} multisig for {}",
+            threshold,
+            signers.len(),
+            user
         );
-        let client =
-            Client::new_with_options(cluster, payer.clone(), CommitmentConfig::confirmed());
-        let program = client
-            .program(self.program.id())
-            .expect("Failed to create Anchor program client");
 
-        Self {
-            program,
-            payer,
-            user,
-            usdt_mint: self.usdt_mint,
-        }
+        let instruction = self
+            .initialize_multisig_ix(user, signers, threshold)
+            .await?;
+        let sig = self.send(user, &[instruction]).await?;
+
+        println!("Multisig configured: {}", sig);
+        Ok(sig)
     }
 }
@@ -9,8 +9,13 @@ use tokio::time::{interval, Duration};
 pub struct VaultMetrics {
     pub total_vaults: usize,
     pub total_tvl: u64,
+    pub total_locked: u64,
+    pub total_available: u64,
+    /// Slot the vault figures were observed at.
+    pub slot: u64,
     pub average_balance: u64,
     pub total_transactions: usize,
+    /// Vaults whose owner made a transaction in the last 24 hours.
     pub active_vaults: usize,
     pub timestamp: i64,
 }
@@ -49,11 +54,11 @@ impl VaultMonitor {
     }
 
     /// Start monitoring service
-    pub fn start(self: Arc<Self>) {
-        // Metrics collection every 60 seconds
+    pub fn start(self: Arc<Self>, metrics_every: Duration, security_every: Duration) {
+        // Metrics collection
         let monitor_clone = self.clone();
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(60));
+            let mut interval = interval(metrics_every);
             loop {
                 interval.tick().await;
                 if let Err(e) = monitor_clone.collect_metrics().await {
@@ -62,10 +67,10 @@ impl VaultMonitor {
             }
         });
 
-        // Security monitoring every 30 seconds
+        // Security monitoring
         let monitor_clone = self.clone();
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(30));
+            let mut interval = interval(security_every);
             loop {
                 interval.tick().await;
                 if let Err(e) = monitor_clone.security_check().await {
@@ -79,20 +84,27 @@ impl VaultMonitor {
 
     /// Collect vault metrics
     async fn collect_metrics(&self) -> Result<()> {
-        let tvl = self.tracker.calculate_tvl().await;
-        let transactions = self.db.get_all_transactions().await?;
+        let tvl = self.tracker.refresh_tvl().await?;
+        let now = chrono::Utc::now().timestamp();
+        let stats = self.db.get_transaction_stats(now - 86_400).await?;
 
         let metrics = VaultMetrics {
-            total_vaults: 0, // You can track this
-            total_tvl: tvl,
-            average_balance: 0,
-            total_transactions: transactions.len(),
-            active_vaults: 0,
-            timestamp: chrono::Utc::now().timestamp(),
+            total_vaults: tvl.total_vaults,
+            total_tvl: tvl.total_value_locked,
+            total_locked: tvl.total_locked,
+            total_available: tvl.total_available,
+            slot: tvl.slot,
+            average_balance: tvl
+                .total_value_locked
+                .checked_div(tvl.total_vaults as u64)
+                .unwrap_or(0),
+            total_transactions: stats.total_transactions as usize,
+            active_vaults: stats.active_users as usize,
+            timestamp: now,
         };
 
         let mut history = self.metrics_history.write().await;
-        history.push(metrics.clone());
+        history.push(metrics);
 
         // Keep only last 24 hours of data (1440 minutes)
         let len = history.len();
@@ -100,11 +112,6 @@ impl VaultMonitor {
             history.drain(0..(len - 1440));
         }
 
-        println!(
-            "📊 Metrics collected - TVL: {} tokens, Transactions: {}",
-            metrics.total_tvl, metrics.total_transactions
-        );
-
         Ok(())
     }
 
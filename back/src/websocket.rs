@@ -1,3 +1,4 @@
+use crate::db::{MultisigAction, MultisigStatus, TransactionStatus, TransactionType};
 use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
     response::Response,
@@ -27,12 +28,64 @@ pub enum WsMessage {
     TvlUpdate {
         tvl: u64,
     },
+    TransactionStatus {
+        signature: String,
+        user: String,
+        tx_type: TransactionType,
+        status: TransactionStatus,
+        slot: Option<u64>,
+        error: Option<String>,
+    },
+    /// A program was added to (`authorized`) or removed from a vault's
+    /// authorized programs.
+    AuthorizationChanged {
+        user: String,
+        program: String,
+        authorized: bool,
+        signature: String,
+    },
+    /// A multisig operation needs signatures from `awaiting`, each over
+    /// `message`, posted to `/multisig/operations/{id}/approve`.
+    MultisigSignatureRequested {
+        operation_id: String,
+        owner: String,
+        action: MultisigAction,
+        message: String,
+        awaiting: Vec<String>,
+    },
+    /// A guardian started recovering `vault` to `proposed_owner`; the owner
+    /// can veto it until `available_at`.
+    RecoveryInitiated {
+        user: String,
+        vault: String,
+        proposed_owner: String,
+        guardian: String,
+        approvals: u8,
+        threshold: u8,
+        available_at: i64,
+        signature: String,
+    },
+    /// An approval arrived or the operation left `pending`.
+    MultisigOperationUpdated {
+        operation_id: String,
+        owner: String,
+        status: MultisigStatus,
+        approvals: usize,
+        threshold: u8,
+        tx_signature: Option<String>,
+    },
 }
 
 pub struct WebSocketManager {
     tx: broadcast::Sender<String>,
 }
 
+impl Default for WebSocketManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WebSocketManager {
     pub fn new() -> Self {
         let (tx, _) = broadcast::channel(100);
@@ -44,9 +97,18 @@ impl WebSocketManager {
         let _ = self.tx.send(json);
     }
 
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.tx.subscribe()
+    }
+
+    /// Open WebSocket connections; each holds one subscription.
+    pub fn subscriber_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
     pub async fn handle_socket(self: Arc<Self>, socket: WebSocket) {
         let (mut sender, mut receiver) = socket.split();
-        let mut rx = self.tx.subscribe();
+        let mut rx = self.subscribe();
 
         // Send messages to client
         let send_task = tokio::spawn(async move {
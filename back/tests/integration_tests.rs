@@ -1,7 +1,11 @@
 // tests/integration_tests.rs
+#![allow(unused_imports, unused_mut, unused_variables, clippy::useless_vec)]
 use anchor_client::solana_sdk::{
+    account::Account,
+    hash::Hash,
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
 };
 use anchor_lang::prelude::*;
 use anyhow::Result;
@@ -460,6 +464,3615 @@ mod error_handling_tests {
     }
 }
 
+// ============================================
+// SIMULATED CHAIN HARNESS (END TO END, NO VALIDATOR)
+// ============================================
+
+#[cfg(test)]
+mod simulated_chain {
+    use super::*;
+    use test_utils::*;
+    use back::admin::AdminConfig;
+    use back::auth::AuthService;
+    use back::backfill::Backfill;
+    use back::balance_tracker::BalanceTracker;
+    use back::chain::{
+        ChainClient, ProgramLogs, SignatureInfo, SignatureStatus, SimulatedChain, Simulation,
+        TransactionFee,
+    };
+    use back::confirmer::TransactionConfirmer;
+    use back::db::Database;
+    use back::handlers::VaultRequest;
+    use back::idempotency::IdempotencyConfig;
+    use back::indexer::EventIndexer;
+    use back::metrics::Metrics;
+    use back::multisig::MultisigCoordinator;
+    use back::reconciler::Reconciler;
+    use back::router::{self, Services};
+    use back::sender::{PriorityFee, SenderConfig, TransactionSender};
+    use back::signer::LocalSigner;
+    use back::vault_manager::VaultManager;
+    use back::vault_monitor::VaultMonitor;
+    use back::websocket::WebSocketManager;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    pub struct Harness {
+        pub chain: Arc<SimulatedChain>,
+        pub vm: Arc<VaultManager>,
+        pub user: Pubkey,
+        pub mint: Pubkey,
+    }
+
+    pub fn setup() -> Harness {
+        let chain = Arc::new(SimulatedChain::new());
+        let user = Keypair::new();
+        let user_pubkey = user.pubkey();
+        let mint = get_test_usdt_mint();
+        chain.create_mint(mint, 6);
+        chain.mint_to(&mint, &user_pubkey, 10_000);
+
+        let vm = Arc::new(VaultManager::with_chain(
+            chain.clone(),
+            Arc::new(LocalSigner::new(Keypair::new())),
+            Some(user),
+            chain.program_id(),
+            mint,
+        ));
+        Harness {
+            chain,
+            vm,
+            user: user_pubkey,
+            mint,
+        }
+    }
+
+    pub fn request(user: &Pubkey, amount: u64) -> VaultRequest {
+        VaultRequest {
+            user_pubkey: user.to_string(),
+            amount: Some(amount),
+            to_pubkey: None,
+            request_id: None,
+            authorized_programs: None,
+            unsigned: None,
+            sponsor_fees: None,
+        }
+    }
+
+    /// Harness whose sender writes transaction rows through a confirmer, as
+    /// in production.
+    pub fn setup_with_confirmer() -> (
+        Harness,
+        Arc<Database>,
+        Arc<WebSocketManager>,
+        Arc<TransactionConfirmer>,
+    ) {
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let ws = Arc::new(WebSocketManager::new());
+        let confirmer = Arc::new(TransactionConfirmer::new(
+            h.chain.clone(),
+            db.clone(),
+            ws.clone(),
+            h.chain.program_id(),
+        ));
+        let sender = TransactionSender::new(h.chain.clone(), SenderConfig::default())
+            .with_observer(confirmer.clone());
+        let vm = Arc::new((*h.vm).clone().with_sender(sender));
+        (Harness { vm, ..h }, db, ws, confirmer)
+    }
+
+    /// Second vault manager on the same chain, for a different user.
+    pub fn second_user(h: &Harness) -> (Arc<VaultManager>, Pubkey) {
+        let user = Keypair::new();
+        let pubkey = user.pubkey();
+        h.chain.mint_to(&h.mint, &pubkey, 10_000);
+        let vm = Arc::new(VaultManager::with_chain(
+            h.chain.clone(),
+            Arc::new(LocalSigner::new(Keypair::new())),
+            Some(user),
+            h.chain.program_id(),
+            h.mint,
+        ));
+        (vm, pubkey)
+    }
+
+    /// Rewrites `owner`'s vault in the layout the program used before it grew,
+    /// as a vault created before the upgrade and not yet migrated looks.
+    pub async fn rewrite_as_legacy(h: &Harness, owner: Pubkey) -> Result<Pubkey> {
+        use anchor_lang::{AccountDeserialize, AnchorSerialize, Discriminator};
+        use collateral_vault::state::{CollateralVault, LegacyCollateralVault};
+
+        let vault_pda =
+            Pubkey::find_program_address(&[b"vault", owner.as_ref()], &h.chain.program_id()).0;
+        let mut account = h.chain.get_account(&vault_pda).await?;
+        let current = CollateralVault::try_deserialize(&mut account.data.as_slice())?;
+        let mut data = CollateralVault::DISCRIMINATOR.to_vec();
+        LegacyCollateralVault {
+            owner: current.owner,
+            token_account: current.token_account,
+            vault_authority: current.vault_authority,
+            total_balance: current.total_balance,
+            locked_balance: current.locked_balance,
+            available_balance: current.available_balance,
+            total_deposited: current.total_deposited,
+            total_withdrawn: current.total_withdrawn,
+            created_at: current.created_at,
+            bump: current.bump,
+        }
+        .serialize(&mut data)?;
+        data.resize(8 + std::mem::size_of::<LegacyCollateralVault>(), 0);
+        account.data = data;
+        h.chain.set_account(vault_pda, account);
+        Ok(vault_pda)
+    }
+
+    /// Harness whose vault manager logs send attempts to the returned database.
+    pub fn setup_with_sender(config: SenderConfig) -> (Harness, Arc<Database>) {
+        let h = setup();
+        let database = Arc::new(Database::new(None));
+        let sender = TransactionSender::new(h.chain.clone(), config).with_log(database.clone());
+        let vm = Arc::new((*h.vm).clone().with_sender(sender));
+        (Harness { vm, ..h }, database)
+    }
+
+    pub fn fast_config(priority_fee: PriorityFee) -> SenderConfig {
+        SenderConfig {
+            priority_fee,
+            resend_interval: Duration::from_millis(20),
+            poll_interval: Duration::from_millis(5),
+            ..SenderConfig::default()
+        }
+    }
+
+    /// Accepts the first send without forwarding it and lets its blockhash
+    /// expire, like a transaction dropped by a congested leader. With
+    /// `land_late` the held transaction lands just as its blockhash expires,
+    /// after the sender's last status poll.
+    pub struct DropFirstSend {
+        inner: Arc<SimulatedChain>,
+        dropped: std::sync::atomic::AtomicBool,
+        land_late: bool,
+        held: std::sync::Mutex<Option<Transaction>>,
+    }
+
+    impl DropFirstSend {
+        pub fn new(inner: Arc<SimulatedChain>, land_late: bool) -> Self {
+            Self {
+                inner,
+                dropped: Default::default(),
+                land_late,
+                held: Default::default(),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChainClient for DropFirstSend {
+        async fn get_account(&self, address: &Pubkey) -> Result<Account> {
+            self.inner.get_account(address).await
+        }
+
+        async fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+            self.inner.get_latest_blockhash().await
+        }
+
+        async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool> {
+            let held = self.held.lock().unwrap().take();
+            if let Some(tx) = held {
+                self.inner.send_transaction(&tx).await?;
+                self.inner.expire_blockhashes();
+            }
+            self.inner.is_blockhash_valid(blockhash).await
+        }
+
+        async fn simulate_transaction(&self, tx: &Transaction) -> Result<Simulation> {
+            self.inner.simulate_transaction(tx).await
+        }
+
+        async fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+            if !self.dropped.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                if self.land_late {
+                    *self.held.lock().unwrap() = Some(tx.clone());
+                } else {
+                    self.inner.expire_blockhashes();
+                }
+                return Ok(tx.signatures[0]);
+            }
+            self.inner.send_transaction(tx).await
+        }
+
+        async fn get_signature_status(
+            &self,
+            signature: &Signature,
+        ) -> Result<Option<std::result::Result<(), String>>> {
+            self.inner.get_signature_status(signature).await
+        }
+
+        async fn get_signature_statuses(
+            &self,
+            signatures: &[Signature],
+        ) -> Result<Vec<Option<SignatureStatus>>> {
+            self.inner.get_signature_statuses(signatures).await
+        }
+
+        async fn get_program_accounts(
+            &self,
+            program_id: &Pubkey,
+            discriminator: &[u8],
+        ) -> Result<(u64, Vec<(Pubkey, Account)>)> {
+            self.inner.get_program_accounts(program_id, discriminator).await
+        }
+
+        async fn get_block_time(&self, slot: u64) -> Result<Option<i64>> {
+            self.inner.get_block_time(slot).await
+        }
+
+        async fn get_slot(&self) -> Result<u64> {
+            self.inner.get_slot().await
+        }
+
+        async fn get_signatures_for_address(
+            &self,
+            address: &Pubkey,
+            before: Option<Signature>,
+            until: Option<Signature>,
+            limit: usize,
+        ) -> Result<Vec<SignatureInfo>> {
+            self.inner
+                .get_signatures_for_address(address, before, until, limit)
+                .await
+        }
+
+        async fn get_transaction_logs(&self, signature: &Signature) -> Result<Option<ProgramLogs>> {
+            self.inner.get_transaction_logs(signature).await
+        }
+
+        async fn get_transaction_fee(
+            &self,
+            signature: &Signature,
+        ) -> Result<Option<TransactionFee>> {
+            self.inner.get_transaction_fee(signature).await
+        }
+
+        async fn get_recent_prioritization_fees(&self, accounts: &[Pubkey]) -> Result<Vec<u64>> {
+            self.inner.get_recent_prioritization_fees(accounts).await
+        }
+
+        async fn subscribe_logs(
+            &self,
+            program_id: Pubkey,
+        ) -> Result<tokio::sync::broadcast::Receiver<ProgramLogs>> {
+            self.inner.subscribe_logs(program_id).await
+        }
+    }
+
+    /// The production router over `h`, served on a local port.
+    pub async fn serve(h: &Harness, db: &Arc<Database>, auth: Arc<AuthService>) -> Result<String> {
+        serve_with_metrics(h, db, auth, Arc::new(Metrics::new())).await
+    }
+
+    pub async fn serve_with_metrics(
+        h: &Harness,
+        db: &Arc<Database>,
+        auth: Arc<AuthService>,
+        metrics: Arc<Metrics>,
+    ) -> Result<String> {
+        let tracker = Arc::new(BalanceTracker::new(h.vm.clone(), db.clone()));
+        let indexer = Arc::new(EventIndexer::new(
+            h.chain.clone(),
+            db.clone(),
+            h.chain.program_id(),
+        ));
+        let app = router::router(Services {
+            vault_manager: h.vm.clone(),
+            balance_tracker: tracker.clone(),
+            vault_monitor: Arc::new(VaultMonitor::new(tracker, db.clone())),
+            database: db.clone(),
+            analytics: None,
+            backfill: Arc::new(Backfill::new(
+                h.chain.clone(),
+                db.clone(),
+                indexer,
+                h.chain.program_id(),
+            )),
+            reconciler: Arc::new(Reconciler::new(
+                h.chain.clone(),
+                db.clone(),
+                h.chain.program_id(),
+            )),
+            multisig: Arc::new(MultisigCoordinator::new(
+                h.vm.clone(),
+                db.clone(),
+                Arc::new(WebSocketManager::new()),
+            )),
+            auth,
+            websocket: Arc::new(WebSocketManager::new()),
+            admin: AdminConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            metrics,
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(base)
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: VAULT PROGRAM
+// ============================================
+
+#[cfg(test)]
+mod vault_program_tests {
+    use super::*;
+    use simulated_chain::*;
+    use anchor_lang::{AnchorDeserialize, Discriminator};
+    use back::balance_tracker::BalanceTracker;
+    use back::chain::ChainClient;
+    use back::cpi_manager::CPIManager;
+    use back::db::Database;
+    use back::indexer::{EventIndexer, VaultEvent};
+    use back::vault_manager::VaultManager;
+    use back::signer::{LocalSigner, TransactionSigner};
+    use back::websocket::{WebSocketManager, WsMessage};
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use collateral_vault::events::DepositEvent;
+    use collateral_vault::instruction as ix;
+    use collateral_vault::instructions::forced_unlock::{
+        FORCED_UNLOCK_DELAY_SECONDS, FORCED_UNLOCK_INACTIVITY_SECONDS,
+    };
+    use collateral_vault::instructions::recovery::RECOVERY_DELAY_SECONDS;
+    use collateral_vault::state::SettlementBucket;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_deposit_and_withdraw_move_tokens() -> Result<()> {
+        println!("🧪 TEST: Simulated Deposit/Withdraw");
+
+        let h = setup();
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 4_000).await?;
+        h.vm.withdraw(h.user, 1_500).await?;
+
+        let tracker = BalanceTracker::new(h.vm.clone(), Arc::new(Database::new(None)));
+        let balance = tracker.get_vault_balance(h.user).await?;
+        assert_eq!(balance.total_balance, 2_500);
+        assert_eq!(balance.available_balance, 2_500);
+        assert_eq!(balance.total_deposited, 4_000);
+        assert_eq!(balance.total_withdrawn, 1_500);
+
+        let user_ata =
+            anchor_spl::associated_token::get_associated_token_address(&h.user, &h.mint);
+        assert_eq!(h.chain.token_balance(&user_ata), Some(7_500));
+
+        println!("✅ Vault state and token balances agree");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_locked_collateral_blocks_withdrawal() -> Result<()> {
+        println!("🧪 TEST: Simulated Lock via CPIManager");
+
+        let h = setup();
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+
+        let cpi = CPIManager::new(h.vm.clone());
+        cpi.lock_for_position(h.user, 600, "pos-1".to_string())
+            .await?;
+
+        let err = h.vm.withdraw(h.user, 100).await.unwrap_err();
+        assert!(err.to_string().contains("ActivePosition"), "{}", err);
+
+        cpi.unlock_after_close(h.user, 600, "pos-1".to_string())
+            .await?;
+        h.vm.withdraw(h.user, 1_000).await?;
+
+        println!("✅ Withdrawal blocked while collateral is locked");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_program_rejected() -> Result<()> {
+        println!("🧪 TEST: Simulated Unauthorized Lock");
+
+        let h = setup();
+        let other_program = Pubkey::new_unique();
+        h.chain.add_program(other_program);
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+
+        let err = h.vm.lock(h.user, other_program, 100).await.unwrap_err();
+        assert!(err.to_string().contains("Unauthorized"), "{}", err);
+
+        println!("✅ Lock through an unlisted program rejected");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delayed_withdrawal_respects_clock() -> Result<()> {
+        println!("🧪 TEST: Simulated Delayed Withdrawal");
+
+        let h = setup();
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        h.vm.request_withdrawal(h.user, 1, 400).await?;
+
+        let err = h.vm.execute_withdrawal(h.user, 1).await.unwrap_err();
+        assert!(err.to_string().contains("WithdrawalDelayNotMet"), "{}", err);
+
+        h.chain.advance_clock(86_400);
+        h.vm.execute_withdrawal(h.user, 1).await?;
+
+        let user_ata =
+            anchor_spl::associated_token::get_associated_token_address(&h.user, &h.mint);
+        assert_eq!(h.chain.token_balance(&user_ata), Some(9_400));
+
+        println!("✅ Withdrawal only executes after the delay");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_log_subscription_carries_events() -> Result<()> {
+        println!("🧪 TEST: Simulated Log Subscription");
+
+        let h = setup();
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let sig = h.vm.deposit(h.user, 250).await?;
+
+        let _init = logs.recv().await?;
+        let deposit = logs.recv().await?;
+        assert_eq!(deposit.signature, sig);
+
+        let data = deposit
+            .logs
+            .iter()
+            .find_map(|l| l.strip_prefix("Program data: "))
+            .expect("event log line");
+        let bytes = BASE64.decode(data)?;
+        assert_eq!(&bytes[..8], DepositEvent::DISCRIMINATOR);
+        let event = DepositEvent::try_from_slice(&bytes[8..])?;
+        assert_eq!(event.amount, 250);
+        assert_eq!(event.sequence, 1);
+
+        println!("✅ DepositEvent decoded from program logs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_legacy_vault_migrates_to_current_layout() -> Result<()> {
+        println!("🧪 TEST: Legacy Vault Migration");
+
+        let h = setup();
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+
+        // Rewrite the vault as the program stored it before the layout grew.
+        let vault_pda = rewrite_as_legacy(&h, h.user).await?;
+
+        let err = h.vm.deposit(h.user, 100).await.unwrap_err();
+        assert!(err.to_string().contains("AccountDidNotDeserialize"), "{}", err);
+
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+        h.vm.migrate_vault(h.user).await?;
+        let migrated = logs.recv().await?;
+        let events = back::indexer::decode_logs(&h.chain.program_id(), &migrated.logs);
+        match &events[0].event {
+            VaultEvent::VaultMigrated(e) => {
+                assert_eq!(e.sequence, 0);
+                assert_eq!(e.total_balance, 1_000);
+            }
+            other => panic!("unexpected {}", other.name()),
+        }
+
+        h.vm.deposit(h.user, 100).await?;
+        let tracker = BalanceTracker::new(h.vm.clone(), Arc::new(Database::new(None)));
+        let balance = tracker.get_vault_balance(h.user).await?;
+        assert_eq!(balance.total_balance, 1_100);
+        assert_eq!(balance.total_deposited, 1_100);
+
+        let err = h.vm.migrate_vault(h.user).await.unwrap_err();
+        assert!(err.to_string().contains("AlreadyMigrated"), "{}", err);
+
+        println!("✅ Legacy vault grows to the current layout and keeps its balances");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forced_unlock_resets_when_stale_and_heartbeat_cancels() -> Result<()> {
+        println!("🧪 TEST: Forced Unlock Request, Heartbeat and Execute");
+
+        let h = setup();
+        let position_program = Pubkey::new_unique();
+        h.chain.add_program(position_program);
+        h.vm.initialize_vault(h.user, vec![position_program]).await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        h.vm.lock(h.user, position_program, 600).await?;
+
+        let err = h.vm.request_forced_unlock(h.user).await.unwrap_err();
+        assert!(err.to_string().contains("ForcedUnlockInactivityNotMet"), "{}", err);
+
+        h.chain.advance_clock(FORCED_UNLOCK_INACTIVITY_SECONDS);
+        h.vm.request_forced_unlock(h.user).await?;
+        let err = h.vm.request_forced_unlock(h.user).await.unwrap_err();
+        assert!(err.to_string().contains("ForcedUnlockPending"), "{}", err);
+        let err = h.vm.execute_forced_unlock(h.user).await.unwrap_err();
+        assert!(err.to_string().contains("ForcedUnlockDelayNotMet"), "{}", err);
+
+        // Activity since the request leaves it unexecutable, but a new one
+        // can be filed once the vault is idle again.
+        h.chain.advance_clock(1);
+        h.vm.lock(h.user, position_program, 100).await?;
+        h.chain.advance_clock(FORCED_UNLOCK_DELAY_SECONDS);
+        let err = h.vm.execute_forced_unlock(h.user).await.unwrap_err();
+        assert!(err.to_string().contains("ForcedUnlockInactivityNotMet"), "{}", err);
+        h.chain.advance_clock(FORCED_UNLOCK_INACTIVITY_SECONDS);
+        h.vm.request_forced_unlock(h.user).await?;
+
+        // A live position program cancels it.
+        let logs = h.chain.invoke_from_program(
+            position_program,
+            &h.vm.heartbeat_ix(h.user, position_program).await?,
+        )?;
+        let events = back::indexer::decode_logs(&h.chain.program_id(), &logs.logs);
+        match &events[0].event {
+            VaultEvent::PositionHeartbeat(e) => assert!(e.cancelled_forced_unlock),
+            other => panic!("unexpected {}", other.name()),
+        }
+        let err = h.vm.execute_forced_unlock(h.user).await.unwrap_err();
+        assert!(err.to_string().contains("AccountNotInitialized"), "{}", err);
+
+        // Without the program's signature a heartbeat cannot be forged.
+        let err = h
+            .chain
+            .invoke_from_program(h.chain.program_id(), &h.vm.heartbeat_ix(h.user, position_program).await?)
+            .unwrap_err();
+        assert!(err.to_string().contains("AccountNotSigner"), "{}", err);
+
+        h.chain.advance_clock(FORCED_UNLOCK_INACTIVITY_SECONDS);
+        h.vm.request_forced_unlock(h.user).await?;
+        h.chain.advance_clock(FORCED_UNLOCK_DELAY_SECONDS);
+        h.vm.execute_forced_unlock(h.user).await?;
+
+        let tracker = BalanceTracker::new(h.vm.clone(), Arc::new(Database::new(None)));
+        let balance = tracker.get_vault_balance(h.user).await?;
+        assert_eq!(balance.locked_balance, 0);
+        assert_eq!(balance.available_balance, 1_000);
+
+        println!("✅ Stale requests reset, heartbeats cancel, idle vaults unlock");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_settle_pnl_moves_buckets_and_tokens() -> Result<()> {
+        println!("🧪 TEST: PnL Settlement Between Vaults");
+
+        let h = setup();
+        let position_program = Pubkey::new_unique();
+        h.chain.add_program(position_program);
+        let (winner_vm, winner) = second_user(&h);
+        let loser = h.user;
+        h.vm.initialize_vault(loser, vec![position_program]).await?;
+        winner_vm
+            .initialize_vault(winner, vec![position_program])
+            .await?;
+        h.vm.deposit(loser, 1_000).await?;
+        winner_vm.deposit(winner, 1_000).await?;
+        h.vm.lock(loser, position_program, 400).await?;
+        winner_vm.lock(winner, position_program, 400).await?;
+
+        let settlement = |pnl, margin, debit_bucket, credit_bucket| ix::SettlePnl {
+            position_id: 7,
+            loser_margin: margin,
+            winner_margin: margin,
+            pnl,
+            debit_bucket,
+            credit_bucket,
+        };
+        let settle = |args| async {
+            let instruction = h
+                .vm
+                .settle_pnl_ix(loser, winner, position_program, args)
+                .await?;
+            h.chain.invoke_from_program(position_program, &instruction)
+        };
+
+        // The loss comes out of the loser's margin and lands as available.
+        settle(settlement(
+            150,
+            400,
+            SettlementBucket::Locked,
+            SettlementBucket::Available,
+        ))
+        .await?;
+        let tracker = BalanceTracker::new(h.vm.clone(), Arc::new(Database::new(None)));
+        let loser_balance = tracker.get_vault_balance(loser).await?;
+        assert_eq!(loser_balance.total_balance, 850);
+        assert_eq!(loser_balance.locked_balance, 0);
+        assert_eq!(loser_balance.available_balance, 850);
+        let winner_balance = tracker.get_vault_balance(winner).await?;
+        assert_eq!(winner_balance.total_balance, 1_150);
+        assert_eq!(winner_balance.available_balance, 1_150);
+
+        let vault_token = |user: &Pubkey| {
+            Pubkey::find_program_address(&[b"vault_token", user.as_ref()], &h.chain.program_id()).0
+        };
+        assert_eq!(h.chain.token_balance(&vault_token(&loser)), Some(850));
+        assert_eq!(h.chain.token_balance(&vault_token(&winner)), Some(1_150));
+
+        h.vm.lock(loser, position_program, 100).await?;
+        winner_vm.lock(winner, position_program, 100).await?;
+        let err = settle(settlement(
+            200,
+            100,
+            SettlementBucket::Locked,
+            SettlementBucket::Available,
+        ))
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("PnlExceedsMargin"), "{}", err);
+
+        // A loss beyond the margin can come out of available funds, and the
+        // profit can stay locked on the winner's side.
+        let logs = settle(settlement(
+            200,
+            100,
+            SettlementBucket::Available,
+            SettlementBucket::Locked,
+        ))
+        .await?;
+        let events = back::indexer::decode_logs(&h.chain.program_id(), &logs.logs);
+        match &events[0].event {
+            VaultEvent::PositionSettled(e) => {
+                assert_eq!(e.loser.margin_released, 100);
+                assert_eq!(e.winner.new_locked_balance, 200);
+            }
+            other => panic!("unexpected {}", other.name()),
+        }
+        let loser_balance = tracker.get_vault_balance(loser).await?;
+        assert_eq!(loser_balance.total_balance, 650);
+        assert_eq!(loser_balance.available_balance, 650);
+        let winner_balance = tracker.get_vault_balance(winner).await?;
+        assert_eq!(winner_balance.total_balance, 1_350);
+        assert_eq!(winner_balance.locked_balance, 200);
+        assert_eq!(winner_balance.available_balance, 1_150);
+        assert_eq!(h.chain.token_balance(&vault_token(&loser)), Some(650));
+        assert_eq!(h.chain.token_balance(&vault_token(&winner)), Some(1_350));
+
+        let same_vault = h
+            .vm
+            .settle_pnl_ix(winner, winner, position_program, settlement(
+                0,
+                0,
+                SettlementBucket::Locked,
+                SettlementBucket::Locked,
+            ))
+            .await?;
+        let err = h
+            .chain
+            .invoke_from_program(position_program, &same_vault)
+            .unwrap_err();
+        assert!(err.to_string().contains("SameVaultSettlement"), "{}", err);
+
+        println!("✅ Settlement moves both buckets and the matching tokens");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recovery_approvals_veto_delay_and_guardian_rotation() -> Result<()> {
+        println!("🧪 TEST: Guardian Recovery");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let ws = Arc::new(WebSocketManager::new());
+        let mut pushes = ws.subscribe();
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id())
+            .with_websocket(ws.clone());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+
+        let guardians: Vec<Arc<LocalSigner>> = (0..4)
+            .map(|_| Arc::new(LocalSigner::new(Keypair::new())))
+            .collect();
+        let [g1, g2, g3, g4] = [0, 1, 2, 3].map(|i| guardians[i].pubkey());
+        let as_guardian = |signer: usize, ix: anchor_client::solana_sdk::instruction::Instruction| {
+            let guardian = guardians[signer].clone();
+            let vm = h.vm.clone();
+            async move { vm.sender.send(&[ix], &[&*vm.payer, &*guardian]).await }
+        };
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        h.vm.configure_recovery(h.user, vec![g1, g2, g3], 2).await?;
+        let vault = h.vm.vault_address(&h.user).await?;
+        while logs.try_recv().is_ok() {}
+
+        let new_owner = Keypair::new();
+        let new_owner_pubkey = new_owner.pubkey();
+        let err = as_guardian(3, h.vm.initiate_recovery_ix(g4, vault, new_owner_pubkey))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("NotGuardian"), "{}", err);
+
+        // The owner hears about the attempt once, even if the logs replay.
+        as_guardian(0, h.vm.initiate_recovery_ix(g1, vault, new_owner_pubkey)).await?;
+        let initiated = logs.recv().await?;
+        assert_eq!(indexer.index_logs(&initiated).await?, 1);
+        assert_eq!(indexer.index_logs(&initiated).await?, 0);
+        let mut alerts = Vec::new();
+        while let Ok(json) = pushes.try_recv() {
+            if let Ok(WsMessage::RecoveryInitiated {
+                user,
+                proposed_owner,
+                approvals,
+                threshold,
+                ..
+            }) = serde_json::from_str(&json)
+            {
+                alerts.push((user, proposed_owner, approvals, threshold));
+            }
+        }
+        assert_eq!(
+            alerts,
+            vec![(h.user.to_string(), new_owner_pubkey.to_string(), 1, 2)]
+        );
+
+        let err = as_guardian(0, h.vm.approve_recovery_ix(g1, vault))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("RecoveryAlreadyApproved"), "{}", err);
+        as_guardian(1, h.vm.approve_recovery_ix(g2, vault)).await?;
+        let err = as_guardian(1, h.vm.execute_recovery_ix(g2, vault).await?)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("RecoveryDelayNotMet"), "{}", err);
+
+        // The owner still has the key and cancels it.
+        h.vm.veto_recovery(h.user).await?;
+        let err = h.vm.execute_recovery_ix(g2, vault).await.unwrap_err();
+        assert!(err.to_string().contains("AccountNotFound"), "{}", err);
+
+        // Approvals from rotated-out guardians stop counting.
+        as_guardian(1, h.vm.initiate_recovery_ix(g2, vault, new_owner_pubkey)).await?;
+        as_guardian(2, h.vm.approve_recovery_ix(g3, vault)).await?;
+        h.vm.update_recovery(h.user, vec![g1, g4], 2).await?;
+        h.chain.advance_clock(RECOVERY_DELAY_SECONDS);
+        let err = as_guardian(0, h.vm.execute_recovery_ix(g1, vault).await?)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("RecoveryThresholdNotMet"), "{}", err);
+        as_guardian(0, h.vm.approve_recovery_ix(g1, vault)).await?;
+        as_guardian(3, h.vm.approve_recovery_ix(g4, vault)).await?;
+        as_guardian(3, h.vm.execute_recovery_ix(g4, vault).await?).await?;
+
+        // The vault keeps its address; the backend finds it from the new owner.
+        h.chain.mint_to(&h.mint, &new_owner_pubkey, 0);
+        let new_vm = Arc::new(VaultManager::with_chain(
+            h.chain.clone(),
+            Arc::new(LocalSigner::new(Keypair::new())),
+            Some(new_owner),
+            h.chain.program_id(),
+            h.mint,
+        ));
+        assert_eq!(new_vm.vault_address(&new_owner_pubkey).await?, vault);
+        new_vm.withdraw(new_owner_pubkey, 300).await?;
+        let tracker = BalanceTracker::new(new_vm.clone(), db.clone());
+        let balance = tracker.get_vault_balance(new_owner_pubkey).await?;
+        assert_eq!(balance.owner, new_owner_pubkey.to_string());
+        assert_eq!(balance.total_balance, 700);
+        let err = h.vm.withdraw(h.user, 100).await.unwrap_err();
+        assert!(err.to_string().contains("InvalidAuthority"), "{}", err);
+
+        println!("✅ Recovery needs current guardians, the delay, and no veto");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: REST API
+// ============================================
+
+#[cfg(test)]
+mod rest_api_tests {
+    use super::*;
+    use simulated_chain::*;
+    use axum::{http::StatusCode, Extension, Json};
+    use back::auth::{AuthConfig, AuthService, AuthenticatedWallet, OwnerJson};
+    use back::balance_tracker::BalanceTracker;
+    use back::db::Database;
+    use back::handlers::{self, AuthorizedProgramRequest, SubmitTxRequest};
+    use back::websocket::{WebSocketManager, WsMessage};
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_deposit_handler_end_to_end() -> Result<()> {
+        println!("🧪 TEST: Deposit Handler on Simulated Chain");
+
+        let (h, db, ws, _) = setup_with_confirmer();
+        let tracker = Arc::new(BalanceTracker::new(h.vm.clone(), db.clone()));
+
+        let response = handlers::initialize_vault(
+            Extension(h.vm.clone()),
+            Extension(db.clone()),
+            Extension(tracker.clone()),
+            Extension(ws.clone()),
+            OwnerJson(request(&h.user, 0)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = handlers::deposit_collateral(
+            Extension(h.vm.clone()),
+            Extension(db.clone()),
+            Extension(tracker.clone()),
+            Extension(ws.clone()),
+            OwnerJson(request(&h.user, 300)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let history = db.get_user_transactions(&h.user.to_string()).await?;
+        assert_eq!(history.len(), 2);
+        let cached = tracker.get_vault_balance(h.user).await?;
+        assert_eq!(cached.available_balance, 300);
+
+        let response = handlers::withdraw_collateral(
+            Extension(h.vm.clone()),
+            Extension(db.clone()),
+            Extension(tracker),
+            Extension(ws),
+            OwnerJson(request(&h.user, 5_000)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        println!("✅ Handlers record history and surface program errors");
+        Ok(())
+    }
+
+    fn authorization_pushes(
+        rx: &mut tokio::sync::broadcast::Receiver<String>,
+    ) -> Vec<(String, bool)> {
+        let mut changes = Vec::new();
+        while let Ok(json) = rx.try_recv() {
+            if let Ok(WsMessage::AuthorizationChanged {
+                program, authorized, ..
+            }) = serde_json::from_str(&json)
+            {
+                changes.push((program, authorized));
+            }
+        }
+        changes
+    }
+
+    #[tokio::test]
+    async fn test_authorized_programs_managed_over_rest() -> Result<()> {
+        println!("🧪 TEST: Authorized Program Management");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let ws = Arc::new(WebSocketManager::new());
+        let tracker = Arc::new(BalanceTracker::new(h.vm.clone(), db.clone()));
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let mut rx = ws.subscribe();
+        let perps = Pubkey::new_unique();
+
+        let change = |add: bool, wallet: Pubkey, program: Pubkey, unsigned: bool| {
+            let (vm, db, ws, user) = (h.vm.clone(), db.clone(), ws.clone(), h.user.to_string());
+            let req = AuthorizedProgramRequest {
+                program: program.to_string(),
+                unsigned: Some(unsigned),
+                sponsor_fees: Some(true),
+            };
+            let path = axum::extract::Path(user);
+            let wallet = AuthenticatedWallet(wallet);
+            async move {
+                if add {
+                    handlers::add_authorized_program(
+                        path,
+                        Extension(vm),
+                        Extension(db),
+                        Extension(ws),
+                        wallet,
+                        Json(req),
+                    )
+                    .await
+                } else {
+                    handlers::remove_authorized_program(
+                        path,
+                        Extension(vm),
+                        Extension(db),
+                        Extension(ws),
+                        wallet,
+                        Json(req),
+                    )
+                    .await
+                }
+            }
+        };
+
+        let response = change(true, Pubkey::new_unique(), perps, false).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = change(true, h.user, perps, false).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = change(true, h.user, perps, false).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            h.vm.get_authorized_programs(h.user).await?,
+            vec![h.chain.program_id(), perps]
+        );
+
+        let response = handlers::get_authorized_programs(
+            axum::extract::Path(h.user.to_string()),
+            Extension(h.vm.clone()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let missing = handlers::get_authorized_programs(
+            axum::extract::Path(Pubkey::new_unique().to_string()),
+            Extension(h.vm.clone()),
+        )
+        .await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+        // Revoking through the owner's wallet: build, sign, relay.
+        let response = change(false, h.user, perps, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let unsigned = h
+            .vm
+            .build_unsigned(h.user, &[h.vm.remove_authorized_program_ix(h.user, perps).await?], true)
+            .await?;
+        let mut tx: Transaction = bincode::deserialize(&BASE64.decode(&unsigned.transaction)?)?;
+        let owner = h.vm.user.clone().unwrap();
+        tx.partial_sign(&[owner.keypair()], tx.message.recent_blockhash);
+        let response = handlers::submit_transaction(
+            Extension(h.vm.clone()),
+            Extension(db.clone()),
+            Extension(tracker),
+            Extension(ws.clone()),
+            Json(SubmitTxRequest {
+                transaction: BASE64.encode(bincode::serialize(&tx)?),
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            h.vm.get_authorized_programs(h.user).await?,
+            vec![h.chain.program_id()]
+        );
+
+        let response = change(false, h.user, perps, false).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let actions: Vec<_> = db
+            .get_audit_logs(Some(&h.user.to_string()))
+            .await?
+            .into_iter()
+            .map(|log| log.action)
+            .filter(|action| action.starts_with("AUTHORIZED_PROGRAM"))
+            .collect();
+        assert_eq!(
+            actions,
+            ["AUTHORIZED_PROGRAM_ADDED", "AUTHORIZED_PROGRAM_REMOVED"]
+        );
+        assert_eq!(
+            authorization_pushes(&mut rx),
+            [(perps.to_string(), true), (perps.to_string(), false)]
+        );
+
+        println!("✅ Changes are checked, audited and pushed, sent or relayed");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_router_serves_delayed_withdrawals_and_health() -> Result<()> {
+        println!("🧪 TEST: Full Router");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let base = serve(&h, &db, auth).await?;
+        let client = reqwest::Client::new();
+        let get = |path: String| client.get(format!("{}{}", base, path)).send();
+
+        assert_eq!(get("/health/live".into()).await?.status().as_u16(), 200);
+        let ready: serde_json::Value = get("/health/ready".into()).await?.json().await?;
+        assert_eq!(ready["status"], "ready");
+        assert_eq!(ready["rpc"]["ok"], true);
+        assert_eq!(ready["database"]["ok"], true);
+        assert_eq!(ready["backfill"]["state"], "idle");
+        // Analytics is only routed with Postgres.
+        assert_eq!(get("/analytics/dashboard".into()).await?.status().as_u16(), 404);
+        let unauthenticated = client
+            .post(format!("{}/withdraw/request", base))
+            .json(&serde_json::json!({ "user_pubkey": h.user.to_string() }))
+            .send()
+            .await?;
+        assert_eq!(unauthenticated.status().as_u16(), 401);
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        h.vm.request_withdrawal(h.user, 2, 300).await?;
+        h.vm.request_withdrawal(h.user, 1, 100).await?;
+        let listing = format!("/vault/{}/withdrawal-requests", h.user);
+        let pending: serde_json::Value = get(listing.clone()).await?.json().await?;
+        let ids: Vec<_> = pending
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| (r["request_id"].as_u64(), r["amount"].as_u64()))
+            .collect();
+        assert_eq!(ids, [(Some(1), Some(100)), (Some(2), Some(300))]);
+
+        h.chain.advance_clock(86_400);
+        h.vm.execute_withdrawal(h.user, 1).await?;
+        let pending: serde_json::Value = get(listing).await?.json().await?;
+        assert_eq!(pending.as_array().unwrap().len(), 1);
+        assert_eq!(pending[0]["request_id"], 2);
+
+        println!("✅ Delayed withdrawals and health checks are routed");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: SESSIONS AND ROLES
+// ============================================
+
+#[cfg(test)]
+mod auth_tests {
+    use super::*;
+    use simulated_chain::*;
+    use axum::{extract::FromRequest, http::StatusCode, Extension};
+    use back::admin::{self, AdminConfig, ApiKey, Role};
+    use back::auth::{AuthConfig, AuthService, OwnerJson};
+    use back::balance_tracker::BalanceTracker;
+    use back::db::Database;
+    use back::handlers::VaultRequest;
+    use back::reconciler::Reconciler;
+    use back::vault_monitor::VaultMonitor;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_wallet_signature_opens_single_use_session() -> Result<()> {
+        println!("🧪 TEST: Sign-in With Solana");
+
+        let auth = AuthService::new(AuthConfig::default());
+        let wallet = Keypair::new();
+
+        let challenge = auth.challenge(wallet.pubkey()).await?;
+        assert!(challenge.message.contains(&wallet.pubkey().to_string()));
+        assert!(challenge.message.contains(&challenge.nonce));
+
+        // A signature from another wallet spends the nonce without a session.
+        let forged = Keypair::new().sign_message(challenge.message.as_bytes());
+        assert!(auth
+            .verify(wallet.pubkey(), &challenge.nonce, &forged)
+            .await
+            .is_err());
+        let signature = wallet.sign_message(challenge.message.as_bytes());
+        assert!(auth
+            .verify(wallet.pubkey(), &challenge.nonce, &signature)
+            .await
+            .is_err());
+
+        let challenge = auth.challenge(wallet.pubkey()).await?;
+        let signature = wallet.sign_message(challenge.message.as_bytes());
+        let session = auth
+            .verify(wallet.pubkey(), &challenge.nonce, &signature)
+            .await?;
+        assert_eq!(auth.authenticate(&session.token).await, Some(wallet.pubkey()));
+        assert_eq!(auth.authenticate("not-a-token").await, None);
+
+        // Replaying the same signed message does not open a second session.
+        assert!(auth
+            .verify(wallet.pubkey(), &challenge.nonce, &signature)
+            .await
+            .is_err());
+
+        println!("✅ Nonces are single-use and bound to the signing wallet");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pending_challenges_are_capped() -> Result<()> {
+        println!("🧪 TEST: Sign-in Challenge Cap");
+
+        let auth = AuthService::new(AuthConfig {
+            max_pending_challenges: 2,
+            ..AuthConfig::default()
+        });
+        let wallet = Keypair::new();
+
+        let first = auth.challenge(wallet.pubkey()).await?;
+        auth.challenge(Keypair::new().pubkey()).await?;
+        assert!(auth.challenge(Keypair::new().pubkey()).await.is_err());
+
+        // Spending a nonce frees its slot.
+        let signature = wallet.sign_message(first.message.as_bytes());
+        auth.verify(wallet.pubkey(), &first.nonce, &signature).await?;
+        auth.challenge(wallet.pubkey()).await?;
+        assert!(auth.challenge(wallet.pubkey()).await.is_err());
+
+        println!("✅ Nonce requests are refused while the store is full");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_vault_requests_need_owner_session() -> Result<()> {
+        println!("🧪 TEST: Owner-Only Vault Requests");
+
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let owner = Keypair::new();
+        let other = Keypair::new();
+        let mut tokens = Vec::new();
+        for wallet in [&owner, &other] {
+            let challenge = auth.challenge(wallet.pubkey()).await?;
+            let signature = wallet.sign_message(challenge.message.as_bytes());
+            let session = auth
+                .verify(wallet.pubkey(), &challenge.nonce, &signature)
+                .await?;
+            tokens.push(session.token);
+        }
+
+        let body = format!(r#"{{"user_pubkey":"{}","amount":100}}"#, owner.pubkey());
+        let extract = |token: Option<&str>| {
+            let mut builder = axum::http::Request::builder()
+                .method("POST")
+                .uri("/deposit")
+                .header("content-type", "application/json")
+                .extension(auth.clone());
+            if let Some(token) = token {
+                builder = builder.header("authorization", format!("Bearer {}", token));
+            }
+            let req = builder.body(axum::body::Body::from(body.clone())).unwrap();
+            async move {
+                match OwnerJson::<VaultRequest>::from_request(req, &()).await {
+                    Ok(OwnerJson(req)) => Ok(req),
+                    Err(response) => Err(response.status()),
+                }
+            }
+        };
+
+        assert_eq!(extract(None).await.err(), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(
+            extract(Some("expired")).await.err(),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            extract(Some(&tokens[1])).await.err(),
+            Some(StatusCode::FORBIDDEN)
+        );
+        let req = extract(Some(&tokens[0])).await.expect("owner is accepted");
+        assert_eq!(req.amount, Some(100));
+
+        println!("✅ Only the vault owner's session passes");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_enforce_roles() -> Result<()> {
+        println!("🧪 TEST: Admin API Roles");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let tracker = Arc::new(BalanceTracker::new(h.vm.clone(), db.clone()));
+        let monitor = Arc::new(VaultMonitor::new(tracker.clone(), db.clone()));
+        let reconciler = Arc::new(Reconciler::new(
+            h.chain.clone(),
+            db.clone(),
+            h.chain.program_id(),
+        ));
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let key = |name: &str, role| ApiKey {
+            name: name.to_string(),
+            key: format!("{}-0123456789abcdef", name),
+            role,
+        };
+        let config = AdminConfig {
+            keys: vec![
+                key("dashboard", Role::Viewer),
+                key("oncall", Role::Operator),
+                key("root", Role::Admin),
+            ],
+        };
+        let app = axum::Router::new()
+            .nest("/admin", admin::router(config))
+            .layer(Extension(db.clone()))
+            .layer(Extension(tracker))
+            .layer(Extension(monitor))
+            .layer(Extension(reconciler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}/admin", listener.local_addr()?);
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let client = reqwest::Client::new();
+        let call = |method: reqwest::Method, path: &str, key: Option<&str>| {
+            let mut req = client.request(method, format!("{}{}", base, path));
+            if let Some(key) = key {
+                req = req.header("X-Api-Key", format!("{}-0123456789abcdef", key));
+            }
+            async move { req.send().await.map(|r| r.status().as_u16()) }
+        };
+        let get = reqwest::Method::GET;
+        let post = reqwest::Method::POST;
+
+        assert_eq!(call(get.clone(), "/whoami", None).await?, 401);
+        assert_eq!(call(get.clone(), "/whoami", Some("intruder")).await?, 401);
+        for path in ["/whoami", "/metrics", "/security-alerts", "/alerts", "/audit-logs"] {
+            assert_eq!(call(get.clone(), path, Some("dashboard")).await?, 200, "{}", path);
+        }
+
+        let reconcile = format!("/reconcile/{}", h.user);
+        assert_eq!(call(post.clone(), &reconcile, Some("dashboard")).await?, 403);
+        assert_eq!(call(post.clone(), &reconcile, Some("oncall")).await?, 200);
+        assert_eq!(call(post.clone(), "/reconciliation", Some("dashboard")).await?, 403);
+        assert_eq!(call(post.clone(), "/reconciliation", Some("oncall")).await?, 200);
+        assert_eq!(call(get.clone(), "/reconciliation", Some("dashboard")).await?, 200);
+        assert_eq!(
+            call(post.clone(), "/alerts/not-an-alert/resolve", Some("oncall")).await?,
+            404
+        );
+
+        assert_eq!(call(get.clone(), "/keys", Some("oncall")).await?, 403);
+        let keys = client
+            .get(format!("{}/keys", base))
+            .header("Authorization", "ApiKey root-0123456789abcdef")
+            .send()
+            .await?
+            .text()
+            .await?;
+        assert!(keys.contains("\"dashboard\"") && keys.contains("\"admin\""));
+        assert!(!keys.contains("0123456789abcdef"));
+
+        println!("✅ Each role reaches its own routes and nothing above");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: MULTISIG
+// ============================================
+
+#[cfg(test)]
+mod multisig_tests {
+    use super::*;
+    use simulated_chain::*;
+    use axum::{http::StatusCode, Extension, Json};
+    use back::auth::{AuthenticatedWallet, OwnerJson};
+    use back::balance_tracker::BalanceTracker;
+    use back::chain::ChainClient;
+    use back::db::{Database, MultisigAction, MultisigOperation, MultisigStatus};
+    use back::handlers::{
+        self, ApproveMultisigRequest, MultisigInitRequest, ProposeMultisigRequest,
+    };
+    use back::multisig::{MultisigCoordinator, ProposalLimits};
+    use back::websocket::{WebSocketManager, WsMessage};
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_multisig_operation_submits_at_threshold() -> Result<()> {
+        println!("🧪 TEST: Multisig Approvals");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let ws = Arc::new(WebSocketManager::new());
+        let tracker = Arc::new(BalanceTracker::new(h.vm.clone(), db.clone()));
+        let multisig = Arc::new(MultisigCoordinator::new(h.vm.clone(), db.clone(), ws.clone()));
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        let owner = h.vm.user.clone().unwrap();
+        let cosigners = [Keypair::new(), Keypair::new(), Keypair::new()];
+
+        let configure = |threshold: u8| {
+            let req = MultisigInitRequest {
+                signers: cosigners.iter().map(|k| k.pubkey().to_string()).collect(),
+                threshold,
+                unsigned: None,
+                sponsor_fees: None,
+            };
+            handlers::initialize_multisig(
+                axum::extract::Path(h.user.to_string()),
+                Extension(h.vm.clone()),
+                Extension(db.clone()),
+                AuthenticatedWallet(h.user),
+                Json(req),
+            )
+        };
+        assert_eq!(configure(4).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(configure(2).await.status(), StatusCode::OK);
+        assert_eq!(configure(2).await.status(), StatusCode::CONFLICT);
+        let config = h.vm.get_multisig_config(h.user).await?;
+        assert_eq!(config.threshold, 2);
+        assert_eq!(config.signers.len(), 3);
+
+        // With a config the owner alone can no longer withdraw.
+        let err = h.vm.withdraw(h.user, 100).await.unwrap_err();
+        assert!(err.to_string().contains("MultisigThresholdNotMet"), "{}", err);
+        let err = h
+            .vm
+            .add_authorized_program(h.user, Pubkey::new_unique())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("MultisigThresholdNotMet"), "{}", err);
+        let response = handlers::withdraw_collateral(
+            Extension(h.vm.clone()),
+            Extension(db.clone()),
+            Extension(tracker.clone()),
+            Extension(ws.clone()),
+            OwnerJson(request(&h.user, 100)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let withdraw = || MultisigAction::Withdraw { amount: 400 };
+        let mut rx = ws.subscribe();
+        let op = multisig
+            .propose(h.user, &config, withdraw())
+            .await?
+            .expect("within the vault's limit");
+        // Any two of the three can approve; nothing is built until they have.
+        assert_eq!(op.awaiting().len(), 3);
+        assert!(op.transaction.is_none());
+        let cosigner = cosigners[2].pubkey().to_string();
+        assert_eq!(multisig.pending(Some(&cosigner)).await?.len(), 1);
+        assert!(multisig
+            .pending(Some(&Pubkey::new_unique().to_string()))
+            .await?
+            .is_empty());
+
+        let message = BASE64.decode(&op.message)?;
+        let approve = |id: String, signer: &Keypair, message: &[u8]| {
+            let req = ApproveMultisigRequest {
+                signer: signer.pubkey().to_string(),
+                signature: signer.sign_message(message).to_string(),
+            };
+            let response = handlers::approve_multisig_operation(
+                axum::extract::Path(id),
+                Extension(db.clone()),
+                Extension(tracker.clone()),
+                Extension(ws.clone()),
+                Extension(multisig.clone()),
+                Json(req),
+            );
+            async move {
+                let response = response.await;
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+                Ok::<_, anyhow::Error>((status, serde_json::from_slice::<MultisigOperation>(&body).ok()))
+            }
+        };
+
+        let (status, _) = approve(op.id.clone(), &Keypair::new(), &message).await?;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        // The owner signs the transaction, not the approval.
+        let (status, _) = approve(op.id.clone(), owner.keypair(), &message).await?;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = approve(op.id.clone(), &cosigners[2], b"something else").await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, pending) = approve(op.id.clone(), &cosigners[2], &message).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(pending.expect("operation in body").signer_approvals(), 1);
+        let (status, _) = approve(op.id.clone(), &cosigners[2], &message).await?;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // cosigners[0] never shows up. The second approval meets the
+        // threshold and the transaction is built for the approvers and owner.
+        let (_, built) = approve(op.id.clone(), &cosigners[1], &message).await?;
+        let built = built.expect("operation in body");
+        assert_eq!(built.status, MultisigStatus::Pending);
+        assert_eq!(
+            built.signing,
+            [
+                cosigners[2].pubkey().to_string(),
+                cosigners[1].pubkey().to_string(),
+                h.user.to_string()
+            ]
+        );
+        let tx: Transaction =
+            bincode::deserialize(&BASE64.decode(built.transaction.as_deref().unwrap())?)?;
+        let nonce = back::chain::durable_nonce(&tx).expect("built on a durable nonce");
+        let tx_message = BASE64.decode(built.transaction_message.as_deref().unwrap())?;
+        assert_eq!(tx_message, tx.message_data());
+
+        let (status, _) = approve(op.id.clone(), &cosigners[0], &message).await?;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = approve(op.id.clone(), &cosigners[1], &message).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (_, pending) = approve(op.id.clone(), &cosigners[1], &tx_message).await?;
+        assert_eq!(pending.expect("operation in body").awaiting().len(), 2);
+        approve(op.id.clone(), owner.keypair(), &tx_message).await?;
+        assert_eq!(tracker.get_vault_balance(h.user).await?.total_balance, 1_000);
+
+        // The durable nonce outlives any blockhash, so slow signers still land.
+        h.chain.expire_blockhashes();
+        let (_, executed) = approve(op.id.clone(), &cosigners[2], &tx_message).await?;
+        let executed = executed.expect("operation in body");
+        assert_eq!(executed.status, MultisigStatus::Executed);
+        assert!(executed.tx_signature.is_some());
+        assert_eq!(tracker.get_vault_balance(h.user).await?.total_balance, 600);
+        assert!(h.chain.get_account(&nonce).await.is_err());
+        let (status, _) = approve(op.id.clone(), &cosigners[0], &message).await?;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let mut requested = 0;
+        let mut statuses = Vec::new();
+        while let Ok(json) = rx.try_recv() {
+            match serde_json::from_str(&json) {
+                Ok(WsMessage::MultisigSignatureRequested { .. }) => requested += 1,
+                Ok(WsMessage::MultisigOperationUpdated { status, .. }) => statuses.push(status),
+                _ => {}
+            }
+        }
+        // Once for the approvals, once for the transaction.
+        assert_eq!(requested, 2);
+        assert_eq!(
+            statuses,
+            [
+                MultisigStatus::Pending,
+                MultisigStatus::Pending,
+                MultisigStatus::Pending,
+                MultisigStatus::Pending,
+                MultisigStatus::Executed
+            ]
+        );
+
+        // An operation whose nonce moved on can no longer land.
+        let stale = multisig
+            .propose(h.user, &config, withdraw())
+            .await?
+            .expect("within the vault's limit");
+        let message = BASE64.decode(&stale.message)?;
+        approve(stale.id.clone(), &cosigners[0], &message).await?;
+        let (_, built) = approve(stale.id.clone(), &cosigners[1], &message).await?;
+        let built = built.expect("operation in body");
+        let tx: Transaction =
+            bincode::deserialize(&BASE64.decode(built.transaction.as_deref().unwrap())?)?;
+        let nonce = back::chain::durable_nonce(&tx).expect("built on a durable nonce");
+        h.vm.close_nonce_account(nonce).await?;
+        let tx_message = BASE64.decode(built.transaction_message.as_deref().unwrap())?;
+        let (status, _) = approve(stale.id.clone(), &cosigners[0], &tx_message).await?;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let stale = multisig.get(&stale.id).await?.expect("stored");
+        assert_eq!(stale.status, MultisigStatus::Expired);
+        assert!(multisig.pending(None).await?.is_empty());
+
+        println!("✅ Any threshold of co-signers approve; their signatures are verified and submitted once");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multisig_operations_are_capped_cancelled_and_expired() -> Result<()> {
+        println!("🧪 TEST: Multisig Operation Limits");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let ws = Arc::new(WebSocketManager::new());
+        let limits = ProposalLimits {
+            max_open_per_vault: 2,
+            ttl_secs: 3_600,
+        };
+        let multisig = Arc::new(
+            MultisigCoordinator::new(h.vm.clone(), db.clone(), ws.clone())
+                .with_limits(limits.clone()),
+        );
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        let cosigners = [Keypair::new(), Keypair::new()];
+        h.vm.initialize_multisig(h.user, cosigners.iter().map(|k| k.pubkey()).collect(), 2)
+            .await?;
+        let config = h.vm.get_multisig_config(h.user).await?;
+
+        let propose = || {
+            handlers::propose_multisig_operation(
+                axum::extract::Path(h.user.to_string()),
+                Extension(h.vm.clone()),
+                Extension(db.clone()),
+                Extension(multisig.clone()),
+                AuthenticatedWallet(h.user),
+                Json(ProposeMultisigRequest {
+                    action: MultisigAction::Withdraw { amount: 100 },
+                }),
+            )
+        };
+        let cancel = |id: String, wallet: Pubkey| {
+            handlers::cancel_multisig_operation(
+                axum::extract::Path(id),
+                Extension(db.clone()),
+                Extension(multisig.clone()),
+                AuthenticatedWallet(wallet),
+            )
+        };
+        assert_eq!(propose().await.status(), StatusCode::OK);
+        assert_eq!(propose().await.status(), StatusCode::OK);
+        assert_eq!(propose().await.status(), StatusCode::CONFLICT);
+
+        // Approved far enough to hold a nonce account, then withdrawn.
+        let first = multisig.pending(None).await?.remove(0);
+        let message = BASE64.decode(&first.message)?;
+        for cosigner in &cosigners {
+            multisig
+                .approve(&first, cosigner.pubkey(), cosigner.sign_message(&message))
+                .await?;
+        }
+        let first = multisig.get(&first.id).await?.expect("stored");
+        let tx: Transaction =
+            bincode::deserialize(&BASE64.decode(first.transaction.as_deref().unwrap())?)?;
+        let nonce = back::chain::durable_nonce(&tx).expect("built on a durable nonce");
+        assert!(h.chain.get_account(&nonce).await.is_ok());
+
+        let response = cancel(first.id.clone(), cosigners[0].pubkey()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = cancel(first.id.clone(), h.user).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cancelled = multisig.get(&first.id).await?.expect("stored");
+        assert_eq!(cancelled.status, MultisigStatus::Cancelled);
+        assert!(h.chain.get_account(&nonce).await.is_err());
+        let response = cancel(first.id.clone(), h.user).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        // The freed slot takes a new proposal.
+        assert_eq!(propose().await.status(), StatusCode::OK);
+        assert_eq!(propose().await.status(), StatusCode::CONFLICT);
+
+        // Past the TTL, pending operations expire and free the vault.
+        let expiring = MultisigCoordinator::new(h.vm.clone(), db.clone(), ws.clone()).with_limits(
+            ProposalLimits {
+                ttl_secs: 0,
+                ..limits
+            },
+        );
+        assert!(expiring.pending(None).await?.is_empty());
+        assert!(db.get_pending_multisig_operations(None).await?.is_empty());
+        let ops = multisig
+            .propose(h.user, &config, MultisigAction::Withdraw { amount: 100 })
+            .await?;
+        assert!(ops.is_some());
+
+        println!("✅ Open operations capped per vault; cancel and TTL close their nonces");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: INDEXER AND BACKFILL
+// ============================================
+
+#[cfg(test)]
+mod indexer_tests {
+    use super::*;
+    use simulated_chain::*;
+    use axum::{http::StatusCode, Extension};
+    use back::auth::OwnerJson;
+    use back::backfill::{Backfill, BackfillState};
+    use back::balance_tracker::BalanceTracker;
+    use back::chain::ChainClient;
+    use back::db::{Database, TransactionStatus, TransactionType};
+    use back::handlers;
+    use back::indexer::EventIndexer;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_indexer_records_direct_transactions_once() -> Result<()> {
+        println!("🧪 TEST: Event Indexer");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let sig = h.vm.deposit(h.user, 400).await?;
+
+        let init = logs.recv().await?;
+        let deposit = logs.recv().await?;
+        assert_eq!(indexer.index_logs(&init).await?, 1);
+        assert_eq!(indexer.index_logs(&deposit).await?, 1);
+        // Replays (reconnects, overlapping backfill) change nothing.
+        assert_eq!(indexer.index_logs(&deposit).await?, 0);
+
+        let history = db.get_user_transactions(&h.user.to_string()).await?;
+        assert_eq!(history.len(), 2);
+        let row = history.iter().find(|t| t.signature == sig).unwrap();
+        assert!(matches!(row.tx_type, TransactionType::Deposit));
+        assert_eq!(row.amount, 400);
+        assert!(matches!(row.status, TransactionStatus::Confirmed));
+        assert_eq!(row.slot, Some(deposit.slot));
+        assert!(row.block_time.is_some());
+        assert_eq!(row.log_index, Some(0));
+
+        let events = db.get_chain_events().await?;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].event_name, "DepositEvent");
+        assert_eq!(events[1].sequence, Some(1));
+
+        println!("✅ Direct deposit indexed once with slot and block time");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_claims_rest_rows_and_splits_transfers() -> Result<()> {
+        println!("🧪 TEST: Event Indexer Merges With REST History");
+
+        let (h, db, ws, _) = setup_with_confirmer();
+        let (vm2, user2) = second_user(&h);
+        let tracker = Arc::new(BalanceTracker::new(h.vm.clone(), db.clone()));
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+
+        let response = handlers::initialize_vault(
+            Extension(h.vm.clone()),
+            Extension(db.clone()),
+            Extension(tracker.clone()),
+            Extension(ws.clone()),
+            OwnerJson(request(&h.user, 0)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        h.vm.deposit(h.user, 500).await?;
+        vm2.initialize_vault(user2, vec![h.chain.program_id()])
+            .await?;
+        // The program only accepts a vault owner as transfer operator.
+        let ix = h.vm.transfer_ix(h.user, h.user, user2, h.chain.program_id(), 200).await?;
+        let owner = h.vm.user.clone().unwrap();
+        let sig = h.vm.sender.send(&[ix], &[&*h.vm.payer, &*owner]).await?;
+
+        for _ in 0..4 {
+            indexer.index_logs(&logs.recv().await?).await?;
+        }
+
+        // The handler's row was completed in place, not duplicated.
+        let history = db.get_user_transactions(&h.user.to_string()).await?;
+        let inits: Vec<_> = history
+            .iter()
+            .filter(|t| matches!(t.tx_type, TransactionType::Initialize))
+            .collect();
+        assert_eq!(inits.len(), 1);
+        assert!(inits[0].slot.is_some());
+
+        let transfers: Vec<_> = db
+            .get_all_transactions()
+            .await?
+            .into_iter()
+            .filter(|t| t.signature == sig)
+            .collect();
+        assert_eq!(transfers.len(), 2);
+        assert!(transfers.iter().any(|t| t.user == h.user.to_string()));
+        assert!(transfers.iter().any(|t| t.user == user2.to_string()));
+
+        println!("✅ REST rows claimed and transfers indexed for both vaults");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_skips_failed_transactions() -> Result<()> {
+        println!("🧪 TEST: Event Indexer Ignores Failed Transactions");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let mut failed = logs.recv().await?;
+        failed.err = Some("custom program error: 0x1".to_string());
+
+        assert_eq!(indexer.index_logs(&failed).await?, 0);
+        assert!(db.get_chain_events().await?.is_empty());
+
+        println!("✅ Events from failed transactions are not indexed");
+        Ok(())
+    }
+
+    fn backfill_for(h: &Harness, db: &Arc<Database>) -> Arc<Backfill> {
+        let indexer = Arc::new(EventIndexer::new(
+            h.chain.clone(),
+            db.clone(),
+            h.chain.program_id(),
+        ));
+        Arc::new(Backfill::new(
+            h.chain.clone(),
+            db.clone(),
+            indexer,
+            h.chain.program_id(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_backfill_fills_gaps_and_resumes_from_checkpoint() -> Result<()> {
+        println!("🧪 TEST: Backfill From Signature History");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let backfill = backfill_for(&h, &db);
+
+        // Nothing is subscribed: these are missed while the indexer is down.
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 700).await?;
+        let last = h.vm.withdraw(h.user, 200).await?;
+
+        let progress = backfill.run(None).await?;
+        assert_eq!(progress.state, BackfillState::Completed);
+        assert_eq!(progress.addresses_total, 1);
+        assert_eq!(progress.transactions_processed, 3);
+        assert_eq!(progress.events_indexed, 3);
+        assert_eq!(db.get_user_transactions(&h.user.to_string()).await?.len(), 3);
+        let checkpoint = db
+            .get_checkpoint(&h.chain.program_id().to_string())
+            .await?
+            .expect("checkpoint");
+        assert_eq!(checkpoint.signature, last);
+
+        // The next run only fetches what landed after the checkpoint; the
+        // vault PDA is now known and walked as well.
+        h.vm.deposit(h.user, 50).await?;
+        let progress = backfill.run(None).await?;
+        assert_eq!(progress.addresses_total, 2);
+        assert_eq!(progress.events_indexed, 1);
+        assert_eq!(db.get_chain_events().await?.len(), 4);
+        assert_eq!(db.get_user_transactions(&h.user.to_string()).await?.len(), 4);
+
+        println!("✅ Missed events indexed once and checkpoints advanced");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill_rescans_from_slot() -> Result<()> {
+        println!("🧪 TEST: Backfill From a Given Slot");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let backfill = backfill_for(&h, &db);
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let from_slot = h.chain.slot() + 1;
+        h.vm.deposit(h.user, 300).await?;
+        let last = h.vm.deposit(h.user, 100).await?;
+
+        let progress = backfill.run(Some(from_slot)).await?;
+        assert_eq!(progress.transactions_processed, 2);
+        assert_eq!(db.get_chain_events().await?.len(), 2);
+        assert!(db
+            .get_user_transactions(&h.user.to_string())
+            .await?
+            .iter()
+            .all(|t| !matches!(t.tx_type, TransactionType::Initialize)));
+
+        // Rescanning older history leaves the checkpoint at the newest signature.
+        backfill.run(Some(0)).await?;
+        assert_eq!(db.get_chain_events().await?.len(), 3);
+        let checkpoint = db
+            .get_checkpoint(&h.chain.program_id().to_string())
+            .await?
+            .expect("checkpoint");
+        assert_eq!(checkpoint.signature, last);
+
+        println!("✅ Slot rescan indexed only the requested range");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill_pages_oldest_first_and_stops_at_missing_logs() -> Result<()> {
+        println!("🧪 TEST: Backfill Page By Page");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let indexer = Arc::new(EventIndexer::new(
+            h.chain.clone(),
+            db.clone(),
+            h.chain.program_id(),
+        ));
+        let backfill = Arc::new(
+            Backfill::new(h.chain.clone(), db.clone(), indexer, h.chain.program_id())
+                .with_page_size(2),
+        );
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let mut signatures = Vec::new();
+        for amount in [100, 200, 300, 400] {
+            signatures.push(h.vm.deposit(h.user, amount).await?);
+        }
+
+        // Pages are [4, 3], [2, 1], [init]; the oldest two are indexed
+        // before the node fails to return deposit 3.
+        h.chain.prune_transactions(&[signatures[2].parse()?]);
+        let err = backfill.run(None).await.unwrap_err();
+        assert!(err.to_string().contains(&signatures[2]), "{}", err);
+        let progress = backfill.progress().await;
+        assert_eq!(progress.signatures_found, 5);
+        assert_eq!(progress.transactions_processed, 3);
+        let checkpoint = db
+            .get_checkpoint(&h.chain.program_id().to_string())
+            .await?
+            .expect("checkpoint");
+        assert_eq!(checkpoint.signature, signatures[1]);
+
+        // Once the node serves it again the program ID resumes from its
+        // checkpoint; the vault PDA, now known, is walked from the start.
+        h.chain.prune_transactions(&[]);
+        let progress = backfill.run(None).await?;
+        assert_eq!(progress.addresses_total, 2);
+        assert_eq!(progress.transactions_processed, 2 + 5);
+        assert_eq!(db.get_chain_events().await?.len(), 5);
+        let checkpoint = db
+            .get_checkpoint(&h.chain.program_id().to_string())
+            .await?
+            .expect("checkpoint");
+        assert_eq!(checkpoint.signature, signatures[3]);
+
+        println!("✅ Missing logs stop the run before them; nothing is skipped");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill_endpoint_reports_progress() -> Result<()> {
+        println!("🧪 TEST: Admin Backfill Endpoint");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let backfill = backfill_for(&h, &db);
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let response = handlers::start_backfill(Extension(backfill.clone()), None).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let response = handlers::start_backfill(Extension(backfill.clone()), None).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let mut progress = backfill.progress().await;
+        for _ in 0..100 {
+            if progress.state != BackfillState::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            progress = backfill.progress().await;
+        }
+        assert_eq!(progress.state, BackfillState::Completed);
+        assert_eq!(progress.events_indexed, 1);
+        assert!(progress.finished_at.is_some());
+
+        println!("✅ Backfill runs in the background and reports progress");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_lag_triggers_backfill() -> Result<()> {
+        println!("🧪 TEST: Backfill After Subscription Lag");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let indexer = Arc::new(EventIndexer::new(
+            h.chain.clone(),
+            db.clone(),
+            h.chain.program_id(),
+        ));
+        let backfill = Arc::new(Backfill::new(
+            h.chain.clone(),
+            db.clone(),
+            indexer.clone(),
+            h.chain.program_id(),
+        ));
+        backfill.follow_indexer();
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 700).await?;
+
+        // A subscription that overflowed: the indexer only sees the lag.
+        let (tx, rx) = tokio::sync::broadcast::channel(1);
+        for _ in 0..2 {
+            tx.send(back::chain::ProgramLogs {
+                signature: Signature::default().to_string(),
+                slot: 0,
+                err: None,
+                logs: Vec::new(),
+            })?;
+        }
+        drop(tx);
+        indexer.run(rx).await;
+
+        let mut progress = backfill.progress().await;
+        for _ in 0..100 {
+            if progress.state == BackfillState::Completed {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            progress = backfill.progress().await;
+        }
+        assert_eq!(progress.state, BackfillState::Completed);
+        assert_eq!(progress.events_indexed, 2);
+        assert_eq!(db.get_chain_events().await?.len(), 2);
+
+        println!("✅ Skipped transactions recovered from the checkpoint");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: RECONCILER
+// ============================================
+
+#[cfg(test)]
+mod reconciler_tests {
+    use super::*;
+    use simulated_chain::*;
+    use axum::{http::StatusCode, Extension, Json};
+    use back::admin::{Operator, Role};
+    use back::chain::ChainClient;
+    use back::db::{Database, ReconciliationSource, ReconciliationStatus};
+    use back::handlers::{self, ReconciliationQuery, ResolveRequest};
+    use back::indexer::EventIndexer;
+    use back::reconciler::Reconciler;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_reconciler_matches_every_vault() -> Result<()> {
+        println!("🧪 TEST: Full-Program Reconciliation");
+
+        let h = setup();
+        let (vm2, user2) = second_user(&h);
+        let db = Arc::new(Database::new(None));
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 700).await?;
+        vm2.initialize_vault(user2, vec![h.chain.program_id()])
+            .await?;
+        let ix = h.vm.transfer_ix(h.user, h.user, user2, h.chain.program_id(), 250).await?;
+        let owner = h.vm.user.clone().unwrap();
+        h.vm.sender.send(&[ix], &[&*h.vm.payer, &*owner]).await?;
+        for _ in 0..4 {
+            indexer.index_logs(&logs.recv().await?).await?;
+        }
+
+        let report = Reconciler::new(h.chain.clone(), db.clone(), h.chain.program_id())
+            .run()
+            .await?;
+        assert_eq!(report.vaults, 2);
+        assert_eq!(report.slot, h.chain.slot());
+        // Token account and events per vault; no rows without Postgres.
+        assert_eq!(report.checks, 4);
+        let logs = db.get_reconciliation_logs(None).await?;
+        assert_eq!(logs.len(), 4);
+        assert!(logs.iter().all(|l| l.slot == Some(report.slot)));
+        assert!(logs
+            .iter()
+            .filter(|l| l.source == ReconciliationSource::Events)
+            .all(|l| l.status == ReconciliationStatus::Match));
+
+        // transfer_collateral moves accounting balances only; the tokens stay
+        // in the source vault's token account.
+        let mut token: Vec<i64> = report
+            .mismatches
+            .iter()
+            .map(|l| {
+                assert_eq!(l.source, ReconciliationSource::VaultToken);
+                l.discrepancy
+            })
+            .collect();
+        token.sort();
+        assert_eq!(token, [-250, 250]);
+
+        println!("✅ Event history matches; transferred tokens flagged on both vaults");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconciler_skips_legacy_vaults() -> Result<()> {
+        println!("🧪 TEST: Reconciliation With An Unmigrated Vault");
+
+        let h = setup();
+        let (vm2, user2) = second_user(&h);
+        let db = Arc::new(Database::new(None));
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 300).await?;
+        vm2.initialize_vault(user2, vec![h.chain.program_id()])
+            .await?;
+        for _ in 0..3 {
+            indexer.index_logs(&logs.recv().await?).await?;
+        }
+        let legacy = rewrite_as_legacy(&h, user2).await?;
+
+        let reconciler = Reconciler::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let report = reconciler.run().await?;
+        assert_eq!(report.vaults, 1);
+        assert_eq!(report.unreadable_vaults, [legacy.to_string()]);
+        assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+
+        // Later runs only read the events after the summed ones.
+        h.vm.deposit(h.user, 200).await?;
+        indexer.index_logs(&logs.recv().await?).await?;
+        let report = reconciler.run().await?;
+        assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+        h.vm.deposit(h.user, 50).await?;
+        let report = reconciler.run().await?;
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].source, ReconciliationSource::Events);
+        assert_eq!(report.mismatches[0].discrepancy, 50);
+
+        println!("✅ Legacy vault listed, not fatal; event sums carried between runs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconciler_flags_mismatches_until_resolved() -> Result<()> {
+        println!("🧪 TEST: Reconciliation Mismatch Workflow");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let reconciler = Arc::new(Reconciler::new(
+            h.chain.clone(),
+            db.clone(),
+            h.chain.program_id(),
+        ));
+        // Nothing is indexed, so the deposit is missing from the event sum.
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 400).await?;
+
+        // Tokens sent straight to the vault's token account bypass the program.
+        let program_id = h.chain.program_id();
+        let (vault_token, _) =
+            Pubkey::find_program_address(&[b"vault_token", h.user.as_ref()], &program_id);
+        let mut account = h.chain.get_account(&vault_token).await?;
+        account.data[64..72].copy_from_slice(&450u64.to_le_bytes());
+        h.chain.set_account(vault_token, account);
+
+        let response = handlers::run_reconciliation(Extension(reconciler)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let open = db
+            .get_reconciliation_logs(Some(ReconciliationStatus::Mismatch))
+            .await?;
+        assert_eq!(open.len(), 2);
+        let token = open
+            .iter()
+            .find(|l| l.source == ReconciliationSource::VaultToken)
+            .unwrap();
+        assert_eq!((token.onchain_balance, token.offchain_balance), (400, 450));
+        assert_eq!(token.discrepancy, -50);
+        let events = open
+            .iter()
+            .find(|l| l.source == ReconciliationSource::Events)
+            .unwrap();
+        assert_eq!(events.discrepancy, 400);
+
+        let resolve = |id: String| {
+            handlers::resolve_reconciliation(
+                axum::extract::Path(id),
+                Extension(db.clone()),
+                Extension(Operator {
+                    name: "oncall".to_string(),
+                    role: Role::Operator,
+                }),
+                Json(ResolveRequest {
+                    action: "Donation swept to treasury".to_string(),
+                }),
+            )
+        };
+        assert_eq!(resolve(token.id.clone()).await.status(), StatusCode::OK);
+        // Already resolved.
+        assert_eq!(resolve(token.id.clone()).await.status(), StatusCode::NOT_FOUND);
+
+        let resolved = db
+            .get_reconciliation_logs(Some(ReconciliationStatus::Resolved))
+            .await?;
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(
+            resolved[0].resolution_action.as_deref(),
+            Some("Donation swept to treasury")
+        );
+        assert!(resolved[0].resolved_at.is_some());
+        let audit = db.get_audit_logs(Some(&h.user.to_string())).await?;
+        assert!(audit
+            .iter()
+            .any(|a| a.action == "RECONCILIATION_RESOLVED" && a.details.contains("oncall")));
+
+        let response = handlers::get_reconciliation_logs(
+            Extension(db.clone()),
+            axum::extract::Query(ReconciliationQuery {
+                status: Some(ReconciliationStatus::Mismatch),
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            db.get_reconciliation_logs(Some(ReconciliationStatus::Mismatch))
+                .await?
+                .len(),
+            1
+        );
+
+        println!("✅ Mismatches logged with discrepancy and closed through the API");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: IDEMPOTENCY
+// ============================================
+
+#[cfg(test)]
+mod idempotency_tests {
+    use super::*;
+    use simulated_chain::*;
+    use back::auth::{AuthConfig, AuthService};
+    use back::balance_tracker::BalanceTracker;
+    use back::db::{Database, IdempotencyRecord, IdempotencyStatus};
+    use back::idempotency::{Idempotency, IdempotencyConfig};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_idempotency_key_replays_first_result() -> Result<()> {
+        println!("🧪 TEST: Idempotent POSTs");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let base = serve(&h, &db, auth.clone()).await?;
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let owner = h.vm.user.clone().unwrap();
+        let challenge = auth.challenge(h.user).await?;
+        let signature = owner.keypair().sign_message(challenge.message.as_bytes());
+        let session = auth.verify(h.user, &challenge.nonce, &signature).await?;
+
+        let client = reqwest::Client::new();
+        let deposit = |key: &str, amount: u64, token: Option<&str>| {
+            let mut req = client
+                .post(format!("{}/deposit", base))
+                .header("idempotency-key", key)
+                .json(&serde_json::json!({ "user_pubkey": h.user.to_string(), "amount": amount }));
+            if let Some(token) = token {
+                req = req.bearer_auth(token);
+            }
+            req.send()
+        };
+        let token = Some(session.token.as_str());
+
+        let first = deposit("deposit-1", 300, token).await?;
+        assert_eq!(first.status().as_u16(), 200);
+        assert!(first.headers().get("idempotent-replayed").is_none());
+        let first: serde_json::Value = first.json().await?;
+        let retry = deposit("deposit-1", 300, token).await?;
+        assert_eq!(retry.status().as_u16(), 200);
+        assert_eq!(retry.headers()["idempotent-replayed"], "true");
+        assert_eq!(retry.json::<serde_json::Value>().await?, first);
+        let tracker = BalanceTracker::new(h.vm.clone(), db.clone());
+        assert_eq!(tracker.get_vault_balance(h.user).await?.total_balance, 300);
+
+        let reused = deposit("deposit-1", 500, token).await?;
+        assert_eq!(reused.status().as_u16(), 422);
+
+        // A rejected request leaves the key free for the corrected retry.
+        assert_eq!(deposit("deposit-2", 200, None).await?.status().as_u16(), 401);
+        assert_eq!(deposit("deposit-2", 200, token).await?.status().as_u16(), 200);
+        assert_eq!(tracker.get_vault_balance(h.user).await?.total_balance, 500);
+
+        // A retry while the first attempt is still running is told so.
+        let body = serde_json::json!({ "user_pubkey": h.user.to_string(), "amount": 100 });
+        let hash = anchor_client::solana_sdk::hash::hashv(&[
+            b"POST",
+            b"/deposit",
+            b"",
+            &serde_json::to_vec(&body)?,
+        ]);
+        let now = chrono::Utc::now().timestamp();
+        let claim = |key: &str, created_at: i64| IdempotencyRecord {
+            wallet: h.user.to_string(),
+            key: key.to_string(),
+            request_hash: hash.to_string(),
+            status: IdempotencyStatus::InProgress,
+            response_status: None,
+            response_body: None,
+            tx_signature: None,
+            created_at,
+            completed_at: None,
+        };
+        db.claim_idempotency_key(claim("deposit-3", now), 0, 0).await?;
+        assert_eq!(deposit("deposit-3", 100, token).await?.status().as_u16(), 409);
+        assert_eq!(tracker.get_vault_balance(h.user).await?.total_balance, 500);
+
+        // A claim left in progress past its lease was abandoned; a retry takes it over.
+        let lease = IdempotencyConfig::default().lease_secs as i64;
+        db.claim_idempotency_key(claim("deposit-4", now - lease - 1), 0, 0)
+            .await?;
+        assert_eq!(deposit("deposit-4", 100, token).await?.status().as_u16(), 200);
+        assert_eq!(tracker.get_vault_balance(h.user).await?.total_balance, 600);
+
+        // Keys belong to the session's wallet; another wallet's same key is its own.
+        let other = Keypair::new();
+        let challenge = auth.challenge(other.pubkey()).await?;
+        let signature = other.sign_message(challenge.message.as_bytes());
+        let other_session = auth
+            .verify(other.pubkey(), &challenge.nonce, &signature)
+            .await?;
+        // Their vault does not exist, so the deposit fails before anything is
+        // sent; the 5xx frees the key and the retry runs again rather than
+        // replaying the failure.
+        for _ in 0..2 {
+            let theirs = client
+                .post(format!("{}/deposit", base))
+                .header("idempotency-key", "deposit-1")
+                .bearer_auth(&other_session.token)
+                .json(&serde_json::json!({ "user_pubkey": other.pubkey().to_string(), "amount": 300 }))
+                .send()
+                .await?;
+            assert_eq!(theirs.status().as_u16(), 500);
+            assert!(theirs.headers().get("idempotent-replayed").is_none());
+        }
+        let ours = deposit("deposit-1", 300, token).await?;
+        assert_eq!(ours.headers()["idempotent-replayed"], "true");
+
+        // A bad session is turned away before the key is looked at.
+        assert_eq!(deposit("deposit-1", 300, Some("bogus")).await?.status().as_u16(), 401);
+
+        println!("✅ Retries replay, conflict or are rejected, never resent");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_kept_after_a_send() -> Result<()> {
+        println!("🧪 TEST: Idempotent Failure After A Send");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let owner = h.vm.user.clone().unwrap();
+        let challenge = auth.challenge(h.user).await?;
+        let signature = owner.keypair().sign_message(challenge.message.as_bytes());
+        let session = auth.verify(h.user, &challenge.nonce, &signature).await?;
+
+        // A handler that deposits, then fails as if its database write had.
+        let vm = h.vm.clone();
+        let user = h.user;
+        let app = axum::Router::new()
+            .route(
+                "/flaky",
+                axum::routing::post(move || async move {
+                    match vm.deposit(user, 300).await {
+                        Ok(_) => (
+                            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                            axum::Json(serde_json::json!({ "error": "database write failed" })),
+                        ),
+                        Err(e) => (
+                            axum::http::StatusCode::BAD_GATEWAY,
+                            axum::Json(serde_json::json!({ "error": e.to_string() })),
+                        ),
+                    }
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(Idempotency::new(db.clone(), IdempotencyConfig::default())),
+                back::idempotency::guard,
+            ))
+            .layer(axum::Extension(auth.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let client = reqwest::Client::new();
+        let post = || {
+            client
+                .post(format!("{}/flaky", base))
+                .header("idempotency-key", "flaky-1")
+                .bearer_auth(&session.token)
+                .send()
+        };
+        let first = post().await?;
+        assert_eq!(first.status().as_u16(), 500);
+        let first: serde_json::Value = first.json().await?;
+        assert_eq!(first["error"], "database write failed");
+        let sent = first["tx_signature"].as_str().expect("signature of the deposit");
+
+        // The deposit went out, so the retry replays the failure instead of
+        // depositing again.
+        let retry = post().await?;
+        assert_eq!(retry.status().as_u16(), 500);
+        assert_eq!(retry.headers()["idempotent-replayed"], "true");
+        assert_eq!(retry.json::<serde_json::Value>().await?, first);
+        let tracker = BalanceTracker::new(h.vm.clone(), db.clone());
+        assert_eq!(tracker.get_vault_balance(h.user).await?.total_balance, 300);
+        let probe = IdempotencyRecord {
+            wallet: h.user.to_string(),
+            key: "flaky-1".to_string(),
+            request_hash: String::new(),
+            status: IdempotencyStatus::InProgress,
+            response_status: None,
+            response_body: None,
+            tx_signature: None,
+            created_at: chrono::Utc::now().timestamp(),
+            completed_at: None,
+        };
+        let record = db.claim_idempotency_key(probe, 0, 0).await?.expect("kept");
+        assert_eq!(record.status, IdempotencyStatus::Completed);
+        assert_eq!(record.tx_signature.as_deref(), Some(sent));
+
+        println!("✅ A failure after a broadcast is kept with its signature and replayed");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: HISTORY AND STATEMENTS
+// ============================================
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+    use simulated_chain::*;
+    use back::auth::{AuthConfig, AuthService};
+    use back::balance_tracker::BalanceTracker;
+    use back::chain::ChainClient;
+    use back::cpi_manager::CPIManager;
+    use back::db::{BalanceSnapshot, Database, TransactionRecord, TransactionStatus, TransactionType};
+    use back::indexer::EventIndexer;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_transaction_history_pages_by_cursor() -> Result<()> {
+        println!("🧪 TEST: Transaction History Paging");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let base = serve(&h, &db, auth).await?;
+        let other = Pubkey::new_unique().to_string();
+        // Two rows share each second, so paging has to break ties by id.
+        for i in 0..10u64 {
+            let tx_type = if i % 3 == 0 {
+                TransactionType::Withdraw
+            } else {
+                TransactionType::Deposit
+            };
+            for user in [h.user.to_string(), other.clone()] {
+                db.insert_transaction(TransactionRecord {
+                    id: format!("00000000-0000-0000-0000-{:012}", i),
+                    user,
+                    tx_type: tx_type.clone(),
+                    amount: (i + 1) * 100,
+                    signature: Signature::new_unique().to_string(),
+                    status: TransactionStatus::Confirmed,
+                    timestamp: 1_700_000_000 + (i / 2) as i64,
+                    slot: None,
+                    block_time: None,
+                    log_index: None,
+                    error: None,
+                    confirmed_at: None,
+                })
+                .await?;
+            }
+        }
+
+        let client = reqwest::Client::new();
+        let page = |query: &str| {
+            client
+                .get(format!("{}/vault/transactions/{}?{}", base, h.user, query))
+                .send()
+        };
+        let amounts = |body: &serde_json::Value| -> Vec<u64> {
+            body["transactions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tx| tx["amount"].as_u64().unwrap())
+                .collect()
+        };
+
+        // Newest first, four at a time, until there is no cursor left.
+        let mut seen = Vec::new();
+        let mut query = "limit=4".to_string();
+        loop {
+            let body: serde_json::Value = page(&query).await?.json().await?;
+            assert_eq!(body["total"], 10);
+            seen.extend(amounts(&body));
+            match body["next_cursor"].as_str() {
+                Some(cursor) => query = format!("limit=4&cursor={}", cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, (1..=10).rev().map(|i| i * 100).collect::<Vec<_>>());
+
+        let body: serde_json::Value = page("tx_type=Deposit&min_amount=300&to=1700000004&order=asc")
+            .await?
+            .json()
+            .await?;
+        assert_eq!(amounts(&body), [300, 500, 600, 800]);
+        assert_eq!(body["total"], 4);
+        assert!(body["next_cursor"].is_null());
+
+        for bad in ["cursor=nope", "limit=0", "min_amount=5&max_amount=1", "from=2&to=2"] {
+            assert_eq!(page(bad).await?.status().as_u16(), 400, "{}", bad);
+        }
+
+        let stats = db.get_transaction_stats(1_700_000_004).await?;
+        assert_eq!(stats.total_transactions, 20);
+        assert_eq!(stats.active_users, 2);
+        assert_eq!(db.count_user_transactions(&other).await?, 10);
+
+        println!("✅ History pages through every row exactly once");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_statement_reconciles_indexed_movements() -> Result<()> {
+        println!("🧪 TEST: Account Statement");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let base = serve(&h, &db, auth).await?;
+        let (vm2, user2) = second_user(&h);
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+        let tracker = BalanceTracker::new(h.vm.clone(), db.clone());
+        let cpi = CPIManager::new(h.vm.clone());
+        let start = h.chain.unix_timestamp();
+
+        // Before the period: the opening balance, and a snapshot of it.
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        tracker.get_vault_balance(h.user).await?;
+
+        h.chain.advance_clock(3_600);
+        cpi.lock_for_position(h.user, 300, "pos-1".to_string())
+            .await?;
+        let vault = h.vm.vault_address(&h.user).await?;
+        // Read after the lock; the closing balance is checked against this
+        // carried past the later entries.
+        db.insert_snapshot(BalanceSnapshot {
+            id: "mid-period".to_string(),
+            user: h.user.to_string(),
+            vault: vault.to_string(),
+            total_balance: 1_000,
+            locked_balance: 300,
+            available_balance: 700,
+            sequence: Some(2),
+            timestamp: h.chain.unix_timestamp(),
+        })
+        .await?;
+        cpi.unlock_after_close(h.user, 300, "pos-1".to_string())
+            .await?;
+        h.vm.withdraw(h.user, 200).await?;
+        vm2.initialize_vault(user2, vec![h.chain.program_id()])
+            .await?;
+        // The owner pays for this one, so it gets a fee line.
+        let ix = h.vm.transfer_ix(h.user, h.user, user2, h.chain.program_id(), 150).await?;
+        let owner = h.vm.user.clone().unwrap();
+        let transfer = h.vm.sender.send(&[ix], &[&*owner]).await?;
+
+        // After the period.
+        h.chain.advance_clock(3_600);
+        h.vm.deposit(h.user, 5).await?;
+        for _ in 0..8 {
+            indexer.index_logs(&logs.recv().await?).await?;
+        }
+
+        let (from, to) = (start + 3_600, start + 7_200);
+        let client = reqwest::Client::new();
+        let statement = |query: String| {
+            client
+                .get(format!("{}/vault/{}/statement?{}", base, h.user, query))
+                .send()
+        };
+        let body: serde_json::Value = statement(format!("from={}&to={}", from, to))
+            .await?
+            .json()
+            .await?;
+        assert_eq!(body["opening_balance"], 1_000);
+        assert_eq!(body["closing_balance"], 650);
+        let entries: Vec<_> = body["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| (e["entry_type"].as_str().unwrap(), e["balance"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("lock", 1_000),
+                ("unlock", 1_000),
+                ("withdrawal", 800),
+                ("transfer_out", 650),
+                ("fee", 650)
+            ]
+        );
+        assert_eq!(body["totals"]["withdrawals"], 200);
+        assert_eq!(body["totals"]["transfers_out"], 150);
+        assert_eq!(body["totals"]["fees_lamports"], 5_000);
+        assert_eq!(body["totals"]["fees_unavailable"], 0);
+        assert_eq!(body["entries"][4]["amount"], 0);
+        assert_eq!(body["entries"][4]["fee_lamports"], 5_000);
+        let reconciliation = &body["reconciliation"];
+        assert_eq!(reconciliation["opening_snapshot"]["total_balance"], 1_000);
+        assert_eq!(reconciliation["closing_check"]["source"], "snapshot");
+        assert_eq!(reconciliation["closing_check"]["read_balance"], 1_000);
+        assert_eq!(reconciliation["closing_check"]["expected_balance"], 650);
+        assert_eq!(reconciliation["balanced"], true);
+
+        // A period running to the present is checked against the vault account.
+        let now = h.chain.unix_timestamp();
+        let body: serde_json::Value = statement(format!("from={}&to={}", from, now + 1))
+            .await?
+            .json()
+            .await?;
+        assert_eq!(body["closing_balance"], 655);
+        assert_eq!(body["reconciliation"]["closing_check"]["source"], "chain");
+        assert_eq!(body["reconciliation"]["closing_check"]["read_balance"], 655);
+        assert_eq!(body["reconciliation"]["balanced"], true);
+
+        // The recipient's statement shows the other side of the transfer.
+        let incoming: serde_json::Value = client
+            .get(format!(
+                "{}/vault/{}/statement?from={}&to={}",
+                base, user2, from, to
+            ))
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(incoming["entries"][0]["entry_type"], "transfer_in");
+        assert_eq!(incoming["closing_balance"], 150);
+        // Nothing to check a past period against without a snapshot from it.
+        assert!(incoming["reconciliation"]["closing_check"].is_null());
+        assert_eq!(incoming["reconciliation"]["balanced"], false);
+
+        let csv = statement(format!("from={}&to={}&format=csv", from, to)).await?;
+        assert_eq!(csv.headers()["content-type"], "text/csv; charset=utf-8");
+        let csv = csv.text().await?;
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 8);
+        assert!(rows[0].ends_with(",type,amount,fee_lamports,balance_change,balance"));
+        assert!(rows[1].ends_with(",opening_balance,,,,1000"));
+        assert!(rows[5].contains(",transfer_out,150,,-150,650"));
+        assert!(rows[6].contains(",fee,,5000,0,650"));
+        assert!(rows[7].ends_with(",closing_balance,,,,650"));
+
+        // A snapshot the events disagree with is reported, not hidden. It is
+        // found by vault even though another key owned the vault when it
+        // was read.
+        db.insert_snapshot(BalanceSnapshot {
+            id: "drifted".to_string(),
+            user: Pubkey::new_unique().to_string(),
+            vault: vault.to_string(),
+            total_balance: 999,
+            locked_balance: 0,
+            available_balance: 999,
+            sequence: None,
+            timestamp: to - 1,
+        })
+        .await?;
+        let body: serde_json::Value = statement(format!("from={}&to={}", from, to))
+            .await?
+            .json()
+            .await?;
+        assert_eq!(body["closing_balance"], 650);
+        assert_eq!(body["reconciliation"]["closing_check"]["read_balance"], 999);
+        assert_eq!(body["reconciliation"]["closing_check"]["matches"], false);
+        assert_eq!(body["reconciliation"]["balanced"], false);
+
+        // A fee that can no longer be read is marked, not fatal.
+        h.chain.prune_transactions(&[transfer.parse()?]);
+        let body: serde_json::Value = statement(format!("from={}&to={}", from, to))
+            .await?
+            .json()
+            .await?;
+        assert_eq!(body["closing_balance"], 650);
+        assert_eq!(body["entries"][4]["entry_type"], "fee_unavailable");
+        assert!(body["entries"][4]["fee_lamports"].is_null());
+        assert_eq!(body["totals"]["fees_lamports"], 0);
+        assert_eq!(body["totals"]["fees_unavailable"], 1);
+
+        let bad = statement(format!("from={}&to={}", to, from)).await?;
+        assert_eq!(bad.status().as_u16(), 400);
+
+        println!("✅ Statement closing balances are checked against snapshots and the chain");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_balance_at_replays_from_snapshot_and_refuses_gaps() -> Result<()> {
+        println!("🧪 TEST: Point-in-Time Balance");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let base = serve(&h, &db, auth).await?;
+        let (vm2, user2) = second_user(&h);
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+        let tracker = BalanceTracker::new(h.vm.clone(), db.clone());
+        let cpi = CPIManager::new(h.vm.clone());
+        let created = h.chain.unix_timestamp();
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        h.chain.advance_clock(100);
+        h.vm.withdraw(h.user, 100).await?;
+        let withdrawn_at = h.chain.unix_timestamp();
+        // Read at sequence 2; replays after this point start here.
+        tracker.get_vault_balance(h.user).await?;
+        h.chain.advance_clock(100);
+        cpi.lock_for_position(h.user, 400, "pos-1".to_string())
+            .await?;
+        let locked_at = h.chain.unix_timestamp();
+        h.chain.advance_clock(100);
+        cpi.unlock_after_close(h.user, 400, "pos-1".to_string())
+            .await?;
+
+        let mut deposit_slot = 0;
+        for i in 0..5 {
+            let entry = logs.recv().await?;
+            if i == 1 {
+                deposit_slot = entry.slot;
+            }
+            indexer.index_logs(&entry).await?;
+        }
+
+        let client = reqwest::Client::new();
+        let balance_at = |user: Pubkey, at: String| {
+            client
+                .get(format!("{}/vault/{}/balance", base, user))
+                .query(&[("at", at)])
+                .send()
+        };
+
+        let body: serde_json::Value = balance_at(h.user, (withdrawn_at + 50).to_string())
+            .await?
+            .json()
+            .await?;
+        assert_eq!(body["sequence"], 2);
+        assert_eq!(body["total_balance"], 900);
+        assert_eq!(body["locked_balance"], 0);
+        assert_eq!(body["available_balance"], 900);
+        assert_eq!(body["snapshot"]["sequence"], 2);
+        assert_eq!(body["events"].as_array().unwrap().len(), 0);
+
+        let at = chrono::DateTime::from_timestamp(locked_at + 50, 0)
+            .unwrap()
+            .to_rfc3339();
+        let body: serde_json::Value = balance_at(h.user, at).await?.json().await?;
+        assert_eq!(body["at"]["timestamp"], locked_at + 50);
+        assert_eq!(body["sequence"], 3);
+        assert_eq!(body["total_balance"], 900);
+        assert_eq!(body["locked_balance"], 400);
+        assert_eq!(body["available_balance"], 500);
+        assert_eq!(body["events"][0]["event"], "CollateralLocked");
+        assert_eq!(body["events"][0]["available_balance"], 500);
+
+        // Before the snapshot, the replay starts from the vault's creation.
+        let body: serde_json::Value = balance_at(h.user, format!("slot:{}", deposit_slot))
+            .await?
+            .json()
+            .await?;
+        assert_eq!(body["sequence"], 1);
+        assert_eq!(body["available_balance"], 1_000);
+        assert!(body["snapshot"].is_null());
+        let events: Vec<_> = body["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(events, ["VaultInitialized", "DepositEvent"]);
+
+        let now = h.chain.unix_timestamp().to_string();
+        let body: serde_json::Value = balance_at(h.user, now.clone()).await?.json().await?;
+        assert_eq!(body["sequence"], 4);
+        assert_eq!(body["locked_balance"], 0);
+        assert_eq!(body["available_balance"], 900);
+
+        let before = balance_at(h.user, (created - 10).to_string()).await?;
+        assert_eq!(before.status().as_u16(), 404);
+        let bad = balance_at(h.user, "yesterday".to_string()).await?;
+        assert_eq!(bad.status().as_u16(), 400);
+
+        // The indexer missed the second user's first deposit.
+        vm2.initialize_vault(user2, vec![h.chain.program_id()])
+            .await?;
+        vm2.deposit(user2, 300).await?;
+        vm2.deposit(user2, 200).await?;
+        indexer.index_logs(&logs.recv().await?).await?;
+        logs.recv().await?;
+        indexer.index_logs(&logs.recv().await?).await?;
+        let gap = balance_at(user2, now).await?;
+        assert_eq!(gap.status().as_u16(), 409);
+        let gap: serde_json::Value = gap.json().await?;
+        assert_eq!(gap["first_missing_sequence"], 1);
+        assert_eq!(gap["missing_sequences"], 1);
+
+        // A snapshot the events cannot follow from is refused, not clamped.
+        // It is found by vault though another key owned the vault when it
+        // was read.
+        db.insert_snapshot(BalanceSnapshot {
+            id: "drifted".to_string(),
+            user: Pubkey::new_unique().to_string(),
+            vault: h.vm.vault_address(&h.user).await?.to_string(),
+            total_balance: 900,
+            locked_balance: 0,
+            available_balance: 900,
+            sequence: Some(3),
+            timestamp: h.chain.unix_timestamp(),
+        })
+        .await?;
+        let inconsistent = balance_at(h.user, h.chain.unix_timestamp().to_string()).await?;
+        assert_eq!(inconsistent.status().as_u16(), 409);
+        let inconsistent: serde_json::Value = inconsistent.json().await?;
+        assert_eq!(inconsistent["sequence"], 4);
+        assert_eq!(inconsistent["balance"], "locked_balance");
+        assert_eq!(inconsistent["before"], 0);
+        assert_eq!(inconsistent["change"], -400);
+
+        println!("✅ Balances replayed from the nearest snapshot; gaps and inconsistencies refused");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: TVL AND METRICS
+// ============================================
+
+#[cfg(test)]
+mod monitoring_tests {
+    use super::*;
+    use simulated_chain::*;
+    use axum::{http::StatusCode, Extension};
+    use back::auth::{AuthConfig, AuthService};
+    use back::balance_tracker::BalanceTracker;
+    use back::chain::{ChainClient, InstrumentedChain};
+    use back::cpi_manager::CPIManager;
+    use back::db::Database;
+    use back::handlers;
+    use back::indexer::EventIndexer;
+    use back::metrics::Metrics;
+    use back::sender::{SenderConfig, TransactionSender};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_tvl_comes_from_program_account_scan() -> Result<()> {
+        println!("🧪 TEST: Authoritative TVL");
+
+        let h = setup();
+        let (vm2, user2) = second_user(&h);
+        let db = Arc::new(Database::new(None));
+        let tracker = Arc::new(BalanceTracker::new(h.vm.clone(), db));
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        CPIManager::new(h.vm.clone())
+            .lock_for_position(h.user, 400, "pos-1".to_string())
+            .await?;
+        vm2.initialize_vault(user2, vec![h.chain.program_id()])
+            .await?;
+        vm2.deposit(user2, 250).await?;
+
+        // No balance was ever queried, so nothing is cached.
+        assert!(tracker.get_cached_balance(&h.user.to_string()).await.is_none());
+
+        let response = handlers::get_tvl(Extension(tracker.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let tvl = tracker.tvl().await?;
+        assert_eq!(tvl.total_vaults, 2);
+        assert_eq!(tvl.total_value_locked, 1_250);
+        assert_eq!(tvl.total_locked, 400);
+        assert_eq!(tvl.total_available, 850);
+        assert_eq!(tvl.slot, h.chain.slot());
+        assert!(tvl.matches_database.is_none());
+
+        // Served from the last scan until the next refresh.
+        vm2.deposit(user2, 50).await?;
+        assert_eq!(tracker.tvl().await?.total_value_locked, 1_250);
+        let refreshed = tracker.refresh_tvl().await?;
+        assert_eq!(refreshed.total_value_locked, 1_300);
+        assert!(refreshed.slot > tvl.slot);
+        assert_eq!(refreshed.unreadable_vaults, 0);
+
+        // A vault not yet migrated is left out and counted, not fatal.
+        rewrite_as_legacy(&h, user2).await?;
+        let refreshed = tracker.refresh_tvl().await?;
+        assert_eq!(refreshed.total_vaults, 1);
+        assert_eq!(refreshed.unreadable_vaults, 1);
+        assert_eq!(refreshed.total_value_locked, 1_000);
+
+        println!("✅ TVL, locked, available and vault count reported with their slot; legacy vaults counted apart");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_exports_prometheus_text() -> Result<()> {
+        println!("🧪 TEST: Prometheus Metrics");
+
+        let h = setup();
+        let metrics = Arc::new(Metrics::new());
+        let chain: Arc<dyn ChainClient> =
+            Arc::new(InstrumentedChain::new(h.chain.clone(), metrics.clone()));
+        let mut vm = (*h.vm).clone().with_sender(
+            TransactionSender::new(chain.clone(), SenderConfig::default())
+                .with_metrics(metrics.clone()),
+        );
+        vm.chain = chain;
+        let h = Harness {
+            vm: Arc::new(vm),
+            ..h
+        };
+        let db = Arc::new(Database::new(None));
+        let auth = Arc::new(AuthService::new(AuthConfig::default()));
+        let base = serve_with_metrics(&h, &db, auth, metrics.clone()).await?;
+
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.vm.deposit(h.user, 1_000).await?;
+        let indexer = EventIndexer::new(h.chain.clone(), db.clone(), h.chain.program_id())
+            .with_metrics(metrics.clone());
+        let mut logs = h.chain.subscribe_logs(h.chain.program_id()).await?;
+        h.vm.withdraw(h.user, 100).await?;
+        indexer.index_logs(&logs.recv().await?).await?;
+        CPIManager::new(h.vm.clone())
+            .lock_for_position(h.user, 250, "pos-1".to_string())
+            .await?;
+
+        let client = reqwest::Client::new();
+        for path in [
+            format!("/vault/balance/{}", h.user),
+            format!("/vault/balance/{}", Pubkey::new_unique()),
+            "/no-such-route".to_string(),
+        ] {
+            client.get(format!("{}{}", base, path)).send().await?;
+        }
+
+        let response = client.get(format!("{}/metrics", base)).send().await?;
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response.headers()["content-type"]
+            .to_str()?
+            .starts_with("text/plain; version=0.0.4"));
+        let text = response.text().await?;
+        let value = |series: &str| -> Option<f64> {
+            text.lines()
+                .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+                .and_then(|v| v.parse().ok())
+        };
+
+        // One series per route template, not per vault.
+        assert!(text.contains(
+            "vault_http_request_duration_seconds_count{method=\"GET\",route=\"/vault/balance/{user}\",status=\"200\"} 1"
+        ));
+        assert!(text.contains("route=\"unmatched\",status=\"404\""));
+        assert!(!text.contains(&h.user.to_string()));
+
+        assert!(
+            value("vault_rpc_request_duration_seconds_count{method=\"sendTransaction\"}")
+                .unwrap()
+                >= 4.0
+        );
+        assert!(value("vault_rpc_request_duration_seconds_count{method=\"getSlot\"}").is_some());
+        assert_eq!(
+            value("vault_tx_send_attempts_total{outcome=\"sent\"}"),
+            Some(4.0)
+        );
+
+        assert_eq!(value("vault_tvl"), Some(900.0));
+        assert_eq!(value("vault_locked"), Some(250.0));
+        assert_eq!(value("vault_locked_ratio"), Some(250.0 / 900.0));
+        let slot = value("vault_chain_slot").unwrap();
+        let indexed = value("vault_indexer_last_slot").unwrap();
+        assert_eq!(value("vault_indexer_lag_slots"), Some(slot - indexed));
+        assert_eq!(value("vault_websocket_subscribers"), Some(0.0));
+        assert!(text.contains("# TYPE vault_active_alerts gauge"));
+
+        println!("✅ /metrics exports HTTP, RPC, send, indexer and vault series");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: TRANSACTION SENDER
+// ============================================
+
+#[cfg(test)]
+mod sender_tests {
+    use super::*;
+    use simulated_chain::*;
+    use back::db::{AttemptOutcome, Database};
+    use back::sender::{PriorityFee, TransactionSender};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_sender_sizes_compute_and_records_attempts() -> Result<()> {
+        println!("🧪 TEST: Sender Compute Budget and Attempt Log");
+
+        let (h, database) = setup_with_sender(fast_config(PriorityFee::Fixed(5_000)));
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let signature = h.vm.deposit(h.user, 1_000).await?;
+
+        let attempts = database.get_transaction_attempts(&signature).await;
+        let outcomes: Vec<AttemptOutcome> = attempts.iter().map(|a| a.outcome).collect();
+        assert_eq!(outcomes, [AttemptOutcome::Sent, AttemptOutcome::Confirmed]);
+
+        let sent = &attempts[0];
+        assert_eq!(sent.signature, signature);
+        assert_eq!(sent.rebuild, 0);
+        assert_eq!(sent.compute_unit_price, Some(5_000));
+        let limit = sent.compute_unit_limit.expect("limit recorded");
+        assert!(limit > 0 && limit < 1_400_000, "limit sized from simulation: {}", limit);
+
+        println!("✅ Deposit landed with a simulated compute limit of {}", limit);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sender_estimates_priority_fee() -> Result<()> {
+        println!("🧪 TEST: Sender Priority Fee Estimate");
+
+        let (h, database) = setup_with_sender(fast_config(PriorityFee::Estimate {
+            percentile: 50,
+            max: 2_500,
+        }));
+        h.chain.set_prioritization_fees(vec![100, 900, 300, 10_000, 700]);
+        let signature = h
+            .vm
+            .initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let attempts = database.get_transaction_attempts(&signature).await;
+        assert_eq!(attempts[0].compute_unit_price, Some(700));
+
+        h.chain.set_prioritization_fees(vec![5_000, 8_000]);
+        let signature = h.vm.deposit(h.user, 10).await?;
+        let attempts = database.get_transaction_attempts(&signature).await;
+        assert_eq!(attempts[0].compute_unit_price, Some(2_500));
+
+        println!("✅ Median fee used and capped at the configured max");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sender_stops_on_simulation_failure() -> Result<()> {
+        println!("🧪 TEST: Sender Simulation Failure");
+
+        let (h, database) = setup_with_sender(fast_config(PriorityFee::Fixed(0)));
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let err = h.vm.withdraw(h.user, 1).await.unwrap_err();
+        assert!(err.to_string().contains("simulation failed"), "{}", err);
+
+        let attempts = database.get_all_transaction_attempts().await;
+        let last = attempts.last().expect("attempt recorded");
+        assert_eq!(last.outcome, AttemptOutcome::SimulationFailed);
+        assert!(last.error.is_some());
+        assert!(!attempts
+            .iter()
+            .any(|a| a.send_id == last.send_id && a.outcome == AttemptOutcome::Sent));
+
+        println!("✅ Failing transaction never sent");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sender_rebuilds_after_blockhash_expiry() -> Result<()> {
+        println!("🧪 TEST: Sender Rebuild After Expiry");
+
+        let h = setup();
+        let database = Arc::new(Database::new(None));
+        let chain = Arc::new(DropFirstSend::new(h.chain.clone(), false));
+        let sender = TransactionSender::new(chain, fast_config(PriorityFee::Fixed(1)))
+            .with_log(database.clone());
+        let vm = (*h.vm).clone().with_sender(sender);
+
+        let signature = vm
+            .initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let attempts = database.get_transaction_attempts(&signature).await;
+        let outcomes: Vec<(u32, AttemptOutcome)> =
+            attempts.iter().map(|a| (a.rebuild, a.outcome)).collect();
+        assert_eq!(
+            outcomes,
+            [
+                (0, AttemptOutcome::Sent),
+                (0, AttemptOutcome::Expired),
+                (1, AttemptOutcome::Sent),
+                (1, AttemptOutcome::Confirmed),
+            ]
+        );
+        assert_ne!(attempts[0].blockhash, attempts[2].blockhash);
+        assert_ne!(attempts[0].signature, signature);
+
+        println!("✅ Dropped transaction re-signed with a fresh blockhash");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sender_rechecks_status_before_rebuilding() -> Result<()> {
+        println!("🧪 TEST: Sender Status Re-check At Expiry");
+
+        let h = setup();
+        let database = Arc::new(Database::new(None));
+        let chain = Arc::new(DropFirstSend::new(h.chain.clone(), true));
+        let sender = TransactionSender::new(chain, fast_config(PriorityFee::Fixed(1)))
+            .with_log(database.clone());
+        let vm = (*h.vm).clone().with_sender(sender);
+
+        let signature = vm
+            .initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let attempts = database.get_transaction_attempts(&signature).await;
+        let outcomes: Vec<(u32, AttemptOutcome)> =
+            attempts.iter().map(|a| (a.rebuild, a.outcome)).collect();
+        assert_eq!(
+            outcomes,
+            [(0, AttemptOutcome::Sent), (0, AttemptOutcome::Confirmed)]
+        );
+        assert_eq!(attempts[0].signature, signature);
+        assert_eq!(
+            h.vm.get_authorized_programs(h.user).await?,
+            [h.chain.program_id()]
+        );
+
+        println!("✅ Transaction that landed at expiry was not re-signed");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sender_retries_failed_lookups() -> Result<()> {
+        println!("🧪 TEST: Sender Lookup Errors");
+
+        let (h, database) = setup_with_sender(fast_config(PriorityFee::Fixed(0)));
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        h.chain.fail_lookups(3);
+        let signature = h.vm.deposit(h.user, 1_000).await?;
+
+        // Resends of the same signature may be logged while lookups fail.
+        let attempts = database.get_transaction_attempts(&signature).await;
+        let last = attempts.last().expect("attempt recorded");
+        assert_eq!(last.outcome, AttemptOutcome::Confirmed);
+        assert!(attempts[..attempts.len() - 1]
+            .iter()
+            .all(|a| a.outcome == AttemptOutcome::Sent));
+
+        println!("✅ Failed status lookups retried until the deposit confirmed");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: TRANSACTION CONFIRMER
+// ============================================
+
+#[cfg(test)]
+mod confirmer_tests {
+    use super::*;
+    use simulated_chain::*;
+    use back::confirmer::{self, TransactionConfirmer};
+    use back::db::{AttemptOutcome, Database, TransactionStatus, TransactionType};
+    use back::sender::{PriorityFee, SenderConfig, TransactionSender};
+    use back::websocket::{WebSocketManager, WsMessage};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn status_pushes(
+        rx: &mut tokio::sync::broadcast::Receiver<String>,
+    ) -> Vec<(String, TransactionStatus)> {
+        let mut statuses = Vec::new();
+        while let Ok(json) = rx.try_recv() {
+            if let Ok(WsMessage::TransactionStatus {
+                signature, status, ..
+            }) = serde_json::from_str(&json)
+            {
+                statuses.push((signature, status));
+            }
+        }
+        statuses
+    }
+
+    #[tokio::test]
+    async fn test_confirmer_tracks_pending_to_finalized() -> Result<()> {
+        println!("🧪 TEST: Transaction Lifecycle Pending → Finalized");
+
+        let (h, db, ws, confirmer) = setup_with_confirmer();
+        let mut rx = ws.subscribe();
+        let signature = h
+            .vm
+            .initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let rows = db.get_transactions_by_signature(&signature).await?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].status, TransactionStatus::Pending);
+
+        assert_eq!(confirmer.poll().await?, 1);
+        let row = &db.get_transactions_by_signature(&signature).await?[0];
+        assert_eq!(row.status, TransactionStatus::Confirmed);
+        assert!(row.slot.is_some());
+        assert!(row.confirmed_at.is_some());
+        // Nothing changes until the slot is deep enough.
+        assert_eq!(confirmer.poll().await?, 0);
+
+        h.chain.advance_slots(32);
+        assert_eq!(confirmer.poll().await?, 1);
+        let row = &db.get_transactions_by_signature(&signature).await?[0];
+        assert_eq!(row.status, TransactionStatus::Finalized);
+        assert!(db.get_unsettled_transactions().await?.is_empty());
+
+        // The simulation probe is pushed first, then re-pointed to the
+        // transaction actually sent.
+        let pushes = status_pushes(&mut rx);
+        assert_eq!(pushes.len(), 4);
+        assert_eq!(pushes[0].1, TransactionStatus::Pending);
+        assert_ne!(pushes[0].0, signature);
+        assert_eq!(
+            pushes[1..],
+            [
+                (signature.clone(), TransactionStatus::Pending),
+                (signature.clone(), TransactionStatus::Confirmed),
+                (signature.clone(), TransactionStatus::Finalized),
+            ]
+        );
+
+        println!("✅ Every status change stored and pushed over WebSocket");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_confirmer_records_decoded_program_error() -> Result<()> {
+        println!("🧪 TEST: Transaction Lifecycle Failure");
+
+        let (h, db, ws, _) = setup_with_confirmer();
+        h.vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        let mut rx = ws.subscribe();
+
+        assert!(h.vm.withdraw(h.user, 1).await.is_err());
+
+        let failed: Vec<_> = db
+            .get_user_transactions(&h.user.to_string())
+            .await?
+            .into_iter()
+            .filter(|t| matches!(t.tx_type, TransactionType::Withdraw))
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, TransactionStatus::Failed);
+        assert_eq!(
+            failed[0].error.as_deref(),
+            Some("InsufficientFunds: Insufficient balance")
+        );
+        let pushes: Vec<_> = status_pushes(&mut rx).into_iter().map(|p| p.1).collect();
+        assert_eq!(pushes, [TransactionStatus::Pending, TransactionStatus::Failed]);
+
+        let code = confirmer::decode_vault_error(
+            "Error processing Instruction 2: custom program error: 0x1775",
+        );
+        assert_eq!(code.map(u32::from), Some(0x1775));
+        assert!(confirmer::decode_vault_error("Blockhash not found").is_none());
+
+        println!("✅ Failure stored with the program error name and message");
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirmer_decodes_every_vault_error() {
+        println!("🧪 TEST: Vault Error Table Covers The Program");
+
+        // Variants in declaration order, as the program assigns codes.
+        let source = include_str!("../../programs/collateral_vault/src/errors.rs");
+        let variants: Vec<&str> = source
+            .split_once("pub enum ErrorCode {")
+            .expect("ErrorCode enum")
+            .1
+            .lines()
+            .take_while(|line| *line != "}")
+            .map(str::trim)
+            .filter_map(|line| line.strip_suffix(','))
+            .filter(|name| name.chars().all(|c| c.is_ascii_alphanumeric()))
+            .collect();
+        assert!(variants.len() > 20, "parsed {:?}", variants);
+
+        for (i, name) in variants.iter().enumerate() {
+            let code = anchor_lang::error::ERROR_CODE_OFFSET + i as u32;
+            let error = format!("custom program error: {:#x}", code);
+            let decoded = confirmer::decode_vault_error(&error)
+                .unwrap_or_else(|| panic!("{} ({:#x}) is not decoded", name, code));
+            assert_eq!(decoded.name(), *name);
+        }
+
+        println!("✅ All {} ErrorCode variants decode by name", variants.len());
+    }
+
+    #[tokio::test]
+    async fn test_confirmer_follows_rebuilt_transaction() -> Result<()> {
+        println!("🧪 TEST: Transaction Lifecycle Across Rebuilds");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let chain = Arc::new(DropFirstSend::new(h.chain.clone(), false));
+        let confirmer = Arc::new(TransactionConfirmer::new(
+            chain.clone(),
+            db.clone(),
+            Arc::new(WebSocketManager::new()),
+            h.chain.program_id(),
+        ));
+        let sender = TransactionSender::new(chain, fast_config(PriorityFee::Fixed(1)))
+            .with_observer(confirmer.clone());
+        let vm = (*h.vm).clone().with_sender(sender);
+
+        let signature = vm
+            .initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        let rows = db.get_all_transactions().await?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].signature, signature);
+
+        confirmer.poll().await?;
+        let row = &db.get_transactions_by_signature(&signature).await?[0];
+        assert_eq!(row.status, TransactionStatus::Confirmed);
+
+        println!("✅ One row, moved to the signature that landed");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_confirmer_settles_send_with_unknown_status() -> Result<()> {
+        println!("🧪 TEST: Unknown Send Outcome Left To Confirmer");
+
+        let h = setup();
+        let db = Arc::new(Database::new(None));
+        let confirmer = Arc::new(TransactionConfirmer::new(
+            h.chain.clone(),
+            db.clone(),
+            Arc::new(WebSocketManager::new()),
+            h.chain.program_id(),
+        ));
+        let config = SenderConfig {
+            status_error_timeout: Duration::from_millis(50),
+            ..fast_config(PriorityFee::Fixed(0))
+        };
+        let sender = TransactionSender::new(h.chain.clone(), config)
+            .with_log(db.clone())
+            .with_observer(confirmer.clone());
+        let vm = (*h.vm).clone().with_sender(sender);
+        vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+
+        // The deposit lands, but the node cannot say so.
+        h.chain.fail_lookups(u32::MAX);
+        let signature = vm.deposit(h.user, 1_000).await?;
+        let attempts = db.get_transaction_attempts(&signature).await;
+        let last = attempts.last().expect("attempt recorded");
+        assert_eq!(last.outcome, AttemptOutcome::Unknown);
+        assert!(last.error.as_deref().is_some_and(|e| e.contains("timed out")));
+        let row = &db.get_transactions_by_signature(&signature).await?[0];
+        assert_eq!(row.status, TransactionStatus::Pending);
+
+        h.chain.fail_lookups(0);
+        confirmer.poll().await?;
+        let row = &db.get_transactions_by_signature(&signature).await?[0];
+        assert_eq!(row.status, TransactionStatus::Confirmed);
+
+        println!("✅ Send returned its signature as pending and the confirmer settled it");
+        Ok(())
+    }
+}
+
+// ============================================
+// SIMULATED CHAIN: REMOTE SIGNER
+// ============================================
+
+#[cfg(test)]
+mod signer_tests {
+    use super::*;
+    use simulated_chain::*;
+    use axum::{http::StatusCode, Json};
+    use back::vault_manager::VaultManager;
+    use back::signer::{PubkeyResponse, RemoteSigner, SignRequest, SignResponse, TransactionSigner};
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use std::sync::Arc;
+
+    /// Minimal remote signing service: advertises `advertised` and signs
+    /// anything with `keypair` when the bearer token matches. Returns its base URL.
+    async fn spawn_mock_signer(
+        keypair: Keypair,
+        advertised: Pubkey,
+        token: &'static str,
+    ) -> Result<String> {
+        use axum::extract::State;
+        use axum::http::HeaderMap;
+        use axum::routing::{get, post};
+
+        fn authorized(headers: &HeaderMap, token: &str) -> bool {
+            headers
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v == format!("Bearer {}", token))
+        }
+
+        let state = Arc::new((keypair, advertised, token));
+        let app = axum::Router::new()
+            .route(
+                "/pubkey",
+                get(
+                    |State(state): State<Arc<(Keypair, Pubkey, &'static str)>>, headers: HeaderMap| async move {
+                        if !authorized(&headers, state.2) {
+                            return Err(StatusCode::UNAUTHORIZED);
+                        }
+                        Ok(Json(PubkeyResponse {
+                            pubkey: state.1.to_string(),
+                        }))
+                    },
+                ),
+            )
+            .route(
+                "/sign",
+                post(
+                    |State(state): State<Arc<(Keypair, Pubkey, &'static str)>>,
+                     headers: HeaderMap,
+                     Json(req): Json<SignRequest>| async move {
+                        if !authorized(&headers, state.2) {
+                            return Err(StatusCode::UNAUTHORIZED);
+                        }
+                        let message = BASE64
+                            .decode(req.message)
+                            .map_err(|_| StatusCode::BAD_REQUEST)?;
+                        Ok(Json(SignResponse {
+                            signature: state.0.sign_message(&message).to_string(),
+                        }))
+                    },
+                ),
+            )
+            .with_state(state);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(format!("http://{}", addr))
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_pays_fees() -> Result<()> {
+        println!("🧪 TEST: Remote Signer");
+
+        let h = setup();
+        let payer = Keypair::new();
+        let payer_pubkey = payer.pubkey();
+        let url = spawn_mock_signer(payer, payer_pubkey, "secret-token").await?;
+
+        let remote = RemoteSigner::connect(&url, Some("secret-token".to_string())).await?;
+        assert_eq!(remote.pubkey(), payer_pubkey);
+
+        let vm = VaultManager::with_chain(
+            h.chain.clone(),
+            Arc::new(remote),
+            h.vm.user.as_ref().map(|u| u.keypair().insecure_clone()),
+            h.chain.program_id(),
+            h.mint,
+        );
+        vm.initialize_vault(h.user, vec![h.chain.program_id()])
+            .await?;
+        vm.deposit(h.user, 250).await?;
+
+        let unsigned = vm
+            .build_unsigned(h.user, &[vm.withdraw_ix(h.user, 50).await?], true)
+            .await?;
+        assert_eq!(unsigned.fee_payer, payer_pubkey.to_string());
+        assert_eq!(unsigned.required_signers, vec![h.user.to_string()]);
+
+        println!("✅ Fee payer signatures come from the remote service");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_rejects_bad_token_and_signatures() -> Result<()> {
+        println!("🧪 TEST: Remote Signer Failures");
+
+        let signing_key = Keypair::new();
+        let url = spawn_mock_signer(signing_key.insecure_clone(), signing_key.pubkey(), "secret-token").await?;
+        assert!(RemoteSigner::connect(&url, Some("wrong".to_string()))
+            .await
+            .is_err());
+        let honest = RemoteSigner::connect(&url, Some("secret-token".to_string())).await?;
+        assert!(honest.sign_message(b"hello").await.is_ok());
+
+        // A service that signs with a different key than it advertises.
+        let lying_url = spawn_mock_signer(Keypair::new(), Pubkey::new_unique(), "t").await?;
+        let lying = RemoteSigner::connect(&lying_url, Some("t".to_string())).await?;
+        let err = lying.sign_message(b"hello").await.unwrap_err();
+        assert!(err.to_string().contains("does not verify"), "{}", err);
+
+        println!("✅ Bad tokens and forged signatures are rejected");
+        Ok(())
+    }
+}
+
+// ============================================
+// CONFIGURATION TESTS
+// ============================================
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+    use back::config::{AppConfig, Cluster};
+    use back::sender::PriorityFee;
+    use back::signer::SignerConfig;
+
+    const DEVNET_USDT: &str = "36YuSPgXkeWCKQcyoibHgdHzF91CMPyhbRo22EiaWFZD";
+
+    fn load(toml: &str, args: &[&str]) -> Result<AppConfig> {
+        AppConfig::from_toml_and_args(toml, args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_profiles_sections_and_cli_overrides() -> Result<()> {
+        println!("🧪 TEST: Layered Configuration");
+
+        let mint = Pubkey::new_unique();
+        let toml = format!(
+            r#"
+cluster = "localnet"
+bind_addr = "127.0.0.1:9000"
+database_url = "postgresql://localhost/vault"
+
+[signer]
+type = "remote"
+url = "https://signer.internal"
+token = "t"
+
+[profiles.localnet]
+usdt_mint = "{mint}"
+
+[profiles.devnet]
+rpc_url = "https://devnet.example.com"
+
+[alerts]
+low_balance = 5000
+
+[monitor]
+metrics_secs = 5
+
+[features]
+vault_monitor = false
+
+[sender]
+priority_fee = {{ fixed = 1234 }}
+max_rebuilds = 1
+"#
+        );
+
+        let config = load(&toml, &[])?;
+        assert_eq!(config.cluster, Cluster::Localnet);
+        assert_eq!(config.rpc_url, "http://127.0.0.1:8899");
+        assert_eq!(config.usdt_mint, mint);
+        assert_eq!(config.program_id, collateral_vault::ID);
+        assert_eq!(config.bind_addr.port(), 9000);
+        assert!(matches!(config.signer, SignerConfig::Remote { .. }));
+        assert_eq!(config.alerts.low_balance, 5000);
+        assert_eq!(config.alerts.high_locked_ratio_percent, 80.0);
+        assert_eq!(config.monitor.metrics_secs, 5);
+        assert_eq!(config.monitor.security_secs, 30);
+        assert!(!config.features.vault_monitor);
+        assert!(config.features.balance_tracker);
+        assert!(matches!(config.sender.priority_fee, PriorityFee::Fixed(1234)));
+        assert_eq!(config.sender.max_rebuilds, 1);
+
+        // CLI flags beat the file, and switching cluster picks its profile.
+        let config = load(
+            &toml,
+            &["--cluster", "devnet", "--bind-addr=0.0.0.0:7000", "--keypair", "/tmp/id.json"],
+        )?;
+        assert_eq!(config.cluster, Cluster::Devnet);
+        assert_eq!(config.rpc_url, "https://devnet.example.com");
+        assert_eq!(config.usdt_mint.to_string(), DEVNET_USDT);
+        assert_eq!(config.bind_addr.port(), 7000);
+        assert!(matches!(config.signer, SignerConfig::File { ref path } if path == "/tmp/id.json"));
+
+        println!("✅ File, profile and CLI layers merge in order");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config_fails_fast() {
+        println!("🧪 TEST: Invalid Configuration");
+
+        let cases = [
+            ("cluster = \"testnet\"", "unknown cluster"),
+            ("database_url = \"x\"\nbogus = 1", "unknown field"),
+            ("cluster = \"mainnet\"\ndatabase_url = \"x\"", "usdt_mint must be set"),
+            ("[features]\nanalytics = true", "database_url must be set"),
+            (
+                "database_url = \"x\"\n[profiles.devnet]\nprogram_id = \"nope\"",
+                "program_id is not a valid pubkey",
+            ),
+            ("database_url = \"x\"\n[monitor]\nmetrics_secs = 0", "greater than zero"),
+            (
+                "database_url = \"x\"\n[alerts]\nhigh_locked_ratio_percent = 150.0",
+                "between 0 and 100",
+            ),
+            ("database_url = \"x\"\nbind_addr = \"localhost\"", "bind_addr"),
+            (
+                "database_url = \"x\"\n[[admin.keys]]\nname = \"ops\"\nkey = \"short\"\nrole = \"admin\"",
+                "admin.keys[0].key must be at least 16 characters",
+            ),
+            (
+                "database_url = \"x\"\n[[admin.keys]]\nname = \"ops\"\nkey = \"0123456789abcdef\"\nrole = \"root\"",
+                "unknown variant",
+            ),
+            (
+                "database_url = \"x\"\n[auth]\nsession_ttl_secs = 0",
+                "auth.nonce_ttl_secs and auth.session_ttl_secs",
+            ),
+        ];
+
+        for (toml, expected) in cases {
+            let err = match load(toml, &[]) {
+                Ok(_) => panic!("config should be rejected:\n{}", toml),
+                Err(err) => format!("{:#}", err),
+            };
+            assert!(err.contains(expected), "{:?} not in {:?}", expected, err);
+        }
+
+        let err = load("database_url = \"x\"", &["--verbose"]).err().unwrap();
+        assert!(err.to_string().contains("unknown argument"));
+
+        println!("✅ Bad values are rejected with the offending key");
+    }
+
+    #[test]
+    fn test_example_config_is_valid() -> Result<()> {
+        let config = load(include_str!("../config.example.toml"), &[])?;
+        assert_eq!(config.cluster, Cluster::Devnet);
+        assert_eq!(config.usdt_mint.to_string(), DEVNET_USDT);
+
+        let config = load(
+            include_str!("../config.example.toml"),
+            &["--cluster", "mainnet"],
+        )?;
+        assert_eq!(config.rpc_url, "https://api.mainnet-beta.solana.com");
+        Ok(())
+    }
+}
+
 // ============================================
 // INTEGRATION TEST RUNNER
 // ============================================
@@ -32,4 +32,30 @@ pub enum ErrorCode {
     InvalidVaultAuthority,
     #[msg("Withdrawal request does not match vault")]
     InvalidWithdrawalRequest,
+    #[msg("Lock activity too recent for a forced unlock")]
+    ForcedUnlockInactivityNotMet,
+    #[msg("Forced unlock not yet available")]
+    ForcedUnlockDelayNotMet,
+    #[msg("Settlement requires two distinct vaults")]
+    SameVaultSettlement,
+    #[msg("PnL exceeds the released margin")]
+    PnlExceedsMargin,
+    #[msg("Invalid guardian set or threshold")]
+    InvalidGuardianSet,
+    #[msg("Signer is not a guardian of this vault")]
+    NotGuardian,
+    #[msg("Guardian already approved this recovery")]
+    RecoveryAlreadyApproved,
+    #[msg("Not enough guardian approvals")]
+    RecoveryThresholdNotMet,
+    #[msg("Recovery not yet available")]
+    RecoveryDelayNotMet,
+    #[msg("Invalid multisig signer set or threshold")]
+    InvalidSignerSet,
+    #[msg("Vault already uses the current layout")]
+    AlreadyMigrated,
+    #[msg("A forced unlock is already pending")]
+    ForcedUnlockPending,
+    #[msg("Not enough multisig co-signers signed")]
+    MultisigThresholdNotMet,
 }
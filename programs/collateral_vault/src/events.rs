@@ -1,16 +1,44 @@
+use crate::state::SettlementBucket;
 use anchor_lang::prelude::*;
 
+// Every event carries the vault it touched, the signer that triggered it
+// (`actor`), the authorized program it came through (`caller_program`, `None`
+// for direct owner calls) and the vault's `sequence` after the change, so
+// indexers can order events within a slot and detect gaps.
+
 #[event]
 pub struct VaultInitialized {
     pub user: Pubkey,
     pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub amount: u64,
     pub timestamp: i64,
 }
 
+/// A vault created before events carried sequences, moved to the current
+/// layout. Its balances are the starting point for replaying later events.
+#[event]
+pub struct VaultMigrated {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub total_balance: u64,
+    pub locked_balance: u64,
+    pub available_balance: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct DepositEvent {
     pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub amount: u64,
     pub new_balance: u64,
     pub timestamp: i64,
@@ -19,6 +47,10 @@ pub struct DepositEvent {
 #[event]
 pub struct WithdrawEvent {
     pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub amount: u64,
     pub new_balance: u64,
     pub timestamp: i64,
@@ -27,6 +59,10 @@ pub struct WithdrawEvent {
 #[event]
 pub struct CollateralLocked {
     pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub amount: u64,
     pub new_locked_balance: u64,
     pub timestamp: i64,
@@ -35,6 +71,10 @@ pub struct CollateralLocked {
 #[event]
 pub struct CollateralUnlocked {
     pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub amount: u64,
     pub new_available_balance: u64,
     pub timestamp: i64,
@@ -44,6 +84,10 @@ pub struct CollateralUnlocked {
 pub struct CollateralTransferred {
     pub from_vault: Pubkey,
     pub to_vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub from_sequence: u64,
+    pub to_sequence: u64,
     pub amount: u64,
     pub timestamp: i64,
 }
@@ -51,6 +95,9 @@ pub struct CollateralTransferred {
 #[event]
 pub struct AuthorizedProgramAdded {
     pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub program: Pubkey,
     pub timestamp: i64,
 }
@@ -58,6 +105,9 @@ pub struct AuthorizedProgramAdded {
 #[event]
 pub struct AuthorizedProgramRemoved {
     pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub program: Pubkey,
     pub timestamp: i64,
 }
@@ -66,6 +116,9 @@ pub struct AuthorizedProgramRemoved {
 pub struct WithdrawalRequested {
     pub user: Pubkey,
     pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub amount: u64,
     pub available_at: i64,
     pub timestamp: i64,
@@ -75,6 +128,9 @@ pub struct WithdrawalRequested {
 pub struct WithdrawalExecuted {
     pub user: Pubkey,
     pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub amount: u64,
     pub timestamp: i64,
 }
@@ -82,7 +138,129 @@ pub struct WithdrawalExecuted {
 #[event]
 pub struct MultisigInitialized {
     pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
     pub threshold: u8,
     pub signer_count: u8,
     pub timestamp: i64,
 }
+
+#[event]
+pub struct ForcedUnlockRequested {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub locked_balance: u64,
+    pub available_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ForcedUnlockExecuted {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub amount: u64,
+    pub new_available_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionHeartbeat {
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub cancelled_forced_unlock: bool,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SettlementLeg {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub sequence: u64,
+    pub margin_released: u64,
+    pub bucket: SettlementBucket,
+    pub new_locked_balance: u64,
+    pub new_available_balance: u64,
+}
+
+#[event]
+pub struct PositionSettled {
+    pub position_id: u64,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub pnl: u64,
+    pub loser: SettlementLeg,
+    pub winner: SettlementLeg,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecoveryConfigured {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecoveryInitiated {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub proposed_owner: Pubkey,
+    pub approvals: u8,
+    pub threshold: u8,
+    pub available_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecoveryApproved {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub proposed_owner: Pubkey,
+    pub approvals: u8,
+    pub threshold: u8,
+    pub available_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecoveryVetoed {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub proposed_owner: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecoveryExecuted {
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub vault: Pubkey,
+    pub actor: Pubkey,
+    pub caller_program: Option<Pubkey>,
+    pub sequence: u64,
+    pub approvals: u8,
+    pub timestamp: i64,
+}
@@ -1,5 +1,6 @@
 use crate::errors::ErrorCode;
 use crate::events::{AuthorizedProgramAdded, AuthorizedProgramRemoved};
+use crate::instructions::multisig::require_cosigners;
 use crate::state::{CollateralVault, VaultAuthority};
 use anchor_lang::prelude::*;
 
@@ -10,8 +11,9 @@ pub struct AddAuthorizedProgram<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", owner.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
         constraint = vault.vault_authority == vault_authority.key() @ ErrorCode::InvalidVaultAuthority
     )]
     pub vault: Account<'info, CollateralVault>,
@@ -23,9 +25,15 @@ pub struct AddAuthorizedProgram<'info> {
         has_one = vault @ ErrorCode::InvalidVaultAuthority
     )]
     pub vault_authority: Account<'info, VaultAuthority>,
+
+    /// CHECK: the vault's `MultisigConfig` if it has one; checked by
+    /// `require_cosigners`.
+    #[account(seeds = [b"multisig", vault.key().as_ref()], bump)]
+    pub multisig_config: UncheckedAccount<'info>,
 }
 
 pub fn add_authorized_program(ctx: Context<AddAuthorizedProgram>, program: Pubkey) -> Result<()> {
+    require_cosigners(&ctx.accounts.multisig_config, ctx.remaining_accounts)?;
     require!(program != Pubkey::default(), ErrorCode::InvalidAuthority);
 
     let authority = &mut ctx.accounts.vault_authority;
@@ -39,9 +47,13 @@ pub fn add_authorized_program(ctx: Context<AddAuthorizedProgram>, program: Pubke
     );
 
     authority.authorized_programs.push(program);
+    let sequence = ctx.accounts.vault.next_sequence()?;
 
     emit!(AuthorizedProgramAdded {
         vault: ctx.accounts.vault.key(),
+        actor: ctx.accounts.owner.key(),
+        caller_program: None,
+        sequence,
         program,
         timestamp: Clock::get()?.unix_timestamp,
     });
@@ -56,8 +68,9 @@ pub struct RemoveAuthorizedProgram<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", owner.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
         constraint = vault.vault_authority == vault_authority.key() @ ErrorCode::InvalidVaultAuthority
     )]
     pub vault: Account<'info, CollateralVault>,
@@ -69,12 +82,19 @@ pub struct RemoveAuthorizedProgram<'info> {
         has_one = vault @ ErrorCode::InvalidVaultAuthority
     )]
     pub vault_authority: Account<'info, VaultAuthority>,
+
+    /// CHECK: the vault's `MultisigConfig` if it has one; checked by
+    /// `require_cosigners`.
+    #[account(seeds = [b"multisig", vault.key().as_ref()], bump)]
+    pub multisig_config: UncheckedAccount<'info>,
 }
 
 pub fn remove_authorized_program(
     ctx: Context<RemoveAuthorizedProgram>,
     program: Pubkey,
 ) -> Result<()> {
+    require_cosigners(&ctx.accounts.multisig_config, ctx.remaining_accounts)?;
+
     let authority = &mut ctx.accounts.vault_authority;
     if let Some(index) = authority
         .authorized_programs
@@ -82,9 +102,13 @@ pub fn remove_authorized_program(
         .position(|p| p == &program)
     {
         authority.authorized_programs.swap_remove(index);
+        let sequence = ctx.accounts.vault.next_sequence()?;
 
         emit!(AuthorizedProgramRemoved {
             vault: ctx.accounts.vault.key(),
+            actor: ctx.accounts.owner.key(),
+            caller_program: None,
+            sequence,
             program,
             timestamp: Clock::get()?.unix_timestamp,
         });
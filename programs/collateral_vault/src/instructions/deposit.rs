@@ -11,8 +11,9 @@ pub struct Deposit<'info> {
     pub user: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"vault", user.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        constraint = vault.owner == user.key() @ ErrorCode::InvalidAuthority,
     )]
     pub vault: Account<'info, CollateralVault>,
 
@@ -63,10 +64,15 @@ pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
         .total_deposited
         .checked_add(amount)
         .ok_or(ErrorCode::Overflow)?;
+    let sequence = vault.next_sequence()?;
 
     // Emit event for off-chain indexing
     emit!(DepositEvent {
         user: ctx.accounts.user.key(),
+        vault: vault.key(),
+        actor: ctx.accounts.user.key(),
+        caller_program: None,
+        sequence,
         amount,
         new_balance: vault.total_balance,
         timestamp: Clock::get()?.unix_timestamp,
@@ -0,0 +1,223 @@
+use crate::errors::ErrorCode;
+use crate::events::{ForcedUnlockExecuted, ForcedUnlockRequested, PositionHeartbeat};
+use crate::state::{CollateralVault, ForcedUnlockRequest, VaultAuthority};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+pub const FORCED_UNLOCK_INACTIVITY_SECONDS: i64 = 30 * 86_400; // 30 days
+pub const FORCED_UNLOCK_DELAY_SECONDS: i64 = 7 * 86_400; // 7 days
+
+#[derive(Accounts)]
+pub struct RequestForcedUnlock<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    /// Reused when a previous request went stale.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + std::mem::size_of::<ForcedUnlockRequest>(),
+        seeds = [b"forced_unlock", vault.key().as_ref()],
+        bump,
+    )]
+    pub forced_unlock_request: Account<'info, ForcedUnlockRequest>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn request_forced_unlock(ctx: Context<RequestForcedUnlock>) -> Result<()> {
+    let vault = &mut ctx.accounts.vault;
+    let request = &mut ctx.accounts.forced_unlock_request;
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // An existing request only blocks a new one while no position activity
+    // has happened since it was filed; otherwise it can never execute.
+    require!(
+        request.vault == Pubkey::default() || vault.last_position_activity > request.requested_at,
+        ErrorCode::ForcedUnlockPending
+    );
+    require!(vault.locked_balance > 0, ErrorCode::InsufficientLockedFunds);
+    require!(
+        current_time - vault.last_position_activity >= FORCED_UNLOCK_INACTIVITY_SECONDS,
+        ErrorCode::ForcedUnlockInactivityNotMet
+    );
+
+    request.vault = vault.key();
+    request.owner = ctx.accounts.owner.key();
+    request.requested_at = current_time;
+    request.available_at = current_time + FORCED_UNLOCK_DELAY_SECONDS;
+    request.bump = ctx.bumps.forced_unlock_request;
+    let sequence = vault.next_sequence()?;
+
+    emit!(ForcedUnlockRequested {
+        user: ctx.accounts.owner.key(),
+        vault: vault.key(),
+        actor: ctx.accounts.owner.key(),
+        caller_program: None,
+        sequence,
+        locked_balance: vault.locked_balance,
+        available_at: request.available_at,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ExecuteForcedUnlock<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"forced_unlock", vault.key().as_ref()],
+        bump = forced_unlock_request.bump,
+        constraint = forced_unlock_request.vault == vault.key() @ ErrorCode::InvalidVaultAuthority
+    )]
+    pub forced_unlock_request: Account<'info, ForcedUnlockRequest>,
+}
+
+pub fn execute_forced_unlock(ctx: Context<ExecuteForcedUnlock>) -> Result<()> {
+    let vault = &mut ctx.accounts.vault;
+    let request = &ctx.accounts.forced_unlock_request;
+    let current_time = Clock::get()?.unix_timestamp;
+
+    require!(
+        current_time >= request.available_at,
+        ErrorCode::ForcedUnlockDelayNotMet
+    );
+    // Any lock, unlock, settlement or heartbeat since the request makes it stale.
+    require!(
+        vault.last_position_activity <= request.requested_at,
+        ErrorCode::ForcedUnlockInactivityNotMet
+    );
+
+    let amount = vault.locked_balance;
+    vault.locked_balance = 0;
+    vault.available_balance = vault
+        .available_balance
+        .checked_add(amount)
+        .ok_or(ErrorCode::Overflow)?;
+    let sequence = vault.next_sequence()?;
+
+    emit!(ForcedUnlockExecuted {
+        user: ctx.accounts.owner.key(),
+        vault: vault.key(),
+        actor: ctx.accounts.owner.key(),
+        caller_program: None,
+        sequence,
+        amount,
+        new_available_balance: vault.available_balance,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+/// Called by an authorized program (via CPI, signing with its
+/// `VaultAuthority::CALLER_SEED` PDA) to prove it is still alive. Cancels any
+/// pending forced unlock; the request address is always passed so a caller
+/// cannot leave one open by omitting it.
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        seeds = [b"vault_authority", vault.key().as_ref()],
+        bump = vault_authority.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+        constraint = vault.vault_authority == vault_authority.key() @ ErrorCode::InvalidVaultAuthority
+    )]
+    pub vault_authority: Account<'info, VaultAuthority>,
+
+    /// CHECK: Validated as executable + authorized in handler
+    pub authority_program: AccountInfo<'info>,
+
+    #[account(
+        seeds = [VaultAuthority::CALLER_SEED],
+        bump,
+        seeds::program = authority_program.key(),
+    )]
+    pub caller: Signer<'info>,
+
+    /// CHECK: The vault's forced unlock PDA; closed in the handler if a
+    /// request exists there
+    #[account(
+        mut,
+        seeds = [b"forced_unlock", vault.key().as_ref()],
+        bump,
+    )]
+    pub forced_unlock_request: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the rent of a cancelled request
+    #[account(mut, address = vault.owner @ ErrorCode::InvalidAuthority)]
+    pub owner: AccountInfo<'info>,
+}
+
+pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+    require!(
+        ctx.accounts
+            .vault_authority
+            .is_authorized(ctx.accounts.authority_program.key),
+        ErrorCode::Unauthorized
+    );
+    require!(
+        ctx.accounts.authority_program.executable,
+        ErrorCode::Unauthorized
+    );
+
+    let request = ctx.accounts.forced_unlock_request.to_account_info();
+    let cancelled = request.owner == ctx.program_id
+        && request
+            .try_borrow_data()?
+            .starts_with(ForcedUnlockRequest::DISCRIMINATOR);
+    if cancelled {
+        // What `close = owner` does.
+        let owner = ctx.accounts.owner.to_account_info();
+        **owner.try_borrow_mut_lamports()? = owner
+            .lamports()
+            .checked_add(request.lamports())
+            .ok_or(ErrorCode::Overflow)?;
+        **request.try_borrow_mut_lamports()? = 0;
+        request.assign(&System::id());
+        request.resize(0)?;
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let vault = &mut ctx.accounts.vault;
+    vault.last_position_activity = current_time;
+    let sequence = vault.next_sequence()?;
+
+    emit!(PositionHeartbeat {
+        vault: vault.key(),
+        actor: ctx.accounts.caller.key(),
+        caller_program: Some(ctx.accounts.authority_program.key()),
+        sequence,
+        cancelled_forced_unlock: cancelled,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
@@ -67,6 +67,7 @@ pub fn handler(ctx: Context<InitializeVault>, authorized_programs: Vec<Pubkey>)
     let vault = &mut ctx.accounts.vault;
 
     vault.owner = ctx.accounts.user.key();
+    vault.original_owner = ctx.accounts.user.key();
     vault.token_account = ctx.accounts.vault_token_account.key();
     vault.vault_authority = ctx.accounts.vault_authority.key();
     vault.total_balance = 0;
@@ -76,6 +77,8 @@ pub fn handler(ctx: Context<InitializeVault>, authorized_programs: Vec<Pubkey>)
     vault.total_withdrawn = 0;
     vault.created_at = Clock::get()?.unix_timestamp;
     vault.bump = ctx.bumps.vault;
+    vault.sequence = 0;
+    vault.last_position_activity = vault.created_at;
 
     let vault_authority = &mut ctx.accounts.vault_authority;
     vault_authority.vault = vault.key();
@@ -85,6 +88,9 @@ pub fn handler(ctx: Context<InitializeVault>, authorized_programs: Vec<Pubkey>)
     emit!(VaultInitialized {
         user: ctx.accounts.user.key(),
         vault: ctx.accounts.vault.key(),
+        actor: ctx.accounts.user.key(),
+        caller_program: None,
+        sequence: ctx.accounts.vault.sequence,
         amount: 0,
         timestamp: Clock::get()?.unix_timestamp,
     });
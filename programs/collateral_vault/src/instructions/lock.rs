@@ -10,8 +10,9 @@ pub struct LockCollateral<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", user.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        constraint = vault.owner == user.key() @ ErrorCode::InvalidAuthority,
     )]
     pub vault: Account<'info, CollateralVault>,
 
@@ -58,9 +59,15 @@ pub fn lock_collateral(ctx: Context<LockCollateral>, amount: u64) -> Result<()>
         .locked_balance
         .checked_add(amount)
         .ok_or(ErrorCode::Overflow)?;
+    vault.last_position_activity = Clock::get()?.unix_timestamp;
+    let sequence = vault.next_sequence()?;
 
     emit!(CollateralLocked {
         user: ctx.accounts.user.key(),
+        vault: vault.key(),
+        actor: ctx.accounts.user.key(),
+        caller_program: Some(ctx.accounts.authority_program.key()),
+        sequence,
         amount,
         new_locked_balance: vault.locked_balance,
         timestamp: Clock::get()?.unix_timestamp,
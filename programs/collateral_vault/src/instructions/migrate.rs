@@ -0,0 +1,105 @@
+use crate::errors::ErrorCode;
+use crate::events::VaultMigrated;
+use crate::state::{CollateralVault, LegacyCollateralVault};
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: Still in the legacy layout, so it cannot be loaded as
+    /// `Account<CollateralVault>`; discriminator, owner and seeds are checked
+    /// in the handler
+    #[account(mut, owner = crate::ID)]
+    pub vault: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a vault created before `sequence`, `last_position_activity` and
+/// `original_owner` existed to the current layout. The owner pays the extra
+/// rent. Position activity starts at the migration, so a forced unlock needs
+/// a full inactivity window from here.
+pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+    let info = ctx.accounts.vault.to_account_info();
+    let space = 8 + std::mem::size_of::<CollateralVault>();
+
+    let legacy = {
+        let data = info.try_borrow_data()?;
+        require!(
+            data.starts_with(CollateralVault::DISCRIMINATOR),
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        );
+        require!(data.len() < space, ErrorCode::AlreadyMigrated);
+        LegacyCollateralVault::deserialize(&mut &data[8..])?
+    };
+    require_keys_eq!(
+        legacy.owner,
+        ctx.accounts.owner.key(),
+        ErrorCode::InvalidAuthority
+    );
+    let expected = Pubkey::create_program_address(
+        &[b"vault", legacy.owner.as_ref(), &[legacy.bump]],
+        ctx.program_id,
+    )
+    .map_err(|_| anchor_lang::error::ErrorCode::ConstraintSeeds)?;
+    require_keys_eq!(
+        expected,
+        info.key(),
+        anchor_lang::error::ErrorCode::ConstraintSeeds
+    );
+
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(info.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    info.resize(space)?;
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let vault = CollateralVault {
+        owner: legacy.owner,
+        token_account: legacy.token_account,
+        vault_authority: legacy.vault_authority,
+        total_balance: legacy.total_balance,
+        locked_balance: legacy.locked_balance,
+        available_balance: legacy.available_balance,
+        total_deposited: legacy.total_deposited,
+        total_withdrawn: legacy.total_withdrawn,
+        created_at: legacy.created_at,
+        bump: legacy.bump,
+        sequence: 0,
+        last_position_activity: current_time,
+        original_owner: legacy.owner,
+    };
+    let mut data = info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    vault.try_serialize(&mut writer)?;
+
+    emit!(VaultMigrated {
+        user: legacy.owner,
+        vault: info.key(),
+        actor: legacy.owner,
+        caller_program: None,
+        sequence: vault.sequence,
+        total_balance: vault.total_balance,
+        locked_balance: vault.locked_balance,
+        available_balance: vault.available_balance,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
@@ -1,19 +1,27 @@
 pub mod authority;
 pub mod deposit;
+pub mod forced_unlock;
 pub mod initialize_vault;
 pub mod lock;
+pub mod migrate;
 pub mod multisig;
+pub mod recovery;
 pub mod security;
+pub mod settle_pnl;
 pub mod transfer_collateral;
 pub mod unlock;
 pub mod withdraw;
 
 pub use authority::*;
 pub use deposit::*;
+pub use forced_unlock::*;
 pub use initialize_vault::*;
 pub use lock::*;
+pub use migrate::*;
 pub use multisig::*;
+pub use recovery::*;
 pub use security::*;
+pub use settle_pnl::*;
 pub use transfer_collateral::*;
 pub use unlock::*;
 pub use withdraw::*;
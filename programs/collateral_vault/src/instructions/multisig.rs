@@ -2,6 +2,7 @@ use crate::errors::ErrorCode;
 use crate::events::*;
 use crate::state::{CollateralVault, MultisigConfig};
 use anchor_lang::prelude::*;
+use std::collections::HashSet;
 
 #[derive(Accounts)]
 pub struct InitializeMultisig<'info> {
@@ -10,15 +11,16 @@ pub struct InitializeMultisig<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", owner.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
     )]
     pub vault: Account<'info, CollateralVault>,
 
     #[account(
         init,
         payer = owner,
-        space = 8 + std::mem::size_of::<MultisigConfig>(),
+        space = 8 + MultisigConfig::MAX_SIZE,
         seeds = [b"multisig", vault.key().as_ref()],
         bump,
     )]
@@ -27,25 +29,59 @@ pub struct InitializeMultisig<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// Once a vault has a `MultisigConfig`, owner operations that move funds or
+/// change authorizations also need `threshold` of its co-signers to sign.
+/// They are passed as remaining accounts. Vaults without one are unaffected.
+pub fn require_cosigners(multisig_config: &AccountInfo, cosigners: &[AccountInfo]) -> Result<()> {
+    if multisig_config.owner != &crate::ID {
+        return Ok(());
+    }
+    let config = MultisigConfig::try_deserialize(&mut &multisig_config.try_borrow_data()?[..])?;
+    let signed: HashSet<&Pubkey> = cosigners
+        .iter()
+        .filter(|account| account.is_signer && config.is_signer(account.key))
+        .map(|account| account.key)
+        .collect();
+    require!(
+        signed.len() >= config.threshold as usize,
+        ErrorCode::MultisigThresholdNotMet
+    );
+    Ok(())
+}
+
 pub fn initialize_multisig(
     ctx: Context<InitializeMultisig>,
     signers: Vec<Pubkey>,
     threshold: u8,
 ) -> Result<()> {
-    require!(threshold > 0, ErrorCode::InvalidAmount);
     require!(
-        threshold as usize <= signers.len(),
-        ErrorCode::InvalidAmount
+        !signers.is_empty() && signers.len() <= MultisigConfig::MAX_SIGNERS,
+        ErrorCode::InvalidSignerSet
+    );
+    require!(
+        threshold > 0 && threshold as usize <= signers.len(),
+        ErrorCode::InvalidSignerSet
     );
+    let mut seen = HashSet::with_capacity(signers.len());
+    for signer in &signers {
+        require!(
+            *signer != Pubkey::default() && seen.insert(*signer),
+            ErrorCode::InvalidSignerSet
+        );
+    }
 
     let multisig = &mut ctx.accounts.multisig_config;
     multisig.vault = ctx.accounts.vault.key();
     multisig.signers = signers;
     multisig.threshold = threshold;
     multisig.bump = ctx.bumps.multisig_config;
+    let sequence = ctx.accounts.vault.next_sequence()?;
 
     emit!(MultisigInitialized {
         vault: ctx.accounts.vault.key(),
+        actor: ctx.accounts.owner.key(),
+        caller_program: None,
+        sequence,
         threshold,
         signer_count: multisig.signers.len() as u8,
         timestamp: Clock::get()?.unix_timestamp,
@@ -0,0 +1,369 @@
+use crate::errors::ErrorCode;
+use crate::events::{
+    RecoveryApproved, RecoveryConfigured, RecoveryExecuted, RecoveryInitiated, RecoveryVetoed,
+};
+use crate::state::{CollateralVault, RecoveryConfig, RecoveryRequest};
+use anchor_lang::prelude::*;
+use std::collections::HashSet;
+
+pub const RECOVERY_DELAY_SECONDS: i64 = 7 * 86_400; // 7 days
+
+pub fn validate_guardians(guardians: &[Pubkey], threshold: u8, owner: &Pubkey) -> Result<()> {
+    require!(
+        !guardians.is_empty() && guardians.len() <= RecoveryConfig::MAX_GUARDIANS,
+        ErrorCode::InvalidGuardianSet
+    );
+    require!(
+        threshold > 0 && threshold as usize <= guardians.len(),
+        ErrorCode::InvalidGuardianSet
+    );
+
+    let mut seen = HashSet::with_capacity(guardians.len());
+    for guardian in guardians {
+        require!(
+            *guardian != Pubkey::default() && guardian != owner,
+            ErrorCode::InvalidGuardianSet
+        );
+        require!(seen.insert(*guardian), ErrorCode::InvalidGuardianSet);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ConfigureRecovery<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + RecoveryConfig::MAX_SIZE,
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn configure_recovery(
+    ctx: Context<ConfigureRecovery>,
+    guardians: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    validate_guardians(&guardians, threshold, &ctx.accounts.owner.key())?;
+
+    let config = &mut ctx.accounts.recovery_config;
+    config.vault = ctx.accounts.vault.key();
+    config.guardians = guardians;
+    config.threshold = threshold;
+    config.bump = ctx.bumps.recovery_config;
+    let sequence = ctx.accounts.vault.next_sequence()?;
+
+    emit!(RecoveryConfigured {
+        user: ctx.accounts.owner.key(),
+        vault: ctx.accounts.vault.key(),
+        actor: ctx.accounts.owner.key(),
+        caller_program: None,
+        sequence,
+        guardians: config.guardians.clone(),
+        threshold,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateRecovery<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump = recovery_config.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+}
+
+pub fn update_recovery(
+    ctx: Context<UpdateRecovery>,
+    guardians: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    validate_guardians(&guardians, threshold, &ctx.accounts.owner.key())?;
+
+    let config = &mut ctx.accounts.recovery_config;
+    config.guardians = guardians;
+    config.threshold = threshold;
+    let sequence = ctx.accounts.vault.next_sequence()?;
+
+    emit!(RecoveryConfigured {
+        user: ctx.accounts.owner.key(),
+        vault: ctx.accounts.vault.key(),
+        actor: ctx.accounts.owner.key(),
+        caller_program: None,
+        sequence,
+        guardians: config.guardians.clone(),
+        threshold,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitiateRecovery<'info> {
+    #[account(mut)]
+    pub guardian: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump = recovery_config.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+        constraint = recovery_config.is_guardian(&guardian.key()) @ ErrorCode::NotGuardian,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    #[account(
+        init,
+        payer = guardian,
+        space = 8 + RecoveryRequest::MAX_SIZE,
+        seeds = [b"recovery_request", vault.key().as_ref()],
+        bump,
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn initiate_recovery(ctx: Context<InitiateRecovery>, new_owner: Pubkey) -> Result<()> {
+    let vault = &mut ctx.accounts.vault;
+    require!(
+        new_owner != Pubkey::default() && new_owner != vault.owner,
+        ErrorCode::InvalidAuthority
+    );
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let request = &mut ctx.accounts.recovery_request;
+    request.vault = vault.key();
+    request.proposed_owner = new_owner;
+    request.initiator = ctx.accounts.guardian.key();
+    request.approvals = vec![ctx.accounts.guardian.key()];
+    request.initiated_at = current_time;
+    request.available_at = current_time + RECOVERY_DELAY_SECONDS;
+    request.bump = ctx.bumps.recovery_request;
+    let sequence = vault.next_sequence()?;
+
+    emit!(RecoveryInitiated {
+        user: vault.owner,
+        vault: vault.key(),
+        actor: ctx.accounts.guardian.key(),
+        caller_program: None,
+        sequence,
+        proposed_owner: new_owner,
+        approvals: 1,
+        threshold: ctx.accounts.recovery_config.threshold,
+        available_at: request.available_at,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    pub guardian: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump = recovery_config.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+        constraint = recovery_config.is_guardian(&guardian.key()) @ ErrorCode::NotGuardian,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery_request", vault.key().as_ref()],
+        bump = recovery_request.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+}
+
+pub fn approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
+    let guardian = ctx.accounts.guardian.key();
+    let request = &mut ctx.accounts.recovery_request;
+    require!(
+        !request.approvals.contains(&guardian),
+        ErrorCode::RecoveryAlreadyApproved
+    );
+    request.approvals.push(guardian);
+
+    let vault = &mut ctx.accounts.vault;
+    let sequence = vault.next_sequence()?;
+
+    emit!(RecoveryApproved {
+        user: vault.owner,
+        vault: vault.key(),
+        actor: guardian,
+        caller_program: None,
+        sequence,
+        proposed_owner: request.proposed_owner,
+        approvals: request.approvals.len() as u8,
+        threshold: ctx.accounts.recovery_config.threshold,
+        available_at: request.available_at,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct VetoRecovery<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+        has_one = owner @ ErrorCode::InvalidAuthority,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        close = initiator,
+        seeds = [b"recovery_request", vault.key().as_ref()],
+        bump = recovery_request.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+        has_one = initiator @ ErrorCode::InvalidAuthority,
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+
+    /// CHECK: Guardian that paid for the request; receives its rent back
+    #[account(mut)]
+    pub initiator: AccountInfo<'info>,
+}
+
+pub fn veto_recovery(ctx: Context<VetoRecovery>) -> Result<()> {
+    let vault = &mut ctx.accounts.vault;
+    let sequence = vault.next_sequence()?;
+
+    emit!(RecoveryVetoed {
+        user: vault.owner,
+        vault: vault.key(),
+        actor: ctx.accounts.owner.key(),
+        caller_program: None,
+        sequence,
+        proposed_owner: ctx.accounts.recovery_request.proposed_owner,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    pub guardian: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.original_owner.as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, CollateralVault>,
+
+    #[account(
+        seeds = [b"recovery", vault.key().as_ref()],
+        bump = recovery_config.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+        constraint = recovery_config.is_guardian(&guardian.key()) @ ErrorCode::NotGuardian,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    #[account(
+        mut,
+        close = initiator,
+        seeds = [b"recovery_request", vault.key().as_ref()],
+        bump = recovery_request.bump,
+        has_one = vault @ ErrorCode::InvalidVaultAuthority,
+        has_one = initiator @ ErrorCode::InvalidAuthority,
+    )]
+    pub recovery_request: Account<'info, RecoveryRequest>,
+
+    /// CHECK: Guardian that paid for the request; receives its rent back
+    #[account(mut)]
+    pub initiator: AccountInfo<'info>,
+}
+
+pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+    let config = &ctx.accounts.recovery_config;
+    let request = &ctx.accounts.recovery_request;
+    let current_time = Clock::get()?.unix_timestamp;
+
+    require!(
+        current_time >= request.available_at,
+        ErrorCode::RecoveryDelayNotMet
+    );
+    // Only count approvals from guardians still in the current set.
+    let approvals = request
+        .approvals
+        .iter()
+        .filter(|g| config.is_guardian(g))
+        .count();
+    require!(
+        approvals >= config.threshold as usize,
+        ErrorCode::RecoveryThresholdNotMet
+    );
+
+    let vault = &mut ctx.accounts.vault;
+    let previous_owner = vault.owner;
+    vault.owner = request.proposed_owner;
+    let sequence = vault.next_sequence()?;
+
+    emit!(RecoveryExecuted {
+        previous_owner,
+        new_owner: vault.owner,
+        vault: vault.key(),
+        actor: ctx.accounts.guardian.key(),
+        caller_program: None,
+        sequence,
+        approvals: approvals as u8,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::errors::ErrorCode;
 use crate::events::{WithdrawalExecuted, WithdrawalRequested};
+use crate::instructions::multisig::require_cosigners;
 use crate::state::{CollateralVault, WithdrawalRequest};
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
@@ -14,8 +15,9 @@ pub struct RequestWithdrawal<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", user.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        constraint = vault.owner == user.key() @ ErrorCode::InvalidAuthority,
     )]
     pub vault: Account<'info, CollateralVault>,
 
@@ -29,6 +31,11 @@ pub struct RequestWithdrawal<'info> {
     pub withdrawal_request: Account<'info, WithdrawalRequest>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's `MultisigConfig` if it has one; checked by
+    /// `require_cosigners`.
+    #[account(seeds = [b"multisig", vault.key().as_ref()], bump)]
+    pub multisig_config: UncheckedAccount<'info>,
 }
 
 pub fn request_withdrawal(
@@ -36,7 +43,9 @@ pub fn request_withdrawal(
     request_id: u64,
     amount: u64,
 ) -> Result<()> {
-    let vault = &ctx.accounts.vault;
+    require_cosigners(&ctx.accounts.multisig_config, ctx.remaining_accounts)?;
+
+    let vault = &mut ctx.accounts.vault;
     let request = &mut ctx.accounts.withdrawal_request;
 
     require!(amount > 0, ErrorCode::InvalidAmount);
@@ -58,10 +67,14 @@ pub fn request_withdrawal(
     request.request_id = request_id;
     request.executed = false;
     request.bump = ctx.bumps.withdrawal_request;
+    let sequence = vault.next_sequence()?;
 
     emit!(WithdrawalRequested {
         user: ctx.accounts.user.key(),
         vault: vault.key(),
+        actor: ctx.accounts.user.key(),
+        caller_program: None,
+        sequence,
         amount,
         available_at: request.available_at,
         timestamp: current_time,
@@ -77,8 +90,9 @@ pub struct ExecuteWithdrawal<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", user.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        constraint = vault.owner == user.key() @ ErrorCode::InvalidAuthority,
         constraint = vault.vault_authority != Pubkey::default() @ ErrorCode::InvalidVaultAuthority
     )]
     pub vault: Account<'info, CollateralVault>,
@@ -127,7 +141,7 @@ pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
         ErrorCode::InvalidAuthority
     );
 
-    let binding = ctx.accounts.user.key();
+    let binding = vault.original_owner;
     let seeds = &[b"vault", binding.as_ref(), &[vault.bump]];
     let signer = &[&seeds[..]];
 
@@ -158,10 +172,14 @@ pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
         .ok_or(ErrorCode::Overflow)?;
 
     request.executed = true;
+    let sequence = vault.next_sequence()?;
 
     emit!(WithdrawalExecuted {
         user: ctx.accounts.user.key(),
         vault: vault.key(),
+        actor: ctx.accounts.user.key(),
+        caller_program: None,
+        sequence,
         amount: request.amount,
         timestamp: current_time,
     });
@@ -0,0 +1,221 @@
+use crate::errors::ErrorCode;
+use crate::events::{PositionSettled, SettlementLeg};
+use crate::state::{CollateralVault, SettlementBucket, VaultAuthority};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+#[derive(Accounts)]
+pub struct SettlePnl<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", loser_vault.original_owner.as_ref()],
+        bump = loser_vault.bump,
+        constraint = loser_vault.vault_authority == loser_vault_authority.key() @ ErrorCode::InvalidVaultAuthority
+    )]
+    pub loser_vault: Account<'info, CollateralVault>,
+
+    #[account(
+        seeds = [b"vault_authority", loser_vault.key().as_ref()],
+        bump = loser_vault_authority.bump,
+        constraint = loser_vault_authority.vault == loser_vault.key() @ ErrorCode::InvalidVaultAuthority
+    )]
+    pub loser_vault_authority: Account<'info, VaultAuthority>,
+
+    #[account(
+        mut,
+        address = loser_vault.token_account
+    )]
+    pub loser_vault_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", winner_vault.original_owner.as_ref()],
+        bump = winner_vault.bump,
+        constraint = winner_vault.vault_authority == winner_vault_authority.key() @ ErrorCode::InvalidVaultAuthority
+    )]
+    pub winner_vault: Account<'info, CollateralVault>,
+
+    #[account(
+        seeds = [b"vault_authority", winner_vault.key().as_ref()],
+        bump = winner_vault_authority.bump,
+        constraint = winner_vault_authority.vault == winner_vault.key() @ ErrorCode::InvalidVaultAuthority
+    )]
+    pub winner_vault_authority: Account<'info, VaultAuthority>,
+
+    #[account(
+        mut,
+        address = winner_vault.token_account
+    )]
+    pub winner_vault_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Validated as executable + authorized in handler
+    pub authority_program: AccountInfo<'info>,
+
+    #[account(
+        seeds = [VaultAuthority::CALLER_SEED],
+        bump,
+        seeds::program = authority_program.key(),
+    )]
+    pub caller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Closes a position between two vaults: releases each side's margin and
+/// moves `pnl` from the loser's `debit_bucket` to the winner's `credit_bucket`,
+/// with the matching SPL transfer between the vault token accounts.
+pub fn settle_pnl(
+    ctx: Context<SettlePnl>,
+    position_id: u64,
+    loser_margin: u64,
+    winner_margin: u64,
+    pnl: u64,
+    debit_bucket: SettlementBucket,
+    credit_bucket: SettlementBucket,
+) -> Result<()> {
+    require!(
+        ctx.accounts.loser_vault.key() != ctx.accounts.winner_vault.key(),
+        ErrorCode::SameVaultSettlement
+    );
+    require!(
+        ctx.accounts
+            .loser_vault_authority
+            .is_authorized(ctx.accounts.authority_program.key),
+        ErrorCode::Unauthorized
+    );
+    require!(
+        ctx.accounts
+            .winner_vault_authority
+            .is_authorized(ctx.accounts.authority_program.key),
+        ErrorCode::Unauthorized
+    );
+    require!(
+        ctx.accounts.authority_program.executable,
+        ErrorCode::Unauthorized
+    );
+
+    let loser = &mut ctx.accounts.loser_vault;
+    let winner = &mut ctx.accounts.winner_vault;
+
+    require!(
+        loser.locked_balance >= loser_margin,
+        ErrorCode::InsufficientLockedFunds
+    );
+    require!(
+        winner.locked_balance >= winner_margin,
+        ErrorCode::InsufficientLockedFunds
+    );
+
+    // Loser leg: realize the loss, then release whatever margin is left.
+    let loser_released = match debit_bucket {
+        SettlementBucket::Locked => {
+            require!(pnl <= loser_margin, ErrorCode::PnlExceedsMargin);
+            loser.locked_balance = loser
+                .locked_balance
+                .checked_sub(pnl)
+                .ok_or(ErrorCode::Underflow)?;
+            loser_margin - pnl
+        }
+        SettlementBucket::Available => {
+            require!(loser.available_balance >= pnl, ErrorCode::InsufficientFunds);
+            loser.available_balance = loser
+                .available_balance
+                .checked_sub(pnl)
+                .ok_or(ErrorCode::Underflow)?;
+            loser_margin
+        }
+    };
+    loser.locked_balance = loser
+        .locked_balance
+        .checked_sub(loser_released)
+        .ok_or(ErrorCode::Underflow)?;
+    loser.available_balance = loser
+        .available_balance
+        .checked_add(loser_released)
+        .ok_or(ErrorCode::Overflow)?;
+    loser.total_balance = loser
+        .total_balance
+        .checked_sub(pnl)
+        .ok_or(ErrorCode::Underflow)?;
+
+    // Winner leg: release margin, then credit the profit.
+    winner.locked_balance = winner
+        .locked_balance
+        .checked_sub(winner_margin)
+        .ok_or(ErrorCode::Underflow)?;
+    winner.available_balance = winner
+        .available_balance
+        .checked_add(winner_margin)
+        .ok_or(ErrorCode::Overflow)?;
+    match credit_bucket {
+        SettlementBucket::Locked => {
+            winner.locked_balance = winner
+                .locked_balance
+                .checked_add(pnl)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+        SettlementBucket::Available => {
+            winner.available_balance = winner
+                .available_balance
+                .checked_add(pnl)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+    }
+    winner.total_balance = winner
+        .total_balance
+        .checked_add(pnl)
+        .ok_or(ErrorCode::Overflow)?;
+
+    if pnl > 0 {
+        let loser_owner = loser.original_owner;
+        let seeds = &[b"vault", loser_owner.as_ref(), &[loser.bump]];
+        let signer = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.loser_vault_token_account.to_account_info(),
+                    to: ctx.accounts.winner_vault_token_account.to_account_info(),
+                    authority: loser.to_account_info(),
+                },
+                signer,
+            ),
+            pnl,
+        )?;
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+    loser.last_position_activity = current_time;
+    winner.last_position_activity = current_time;
+    let loser_sequence = loser.next_sequence()?;
+    let winner_sequence = winner.next_sequence()?;
+
+    emit!(PositionSettled {
+        position_id,
+        actor: ctx.accounts.caller.key(),
+        caller_program: Some(ctx.accounts.authority_program.key()),
+        pnl,
+        loser: SettlementLeg {
+            user: loser.owner,
+            vault: loser.key(),
+            sequence: loser_sequence,
+            margin_released: loser_released,
+            bucket: debit_bucket,
+            new_locked_balance: loser.locked_balance,
+            new_available_balance: loser.available_balance,
+        },
+        winner: SettlementLeg {
+            user: winner.owner,
+            vault: winner.key(),
+            sequence: winner_sequence,
+            margin_released: winner_margin,
+            bucket: credit_bucket,
+            new_locked_balance: winner.locked_balance,
+            new_available_balance: winner.available_balance,
+        },
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
@@ -10,7 +10,7 @@ pub struct TransferCollateral<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", from_vault.owner.as_ref()],
+        seeds = [b"vault", from_vault.original_owner.as_ref()],
         bump = from_vault.bump,
         constraint = from_vault.vault_authority == from_vault_authority.key() @ ErrorCode::InvalidVaultAuthority
     )]
@@ -26,7 +26,7 @@ pub struct TransferCollateral<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", to_vault.owner.as_ref()],
+        seeds = [b"vault", to_vault.original_owner.as_ref()],
         bump = to_vault.bump,
         constraint = to_vault.vault_authority == to_vault_authority.key() @ ErrorCode::InvalidVaultAuthority
     )]
@@ -107,9 +107,16 @@ pub fn transfer_collateral(
         .checked_add(amount)
         .ok_or(ErrorCode::Overflow)?;
 
+    let from_sequence = from_vault_acc.next_sequence()?;
+    let to_sequence = to_vault_acc.next_sequence()?;
+
     emit!(CollateralTransferred {
         from_vault,
         to_vault,
+        actor: ctx.accounts.operator.key(),
+        caller_program: Some(ctx.accounts.authority_program.key()),
+        from_sequence,
+        to_sequence,
         amount,
         timestamp: Clock::get()?.unix_timestamp,
     });
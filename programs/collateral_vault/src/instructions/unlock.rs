@@ -10,8 +10,9 @@ pub struct UnlockCollateral<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", user.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        constraint = vault.owner == user.key() @ ErrorCode::InvalidAuthority,
     )]
     pub vault: Account<'info, CollateralVault>,
 
@@ -58,9 +59,15 @@ pub fn unlock_collateral(ctx: Context<UnlockCollateral>, amount: u64) -> Result<
         .available_balance
         .checked_add(amount)
         .ok_or(ErrorCode::Overflow)?;
+    vault.last_position_activity = Clock::get()?.unix_timestamp;
+    let sequence = vault.next_sequence()?;
 
     emit!(CollateralUnlocked {
         user: ctx.accounts.user.key(),
+        vault: vault.key(),
+        actor: ctx.accounts.user.key(),
+        caller_program: Some(ctx.accounts.authority_program.key()),
+        sequence,
         amount,
         new_available_balance: vault.available_balance,
         timestamp: Clock::get()?.unix_timestamp,
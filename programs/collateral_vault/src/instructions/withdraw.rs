@@ -1,5 +1,6 @@
 use crate::errors::ErrorCode;
 use crate::events::*;
+use crate::instructions::multisig::require_cosigners;
 use crate::state::CollateralVault;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
@@ -11,8 +12,9 @@ pub struct Withdraw<'info> {
 
     #[account(
         mut,
-        seeds = [b"vault", user.key().as_ref()],
+        seeds = [b"vault", vault.original_owner.as_ref()],
         bump = vault.bump,
+        constraint = vault.owner == user.key() @ ErrorCode::InvalidAuthority,
     )]
     pub vault: Account<'info, CollateralVault>,
 
@@ -26,11 +28,18 @@ pub struct Withdraw<'info> {
     pub user_token_account: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
+
+    /// CHECK: the vault's `MultisigConfig` if it has one; checked by
+    /// `require_cosigners`.
+    #[account(seeds = [b"multisig", vault.key().as_ref()], bump)]
+    pub multisig_config: UncheckedAccount<'info>,
 }
 
 pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+    require_cosigners(&ctx.accounts.multisig_config, ctx.remaining_accounts)?;
+
     let vault = &mut ctx.accounts.vault;
-    let binding = ctx.accounts.user.key();
+    let binding = vault.original_owner;
 
     let seeds = &[b"vault", binding.as_ref(), &[vault.bump]];
     let signer = &[&seeds[..]]; //@audit
@@ -73,9 +82,14 @@ pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
         .total_withdrawn
         .checked_add(amount)
         .ok_or(ErrorCode::Overflow)?;
+    let sequence = vault.next_sequence()?;
 
     emit!(WithdrawEvent {
         user: ctx.accounts.user.key(),
+        vault: vault.key(),
+        actor: ctx.accounts.user.key(),
+        caller_program: None,
+        sequence,
         amount,
         new_balance: vault.total_balance,
         timestamp: Clock::get()?.unix_timestamp,
@@ -7,6 +7,7 @@ pub mod instructions;
 pub mod state;
 
 use instructions::*;
+use state::SettlementBucket;
 
 declare_id!("GfHdK9T6kBwS55D9pv97CbNE9PdP4kpASxMipM7gWSKa");
 
@@ -21,6 +22,10 @@ pub mod collateral_vault {
         instructions::initialize_vault::handler(ctx, authorized_programs)
     }
 
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        instructions::migrate::migrate_vault(ctx)
+    }
+
     pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
         instructions::deposit::deposit(ctx, amount)
     }
@@ -79,4 +84,68 @@ pub mod collateral_vault {
     ) -> Result<()> {
         instructions::multisig::initialize_multisig(ctx, signers, threshold)
     }
+
+    pub fn request_forced_unlock(ctx: Context<RequestForcedUnlock>) -> Result<()> {
+        instructions::forced_unlock::request_forced_unlock(ctx)
+    }
+
+    pub fn execute_forced_unlock(ctx: Context<ExecuteForcedUnlock>) -> Result<()> {
+        instructions::forced_unlock::execute_forced_unlock(ctx)
+    }
+
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        instructions::forced_unlock::heartbeat(ctx)
+    }
+
+    pub fn settle_pnl(
+        ctx: Context<SettlePnl>,
+        position_id: u64,
+        loser_margin: u64,
+        winner_margin: u64,
+        pnl: u64,
+        debit_bucket: SettlementBucket,
+        credit_bucket: SettlementBucket,
+    ) -> Result<()> {
+        instructions::settle_pnl::settle_pnl(
+            ctx,
+            position_id,
+            loser_margin,
+            winner_margin,
+            pnl,
+            debit_bucket,
+            credit_bucket,
+        )
+    }
+
+    pub fn configure_recovery(
+        ctx: Context<ConfigureRecovery>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        instructions::recovery::configure_recovery(ctx, guardians, threshold)
+    }
+
+    pub fn update_recovery(
+        ctx: Context<UpdateRecovery>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        instructions::recovery::update_recovery(ctx, guardians, threshold)
+    }
+
+    pub fn initiate_recovery(ctx: Context<InitiateRecovery>, new_owner: Pubkey) -> Result<()> {
+        instructions::recovery::initiate_recovery(ctx, new_owner)
+    }
+
+    pub fn approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
+        instructions::recovery::approve_recovery(ctx)
+    }
+
+    pub fn veto_recovery(ctx: Context<VetoRecovery>) -> Result<()> {
+        instructions::recovery::veto_recovery(ctx)
+    }
+
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+        instructions::recovery::execute_recovery(ctx)
+    }
 }
@@ -1,3 +1,4 @@
+use crate::errors::ErrorCode;
 use anchor_lang::prelude::*;
 
 #[account]
@@ -12,6 +13,35 @@ pub struct CollateralVault {
     pub total_withdrawn: u64,
     pub created_at: i64,
     pub bump: u8,
+    /// Monotonic counter bumped on every state change; carried by every event.
+    pub sequence: u64,
+    /// Last time an authorized program locked, unlocked or heartbeated.
+    pub last_position_activity: i64,
+    /// Owner at creation. PDA seeds stay bound to it after a recovery changes `owner`.
+    pub original_owner: Pubkey,
+}
+
+impl CollateralVault {
+    pub fn next_sequence(&mut self) -> Result<u64> {
+        self.sequence = self.sequence.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        Ok(self.sequence)
+    }
+}
+
+/// `CollateralVault` as stored before `sequence`, `last_position_activity`
+/// and `original_owner` were added. Only `migrate_vault` reads it.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyCollateralVault {
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub vault_authority: Pubkey,
+    pub total_balance: u64,
+    pub locked_balance: u64,
+    pub available_balance: u64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub created_at: i64,
+    pub bump: u8,
 }
 
 #[account]
@@ -24,6 +54,13 @@ pub struct VaultAuthority {
 impl VaultAuthority {
     pub const MAX_AUTHORIZED_PROGRAMS: usize = 16;
     pub const MAX_SIZE: usize = 32 + 4 + (Self::MAX_AUTHORIZED_PROGRAMS * 32) + 1;
+
+    /// Seed of the PDA an authorized program signs with to prove a call came through it.
+    pub const CALLER_SEED: &'static [u8] = b"vault_caller";
+
+    pub fn is_authorized(&self, program: &Pubkey) -> bool {
+        self.authorized_programs.iter().any(|p| p == program)
+    }
 }
 
 #[account]
@@ -34,6 +71,47 @@ pub struct MultisigConfig {
     pub bump: u8,
 }
 
+impl MultisigConfig {
+    pub const MAX_SIGNERS: usize = 10;
+    pub const MAX_SIZE: usize = 32 + 4 + (Self::MAX_SIGNERS * 32) + 1 + 1;
+
+    pub fn is_signer(&self, key: &Pubkey) -> bool {
+        self.signers.iter().any(|s| s == key)
+    }
+}
+
+#[account]
+pub struct RecoveryConfig {
+    pub vault: Pubkey,
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    pub bump: u8,
+}
+
+impl RecoveryConfig {
+    pub const MAX_GUARDIANS: usize = 10;
+    pub const MAX_SIZE: usize = 32 + 4 + (Self::MAX_GUARDIANS * 32) + 1 + 1;
+
+    pub fn is_guardian(&self, key: &Pubkey) -> bool {
+        self.guardians.iter().any(|g| g == key)
+    }
+}
+
+#[account]
+pub struct RecoveryRequest {
+    pub vault: Pubkey,
+    pub proposed_owner: Pubkey,
+    pub initiator: Pubkey,
+    pub approvals: Vec<Pubkey>,
+    pub initiated_at: i64,
+    pub available_at: i64,
+    pub bump: u8,
+}
+
+impl RecoveryRequest {
+    pub const MAX_SIZE: usize = 32 + 32 + 32 + 4 + (RecoveryConfig::MAX_GUARDIANS * 32) + 8 + 8 + 1;
+}
+
 #[account]
 pub struct WithdrawalRequest {
     pub vault: Pubkey,
@@ -46,6 +124,22 @@ pub struct WithdrawalRequest {
     pub bump: u8,
 }
 
+#[account]
+pub struct ForcedUnlockRequest {
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub requested_at: i64,
+    pub available_at: i64,
+    pub bump: u8,
+}
+
+/// Which balance bucket a settlement leg debits or credits.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettlementBucket {
+    Locked,
+    Available,
+}
+
 #[account]
 pub struct WithdrawalWhitelist {
     pub vault: Pubkey,
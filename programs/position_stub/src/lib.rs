@@ -0,0 +1,130 @@
+use anchor_lang::prelude::*;
+use collateral_vault::program::CollateralVault;
+use collateral_vault::state::{SettlementBucket, VaultAuthority};
+
+declare_id!("DHKew4Mu7J96iDR7MUqWeeDowotpCSzMPSfZWQ34GKp4");
+
+/// Stands in for a position program in the vault's tests: forwards the
+/// instructions only an authorized program may call, signing for its
+/// `VaultAuthority::CALLER_SEED` PDA. Vault accounts are checked by the vault.
+#[program]
+pub mod position_stub {
+    use super::*;
+
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        let seeds: &[&[u8]] = &[VaultAuthority::CALLER_SEED, &[ctx.bumps.caller]];
+        collateral_vault::cpi::heartbeat(CpiContext::new_with_signer(
+            ctx.accounts.vault_program.to_account_info(),
+            collateral_vault::cpi::accounts::Heartbeat {
+                vault: ctx.accounts.vault.to_account_info(),
+                vault_authority: ctx.accounts.vault_authority.to_account_info(),
+                authority_program: ctx.accounts.position_program.to_account_info(),
+                caller: ctx.accounts.caller.to_account_info(),
+                forced_unlock_request: ctx.accounts.forced_unlock_request.to_account_info(),
+                owner: ctx.accounts.owner.to_account_info(),
+            },
+            &[seeds],
+        ))
+    }
+
+    pub fn settle_pnl(
+        ctx: Context<SettlePnl>,
+        position_id: u64,
+        loser_margin: u64,
+        winner_margin: u64,
+        pnl: u64,
+        debit_bucket: SettlementBucket,
+        credit_bucket: SettlementBucket,
+    ) -> Result<()> {
+        let seeds: &[&[u8]] = &[VaultAuthority::CALLER_SEED, &[ctx.bumps.caller]];
+        collateral_vault::cpi::settle_pnl(
+            CpiContext::new_with_signer(
+                ctx.accounts.vault_program.to_account_info(),
+                collateral_vault::cpi::accounts::SettlePnl {
+                    loser_vault: ctx.accounts.loser_vault.to_account_info(),
+                    loser_vault_authority: ctx.accounts.loser_vault_authority.to_account_info(),
+                    loser_vault_token_account: ctx
+                        .accounts
+                        .loser_vault_token_account
+                        .to_account_info(),
+                    winner_vault: ctx.accounts.winner_vault.to_account_info(),
+                    winner_vault_authority: ctx.accounts.winner_vault_authority.to_account_info(),
+                    winner_vault_token_account: ctx
+                        .accounts
+                        .winner_vault_token_account
+                        .to_account_info(),
+                    authority_program: ctx.accounts.position_program.to_account_info(),
+                    caller: ctx.accounts.caller.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+                &[seeds],
+            ),
+            position_id,
+            loser_margin,
+            winner_margin,
+            pnl,
+            debit_bucket,
+            credit_bucket,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    /// CHECK: Checked by the vault program
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    pub vault_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Signs the CPI
+    #[account(seeds = [VaultAuthority::CALLER_SEED], bump)]
+    pub caller: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    #[account(mut)]
+    pub forced_unlock_request: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    pub position_program: Program<'info, crate::program::PositionStub>,
+    pub vault_program: Program<'info, CollateralVault>,
+}
+
+#[derive(Accounts)]
+pub struct SettlePnl<'info> {
+    /// CHECK: Checked by the vault program
+    #[account(mut)]
+    pub loser_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    pub loser_vault_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    #[account(mut)]
+    pub loser_vault_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    #[account(mut)]
+    pub winner_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    pub winner_vault_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    #[account(mut)]
+    pub winner_vault_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Signs the CPI
+    #[account(seeds = [VaultAuthority::CALLER_SEED], bump)]
+    pub caller: UncheckedAccount<'info>,
+
+    /// CHECK: Checked by the vault program
+    pub token_program: UncheckedAccount<'info>,
+
+    pub position_program: Program<'info, crate::program::PositionStub>,
+    pub vault_program: Program<'info, CollateralVault>,
+}
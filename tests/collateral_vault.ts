@@ -0,0 +1,631 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { assert } from "chai";
+import { CollateralVault } from "../target/types/collateral_vault";
+import { PositionStub } from "../target/types/position_stub";
+
+// Run against a local validator:
+//   anchor test --provider.cluster localnet
+// Delays measured in days (forced unlock, recovery) cannot elapse on a
+// validator, so only their "not yet" paths are checked here; the backend's
+// simulated chain tests advance its clock past them.
+
+const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+const program = anchor.workspace.collateralVault as Program<CollateralVault>;
+// Stands in for an authorized position program in CPI-only instructions.
+const positionStub = anchor.workspace.positionStub as Program<PositionStub>;
+const connection = provider.connection;
+const payer = (provider.wallet as anchor.Wallet).payer;
+
+const pda = (...seeds: (Buffer | Uint8Array)[]) =>
+  PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+
+async function fund(keypair: Keypair) {
+  const signature = await connection.requestAirdrop(
+    keypair.publicKey,
+    2 * LAMPORTS_PER_SOL
+  );
+  await connection.confirmTransaction(signature, "confirmed");
+}
+
+async function expectError(call: Promise<unknown>, code: string) {
+  let error: unknown;
+  try {
+    await call;
+  } catch (e) {
+    error = e;
+  }
+  assert.exists(error, `expected ${code}`);
+  const message =
+    error instanceof anchor.AnchorError
+      ? error.error.errorCode.code
+      : String(error);
+  assert.include(message, code);
+}
+
+/** Events the program emitted in a confirmed transaction. */
+async function eventsOf(signature: string) {
+  const tx = await connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const parser = new anchor.EventParser(program.programId, program.coder);
+  return [...parser.parseLogs(tx!.meta!.logMessages!)];
+}
+
+function eventNamed(events: anchor.Event[], name: string) {
+  const event = events.find((e) => e.name.toLowerCase() === name.toLowerCase());
+  assert.exists(event, `no ${name} event`);
+  return event!.data as any;
+}
+
+interface TestVault {
+  owner: Keypair;
+  vault: PublicKey;
+  vaultAuthority: PublicKey;
+  vaultTokenAccount: PublicKey;
+  ownerTokenAccount: PublicKey;
+}
+
+async function createVault(
+  mint: PublicKey,
+  authorizedPrograms: PublicKey[],
+  deposit = 0
+): Promise<TestVault> {
+  const owner = Keypair.generate();
+  await fund(owner);
+  const vault = pda(Buffer.from("vault"), owner.publicKey.toBuffer());
+  const vaultAuthority = pda(Buffer.from("vault_authority"), vault.toBuffer());
+  const vaultTokenAccount = pda(
+    Buffer.from("vault_token"),
+    owner.publicKey.toBuffer()
+  );
+
+  await program.methods
+    .initializeVault(authorizedPrograms)
+    .accountsPartial({
+      user: owner.publicKey,
+      vault,
+      vaultAuthority,
+      usdtMint: mint,
+      vaultTokenAccount,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+    })
+    .signers([owner])
+    .rpc({ commitment: "confirmed" });
+
+  const ownerTokenAccount = (
+    await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      mint,
+      owner.publicKey
+    )
+  ).address;
+  await mintTo(connection, payer, mint, ownerTokenAccount, payer, 10_000);
+
+  const created = {
+    owner,
+    vault,
+    vaultAuthority,
+    vaultTokenAccount,
+    ownerTokenAccount,
+  };
+  if (deposit > 0) {
+    await depositInto(created, deposit);
+  }
+  return created;
+}
+
+function depositInto(v: TestVault, amount: number) {
+  return program.methods
+    .deposit(new BN(amount))
+    .accountsPartial({
+      user: v.owner.publicKey,
+      vault: v.vault,
+      userTokenAccount: v.ownerTokenAccount,
+      vaultTokenAccount: v.vaultTokenAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .signers([v.owner])
+    .rpc({ commitment: "confirmed" });
+}
+
+function withdrawFrom(v: TestVault, amount: number) {
+  return program.methods
+    .withdraw(new BN(amount))
+    .accountsPartial({
+      user: v.owner.publicKey,
+      vault: v.vault,
+      vaultTokenAccount: v.vaultTokenAccount,
+      userTokenAccount: v.ownerTokenAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .signers([v.owner])
+    .rpc({ commitment: "confirmed" });
+}
+
+function lockVia(v: TestVault, authorityProgram: PublicKey, amount: number) {
+  return program.methods
+    .lockCollateral(new BN(amount))
+    .accountsPartial({
+      user: v.owner.publicKey,
+      vault: v.vault,
+      vaultAuthority: v.vaultAuthority,
+      authorityProgram,
+    })
+    .signers([v.owner])
+    .rpc({ commitment: "confirmed" });
+}
+
+const forcedUnlockPda = (v: TestVault) =>
+  pda(Buffer.from("forced_unlock"), v.vault.toBuffer());
+
+function heartbeatVia(v: TestVault) {
+  return positionStub.methods
+    .heartbeat()
+    .accountsPartial({
+      vault: v.vault,
+      vaultAuthority: v.vaultAuthority,
+      forcedUnlockRequest: forcedUnlockPda(v),
+      owner: v.owner.publicKey,
+      positionProgram: positionStub.programId,
+      vaultProgram: program.programId,
+    })
+    .rpc({ commitment: "confirmed" });
+}
+
+const LOCKED = { locked: {} };
+const AVAILABLE = { available: {} };
+
+function settleVia(
+  loser: TestVault,
+  winner: TestVault,
+  pnl: number,
+  margin: number,
+  debitBucket: object,
+  creditBucket: object
+) {
+  return positionStub.methods
+    .settlePnl(
+      new BN(1),
+      new BN(margin),
+      new BN(margin),
+      new BN(pnl),
+      debitBucket as any,
+      creditBucket as any
+    )
+    .accountsPartial({
+      loserVault: loser.vault,
+      loserVaultAuthority: loser.vaultAuthority,
+      loserVaultTokenAccount: loser.vaultTokenAccount,
+      winnerVault: winner.vault,
+      winnerVaultAuthority: winner.vaultAuthority,
+      winnerVaultTokenAccount: winner.vaultTokenAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      positionProgram: positionStub.programId,
+      vaultProgram: program.programId,
+    })
+    .rpc({ commitment: "confirmed" });
+}
+
+describe("collateral_vault", () => {
+  let mint: PublicKey;
+
+  before(async () => {
+    mint = await createMint(connection, payer, payer.publicKey, null, 6);
+  });
+
+  describe("sequence", () => {
+    it("bumps on every state change and is carried by each event", async () => {
+      const v = await createVault(mint, []);
+      let state = await program.account.collateralVault.fetch(v.vault);
+      assert.equal(state.sequence.toNumber(), 0);
+      assert.isTrue(state.originalOwner.equals(v.owner.publicKey));
+
+      const deposited = eventNamed(
+        await eventsOf(await depositInto(v, 1_000)),
+        "DepositEvent"
+      );
+      assert.equal(deposited.sequence.toNumber(), 1);
+      assert.isNull(deposited.callerProgram);
+      assert.isTrue(deposited.actor.equals(v.owner.publicKey));
+
+      const withdrawn = eventNamed(
+        await eventsOf(await withdrawFrom(v, 400)),
+        "WithdrawEvent"
+      );
+      assert.equal(withdrawn.sequence.toNumber(), 2);
+
+      state = await program.account.collateralVault.fetch(v.vault);
+      assert.equal(state.sequence.toNumber(), 2);
+      assert.equal(state.totalBalance.toNumber(), 600);
+    });
+
+    it("does not move on a failed instruction", async () => {
+      const v = await createVault(mint, [], 100);
+      await expectError(withdrawFrom(v, 500), "InsufficientFunds");
+      const state = await program.account.collateralVault.fetch(v.vault);
+      assert.equal(state.sequence.toNumber(), 1);
+    });
+  });
+
+  describe("migrate_vault", () => {
+    it("refuses a vault already in the current layout", async () => {
+      const v = await createVault(mint, []);
+      await expectError(
+        program.methods
+          .migrateVault()
+          .accountsPartial({
+            owner: v.owner.publicKey,
+            vault: v.vault,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([v.owner])
+          .rpc(),
+        "AlreadyMigrated"
+      );
+    });
+  });
+
+  describe("forced unlock", () => {
+    const request = (v: TestVault) =>
+      program.methods
+        .requestForcedUnlock()
+        .accountsPartial({
+          owner: v.owner.publicKey,
+          vault: v.vault,
+          forcedUnlockRequest: forcedUnlockPda(v),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([v.owner])
+        .rpc();
+
+    it("needs locked funds and an idle vault", async () => {
+      const v = await createVault(mint, [positionStub.programId], 1_000);
+      await expectError(request(v), "InsufficientLockedFunds");
+      await lockVia(v, positionStub.programId, 600);
+      await expectError(request(v), "ForcedUnlockInactivityNotMet");
+    });
+
+    it("executes only a request on file", async () => {
+      const v = await createVault(mint, [positionStub.programId], 1_000);
+      await lockVia(v, positionStub.programId, 600);
+      await expectError(
+        program.methods
+          .executeForcedUnlock()
+          .accountsPartial({
+            owner: v.owner.publicKey,
+            vault: v.vault,
+            forcedUnlockRequest: forcedUnlockPda(v),
+          })
+          .signers([v.owner])
+          .rpc(),
+        "AccountNotInitialized"
+      );
+    });
+
+    it("takes heartbeats from the authorized program only", async () => {
+      const v = await createVault(mint, [positionStub.programId], 1_000);
+      await lockVia(v, positionStub.programId, 600);
+
+      const beat = eventNamed(
+        await eventsOf(await heartbeatVia(v)),
+        "PositionHeartbeat"
+      );
+      assert.isFalse(beat.cancelledForcedUnlock);
+      assert.isTrue(beat.callerProgram.equals(positionStub.programId));
+      assert.equal(beat.sequence.toNumber(), 3);
+
+      // The owner cannot stand in for the program's caller PDA.
+      await expectError(
+        program.methods
+          .heartbeat()
+          .accountsPartial({
+            vault: v.vault,
+            vaultAuthority: v.vaultAuthority,
+            authorityProgram: positionStub.programId,
+            caller: v.owner.publicKey,
+            forcedUnlockRequest: forcedUnlockPda(v),
+            owner: v.owner.publicKey,
+          })
+          .signers([v.owner])
+          .rpc(),
+        "ConstraintSeeds"
+      );
+
+      const unlisted = await createVault(mint, [], 1_000);
+      await expectError(heartbeatVia(unlisted), "Unauthorized");
+    });
+  });
+
+  describe("settle_pnl", () => {
+    async function lockedPair(margin: number) {
+      const loser = await createVault(mint, [positionStub.programId], 1_000);
+      const winner = await createVault(mint, [positionStub.programId], 1_000);
+      await lockVia(loser, positionStub.programId, margin);
+      await lockVia(winner, positionStub.programId, margin);
+      return [loser, winner];
+    }
+
+    const tokens = async (v: TestVault) =>
+      Number((await getAccount(connection, v.vaultTokenAccount)).amount);
+
+    it("takes the loss from the margin and credits it as available", async () => {
+      const [loser, winner] = await lockedPair(400);
+      const settled = eventNamed(
+        await eventsOf(await settleVia(loser, winner, 150, 400, LOCKED, AVAILABLE)),
+        "PositionSettled"
+      );
+      assert.equal(settled.loser.marginReleased.toNumber(), 250);
+      assert.isTrue(settled.callerProgram.equals(positionStub.programId));
+
+      const l = await program.account.collateralVault.fetch(loser.vault);
+      assert.equal(l.totalBalance.toNumber(), 850);
+      assert.equal(l.lockedBalance.toNumber(), 0);
+      assert.equal(l.availableBalance.toNumber(), 850);
+      const w = await program.account.collateralVault.fetch(winner.vault);
+      assert.equal(w.totalBalance.toNumber(), 1_150);
+      assert.equal(w.availableBalance.toNumber(), 1_150);
+      assert.equal(await tokens(loser), 850);
+      assert.equal(await tokens(winner), 1_150);
+    });
+
+    it("takes the loss from available funds and keeps the profit locked", async () => {
+      const [loser, winner] = await lockedPair(100);
+      await settleVia(loser, winner, 200, 100, AVAILABLE, LOCKED);
+
+      const l = await program.account.collateralVault.fetch(loser.vault);
+      assert.equal(l.totalBalance.toNumber(), 800);
+      assert.equal(l.lockedBalance.toNumber(), 0);
+      assert.equal(l.availableBalance.toNumber(), 800);
+      const w = await program.account.collateralVault.fetch(winner.vault);
+      assert.equal(w.totalBalance.toNumber(), 1_200);
+      assert.equal(w.lockedBalance.toNumber(), 200);
+      assert.equal(w.availableBalance.toNumber(), 1_000);
+      assert.equal(await tokens(loser), 800);
+      assert.equal(await tokens(winner), 1_200);
+    });
+
+    it("rejects a loss beyond the margin and a single-vault settlement", async () => {
+      const [loser, winner] = await lockedPair(100);
+      await expectError(
+        settleVia(loser, winner, 200, 100, LOCKED, AVAILABLE),
+        "PnlExceedsMargin"
+      );
+      await expectError(
+        settleVia(winner, winner, 0, 0, LOCKED, LOCKED),
+        "SameVaultSettlement"
+      );
+    });
+  });
+
+  describe("recovery", () => {
+    async function withGuardians(count: number, threshold: number) {
+      const v = await createVault(mint, [], 1_000);
+      const guardians = [...Array(count)].map(() => Keypair.generate());
+      await Promise.all(guardians.map(fund));
+      await program.methods
+        .configureRecovery(
+          guardians.map((g) => g.publicKey),
+          threshold
+        )
+        .accountsPartial({
+          owner: v.owner.publicKey,
+          vault: v.vault,
+          recoveryConfig: pda(Buffer.from("recovery"), v.vault.toBuffer()),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([v.owner])
+        .rpc({ commitment: "confirmed" });
+      return { v, guardians };
+    }
+
+    const recoveryAccounts = (v: TestVault, guardian: Keypair) => ({
+      guardian: guardian.publicKey,
+      vault: v.vault,
+      recoveryConfig: pda(Buffer.from("recovery"), v.vault.toBuffer()),
+      recoveryRequest: pda(Buffer.from("recovery_request"), v.vault.toBuffer()),
+    });
+
+    const initiate = (v: TestVault, guardian: Keypair, newOwner: PublicKey) =>
+      program.methods
+        .initiateRecovery(newOwner)
+        .accountsPartial({
+          ...recoveryAccounts(v, guardian),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([guardian])
+        .rpc({ commitment: "confirmed" });
+
+    const approve = (v: TestVault, guardian: Keypair) =>
+      program.methods
+        .approveRecovery()
+        .accountsPartial(recoveryAccounts(v, guardian))
+        .signers([guardian])
+        .rpc({ commitment: "confirmed" });
+
+    const execute = (v: TestVault, guardian: Keypair, initiator: PublicKey) =>
+      program.methods
+        .executeRecovery()
+        .accountsPartial({ ...recoveryAccounts(v, guardian), initiator })
+        .signers([guardian])
+        .rpc();
+
+    it("rejects the owner as a guardian and an unreachable threshold", async () => {
+      const v = await createVault(mint, []);
+      const configure = (guardians: PublicKey[], threshold: number) =>
+        program.methods
+          .configureRecovery(guardians, threshold)
+          .accountsPartial({
+            owner: v.owner.publicKey,
+            vault: v.vault,
+            recoveryConfig: pda(Buffer.from("recovery"), v.vault.toBuffer()),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([v.owner])
+          .rpc();
+      await expectError(
+        configure([v.owner.publicKey, Keypair.generate().publicKey], 1),
+        "InvalidGuardianSet"
+      );
+      await expectError(
+        configure([Keypair.generate().publicKey], 2),
+        "InvalidGuardianSet"
+      );
+    });
+
+    it("collects approvals from guardians only, once each", async () => {
+      const { v, guardians } = await withGuardians(3, 2);
+      const [g1, g2] = guardians;
+      const newOwner = Keypair.generate().publicKey;
+      const outsider = Keypair.generate();
+      await fund(outsider);
+      await expectError(initiate(v, outsider, newOwner), "NotGuardian");
+
+      const initiated = eventNamed(
+        await eventsOf(await initiate(v, g1, newOwner)),
+        "RecoveryInitiated"
+      );
+      assert.equal(initiated.approvals, 1);
+      assert.equal(initiated.threshold, 2);
+      assert.isTrue(initiated.user.equals(v.owner.publicKey));
+
+      await expectError(approve(v, g1), "RecoveryAlreadyApproved");
+      const approved = eventNamed(
+        await eventsOf(await approve(v, g2)),
+        "RecoveryApproved"
+      );
+      assert.equal(approved.approvals, 2);
+
+      // Enough approvals, but the owner's veto window is still open.
+      await expectError(execute(v, g2, g1.publicKey), "RecoveryDelayNotMet");
+    });
+
+    it("lets the owner veto and refund the initiator", async () => {
+      const { v, guardians } = await withGuardians(2, 1);
+      const [g1] = guardians;
+      await initiate(v, g1, Keypair.generate().publicKey);
+      const requestAddress = recoveryAccounts(v, g1).recoveryRequest;
+
+      await program.methods
+        .vetoRecovery()
+        .accountsPartial({
+          owner: v.owner.publicKey,
+          vault: v.vault,
+          recoveryRequest: requestAddress,
+          initiator: g1.publicKey,
+        })
+        .signers([v.owner])
+        .rpc({ commitment: "confirmed" });
+      assert.isNull(await connection.getAccountInfo(requestAddress));
+      const state = await program.account.collateralVault.fetch(v.vault);
+      assert.isTrue(state.owner.equals(v.owner.publicKey));
+    });
+
+    it("rotates guardians without touching a pending request", async () => {
+      const { v, guardians } = await withGuardians(3, 2);
+      const [g1, g2, g3] = guardians;
+      await initiate(v, g1, Keypair.generate().publicKey);
+      const g4 = Keypair.generate();
+
+      await program.methods
+        .updateRecovery([g3.publicKey, g4.publicKey], 2)
+        .accountsPartial({
+          owner: v.owner.publicKey,
+          vault: v.vault,
+          recoveryConfig: recoveryAccounts(v, g1).recoveryConfig,
+        })
+        .signers([v.owner])
+        .rpc({ commitment: "confirmed" });
+
+      const config = await program.account.recoveryConfig.fetch(
+        recoveryAccounts(v, g1).recoveryConfig
+      );
+      assert.deepEqual(
+        config.guardians.map((g) => g.toBase58()),
+        [g3.publicKey.toBase58(), g4.publicKey.toBase58()]
+      );
+      // Rotated-out guardians can no longer act on the request.
+      await expectError(approve(v, g2), "NotGuardian");
+      await approve(v, g3);
+      const request = await program.account.recoveryRequest.fetch(
+        recoveryAccounts(v, g1).recoveryRequest
+      );
+      assert.equal(request.approvals.length, 2);
+    });
+  });
+
+  describe("multisig", () => {
+    it("needs threshold co-signers alongside the owner to withdraw", async () => {
+      const v = await createVault(mint, [], 1_000);
+      const cosigners = [...Array(3)].map(() => Keypair.generate());
+      await program.methods
+        .initializeMultisig(
+          cosigners.map((c) => c.publicKey),
+          2
+        )
+        .accountsPartial({
+          owner: v.owner.publicKey,
+          vault: v.vault,
+          multisigConfig: pda(Buffer.from("multisig"), v.vault.toBuffer()),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([v.owner])
+        .rpc({ commitment: "confirmed" });
+
+      const withdrawSignedBy = (signers: Keypair[]) =>
+        program.methods
+          .withdraw(new BN(400))
+          .accountsPartial({
+            user: v.owner.publicKey,
+            vault: v.vault,
+            vaultTokenAccount: v.vaultTokenAccount,
+            userTokenAccount: v.ownerTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            multisigConfig: pda(Buffer.from("multisig"), v.vault.toBuffer()),
+          })
+          .remainingAccounts(
+            signers.map((s) => ({
+              pubkey: s.publicKey,
+              isSigner: true,
+              isWritable: false,
+            }))
+          )
+          .signers([v.owner, ...signers])
+          .rpc({ commitment: "confirmed" });
+
+      await expectError(withdrawFrom(v, 400), "MultisigThresholdNotMet");
+      await expectError(
+        withdrawSignedBy([cosigners[0]]),
+        "MultisigThresholdNotMet"
+      );
+      // Outsiders do not count towards the threshold.
+      await expectError(
+        withdrawSignedBy([cosigners[0], Keypair.generate()]),
+        "MultisigThresholdNotMet"
+      );
+
+      await withdrawSignedBy([cosigners[0], cosigners[2]]);
+      const state = await program.account.collateralVault.fetch(v.vault);
+      assert.equal(state.totalBalance.toNumber(), 600);
+    });
+  });
+});